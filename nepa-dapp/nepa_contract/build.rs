@@ -0,0 +1,22 @@
+use std::process::Command;
+
+// Bakes the git commit into the contract so `get_version` can report exactly
+// what was deployed. CI can pin it by exporting NEPA_GIT_COMMIT.
+fn main() {
+    println!("cargo:rerun-if-env-changed=NEPA_GIT_COMMIT");
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../.git/refs/heads");
+
+    let commit = std::env::var("NEPA_GIT_COMMIT").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    });
+    println!(
+        "cargo:rustc-env=NEPA_GIT_COMMIT={}",
+        commit.unwrap_or_else(|| "unknown".to_string())
+    );
+}
@@ -0,0 +1,379 @@
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{contracttype, Address, Env, FromVal, Map, String, Symbol, Val, Vec};
+
+use crate::errors::Error;
+use crate::fees;
+use crate::math::{self, pow10};
+use crate::oracle::{OracleManager, PriceFeed, PriceSource};
+use crate::peg;
+use crate::storage;
+use crate::taxes::LineItem;
+use crate::tokens::{self, TokenConfig};
+
+// Every payment is also valued in NGN with this many decimals.
+pub const NGN_DECIMALS: u32 = 7;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentRecord {
+    pub payer: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub normalized_amount: i128,
+    // The feed price used for the conversion, in the feed's own decimals.
+    pub rate: i128,
+    pub rate_decimals: u32,
+    // Which source of the feed's fallback chain supplied the price.
+    pub price_source: PriceSource,
+    pub timestamp: u64,
+    // Part of `amount` taken as fees; `normalized_amount` values the rest.
+    pub fee_amount: i128,
+    // The fees in NGN units, one line item each.
+    pub fees: Vec<LineItem>,
+    // Position in the contract-wide payment sequence, from 1; 0 until booked.
+    pub sequence: u64,
+}
+
+// Layout of records booked before the payment sequence (schema 3).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct PaymentRecordV3 {
+    payer: Address,
+    token: Address,
+    amount: i128,
+    normalized_amount: i128,
+    rate: i128,
+    rate_decimals: u32,
+    price_source: PriceSource,
+    timestamp: u64,
+    fee_amount: i128,
+    fees: Vec<LineItem>,
+}
+
+// Layout of records booked before fees were charged (schema 2). The oldest
+// of them also lack `price_source`: the push feed priced every payment then.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct PaymentRecordV2 {
+    payer: Address,
+    token: Address,
+    amount: i128,
+    normalized_amount: i128,
+    rate: i128,
+    rate_decimals: u32,
+    price_source: PriceSource,
+    timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct PaymentRecordV1 {
+    payer: Address,
+    token: Address,
+    amount: i128,
+    normalized_amount: i128,
+    rate: i128,
+    rate_decimals: u32,
+    timestamp: u64,
+}
+
+// Where a sequenced payment sits in its meter's history.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SequencedPayment {
+    pub meter_id: String,
+    pub index: u32,
+    pub record: PaymentRecord,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MeterSummary {
+    // Token amounts summed across tokens in 7-decimal accounting units, which
+    // for 7-decimal tokens are the raw units the original API reported.
+    pub total_paid: i128,
+    pub total_paid_ngn: i128,
+    pub payment_count: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AccountingKey {
+    PaymentCount(String),
+    Payment(String, u32),
+    NormalizedTotal(String),
+    // Kept in instance storage.
+    LatestPaymentSeq,
+    PaymentAtSeq(u64),
+}
+
+// Converts a raw token amount into the NGN accounting unit, rounded down so a
+// payment is never credited above its value.
+pub fn normalize(amount: i128, token_decimals: u32, feed: &PriceFeed) -> Result<i128, Error> {
+    let scale = token_decimals + feed.decimals;
+    let value = if scale >= NGN_DECIMALS {
+        amount.fixed_mul_floor(feed.price, pow10(scale - NGN_DECIMALS)?)
+    } else {
+        amount
+            .checked_mul(feed.price)
+            .and_then(|value| value.checked_mul(pow10(NGN_DECIMALS - scale).ok()?))
+    };
+    value.ok_or(Error::ArithmeticOverflow)
+}
+
+// Inverse of `normalize`: the token amount worth `value` NGN units, rounded up
+// so the payer never settles for less than the bill.
+pub fn denormalize(value: i128, token_decimals: u32, feed: &PriceFeed) -> Result<i128, Error> {
+    let scale = token_decimals + feed.decimals;
+    let amount = if scale >= NGN_DECIMALS {
+        value.fixed_mul_ceil(pow10(scale - NGN_DECIMALS)?, feed.price)
+    } else {
+        let divisor = feed.price.checked_mul(pow10(NGN_DECIMALS - scale)?);
+        divisor.and_then(|divisor| value.fixed_div_ceil(divisor, 1))
+    };
+    amount.ok_or(Error::ArithmeticOverflow)
+}
+
+// As `denormalize`, rounded down: money paid back out never exceeds the NGN
+// value it stands for.
+pub fn denormalize_floor(
+    value: i128,
+    token_decimals: u32,
+    feed: &PriceFeed,
+) -> Result<i128, Error> {
+    let scale = token_decimals + feed.decimals;
+    let amount = if scale >= NGN_DECIMALS {
+        value.fixed_mul_floor(pow10(scale - NGN_DECIMALS)?, feed.price)
+    } else {
+        let divisor = feed.price.checked_mul(pow10(NGN_DECIMALS - scale)?);
+        divisor.and_then(|divisor| value.fixed_div_floor(divisor, 1))
+    };
+    amount.ok_or(Error::ArithmeticOverflow)
+}
+
+// `amount * bps / 10_000`, rounded down: shares of a charge or payment
+// (taxes, subsidies, commission, refunds) never exceed their rate.
+pub fn apply_bps(amount: i128, bps: i128) -> Result<i128, Error> {
+    amount
+        .fixed_mul_floor(bps, BPS_DENOMINATOR)
+        .ok_or(Error::ArithmeticOverflow)
+}
+
+// Values a payment at the oracle's payment price before any funds move. Only
+// payment paths call this, so it also notes a feed past its heartbeat.
+pub fn quote(
+    env: &Env,
+    payer: &Address,
+    token: &Address,
+    config: &TokenConfig,
+    amount: i128,
+) -> Result<PaymentRecord, Error> {
+    let (feed, source) = peg::payment_price(env, token, config)?;
+    OracleManager::note_missed_heartbeat(env, &config.oracle_pair);
+    build_record(env, payer, token, config, &feed, source, amount)
+}
+
+pub fn build_record(
+    env: &Env,
+    payer: &Address,
+    token: &Address,
+    config: &TokenConfig,
+    feed: &PriceFeed,
+    source: PriceSource,
+    amount: i128,
+) -> Result<PaymentRecord, Error> {
+    let (fee_amount, fees) = fees::assess(env, amount, config, feed)?;
+    // An amount worth nothing once converted would book an empty payment.
+    let normalized_amount = normalize(math::sub(amount, fee_amount)?, config.decimals, feed)?;
+    if normalized_amount <= 0 {
+        return Err(Error::AmountTooSmall);
+    }
+    Ok(PaymentRecord {
+        payer: payer.clone(),
+        token: token.clone(),
+        amount,
+        normalized_amount,
+        rate: feed.price,
+        rate_decimals: feed.decimals,
+        price_source: source,
+        timestamp: env.ledger().timestamp(),
+        fee_amount,
+        fees,
+        sequence: 0,
+    })
+}
+
+pub fn payment_count(env: &Env, meter_id: &String) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&AccountingKey::PaymentCount(meter_id.clone()))
+        .unwrap_or(0)
+}
+
+pub fn read_payment(env: &Env, meter_id: &String, index: u32) -> Option<PaymentRecord> {
+    let stored: Val = env
+        .storage()
+        .persistent()
+        .get(&AccountingKey::Payment(meter_id.clone(), index))?;
+    Some(upgrade_record(env, &stored))
+}
+
+// Records stay in the layout they were booked in; older ones are read into
+// the current one, unsequenced and, before schema 3, with no fees taken.
+fn upgrade_record(env: &Env, stored: &Val) -> PaymentRecord {
+    let fields = Map::<Symbol, Val>::from_val(env, stored);
+    if fields.contains_key(Symbol::new(env, "sequence")) {
+        return PaymentRecord::from_val(env, stored);
+    }
+    let v3 = if fields.contains_key(Symbol::new(env, "fees")) {
+        PaymentRecordV3::from_val(env, stored)
+    } else {
+        upgrade_v2(env, &fields, stored)
+    };
+    PaymentRecord {
+        payer: v3.payer,
+        token: v3.token,
+        amount: v3.amount,
+        normalized_amount: v3.normalized_amount,
+        rate: v3.rate,
+        rate_decimals: v3.rate_decimals,
+        price_source: v3.price_source,
+        timestamp: v3.timestamp,
+        fee_amount: v3.fee_amount,
+        fees: v3.fees,
+        sequence: 0,
+    }
+}
+
+fn upgrade_v2(env: &Env, fields: &Map<Symbol, Val>, stored: &Val) -> PaymentRecordV3 {
+    let v2 = if fields.contains_key(Symbol::new(env, "price_source")) {
+        PaymentRecordV2::from_val(env, stored)
+    } else {
+        let v1 = PaymentRecordV1::from_val(env, stored);
+        PaymentRecordV2 {
+            payer: v1.payer,
+            token: v1.token,
+            amount: v1.amount,
+            normalized_amount: v1.normalized_amount,
+            rate: v1.rate,
+            rate_decimals: v1.rate_decimals,
+            price_source: PriceSource::PushFeed,
+            timestamp: v1.timestamp,
+        }
+    };
+    PaymentRecordV3 {
+        payer: v2.payer,
+        token: v2.token,
+        amount: v2.amount,
+        normalized_amount: v2.normalized_amount,
+        rate: v2.rate,
+        rate_decimals: v2.rate_decimals,
+        price_source: v2.price_source,
+        timestamp: v2.timestamp,
+        fee_amount: 0,
+        fees: Vec::new(env),
+    }
+}
+
+// Raw totals predate the typed keys and still live under the bare meter id.
+pub fn raw_total(env: &Env, meter_id: &String) -> i128 {
+    env.storage().persistent().get(meter_id).unwrap_or(0)
+}
+
+pub fn summary(env: &Env, meter_id: &String) -> MeterSummary {
+    MeterSummary {
+        total_paid: raw_total(env, meter_id),
+        total_paid_ngn: normalized_total(env, meter_id),
+        payment_count: payment_count(env, meter_id),
+    }
+}
+
+pub fn normalized_total(env: &Env, meter_id: &String) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&AccountingKey::NormalizedTotal(meter_id.clone()))
+        .unwrap_or(0)
+}
+
+// Sequence number of the latest booked payment, 0 before the first.
+pub fn latest_sequence(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&AccountingKey::LatestPaymentSeq)
+        .unwrap_or(0)
+}
+
+// None for unused sequence numbers and for payments pruned since.
+pub fn payment_by_sequence(env: &Env, sequence: u64) -> Option<SequencedPayment> {
+    let (meter_id, index): (String, u32) = env
+        .storage()
+        .persistent()
+        .get(&AccountingKey::PaymentAtSeq(sequence))?;
+    let record = read_payment(env, &meter_id, index)?;
+    Some(SequencedPayment {
+        meter_id,
+        index,
+        record,
+    })
+}
+
+// Appends the payment to the meter's history under the next sequence number
+// and returns its index.
+pub fn record_payment(
+    env: &Env,
+    meter_id: &String,
+    record: &mut PaymentRecord,
+) -> Result<u32, Error> {
+    let mut summary = summary(env, meter_id);
+    let index = summary.payment_count;
+    let units = tokens::to_units(record.amount, tokens::decimals(env, &record.token))?;
+    summary.total_paid = math::add(summary.total_paid, units)?;
+    summary.total_paid_ngn = math::add(summary.total_paid_ngn, record.normalized_amount)?;
+    summary.payment_count = index.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+    record.sequence = latest_sequence(env)
+        .checked_add(1)
+        .ok_or(Error::ArithmeticOverflow)?;
+
+    store_payment(env, meter_id, index, record);
+    store_summary(env, meter_id, &summary);
+    Ok(index)
+}
+
+// Sequenced records are also indexed by sequence number, so a mirror's
+// imports stay addressable the same way.
+pub fn store_payment(env: &Env, meter_id: &String, index: u32, record: &PaymentRecord) {
+    storage::write_persistent(
+        env,
+        &AccountingKey::Payment(meter_id.clone(), index),
+        record,
+    );
+    if record.sequence == 0 {
+        return;
+    }
+    storage::write_persistent(
+        env,
+        &AccountingKey::PaymentAtSeq(record.sequence),
+        &(meter_id.clone(), index),
+    );
+    if record.sequence > latest_sequence(env) {
+        env.storage()
+            .instance()
+            .set(&AccountingKey::LatestPaymentSeq, &record.sequence);
+    }
+}
+
+pub fn store_summary(env: &Env, meter_id: &String, summary: &MeterSummary) {
+    storage::write_persistent(env, meter_id, &summary.total_paid);
+    storage::write_persistent(
+        env,
+        &AccountingKey::PaymentCount(meter_id.clone()),
+        &summary.payment_count,
+    );
+    storage::write_persistent(
+        env,
+        &AccountingKey::NormalizedTotal(meter_id.clone()),
+        &summary.total_paid_ngn,
+    );
+}
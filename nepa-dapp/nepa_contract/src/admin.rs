@@ -0,0 +1,56 @@
+use soroban_sdk::{panic_with_error, Address, BytesN, Env, Symbol};
+
+use crate::errors::Error;
+use crate::recovery;
+use crate::storage::DataKey;
+
+pub fn has_admin(env: &Env) -> bool {
+    env.storage().instance().has(&DataKey::Admin)
+}
+
+pub fn read_admin(env: &Env) -> Address {
+    env.storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .unwrap_or_else(|| panic_with_error!(env, Error::NotInitialized))
+}
+
+pub fn write_admin(env: &Env, admin: &Address) {
+    env.storage().instance().set(&DataKey::Admin, admin);
+}
+
+// Loads the admin and makes sure they signed the current invocation. Each
+// such action also counts as a sign of life against recovery claims.
+pub fn require_admin(env: &Env) -> Address {
+    let admin = read_admin(env);
+    admin.require_auth();
+    recovery::on_admin_action(env);
+    admin
+}
+
+pub fn read_treasury(env: &Env) -> Address {
+    env.storage()
+        .instance()
+        .get(&DataKey::Treasury)
+        .unwrap_or_else(|| read_admin(env))
+}
+
+pub fn write_treasury(env: &Env, treasury: &Address) {
+    env.storage().instance().set(&DataKey::Treasury, treasury);
+}
+
+// The treasury manages accepted tokens; until one is appointed the admin acts as treasury.
+pub fn require_treasury(env: &Env) -> Address {
+    let treasury = read_treasury(env);
+    treasury.require_auth();
+    treasury
+}
+
+pub fn upgrade(env: &Env, new_wasm_hash: &BytesN<32>) {
+    env.deployer()
+        .update_current_contract_wasm(new_wasm_hash.clone());
+    env.events().publish(
+        (Symbol::new(env, "contract_upgraded"),),
+        new_wasm_hash.clone(),
+    );
+}
@@ -0,0 +1,109 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::billing;
+use crate::errors::Error;
+use crate::math;
+use crate::ownership;
+use crate::storage;
+
+// A meter's spend in one month against its owner's threshold, in NGN units.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MonthlySpend {
+    pub paid: i128,
+    // Billed consumption for the month.
+    pub consumed: i128,
+    pub exceeded: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AlertKey {
+    SpendThreshold(String),
+    MeterMonthSpend(String, u32),
+}
+
+pub fn threshold(env: &Env, meter_id: &String) -> Option<i128> {
+    env.storage()
+        .persistent()
+        .get(&AlertKey::SpendThreshold(meter_id.clone()))
+}
+
+// The meter's owner sets the threshold; 0 removes it.
+pub fn set_threshold(
+    env: &Env,
+    owner: &Address,
+    meter_id: &String,
+    threshold: i128,
+) -> Result<(), Error> {
+    owner.require_auth();
+    if ownership::owner(env, meter_id).as_ref() != Some(owner) {
+        return Err(Error::InvalidInput);
+    }
+    let key = AlertKey::SpendThreshold(meter_id.clone());
+    match threshold {
+        t if t < 0 => return Err(Error::InvalidConfig),
+        0 => env.storage().persistent().remove(&key),
+        _ => storage::write_persistent(env, &key, &threshold),
+    }
+    Ok(())
+}
+
+pub fn month_spend(env: &Env, meter_id: &String, period: u32) -> MonthlySpend {
+    env.storage()
+        .persistent()
+        .get(&AlertKey::MeterMonthSpend(meter_id.clone(), period))
+        .unwrap_or(MonthlySpend {
+            paid: 0,
+            consumed: 0,
+            exceeded: false,
+        })
+}
+
+// Whether the meter has crossed its threshold this month.
+pub fn is_exceeded(env: &Env, meter_id: &String) -> bool {
+    let period = billing::period_at(env.ledger().timestamp());
+    month_spend(env, meter_id, period).exceeded
+}
+
+// Adds to the month's totals and raises the alert the first time either
+// crosses the threshold. Meters without a threshold are not tracked.
+fn track(
+    env: &Env,
+    meter_id: &String,
+    period: u32,
+    paid: i128,
+    consumed: i128,
+) -> Result<(), Error> {
+    let Some(threshold) = threshold(env, meter_id) else {
+        return Ok(());
+    };
+    let mut spend = month_spend(env, meter_id, period);
+    spend.paid = math::add(spend.paid, paid)?;
+    spend.consumed = math::add(spend.consumed, consumed)?;
+    if !spend.exceeded && (spend.paid > threshold || spend.consumed > threshold) {
+        spend.exceeded = true;
+        env.events().publish(
+            (
+                Symbol::new(env, "budget_exceeded"),
+                meter_id.clone(),
+                period,
+            ),
+            (threshold, spend.paid, spend.consumed),
+        );
+    }
+    storage::write_persistent(
+        env,
+        &AlertKey::MeterMonthSpend(meter_id.clone(), period),
+        &spend,
+    );
+    Ok(())
+}
+
+pub fn on_payment(env: &Env, meter_id: &String, timestamp: u64, value: i128) -> Result<(), Error> {
+    track(env, meter_id, billing::period_at(timestamp), value, 0)
+}
+
+pub fn on_bill(env: &Env, meter_id: &String, period: u32, amount: i128) -> Result<(), Error> {
+    track(env, meter_id, period, 0, amount)
+}
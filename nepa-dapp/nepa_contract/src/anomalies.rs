@@ -0,0 +1,178 @@
+use soroban_sdk::{contracttype, Env, String, Symbol, Vec};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::math;
+use crate::storage;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+
+// Most anomalies kept per meter; the oldest are dropped first.
+pub const MAX_METER_ANOMALIES: u32 = 50;
+
+// Consumption more than `multiple_bps` / 10_000 times the meter's rolling
+// average, or less than its inverse, is flagged once `min_samples` values have
+// been seen. The average weighs roughly the last `window` values.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AnomalyConfig {
+    pub multiple_bps: u32,
+    pub window: u32,
+    pub min_samples: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AnomalyKind {
+    // Consumption per day between two readings, in register units.
+    Reading,
+    // NGN value of a payment.
+    Payment,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RollingAverage {
+    pub average: i128,
+    pub samples: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    // Index of the reading or payment in the meter's history.
+    pub index: u32,
+    pub value: i128,
+    pub average: i128,
+    pub flagged_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AnomalyKey {
+    AnomalyConfig,
+    ConsumptionAverage(String, AnomalyKind),
+    MeterAnomalies(String),
+    // Meters with anomalies awaiting a field audit.
+    FlaggedMeters,
+}
+
+pub fn read_config(env: &Env) -> Option<AnomalyConfig> {
+    env.storage().instance().get(&AnomalyKey::AnomalyConfig)
+}
+
+pub fn set_config(env: &Env, config: &AnomalyConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if i128::from(config.multiple_bps) <= BPS_DENOMINATOR || config.window == 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&AnomalyKey::AnomalyConfig, config);
+    Ok(())
+}
+
+pub fn average(env: &Env, meter_id: &String, kind: AnomalyKind) -> RollingAverage {
+    env.storage()
+        .persistent()
+        .get(&AnomalyKey::ConsumptionAverage(meter_id.clone(), kind))
+        .unwrap_or(RollingAverage {
+            average: 0,
+            samples: 0,
+        })
+}
+
+pub fn anomalies(env: &Env, meter_id: &String) -> Vec<Anomaly> {
+    env.storage()
+        .persistent()
+        .get(&AnomalyKey::MeterAnomalies(meter_id.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn flagged_meters(env: &Env) -> Vec<String> {
+    env.storage()
+        .persistent()
+        .get(&AnomalyKey::FlaggedMeters)
+        .unwrap_or(Vec::new(env))
+}
+
+fn deviates(config: &AnomalyConfig, value: i128, average: i128) -> Result<bool, Error> {
+    if average <= 0 {
+        return Ok(false);
+    }
+    let multiple = i128::from(config.multiple_bps);
+    let high = math::mul(value, BPS_DENOMINATOR)? > math::mul(average, multiple)?;
+    let low = math::mul(value, multiple)? < math::mul(average, BPS_DENOMINATOR)?;
+    Ok(high || low)
+}
+
+fn flag(env: &Env, meter_id: &String, anomaly: Anomaly) {
+    let mut list = anomalies(env, meter_id);
+    if list.len() >= MAX_METER_ANOMALIES {
+        list.pop_front();
+    }
+    list.push_back(anomaly.clone());
+    storage::write_persistent(env, &AnomalyKey::MeterAnomalies(meter_id.clone()), &list);
+
+    let mut meters = flagged_meters(env);
+    if !meters.contains(meter_id) {
+        meters.push_back(meter_id.clone());
+        storage::write_persistent(env, &AnomalyKey::FlaggedMeters, &meters);
+    }
+    env.events().publish(
+        (Symbol::new(env, "consumption_anomaly"), meter_id.clone()),
+        (anomaly.kind, anomaly.index, anomaly.value, anomaly.average),
+    );
+}
+
+// Checks `value` against the meter's average and folds it in. Anomalous values
+// are still accepted; they are only recorded for audit. Nothing is tracked
+// until a config is set.
+pub fn observe(env: &Env, meter_id: &String, kind: AnomalyKind, index: u32, value: i128) {
+    let Some(config) = read_config(env) else {
+        return;
+    };
+    let mut rolling = average(env, meter_id, kind);
+    let anomalous = rolling.samples >= config.min_samples
+        && deviates(&config, value, rolling.average).unwrap_or(false);
+    if anomalous {
+        flag(
+            env,
+            meter_id,
+            Anomaly {
+                kind,
+                index,
+                value,
+                average: rolling.average,
+                flagged_at: env.ledger().timestamp(),
+            },
+        );
+    }
+
+    let weight = i128::from(rolling.samples.min(config.window - 1)) + 1;
+    rolling.average += (value - rolling.average) / weight;
+    rolling.samples = rolling.samples.saturating_add(1);
+    storage::write_persistent(
+        env,
+        &AnomalyKey::ConsumptionAverage(meter_id.clone(), kind),
+        &rolling,
+    );
+}
+
+// The field-audit team clears a meter once it has been inspected.
+pub fn clear(env: &Env, meter_id: &String) -> Result<(), Error> {
+    admin::require_admin(env);
+    let mut meters = flagged_meters(env);
+    let position = meters.first_index_of(meter_id).ok_or(Error::InvalidState)?;
+    meters.remove(position);
+    storage::write_persistent(env, &AnomalyKey::FlaggedMeters, &meters);
+    env.storage()
+        .persistent()
+        .remove(&AnomalyKey::MeterAnomalies(meter_id.clone()));
+    env.events().publish(
+        (Symbol::new(env, "anomalies_cleared"), meter_id.clone()),
+        (),
+    );
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol, Vec};
+
+use crate::storage;
+
+// Most entries one `entries` call returns.
+pub const MAX_AUDIT_PAGE: u32 = 50;
+
+// Admin-sensitive operations, with what a reviewer needs to match them
+// against off-chain records.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AuditAction {
+    // rate id
+    RateChanged(String),
+    // rate id, NGN per kWh
+    EstimatedRateSet(String, i128),
+    FeedInTariffSet(String, i128),
+    // recipient, token, amount
+    Withdrawal(Address, Address, i128),
+    Refund(Address, Address, i128),
+    // role, holder
+    RoleGranted(Symbol, Address),
+    RoleRevoked(Symbol, Address),
+    // signers, threshold
+    SignersChanged(Vec<Address>, u32),
+}
+
+// Entries are numbered from 1 without gaps, so a reader can tell none were
+// skipped.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub action: AuditAction,
+    pub timestamp: u64,
+    pub ledger: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AuditKey {
+    NextAuditSeq,
+    LogEntry(u64),
+}
+
+// Sequence number of the latest entry, 0 while the log is empty.
+pub fn head(env: &Env) -> u64 {
+    let next: u64 = env
+        .storage()
+        .instance()
+        .get(&AuditKey::NextAuditSeq)
+        .unwrap_or(1);
+    next - 1
+}
+
+pub fn record(env: &Env, action: AuditAction) {
+    let seq = head(env) + 1;
+    env.storage()
+        .instance()
+        .set(&AuditKey::NextAuditSeq, &(seq + 1));
+    let entry = AuditEntry {
+        seq,
+        action,
+        timestamp: env.ledger().timestamp(),
+        ledger: env.ledger().sequence(),
+    };
+    storage::write_persistent(env, &AuditKey::LogEntry(seq), &entry);
+    env.events()
+        .publish((Symbol::new(env, "audit_entry"), seq), entry.action);
+}
+
+// Up to `limit` entries starting at `from_seq`, in order.
+pub fn entries(env: &Env, from_seq: u64, limit: u32) -> Vec<AuditEntry> {
+    let mut page = Vec::new(env);
+    let last = head(env);
+    let mut seq = from_seq.max(1);
+    while seq <= last && page.len() < limit.min(MAX_AUDIT_PAGE) {
+        if let Some(entry) = env.storage().persistent().get(&AuditKey::LogEntry(seq)) {
+            page.push_back(entry);
+        }
+        seq += 1;
+    }
+    page
+}
+
+pub fn role_change(env: &Env, role: &str, holder: &Address, granted: bool) {
+    let role = Symbol::new(env, role);
+    record(
+        env,
+        match granted {
+            true => AuditAction::RoleGranted(role, holder.clone()),
+            false => AuditAction::RoleRevoked(role, holder.clone()),
+        },
+    );
+}
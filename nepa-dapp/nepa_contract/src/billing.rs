@@ -0,0 +1,786 @@
+use soroban_sdk::{
+    contracttype, panic_with_error, Address, Env, FromVal, Map, String, Symbol, TryFromVal, Val,
+    Vec,
+};
+
+use crate::accounting;
+use crate::admin;
+use crate::alerts;
+use crate::audit::{self, AuditAction};
+use crate::bounds;
+use crate::dunning;
+use crate::errors::Error;
+use crate::green;
+use crate::math;
+use crate::meters;
+use crate::multisig;
+use crate::netmetering;
+use crate::oracle::{DataFeed, OracleManager, PriceFeed};
+use crate::periods;
+use crate::rollups;
+use crate::settlement;
+use crate::storage;
+use crate::subsidy;
+use crate::tariff::{self, RateKey, TariffOp, TouWindow, UtilityUsage};
+use crate::taxes::{self, LineItem, TaxKind};
+use crate::timelock;
+use crate::tokens;
+use crate::units::{self, Consumption, MeteredUnit};
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoundingMode {
+    Down,
+    Up,
+    // Halves round up.
+    Nearest,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoundingUnit {
+    // The smallest accounting unit: no rounding.
+    Exact,
+    Kobo,
+    Naira,
+}
+
+// How a region's final bill amounts are rounded.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoundingPolicy {
+    pub mode: RoundingMode,
+    pub unit: RoundingUnit,
+}
+
+impl RoundingPolicy {
+    pub fn apply(&self, amount: i128) -> Result<i128, Error> {
+        // NGN carries 7 decimals: a kobo is 10^5 units, a naira 10^7.
+        let step = match self.unit {
+            RoundingUnit::Exact => return Ok(amount),
+            RoundingUnit::Kobo => 100_000,
+            RoundingUnit::Naira => 10_000_000,
+        };
+        let remainder = amount.rem_euclid(step);
+        let down = math::sub(amount, remainder)?;
+        let round_up = match self.mode {
+            RoundingMode::Down => false,
+            RoundingMode::Up => remainder > 0,
+            RoundingMode::Nearest => remainder * 2 >= step,
+        };
+        if round_up {
+            math::add(down, step)
+        } else {
+            Ok(down)
+        }
+    }
+}
+
+// A meter's charge for one period, in NGN accounting units.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BillingRecord {
+    pub meter_id: String,
+    pub period: u32,
+    pub rate_id: String,
+    // Consumption billed, in `unit` (kWh unless the rate meters volume).
+    pub kwh: i128,
+    pub unit: MeteredUnit,
+    // Owed by the customer: the charge after subsidy, plus taxes and levies.
+    pub amount: i128,
+    pub subsidy: i128,
+    pub line_items: Vec<LineItem>,
+    // Scheme the subsidy is claimed under, empty when unsubsidised.
+    pub subsidy_scheme: String,
+    // Solar export credit netted against `amount`; the balance is debited the
+    // difference.
+    pub export_credit: i128,
+    // Billed at the region's estimated flat rate because no utility rate existed.
+    pub estimated: bool,
+    pub trued_up: bool,
+    // Set by the true-up: positive is a further debit, negative a credit.
+    pub adjustment: i128,
+    pub issued_at: u64,
+    // Set when the region's billing period closes; the bill is then final.
+    pub finalized: bool,
+    // Applied to `amount` at issue, and again by any true-up.
+    pub rounding: RoundingPolicy,
+    // What the charge was computed from, retaken by any true-up.
+    pub rate_snapshot: RateSnapshot,
+}
+
+// The full rate context of a bill, captured when it is written so later rate
+// or feed updates never change how the bill reads.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateSnapshot {
+    // Formula in force, tiers included; empty for an estimated bill.
+    pub formula: Vec<TariffOp>,
+    // When the formula was last changed; 0 for an estimated bill.
+    pub rate_updated: u64,
+    // NGN units per kWh of an estimated bill; 0 otherwise.
+    pub estimated_per_kwh: i128,
+    // Formula inputs the consumption was billed as, per time-of-use window.
+    pub inputs: Map<Symbol, i128>,
+    // Data feeds read by FeedAdjust terms, as they stood.
+    pub feeds: Map<String, DataFeed>,
+    // Payment price of each accepted token whose feed was usable.
+    pub fx_rates: Map<Address, PriceFeed>,
+    pub taken_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BillBreakdown {
+    // Energy charge after subsidy.
+    pub subtotal: i128,
+    pub taxes: i128,
+    pub levies: i128,
+    // Added to or taken off by the region's rounding policy.
+    pub rounding: i128,
+    pub total: i128,
+    pub export_credit: i128,
+    pub line_items: Vec<LineItem>,
+}
+
+// What the next invoice would look like for a hypothetical consumption.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvoicePreview {
+    pub meter_id: String,
+    pub period: u32,
+    pub rate_id: String,
+    pub kwh: i128,
+    pub charge: i128,
+    pub subsidy: i128,
+    // Taxes and levies together.
+    pub taxes: i128,
+    pub estimated: bool,
+    // Balance carried into the invoice; negative is credit.
+    pub balance: i128,
+    pub amount_due: i128,
+    // Amount due in each accepted token whose price feed is currently usable.
+    pub token_amounts: Map<Address, i128>,
+}
+
+// What one kWh costs under a rate at a given time, and where that came from.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EffectiveRate {
+    pub rate_id: String,
+    // Time-of-use window in force at the queried time.
+    pub window: TouWindow,
+    // NGN units charged for one kWh in that window.
+    pub per_kwh: i128,
+    // When the rate was last changed; 0 for an estimated rate.
+    pub rate_updated: u64,
+    // Data feed a FeedAdjust term prices against; empty when none.
+    pub feed_id: String,
+    pub feed_updated: u64,
+    pub feed_reliability_bps: u32,
+    // No utility rate was set, so the estimated flat rate applied.
+    pub fallback_used: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BillingKey {
+    Bill(String, u32),
+    EstimatedRate(String),
+    // Utility rate (region) a meter is billed under.
+    MeterRate(String),
+    // NGN owed on the meter; negative when the customer is in credit.
+    MeterBalance(String),
+    RegionRounding(String),
+}
+
+pub fn read_bill(env: &Env, meter_id: &String, period: u32) -> Option<BillingRecord> {
+    let stored: Val = env
+        .storage()
+        .persistent()
+        .get(&BillingKey::Bill(meter_id.clone(), period))?;
+    Some(upgrade_bill(env, &stored))
+}
+
+fn field<T: TryFromVal<Env, Val>>(env: &Env, fields: &Map<Symbol, Val>, name: &str) -> Option<T> {
+    fields
+        .get(Symbol::new(env, name))
+        .map(|value| T::from_val(env, &value))
+}
+
+// Bills stay in the layout they were issued in. Fields added since read as
+// nothing subsidised, itemised or credited, kWh, exact rounding, not final,
+// and an empty rate snapshot taken at 0.
+fn upgrade_bill(env: &Env, stored: &Val) -> BillingRecord {
+    let fields = Map::<Symbol, Val>::from_val(env, stored);
+    if fields.contains_key(Symbol::new(env, "rate_snapshot")) {
+        return BillingRecord::from_val(env, stored);
+    }
+    let required = |name: &str| fields.get_unchecked(Symbol::new(env, name));
+    BillingRecord {
+        meter_id: String::from_val(env, &required("meter_id")),
+        period: u32::from_val(env, &required("period")),
+        rate_id: String::from_val(env, &required("rate_id")),
+        kwh: i128::from_val(env, &required("kwh")),
+        unit: field(env, &fields, "unit").unwrap_or(MeteredUnit::Kwh),
+        amount: i128::from_val(env, &required("amount")),
+        subsidy: field(env, &fields, "subsidy").unwrap_or(0),
+        line_items: field(env, &fields, "line_items").unwrap_or(Vec::new(env)),
+        subsidy_scheme: field(env, &fields, "subsidy_scheme").unwrap_or(String::from_str(env, "")),
+        export_credit: field(env, &fields, "export_credit").unwrap_or(0),
+        estimated: bool::from_val(env, &required("estimated")),
+        trued_up: bool::from_val(env, &required("trued_up")),
+        adjustment: i128::from_val(env, &required("adjustment")),
+        issued_at: u64::from_val(env, &required("issued_at")),
+        finalized: field(env, &fields, "finalized").unwrap_or(false),
+        rounding: field(env, &fields, "rounding").unwrap_or(EXACT),
+        rate_snapshot: RateSnapshot {
+            formula: Vec::new(env),
+            rate_updated: 0,
+            estimated_per_kwh: 0,
+            inputs: Map::new(env),
+            feeds: Map::new(env),
+            fx_rates: Map::new(env),
+            taken_at: 0,
+        },
+    }
+}
+
+fn write_bill(env: &Env, bill: &BillingRecord) {
+    storage::write_persistent(
+        env,
+        &BillingKey::Bill(bill.meter_id.clone(), bill.period),
+        bill,
+    );
+}
+
+pub fn balance(env: &Env, meter_id: &String) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&BillingKey::MeterBalance(meter_id.clone()))
+        .unwrap_or(0)
+}
+
+// Debits (positive) or credits (negative) the meter's running balance.
+pub fn adjust_balance(env: &Env, meter_id: &String, delta: i128) {
+    let key = BillingKey::MeterBalance(meter_id.clone());
+    let updated = math::add(balance(env, meter_id), delta)
+        .unwrap_or_else(|error| panic_with_error!(env, error));
+    storage::write_persistent(env, &key, &updated);
+}
+
+// Regions without a policy, and unregistered rates, bill exact amounts.
+const EXACT: RoundingPolicy = RoundingPolicy {
+    mode: RoundingMode::Nearest,
+    unit: RoundingUnit::Exact,
+};
+
+pub fn rounding_policy(env: &Env, region: &String) -> RoundingPolicy {
+    env.storage()
+        .persistent()
+        .get(&BillingKey::RegionRounding(region.clone()))
+        .unwrap_or(EXACT)
+}
+
+pub fn set_rounding_policy(
+    env: &Env,
+    region: &String,
+    policy: &RoundingPolicy,
+) -> Result<(), Error> {
+    admin::require_admin(env);
+    if !tariff::regions(env).contains(region) {
+        return Err(Error::InvalidInput);
+    }
+    storage::write_persistent(env, &BillingKey::RegionRounding(region.clone()), policy);
+    env.events().publish(
+        (Symbol::new(env, "rounding_policy_set"), region.clone()),
+        policy.clone(),
+    );
+    Ok(())
+}
+
+fn rounding_for_rate(env: &Env, rate_id: &String) -> RoundingPolicy {
+    tariff::key_for_rate(env, rate_id).map_or(EXACT, |key| rounding_policy(env, &key.region))
+}
+
+// Rounds `net` by the policy and records any difference as a line item, so the
+// items still add up to what is charged. Returns the rounded amount.
+fn round_bill(
+    env: &Env,
+    policy: &RoundingPolicy,
+    net: i128,
+    line_items: &mut Vec<LineItem>,
+) -> Result<i128, Error> {
+    let amount = policy.apply(net)?;
+    let delta = math::sub(amount, net)?;
+    if delta != 0 {
+        line_items.push_back(LineItem {
+            name: Symbol::new(env, "rounding"),
+            kind: TaxKind::Rounding,
+            bps: 0,
+            amount: delta,
+        });
+    }
+    Ok(amount)
+}
+
+pub fn estimated_rate(env: &Env, rate_id: &String) -> Option<i128> {
+    env.storage()
+        .persistent()
+        .get(&BillingKey::EstimatedRate(rate_id.clone()))
+}
+
+// NGN units per kWh charged while the region has no utility rate.
+pub fn set_estimated_rate(env: &Env, rate_id: &String, per_kwh: i128) -> Result<(), Error> {
+    multisig::ensure_not_required(env)?;
+    timelock::ensure_not_required(env)?;
+    admin::require_admin(env);
+    apply_estimated_rate(env, rate_id, per_kwh)
+}
+
+pub fn validate_estimated_rate(env: &Env, per_kwh: i128) -> Result<(), Error> {
+    if per_kwh <= 0 {
+        return Err(Error::InvalidTariff);
+    }
+    bounds::rate(env, per_kwh)
+}
+
+// Stores the rate once authorised directly or through the timelock.
+pub fn apply_estimated_rate(env: &Env, rate_id: &String, per_kwh: i128) -> Result<(), Error> {
+    validate_estimated_rate(env, per_kwh)?;
+    storage::write_persistent(env, &BillingKey::EstimatedRate(rate_id.clone()), &per_kwh);
+    audit::record(env, AuditAction::EstimatedRateSet(rate_id.clone(), per_kwh));
+    Ok(())
+}
+
+// Charge for a period's total consumption; time-of-use formulas see it all in
+// the current window.
+pub fn charge(env: &Env, rate_id: &String, kwh: i128) -> Result<i128, Error> {
+    let inputs = tariff::usage_inputs(env, rate_id, &UtilityUsage::Total(kwh))?;
+    tariff::calculate(env, rate_id, &inputs)
+}
+
+// A bill's charge before it is written anywhere.
+pub struct Assessment {
+    pub gross: i128,
+    pub subsidy: i128,
+    pub scheme_id: Option<String>,
+    pub line_items: Vec<LineItem>,
+    pub estimated: bool,
+}
+
+impl Assessment {
+    pub fn subtotal(&self) -> Result<i128, Error> {
+        math::sub(self.gross, self.subsidy)
+    }
+
+    pub fn taxes(&self) -> Result<i128, Error> {
+        self.line_items
+            .iter()
+            .try_fold(0, |sum, item| math::add(sum, item.amount))
+    }
+
+    pub fn net(&self) -> Result<i128, Error> {
+        math::add(self.subtotal()?, self.taxes()?)
+    }
+}
+
+// Prices `kwh` for the meter, applies its subsidy scheme, if any, and then
+// taxes and levies on what remains.
+pub fn assess(
+    env: &Env,
+    meter_id: &String,
+    rate_id: &String,
+    kwh: i128,
+) -> Result<Assessment, Error> {
+    let (gross, estimated) = price(env, rate_id, kwh)?;
+    let scheme_id = subsidy::meter_scheme(env, meter_id);
+    let subsidy = match scheme_id
+        .as_ref()
+        .and_then(|id| subsidy::read_scheme(env, id))
+    {
+        Some(scheme) => subsidy::amount(env, &scheme, rate_id, kwh, gross)?,
+        None => 0,
+    };
+    Ok(Assessment {
+        gross,
+        subsidy,
+        scheme_id,
+        line_items: taxes::line_items(env, math::sub(gross, subsidy)?)?,
+        estimated,
+    })
+}
+
+// Records the rate, inputs and prices `kwh` is charged at under `rate_id`
+// right now.
+fn snapshot(env: &Env, rate_id: &String, kwh: i128) -> Result<RateSnapshot, Error> {
+    let mut snapshot = RateSnapshot {
+        formula: Vec::new(env),
+        rate_updated: 0,
+        estimated_per_kwh: 0,
+        inputs: tariff::usage_inputs(env, rate_id, &UtilityUsage::Total(kwh))?,
+        feeds: Map::new(env),
+        fx_rates: Map::new(env),
+        taken_at: env.ledger().timestamp(),
+    };
+    match tariff::read_rate(env, rate_id) {
+        Some(rate) => {
+            for op in rate.formula.iter() {
+                if let TariffOp::FeedAdjust(feed_id, _, _) = op {
+                    if let Some(feed) = OracleManager::get_data_feed(env, &feed_id) {
+                        snapshot.feeds.set(feed_id, feed);
+                    }
+                }
+            }
+            snapshot.formula = rate.formula;
+            snapshot.rate_updated = rate.last_updated;
+        }
+        None => snapshot.estimated_per_kwh = estimated_rate(env, rate_id).unwrap_or(0),
+    }
+    for token in tokens::list(env).iter() {
+        let Some(config) = tokens::read_config(env, &token) else {
+            continue;
+        };
+        if let Ok(feed) = OracleManager::get_payment_price(env, &config.oracle_pair) {
+            snapshot.fx_rates.set(token, feed);
+        }
+    }
+    Ok(snapshot)
+}
+
+// Charge for `kwh` under `rate_id`, falling back to the region's estimated
+// flat rate. The flag is set when the estimate was used.
+pub fn price(env: &Env, rate_id: &String, kwh: i128) -> Result<(i128, bool), Error> {
+    match tariff::read_rate(env, rate_id) {
+        Some(_) => Ok((charge(env, rate_id, kwh)?, false)),
+        None => {
+            let per_kwh = estimated_rate(env, rate_id).ok_or(Error::RateNotFound)?;
+            Ok((math::mul(kwh, per_kwh)?, true))
+        }
+    }
+}
+
+// The rate registered under `key` as it applies at `timestamp`, with the rate,
+// feed and fallback it was resolved through.
+pub fn effective_rate(env: &Env, key: &RateKey, timestamp: u64) -> Result<EffectiveRate, Error> {
+    tariff::ensure_registered(env, key)?;
+    let rate_id = tariff::rate_id_for(env, key)?;
+    let window = tariff::window_at(env, &rate_id, timestamp);
+    let mut effective = EffectiveRate {
+        rate_id: rate_id.clone(),
+        window,
+        per_kwh: 0,
+        rate_updated: 0,
+        feed_id: String::from_str(env, ""),
+        feed_updated: 0,
+        feed_reliability_bps: 0,
+        fallback_used: false,
+    };
+
+    let Some(rate) = tariff::read_rate(env, &rate_id) else {
+        effective.per_kwh = estimated_rate(env, &rate_id).ok_or(Error::RateNotFound)?;
+        effective.fallback_used = true;
+        return Ok(effective);
+    };
+    let mut one_kwh = Map::new(env);
+    one_kwh.set(window, 1);
+    let inputs = tariff::usage_inputs(env, &rate_id, &UtilityUsage::ByWindow(one_kwh))?;
+    effective.per_kwh = tariff::evaluate(env, &rate.formula, &inputs)?;
+    effective.rate_updated = rate.last_updated;
+
+    let feed_id = rate.formula.iter().find_map(|op| match op {
+        TariffOp::FeedAdjust(feed_id, _, _) => Some(feed_id),
+        _ => None,
+    });
+    if let Some(feed_id) = feed_id {
+        let feed = OracleManager::get_data_feed(env, &feed_id).ok_or(Error::PriceFeedNotFound)?;
+        effective.feed_updated = feed.last_updated;
+        effective.feed_reliability_bps =
+            OracleManager::get_data_feed_reliability(env, &feed_id).score_bps;
+        effective.feed_id = feed_id;
+    }
+    Ok(effective)
+}
+
+// Rate assigned to the meter, as it was assigned.
+pub fn assigned_rate(env: &Env, meter_id: &String) -> Option<String> {
+    env.storage()
+        .persistent()
+        .get(&BillingKey::MeterRate(meter_id.clone()))
+}
+
+// Rate the meter is billed under now: its assigned rate, for the band it has
+// since been reclassified into.
+pub fn meter_rate(env: &Env, meter_id: &String) -> Option<String> {
+    let assigned = assigned_rate(env, meter_id)?;
+    Some(meters::rate_in_force(env, meter_id, assigned))
+}
+
+// Band of a meter with a rate: its current band from its metadata, else the
+// band of the typed rate key its rate was registered under.
+pub fn meter_band(env: &Env, meter_id: &String) -> Option<String> {
+    let assigned = assigned_rate(env, meter_id)?;
+    if let Some(band) = meters::band_at(env, meter_id, env.ledger().timestamp()) {
+        return Some(band);
+    }
+    Some(tariff::key_for_rate(env, &assigned)?.band)
+}
+
+pub fn assign_rate(env: &Env, meter_id: &String, rate_id: &String) {
+    admin::require_admin(env);
+    storage::write_persistent(env, &BillingKey::MeterRate(meter_id.clone()), rate_id);
+    env.events().publish(
+        (Symbol::new(env, "meter_rate_assigned"), meter_id.clone()),
+        rate_id.clone(),
+    );
+}
+
+// YYYYMM of the UTC calendar month containing `timestamp`.
+pub fn period_at(timestamp: u64) -> u32 {
+    // Days-to-civil conversion (Howard Hinnant), valid for any post-1970 date.
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year * 100 + month) as u32
+}
+
+// Runs the billing computation for the period in progress without writing
+// anything, so customers can see what a given consumption would cost.
+pub fn preview(env: &Env, meter_id: &String, assumed_kwh: i128) -> Result<InvoicePreview, Error> {
+    bounds::kwh(env, assumed_kwh)?;
+    let rate_id = meter_rate(env, meter_id).ok_or(Error::RateNotFound)?;
+    let assessment = assess(env, meter_id, &rate_id, assumed_kwh)?;
+    let balance = balance(env, meter_id);
+    let gross_due = assessment.net()?;
+    let net = math::sub(gross_due, netmetering::credit(env, meter_id).min(gross_due))?;
+    let amount_due = math::add(balance, net)?.max(0);
+
+    let mut token_amounts = Map::new(env);
+    for token in tokens::list(env).iter() {
+        let Some(config) = tokens::read_config(env, &token) else {
+            continue;
+        };
+        if let Ok(feed) = OracleManager::get_payment_price(env, &config.oracle_pair) {
+            let amount = accounting::denormalize(amount_due, config.decimals, &feed)?;
+            token_amounts.set(token, amount);
+        }
+    }
+
+    Ok(InvoicePreview {
+        meter_id: meter_id.clone(),
+        period: period_at(env.ledger().timestamp()),
+        rate_id,
+        kwh: assumed_kwh,
+        charge: assessment.gross,
+        subsidy: assessment.subsidy,
+        taxes: assessment.taxes()?,
+        estimated: assessment.estimated,
+        balance,
+        amount_due,
+        token_amounts,
+    })
+}
+
+pub fn issue(
+    env: &Env,
+    meter_id: &String,
+    period: u32,
+    rate_id: &String,
+    kwh: i128,
+) -> Result<BillingRecord, Error> {
+    admin::require_admin(env);
+    bill_usage(env, meter_id, period, rate_id, kwh)
+}
+
+// As `issue`, for a consumption in any unit convertible to the rate's.
+pub fn issue_metered(
+    env: &Env,
+    meter_id: &String,
+    period: u32,
+    rate_id: &String,
+    consumption: &Consumption,
+) -> Result<BillingRecord, Error> {
+    admin::require_admin(env);
+    let quantity = units::convert(
+        consumption.quantity,
+        consumption.unit,
+        tariff::rate_unit(env, rate_id),
+    )?;
+    bill_usage(env, meter_id, period, rate_id, quantity)
+}
+
+// Writes the period's bill for a consumption the caller has already vouched for.
+pub fn bill_usage(
+    env: &Env,
+    meter_id: &String,
+    period: u32,
+    rate_id: &String,
+    kwh: i128,
+) -> Result<BillingRecord, Error> {
+    settlement::validate_period(period)?;
+    bounds::kwh(env, kwh)?;
+    periods::ensure_open(env, rate_id, period)?;
+    if read_bill(env, meter_id, period).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+
+    let assessment = assess(env, meter_id, rate_id, kwh)?;
+    let subsidy_scheme = assessment
+        .scheme_id
+        .clone()
+        .unwrap_or(String::from_str(env, ""));
+    if assessment.subsidy > 0 {
+        subsidy::record(env, &subsidy_scheme, period, assessment.subsidy)?;
+    }
+
+    let rounding = rounding_for_rate(env, rate_id);
+    let mut line_items = assessment.line_items.clone();
+    let amount = round_bill(env, &rounding, assessment.net()?, &mut line_items)?;
+    let mut bill = BillingRecord {
+        meter_id: meter_id.clone(),
+        period,
+        rate_id: rate_id.clone(),
+        kwh,
+        unit: tariff::rate_unit(env, rate_id),
+        amount,
+        subsidy: assessment.subsidy,
+        line_items,
+        subsidy_scheme,
+        export_credit: 0,
+        estimated: assessment.estimated,
+        trued_up: false,
+        adjustment: 0,
+        issued_at: env.ledger().timestamp(),
+        finalized: false,
+        rounding,
+        rate_snapshot: snapshot(env, rate_id, kwh)?,
+    };
+    bill.export_credit = netmetering::apply_credit(env, meter_id, bill.amount);
+    write_bill(env, &bill);
+    adjust_balance(env, meter_id, math::sub(bill.amount, bill.export_credit)?);
+    dunning::refresh_debt_flag(env, meter_id);
+    alerts::on_bill(env, meter_id, period, bill.amount)?;
+    rollups::on_bill(env, meter_id, rate_id, period, kwh)?;
+    periods::on_bill(env, meter_id, rate_id, period);
+    green::on_bill(env, meter_id, rate_id, bill.unit, kwh);
+
+    env.events().publish(
+        (Symbol::new(env, "bill_issued"), meter_id.clone(), period),
+        (bill.amount, bill.subsidy, bill.estimated),
+    );
+    Ok(bill)
+}
+
+// Re-bills an estimated period at the real rate once one exists and books the
+// difference against the meter's balance.
+pub fn true_up(
+    env: &Env,
+    meter_id: &String,
+    period: u32,
+    actual_kwh: i128,
+) -> Result<BillingRecord, Error> {
+    admin::require_admin(env);
+    bounds::kwh(env, actual_kwh)?;
+    let mut bill = read_bill(env, meter_id, period).ok_or(Error::InvalidInput)?;
+    if !bill.estimated || bill.trued_up || bill.finalized {
+        return Err(Error::InvalidState);
+    }
+    periods::ensure_open(env, &bill.rate_id, period)?;
+
+    let gross = charge(env, &bill.rate_id, actual_kwh)?;
+    // The subsidy is re-assessed under the scheme the bill was issued with.
+    let scheme = subsidy::read_scheme(env, &bill.subsidy_scheme);
+    let subsidy = match scheme {
+        Some(scheme) => subsidy::amount(env, &scheme, &bill.rate_id, actual_kwh, gross)?,
+        None => 0,
+    };
+    if subsidy != bill.subsidy {
+        subsidy::record(
+            env,
+            &bill.subsidy_scheme,
+            period,
+            math::sub(subsidy, bill.subsidy)?,
+        )?;
+    }
+
+    let subtotal = math::sub(gross, subsidy)?;
+    let mut charged = bill.line_items.clone();
+    if let Some(position) = charged
+        .iter()
+        .position(|item| item.kind == TaxKind::Rounding)
+    {
+        charged.remove(position as u32);
+    }
+    let mut line_items = taxes::reprice(env, &charged, subtotal)?;
+    let net = line_items
+        .iter()
+        .try_fold(subtotal, |sum, item| math::add(sum, item.amount))?;
+    let amount = round_bill(env, &bill.rounding, net, &mut line_items)?;
+
+    rollups::on_bill(
+        env,
+        meter_id,
+        &bill.rate_id,
+        period,
+        math::sub(actual_kwh, bill.kwh)?,
+    )?;
+    green::on_bill(
+        env,
+        meter_id,
+        &bill.rate_id,
+        bill.unit,
+        actual_kwh - bill.kwh,
+    );
+    bill.adjustment = math::sub(amount, bill.amount)?;
+    bill.amount = amount;
+    bill.subsidy = subsidy;
+    bill.line_items = line_items;
+    bill.kwh = actual_kwh;
+    bill.trued_up = true;
+    bill.rate_snapshot = snapshot(env, &bill.rate_id, actual_kwh)?;
+    write_bill(env, &bill);
+    adjust_balance(env, meter_id, bill.adjustment);
+
+    env.events().publish(
+        (Symbol::new(env, "bill_trued_up"), meter_id.clone(), period),
+        bill.adjustment,
+    );
+    Ok(bill)
+}
+
+// Marks the period's bill final and returns its amount, if the meter has one.
+pub fn finalize(env: &Env, meter_id: &String, period: u32) -> Option<i128> {
+    let mut bill = read_bill(env, meter_id, period)?;
+    bill.finalized = true;
+    write_bill(env, &bill);
+    Some(bill.amount)
+}
+
+pub fn breakdown(
+    env: &Env,
+    meter_id: &String,
+    period: u32,
+) -> Result<Option<BillBreakdown>, Error> {
+    let Some(bill) = read_bill(env, meter_id, period) else {
+        return Ok(None);
+    };
+    let taxes = taxes::sum(&bill.line_items, TaxKind::Tax)?;
+    let levies = taxes::sum(&bill.line_items, TaxKind::Levy)?;
+    let rounding = taxes::sum(&bill.line_items, TaxKind::Rounding)?;
+    let charges = math::add(math::add(taxes, levies)?, rounding)?;
+    Ok(Some(BillBreakdown {
+        subtotal: math::sub(bill.amount, charges)?,
+        taxes,
+        levies,
+        rounding,
+        total: bill.amount,
+        export_credit: bill.export_credit,
+        line_items: bill.line_items,
+    }))
+}
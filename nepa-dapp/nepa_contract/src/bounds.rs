@@ -0,0 +1,103 @@
+use soroban_sdk::{contracttype, Env, Symbol, Vec};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::tariff::TariffOp;
+
+// Token and feed decimals beyond this cannot be scaled within i128.
+pub const MAX_DECIMALS: u32 = 18;
+
+// Admin-set ceilings on numeric inputs. Zero leaves the quantity unbounded.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InputBounds {
+    // Token units in one payment.
+    pub max_amount: i128,
+    // kWh in one bill, reading or export.
+    pub max_kwh: i128,
+    // Feed price, in its own decimals.
+    pub max_price: i128,
+    // NGN units per kWh in a rate.
+    pub max_rate: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BoundsKey {
+    InputBounds,
+}
+
+pub fn read(env: &Env) -> InputBounds {
+    env.storage()
+        .instance()
+        .get(&BoundsKey::InputBounds)
+        .unwrap_or(InputBounds {
+            max_amount: 0,
+            max_kwh: 0,
+            max_price: 0,
+            max_rate: 0,
+        })
+}
+
+pub fn set(env: &Env, bounds: &InputBounds) -> Result<(), Error> {
+    admin::require_admin(env);
+    if bounds.max_amount < 0 || bounds.max_kwh < 0 || bounds.max_price < 0 || bounds.max_rate < 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&BoundsKey::InputBounds, bounds);
+    env.events()
+        .publish((Symbol::new(env, "input_bounds_set"),), bounds.clone());
+    Ok(())
+}
+
+fn within(value: i128, max: i128) -> Result<(), Error> {
+    if value < 0 {
+        return Err(Error::InvalidInput);
+    }
+    if max > 0 && value > max {
+        return Err(Error::OutOfBounds);
+    }
+    Ok(())
+}
+
+pub fn amount(env: &Env, amount: i128) -> Result<(), Error> {
+    within(amount, read(env).max_amount)
+}
+
+pub fn kwh(env: &Env, kwh: i128) -> Result<(), Error> {
+    within(kwh, read(env).max_kwh)
+}
+
+pub fn price(env: &Env, price: i128, decimals: u32) -> Result<(), Error> {
+    self::decimals(decimals)?;
+    within(price, read(env).max_price)
+}
+
+pub fn rate(env: &Env, rate: i128) -> Result<(), Error> {
+    within(rate, read(env).max_rate)
+}
+
+// Checks every per-kWh rate a formula charges.
+pub fn formula(env: &Env, formula: &Vec<TariffOp>) -> Result<(), Error> {
+    for op in formula.iter() {
+        match op {
+            TariffOp::PerUnit(_, per_unit) => rate(env, per_unit)?,
+            TariffOp::Tiered(_, tiers) => {
+                for tier in tiers.iter() {
+                    rate(env, tier.rate)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+pub fn decimals(decimals: u32) -> Result<(), Error> {
+    if decimals > MAX_DECIMALS {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use soroban_sdk::{contracttype, Address, BytesN, Env, String, Symbol};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::storage;
+
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+const HOUR_SECONDS: u64 = 60 * 60;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OracleUsage {
+    // Day number (timestamp / 86_400) `updates_today` counts.
+    pub day: u64,
+    pub updates_today: u32,
+    pub total_updates: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OracleCostStats {
+    // 0 when the feed or caller has no budget.
+    pub daily_limit: u32,
+    pub updates_today: u32,
+    pub total_updates: u64,
+}
+
+// Signed updates made with one reporter key, across the feeds it reports.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReporterStats {
+    // 0 while reporters are not rate limited.
+    pub hourly_limit: u32,
+    // Hour number (timestamp / 3_600) `updates_this_hour` counts.
+    pub hour: u64,
+    pub updates_this_hour: u32,
+    pub total_updates: u64,
+}
+
+// Daily update budgets, set per feed and per relaying caller. A feed or caller
+// without a budget is not limited.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BudgetKey {
+    FeedBudget(String),
+    CallerBudget(Address),
+    FeedUsage(String),
+    CallerUsage(Address),
+    // Kept in instance storage: one hourly cap shared by every reporter key.
+    ReporterHourlyLimit,
+    ReporterUsage(BytesN<32>),
+}
+
+fn read_limit(env: &Env, key: &BudgetKey) -> u32 {
+    env.storage().persistent().get(key).unwrap_or(0)
+}
+
+fn write_limit(env: &Env, key: &BudgetKey, daily_limit: u32) {
+    admin::require_admin(env);
+    if daily_limit == 0 {
+        env.storage().persistent().remove(key);
+    } else {
+        storage::write_persistent(env, key, &daily_limit);
+    }
+}
+
+pub fn set_feed_budget(env: &Env, feed_id: &String, daily_limit: u32) {
+    write_limit(env, &BudgetKey::FeedBudget(feed_id.clone()), daily_limit);
+}
+
+pub fn set_caller_budget(env: &Env, caller: &Address, daily_limit: u32) {
+    write_limit(env, &BudgetKey::CallerBudget(caller.clone()), daily_limit);
+}
+
+// Usage as of today: yesterday's count no longer applies.
+fn read_usage(env: &Env, key: &BudgetKey) -> OracleUsage {
+    let today = env.ledger().timestamp() / DAY_SECONDS;
+    let mut usage = env.storage().persistent().get(key).unwrap_or(OracleUsage {
+        day: today,
+        updates_today: 0,
+        total_updates: 0,
+    });
+    if usage.day != today {
+        usage.day = today;
+        usage.updates_today = 0;
+    }
+    usage
+}
+
+fn charge(env: &Env, usage_key: &BudgetKey, limit_key: &BudgetKey) -> Result<(), Error> {
+    let limit = read_limit(env, limit_key);
+    let mut usage = read_usage(env, usage_key);
+    if limit > 0 && usage.updates_today >= limit {
+        return Err(Error::BudgetExceeded);
+    }
+    usage.updates_today = usage
+        .updates_today
+        .checked_add(1)
+        .ok_or(Error::ArithmeticOverflow)?;
+    usage.total_updates = usage
+        .total_updates
+        .checked_add(1)
+        .ok_or(Error::ArithmeticOverflow)?;
+    storage::write_persistent(env, usage_key, &usage);
+    Ok(())
+}
+
+// Counts one update against the feed's budget.
+pub fn charge_feed(env: &Env, feed_id: &String) -> Result<(), Error> {
+    charge(
+        env,
+        &BudgetKey::FeedUsage(feed_id.clone()),
+        &BudgetKey::FeedBudget(feed_id.clone()),
+    )
+}
+
+// Counts one relayed update against the caller's budget.
+pub fn charge_caller(env: &Env, caller: &Address) -> Result<(), Error> {
+    charge(
+        env,
+        &BudgetKey::CallerUsage(caller.clone()),
+        &BudgetKey::CallerBudget(caller.clone()),
+    )
+}
+
+fn stats(env: &Env, usage_key: &BudgetKey, limit_key: &BudgetKey) -> OracleCostStats {
+    let usage = read_usage(env, usage_key);
+    OracleCostStats {
+        daily_limit: read_limit(env, limit_key),
+        updates_today: usage.updates_today,
+        total_updates: usage.total_updates,
+    }
+}
+
+pub fn feed_stats(env: &Env, feed_id: &String) -> OracleCostStats {
+    stats(
+        env,
+        &BudgetKey::FeedUsage(feed_id.clone()),
+        &BudgetKey::FeedBudget(feed_id.clone()),
+    )
+}
+
+pub fn caller_stats(env: &Env, caller: &Address) -> OracleCostStats {
+    stats(
+        env,
+        &BudgetKey::CallerUsage(caller.clone()),
+        &BudgetKey::CallerBudget(caller.clone()),
+    )
+}
+
+fn reporter_limit(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&BudgetKey::ReporterHourlyLimit)
+        .unwrap_or(0)
+}
+
+// Caps the signed updates any one reporter key may make per hour, so a
+// compromised key cannot churn prices; 0 removes the cap.
+pub fn set_reporter_limit(env: &Env, hourly_limit: u32) {
+    admin::require_admin(env);
+    env.storage()
+        .instance()
+        .set(&BudgetKey::ReporterHourlyLimit, &hourly_limit);
+    env.events()
+        .publish((Symbol::new(env, "reporter_limit_set"),), hourly_limit);
+}
+
+// Usage as of this hour: the previous hour's count no longer applies.
+pub fn reporter_stats(env: &Env, public_key: &BytesN<32>) -> ReporterStats {
+    let hour = env.ledger().timestamp() / HOUR_SECONDS;
+    let mut stats = env
+        .storage()
+        .persistent()
+        .get(&BudgetKey::ReporterUsage(public_key.clone()))
+        .unwrap_or(ReporterStats {
+            hourly_limit: 0,
+            hour,
+            updates_this_hour: 0,
+            total_updates: 0,
+        });
+    if stats.hour != hour {
+        stats.hour = hour;
+        stats.updates_this_hour = 0;
+    }
+    stats.hourly_limit = reporter_limit(env);
+    stats
+}
+
+// Counts one signed update against the reporter key's hourly cap.
+pub fn charge_reporter(env: &Env, public_key: &BytesN<32>) -> Result<(), Error> {
+    let mut stats = reporter_stats(env, public_key);
+    if stats.hourly_limit > 0 && stats.updates_this_hour >= stats.hourly_limit {
+        return Err(Error::BudgetExceeded);
+    }
+    stats.updates_this_hour = stats
+        .updates_this_hour
+        .checked_add(1)
+        .ok_or(Error::ArithmeticOverflow)?;
+    stats.total_updates = stats
+        .total_updates
+        .checked_add(1)
+        .ok_or(Error::ArithmeticOverflow)?;
+    storage::write_persistent(env, &BudgetKey::ReporterUsage(public_key.clone()), &stats);
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::billing;
+use crate::bounds;
+use crate::errors::Error;
+use crate::math;
+use crate::settlement;
+use crate::storage;
+use crate::tariff;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+
+// What happens to block kWh the estate did not draw in a period.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UnusedBlockPolicy {
+    // Paid for and lost.
+    Forfeit,
+    // Added to next period's block.
+    RollOver,
+    // Credited back at this share (bps) of the block rate.
+    Refund(u32),
+}
+
+// Negotiated terms between the provider and an estate. Charges are booked
+// against the balance of the agreement id, which the estate pays like a meter.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CapacityTerms {
+    pub estate: Address,
+    pub block_kwh: i128,
+    // NGN units per block kWh.
+    pub block_rate: i128,
+    // Utility rate overage is billed at.
+    pub spot_rate_id: String,
+    pub unused_policy: UnusedBlockPolicy,
+    // First and last period (YYYYMM) the commitment covers.
+    pub start_period: u32,
+    pub end_period: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CapacityAgreement {
+    pub terms: CapacityTerms,
+    // Rolled-over kWh available to the next period.
+    pub carried_kwh: i128,
+    // Most recent period closed, 0 before the first.
+    pub last_closed: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CapacityStatement {
+    pub drawn_kwh: i128,
+    pub carried_in_kwh: i128,
+    pub block_charge: i128,
+    pub overage_kwh: i128,
+    pub overage_charge: i128,
+    pub unused_kwh: i128,
+    pub refund: i128,
+    pub closed: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CapacityKey {
+    Agreement(String),
+    Statement(String, u32),
+}
+
+pub fn read_agreement(env: &Env, agreement_id: &String) -> Option<CapacityAgreement> {
+    env.storage()
+        .persistent()
+        .get(&CapacityKey::Agreement(agreement_id.clone()))
+}
+
+pub fn read_statement(env: &Env, agreement_id: &String, period: u32) -> CapacityStatement {
+    env.storage()
+        .persistent()
+        .get(&CapacityKey::Statement(agreement_id.clone(), period))
+        .unwrap_or(CapacityStatement {
+            drawn_kwh: 0,
+            carried_in_kwh: 0,
+            block_charge: 0,
+            overage_kwh: 0,
+            overage_charge: 0,
+            unused_kwh: 0,
+            refund: 0,
+            closed: false,
+        })
+}
+
+fn write_statement(env: &Env, agreement_id: &String, period: u32, statement: &CapacityStatement) {
+    storage::write_persistent(
+        env,
+        &CapacityKey::Statement(agreement_id.clone(), period),
+        statement,
+    );
+}
+
+// Both sides sign the agreement.
+pub fn create(env: &Env, agreement_id: &String, terms: &CapacityTerms) -> Result<(), Error> {
+    admin::require_admin(env);
+    terms.estate.require_auth();
+    if read_agreement(env, agreement_id).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+    settlement::validate_period(terms.start_period)?;
+    settlement::validate_period(terms.end_period)?;
+    let valid_refund = match terms.unused_policy {
+        UnusedBlockPolicy::Refund(bps) => bps as i128 <= BPS_DENOMINATOR,
+        _ => true,
+    };
+    if terms.block_kwh <= 0
+        || terms.block_rate <= 0
+        || terms.end_period < terms.start_period
+        || !valid_refund
+    {
+        return Err(Error::InvalidConfig);
+    }
+    if tariff::read_rate(env, &terms.spot_rate_id).is_none() {
+        return Err(Error::RateNotFound);
+    }
+
+    let agreement = CapacityAgreement {
+        terms: terms.clone(),
+        carried_kwh: 0,
+        last_closed: 0,
+    };
+    storage::write_persistent(
+        env,
+        &CapacityKey::Agreement(agreement_id.clone()),
+        &agreement,
+    );
+    env.events().publish(
+        (Symbol::new(env, "capacity_agreed"), agreement_id.clone()),
+        (terms.estate.clone(), terms.block_kwh, terms.block_rate),
+    );
+    Ok(())
+}
+
+fn open_period(agreement: &CapacityAgreement, period: u32) -> Result<(), Error> {
+    settlement::validate_period(period)?;
+    let terms = &agreement.terms;
+    if period < terms.start_period || period > terms.end_period || period <= agreement.last_closed {
+        return Err(Error::InvalidState);
+    }
+    Ok(())
+}
+
+// Adds metered consumption to the period's drawdown.
+pub fn record_drawdown(
+    env: &Env,
+    agreement_id: &String,
+    period: u32,
+    kwh: i128,
+) -> Result<CapacityStatement, Error> {
+    admin::require_admin(env);
+    let agreement = read_agreement(env, agreement_id).ok_or(Error::InvalidInput)?;
+    open_period(&agreement, period)?;
+    if kwh <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    bounds::kwh(env, kwh)?;
+
+    let mut statement = read_statement(env, agreement_id, period);
+    statement.drawn_kwh = math::add(statement.drawn_kwh, kwh)?;
+    write_statement(env, agreement_id, period, &statement);
+    Ok(statement)
+}
+
+// Bills the block, any overage at spot tariff, and settles unused kWh per the
+// agreement. Periods close in order.
+pub fn close_period(
+    env: &Env,
+    agreement_id: &String,
+    period: u32,
+) -> Result<CapacityStatement, Error> {
+    admin::require_admin(env);
+    let mut agreement = read_agreement(env, agreement_id).ok_or(Error::InvalidInput)?;
+    open_period(&agreement, period)?;
+    let terms = agreement.terms.clone();
+
+    let mut statement = read_statement(env, agreement_id, period);
+    let available = math::add(terms.block_kwh, agreement.carried_kwh)?;
+    statement.carried_in_kwh = agreement.carried_kwh;
+    statement.block_charge = math::mul(terms.block_kwh, terms.block_rate)?;
+    statement.overage_kwh = math::sub(statement.drawn_kwh, available)?.max(0);
+    statement.unused_kwh = math::sub(available, statement.drawn_kwh)?.max(0);
+
+    if statement.overage_kwh > 0 {
+        statement.overage_charge =
+            billing::charge(env, &terms.spot_rate_id, statement.overage_kwh)?;
+    }
+
+    agreement.carried_kwh = 0;
+    match terms.unused_policy {
+        UnusedBlockPolicy::Forfeit => {}
+        UnusedBlockPolicy::RollOver => agreement.carried_kwh = statement.unused_kwh,
+        UnusedBlockPolicy::Refund(bps) => {
+            let unused_charge = math::mul(statement.unused_kwh, terms.block_rate)?;
+            statement.refund = accounting::apply_bps(unused_charge, bps as i128)?;
+        }
+    }
+    statement.closed = true;
+    agreement.last_closed = period;
+
+    let charged = math::sub(
+        math::add(statement.block_charge, statement.overage_charge)?,
+        statement.refund,
+    )?;
+    write_statement(env, agreement_id, period, &statement);
+    storage::write_persistent(
+        env,
+        &CapacityKey::Agreement(agreement_id.clone()),
+        &agreement,
+    );
+    billing::adjust_balance(env, agreement_id, charged);
+
+    env.events().publish(
+        (
+            Symbol::new(env, "capacity_period_closed"),
+            agreement_id.clone(),
+            period,
+        ),
+        (
+            statement.drawn_kwh,
+            statement.overage_kwh,
+            statement.unused_kwh,
+        ),
+    );
+    Ok(statement)
+}
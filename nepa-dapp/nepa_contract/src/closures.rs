@@ -0,0 +1,124 @@
+use soroban_sdk::{contracttype, token, Address, Env, String, Symbol};
+
+use crate::accounting::{self, MeterSummary};
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::billing;
+use crate::credits;
+use crate::deposits;
+use crate::disputes;
+use crate::errors::Error;
+use crate::guard;
+use crate::invariants;
+use crate::maintenance;
+use crate::ownership;
+use crate::peg;
+use crate::portability;
+use crate::storage;
+use crate::tokens;
+
+// What a meter's account looked like when it was closed. Its payment history
+// and bills stay where they are; this is the record kept on top of them.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClosedAccount {
+    pub meter_id: String,
+    pub summary: MeterSummary,
+    // NGN of prepaid credit paid back, and the token amount it came to.
+    pub credit_refunded: i128,
+    pub credit_payout: i128,
+    // Token amount of the security deposit returned.
+    pub deposit_refunded: i128,
+    pub closed_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ClosureKey {
+    ClosedAccount(String),
+}
+
+pub fn closed(env: &Env, meter_id: &String) -> Option<ClosedAccount> {
+    env.storage()
+        .persistent()
+        .get(&ClosureKey::ClosedAccount(meter_id.clone()))
+}
+
+// Credit goes back to the meter's owner, or to whoever paid last when no
+// owner was recorded, in the token of the last payment.
+fn credit_recipient(env: &Env, meter_id: &String) -> Result<(Address, Address), Error> {
+    let count = accounting::payment_count(env, meter_id);
+    let last = count
+        .checked_sub(1)
+        .and_then(|index| accounting::read_payment(env, meter_id, index))
+        .ok_or(Error::InvalidState)?;
+    let recipient = ownership::owner(env, meter_id).unwrap_or(last.payer);
+    Ok((recipient, last.token))
+}
+
+// Admin decommissions a meter that owes nothing beyond what its security
+// deposit settles: the deposit's remainder and any prepaid credit are paid
+// back, and it accepts no further payments.
+pub fn close(env: &Env, meter_id: &String) -> Result<ClosedAccount, Error> {
+    maintenance::ensure_writable(env)?;
+    guard::non_reentrant(env, || close_unguarded(env, meter_id))
+}
+
+fn close_unguarded(env: &Env, meter_id: &String) -> Result<ClosedAccount, Error> {
+    admin::require_admin(env);
+    disputes::ensure_settlements_unfrozen(env)?;
+    portability::ensure_active(env, meter_id)?;
+    if billing::balance(env, meter_id) > deposits::coverage(env, meter_id)? {
+        return Err(Error::InvalidState);
+    }
+
+    let credit = credits::credit_balance(env, meter_id);
+    let mut refund = None;
+    let mut payout: i128 = 0;
+    if credit > 0 {
+        let (recipient, token_address) = credit_recipient(env, meter_id)?;
+        let config = tokens::read_config(env, &token_address).ok_or(Error::UnsupportedToken)?;
+        let (feed, _) = peg::payment_price(env, &token_address, &config)?;
+        payout = accounting::denormalize_floor(credit, config.decimals, &feed)?;
+        if payout > invariants::reserve(env, &token_address) {
+            return Err(Error::InvalidState);
+        }
+        refund = Some((recipient, token_address));
+    }
+
+    // A pending credit refund request is superseded by the full refund.
+    credits::clear_request(env, meter_id);
+    if credit > 0 {
+        billing::adjust_balance(env, meter_id, credit);
+    }
+    let deposit_refunded = match deposits::deposit(env, meter_id) {
+        Some(_) => deposits::release_held(env, meter_id)?,
+        None => 0,
+    };
+
+    let account = ClosedAccount {
+        meter_id: meter_id.clone(),
+        summary: accounting::summary(env, meter_id),
+        credit_refunded: credit,
+        credit_payout: payout,
+        deposit_refunded,
+        closed_at: env.ledger().timestamp(),
+    };
+    storage::write_persistent(env, &ClosureKey::ClosedAccount(meter_id.clone()), &account);
+
+    if let Some((recipient, token_address)) = refund {
+        if payout > 0 {
+            token::Client::new(env, &token_address).transfer(
+                &env.current_contract_address(),
+                &recipient,
+                &payout,
+            );
+            audit::record(env, AuditAction::Refund(recipient, token_address, payout));
+        }
+    }
+    env.events().publish(
+        (Symbol::new(env, "account_closed"), meter_id.clone()),
+        (credit, payout, deposit_refunded),
+    );
+    Ok(account)
+}
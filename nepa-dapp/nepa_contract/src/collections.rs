@@ -0,0 +1,156 @@
+use soroban_sdk::{contracttype, token, Address, Env, String, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::fees;
+use crate::guard;
+use crate::limits;
+use crate::maintenance;
+use crate::math;
+use crate::payments;
+use crate::peg;
+use crate::portability;
+use crate::storage;
+use crate::tokens;
+
+// A customer's standing permission for the utility to collect the meter's
+// bills from their token allowance, up to `cycle_cap` NGN per billing period.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollectionMandate {
+    pub payer: Address,
+    pub token: Address,
+    pub cycle_cap: i128,
+    // YYYYMM `collected` counts towards.
+    pub period: u32,
+    pub collected: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CollectionKey {
+    Mandate(String),
+}
+
+pub fn read_mandate(env: &Env, meter_id: &String) -> Option<CollectionMandate> {
+    env.storage()
+        .persistent()
+        .get(&CollectionKey::Mandate(meter_id.clone()))
+}
+
+// The payer also has to approve this contract on the token; the mandate only
+// bounds what the utility may collect with that approval.
+pub fn set_mandate(
+    env: &Env,
+    payer: &Address,
+    meter_id: &String,
+    token_address: &Address,
+    cycle_cap: i128,
+) -> Result<(), Error> {
+    payer.require_auth();
+    if tokens::read_config(env, token_address).is_none() {
+        return Err(Error::UnsupportedToken);
+    }
+    if cycle_cap <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    let period = billing::period_at(env.ledger().timestamp());
+    // Replacing a mandate keeps what was already collected this period.
+    let collected = read_mandate(env, meter_id)
+        .filter(|mandate| mandate.period == period)
+        .map_or(0, |mandate| mandate.collected);
+    let mandate = CollectionMandate {
+        payer: payer.clone(),
+        token: token_address.clone(),
+        cycle_cap,
+        period,
+        collected,
+    };
+    storage::write_persistent(env, &CollectionKey::Mandate(meter_id.clone()), &mandate);
+    env.events().publish(
+        (Symbol::new(env, "collection_mandate_set"), meter_id.clone()),
+        (payer.clone(), token_address.clone(), cycle_cap),
+    );
+    Ok(())
+}
+
+pub fn revoke_mandate(env: &Env, payer: &Address, meter_id: &String) -> Result<(), Error> {
+    payer.require_auth();
+    let mandate = read_mandate(env, meter_id).ok_or(Error::InvalidState)?;
+    if mandate.payer != *payer {
+        return Err(Error::InvalidInput);
+    }
+    env.storage()
+        .persistent()
+        .remove(&CollectionKey::Mandate(meter_id.clone()));
+    env.events().publish(
+        (
+            Symbol::new(env, "collection_mandate_revoked"),
+            meter_id.clone(),
+        ),
+        payer.clone(),
+    );
+    Ok(())
+}
+
+// The utility pulls the meter's full outstanding balance from the mandated
+// payer. Refused when it would pass the period's cap or the payer's token
+// allowance. Returns the payment index.
+pub fn collect(env: &Env, meter_id: &String) -> Result<u32, Error> {
+    maintenance::ensure_writable(env)?;
+    guard::non_reentrant(env, || collect_unguarded(env, meter_id))
+}
+
+fn collect_unguarded(env: &Env, meter_id: &String) -> Result<u32, Error> {
+    admin::require_admin(env);
+    portability::ensure_active(env, meter_id)?;
+    let mut mandate = read_mandate(env, meter_id).ok_or(Error::InvalidState)?;
+    let owed = billing::balance(env, meter_id);
+    if owed <= 0 {
+        return Err(Error::InvalidState);
+    }
+
+    let config = tokens::read_config(env, &mandate.token).ok_or(Error::UnsupportedToken)?;
+    let (feed, source) = peg::payment_price(env, &mandate.token, &config)?;
+    let amount = fees::gross_up(env, accounting::denormalize(owed, config.decimals, &feed)?)?;
+    tokens::require_accepted(env, &mandate.token, amount)?;
+    let record = accounting::build_record(
+        env,
+        &mandate.payer,
+        &mandate.token,
+        &config,
+        &feed,
+        source,
+        amount,
+    )?;
+
+    let period = billing::period_at(record.timestamp);
+    if mandate.period != period {
+        mandate.period = period;
+        mandate.collected = 0;
+    }
+    let collected = math::add(mandate.collected, record.normalized_amount)?;
+    if collected > mandate.cycle_cap {
+        return Err(Error::SpendingLimitExceeded);
+    }
+    let contract = env.current_contract_address();
+    let client = token::Client::new(env, &mandate.token);
+    if client.allowance(&mandate.payer, &contract) < amount {
+        return Err(Error::SpendingLimitExceeded);
+    }
+    limits::spend(env, &mandate.payer, record.normalized_amount)?;
+
+    mandate.collected = collected;
+    storage::write_persistent(env, &CollectionKey::Mandate(meter_id.clone()), &mandate);
+    let index = payments::settle(env, meter_id, &record)?;
+    client.transfer_from(&contract, &mandate.payer, &contract, &amount);
+
+    env.events().publish(
+        (Symbol::new(env, "bill_collected"), meter_id.clone()),
+        (mandate.payer, amount, record.normalized_amount),
+    );
+    storage::extend_instance(env);
+    Ok(index)
+}
@@ -0,0 +1,151 @@
+use soroban_sdk::{contracttype, token, Address, Env, String, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::billing;
+use crate::disputes;
+use crate::errors::Error;
+use crate::invariants;
+use crate::ownership;
+use crate::peg;
+use crate::storage;
+use crate::tokens;
+
+// A meter owner's request to be paid back part of the meter's credit.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CreditRefund {
+    // Owner who asked; the refund goes to whoever owns the meter at approval.
+    pub owner: Address,
+    pub token: Address,
+    // NGN to take off the credit balance.
+    pub amount: i128,
+    pub requested_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CreditKey {
+    RefundRequest(String),
+}
+
+// NGN the meter is in credit by. Credit stays on the running balance, so the
+// next invoice is netted against it without any further step.
+pub fn credit_balance(env: &Env, meter_id: &String) -> i128 {
+    (-billing::balance(env, meter_id)).max(0)
+}
+
+// Announces the part of a payment that went beyond what the meter owed.
+pub fn on_payment(env: &Env, meter_id: &String, owed_before: i128, paid: i128) {
+    let surplus = paid - owed_before.max(0);
+    if surplus > 0 {
+        env.events().publish(
+            (Symbol::new(env, "meter_credited"), meter_id.clone()),
+            (surplus, credit_balance(env, meter_id)),
+        );
+    }
+}
+
+pub fn pending_refund(env: &Env, meter_id: &String) -> Option<CreditRefund> {
+    env.storage()
+        .persistent()
+        .get(&CreditKey::RefundRequest(meter_id.clone()))
+}
+
+// The meter's owner asks for `amount` NGN of its credit back in `token_address`.
+pub fn request_refund(
+    env: &Env,
+    meter_id: &String,
+    token_address: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    let owner = ownership::owner(env, meter_id).ok_or(Error::InvalidState)?;
+    owner.require_auth();
+    if tokens::read_config(env, token_address).is_none() {
+        return Err(Error::UnsupportedToken);
+    }
+    if amount <= 0 || amount > credit_balance(env, meter_id) {
+        return Err(Error::InvalidInput);
+    }
+    if pending_refund(env, meter_id).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+    let request = CreditRefund {
+        owner,
+        token: token_address.clone(),
+        amount,
+        requested_at: env.ledger().timestamp(),
+    };
+    storage::write_persistent(env, &CreditKey::RefundRequest(meter_id.clone()), &request);
+    env.events().publish(
+        (
+            Symbol::new(env, "credit_refund_requested"),
+            meter_id.clone(),
+        ),
+        (token_address.clone(), amount),
+    );
+    Ok(())
+}
+
+// Admin pays the request out of the reserve at the current payment price, to
+// the meter's owner at that time: credit moves with the meter. The credit
+// must still cover it. Returns the token amount paid.
+pub fn approve_refund(env: &Env, meter_id: &String) -> Result<i128, Error> {
+    admin::require_admin(env);
+    disputes::ensure_settlements_unfrozen(env)?;
+    let request = pending_refund(env, meter_id).ok_or(Error::InvalidState)?;
+    let owner = ownership::owner(env, meter_id).ok_or(Error::InvalidState)?;
+    if request.amount > credit_balance(env, meter_id) {
+        return Err(Error::InvalidState);
+    }
+    let config = tokens::read_config(env, &request.token).ok_or(Error::UnsupportedToken)?;
+    let (feed, _) = peg::payment_price(env, &request.token, &config)?;
+    let payout = accounting::denormalize_floor(request.amount, config.decimals, &feed)?;
+    if payout <= 0 {
+        return Err(Error::AmountTooSmall);
+    }
+    if payout > invariants::reserve(env, &request.token) {
+        return Err(Error::InvalidState);
+    }
+
+    env.storage()
+        .persistent()
+        .remove(&CreditKey::RefundRequest(meter_id.clone()));
+    billing::adjust_balance(env, meter_id, request.amount);
+    token::Client::new(env, &request.token).transfer(
+        &env.current_contract_address(),
+        &owner,
+        &payout,
+    );
+    audit::record(
+        env,
+        AuditAction::Refund(owner.clone(), request.token.clone(), payout),
+    );
+    env.events().publish(
+        (Symbol::new(env, "credit_refunded"), meter_id.clone()),
+        (owner, request.token, request.amount, payout),
+    );
+    Ok(payout)
+}
+
+pub fn clear_request(env: &Env, meter_id: &String) {
+    env.storage()
+        .persistent()
+        .remove(&CreditKey::RefundRequest(meter_id.clone()));
+}
+
+pub fn reject_refund(env: &Env, meter_id: &String) -> Result<(), Error> {
+    admin::require_admin(env);
+    if pending_refund(env, meter_id).is_none() {
+        return Err(Error::InvalidState);
+    }
+    env.storage()
+        .persistent()
+        .remove(&CreditKey::RefundRequest(meter_id.clone()));
+    env.events().publish(
+        (Symbol::new(env, "credit_refund_rejected"), meter_id.clone()),
+        (),
+    );
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::ownership;
+use crate::storage;
+
+// Who earns loyalty points on a payment made for someone else's meter.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DelegatedPoints {
+    Payer,
+    Beneficiary,
+}
+
+// A payment one address made for another's meter.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DelegatedPayment {
+    pub payer: Address,
+    pub beneficiary: Address,
+    pub payment_index: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DelegationKey {
+    DelegatedPoints,
+    // Present while the meter's owner refuses payments on their behalf.
+    ThirdPartyOptOut(String),
+    DelegatedPayment(String, u32),
+}
+
+pub fn points_to(env: &Env) -> DelegatedPoints {
+    env.storage()
+        .instance()
+        .get(&DelegationKey::DelegatedPoints)
+        .unwrap_or(DelegatedPoints::Beneficiary)
+}
+
+pub fn set_points_to(env: &Env, party: DelegatedPoints) {
+    admin::require_admin(env);
+    env.storage()
+        .instance()
+        .set(&DelegationKey::DelegatedPoints, &party);
+}
+
+pub fn opted_out(env: &Env, meter_id: &String) -> bool {
+    env.storage()
+        .persistent()
+        .has(&DelegationKey::ThirdPartyOptOut(meter_id.clone()))
+}
+
+// The meter's owner allows or refuses `pay_on_behalf` for their meter.
+pub fn set_opt_out(env: &Env, meter_id: &String, opt_out: bool) -> Result<(), Error> {
+    let owner = ownership::owner(env, meter_id).ok_or(Error::InvalidState)?;
+    owner.require_auth();
+    let key = DelegationKey::ThirdPartyOptOut(meter_id.clone());
+    if opt_out {
+        storage::write_persistent(env, &key, &env.ledger().timestamp());
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+    env.events().publish(
+        (Symbol::new(env, "third_party_payments"), meter_id.clone()),
+        !opt_out,
+    );
+    Ok(())
+}
+
+// The owner a delegated payment is made for, once they have not opted out.
+pub fn beneficiary(env: &Env, payer: &Address, meter_id: &String) -> Result<Address, Error> {
+    let owner = ownership::owner(env, meter_id).ok_or(Error::InvalidState)?;
+    if owner == *payer {
+        return Err(Error::InvalidInput);
+    }
+    if opted_out(env, meter_id) {
+        return Err(Error::InvalidState);
+    }
+    Ok(owner)
+}
+
+// The address that earns loyalty points for the delegated payment.
+pub fn earner(env: &Env, payer: &Address, beneficiary: &Address) -> Address {
+    match points_to(env) {
+        DelegatedPoints::Payer => payer.clone(),
+        DelegatedPoints::Beneficiary => beneficiary.clone(),
+    }
+}
+
+pub fn record(env: &Env, meter_id: &String, payment: &DelegatedPayment) {
+    storage::write_persistent(
+        env,
+        &DelegationKey::DelegatedPayment(meter_id.clone(), payment.payment_index),
+        payment,
+    );
+    env.events().publish(
+        (Symbol::new(env, "paid_on_behalf"), meter_id.clone()),
+        (
+            payment.payer.clone(),
+            payment.beneficiary.clone(),
+            payment.payment_index,
+        ),
+    );
+}
+
+pub fn read(env: &Env, meter_id: &String, index: u32) -> Option<DelegatedPayment> {
+    env.storage()
+        .persistent()
+        .get(&DelegationKey::DelegatedPayment(meter_id.clone(), index))
+}
@@ -0,0 +1,268 @@
+use soroban_sdk::{contracttype, token, Address, Env, String, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::billing;
+use crate::errors::Error;
+use crate::maintenance;
+use crate::math;
+use crate::peg;
+use crate::periods;
+use crate::portability;
+use crate::storage;
+use crate::tokens;
+
+// NGN charged once when a meter is connected, and held as security while its
+// account is open. Either may be 0.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DepositConfig {
+    pub connection_fee: i128,
+    pub security_deposit: i128,
+}
+
+// A security deposit held for a meter; kept apart from bill payments.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SecurityDeposit {
+    pub payer: Address,
+    pub token: Address,
+    pub amount: i128,
+    // NGN value when paid.
+    pub value: i128,
+    pub paid_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DepositKey {
+    DepositConfig,
+    // Timestamp the meter's connection fee was paid.
+    ConnectionFeePaid(String),
+    SecurityDeposit(String),
+    // token -> deposits held, owed back to customers.
+    DepositsHeld(Address),
+}
+
+pub fn read_config(env: &Env) -> DepositConfig {
+    env.storage()
+        .instance()
+        .get(&DepositKey::DepositConfig)
+        .unwrap_or(DepositConfig {
+            connection_fee: 0,
+            security_deposit: 0,
+        })
+}
+
+pub fn set_config(env: &Env, config: &DepositConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if config.connection_fee < 0 || config.security_deposit < 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&DepositKey::DepositConfig, config);
+    env.events().publish(
+        (Symbol::new(env, "deposit_config_set"),),
+        (config.connection_fee, config.security_deposit),
+    );
+    Ok(())
+}
+
+pub fn connection_fee_paid(env: &Env, meter_id: &String) -> Option<u64> {
+    env.storage()
+        .persistent()
+        .get(&DepositKey::ConnectionFeePaid(meter_id.clone()))
+}
+
+pub fn deposit(env: &Env, meter_id: &String) -> Option<SecurityDeposit> {
+    env.storage()
+        .persistent()
+        .get(&DepositKey::SecurityDeposit(meter_id.clone()))
+}
+
+pub fn held_in(env: &Env, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DepositKey::DepositsHeld(token_address.clone()))
+        .unwrap_or(0)
+}
+
+fn add_held(env: &Env, token_address: &Address, delta: i128) -> Result<(), Error> {
+    storage::write_persistent(
+        env,
+        &DepositKey::DepositsHeld(token_address.clone()),
+        &math::add(held_in(env, token_address), delta)?,
+    );
+    Ok(())
+}
+
+// Token amount worth `value` NGN at the current payment price.
+fn price_in(env: &Env, token_address: &Address, value: i128) -> Result<i128, Error> {
+    let config = tokens::read_config(env, token_address).ok_or(Error::UnsupportedToken)?;
+    let (feed, _) = peg::payment_price(env, token_address, &config)?;
+    let amount = accounting::denormalize(value, config.decimals, &feed)?;
+    tokens::require_accepted(env, token_address, amount)?;
+    Ok(amount)
+}
+
+// The one-off connection fee, kept as revenue rather than credited to the
+// meter. Returns the token amount paid.
+pub fn pay_connection_fee(
+    env: &Env,
+    payer: &Address,
+    meter_id: &String,
+    token_address: &Address,
+) -> Result<i128, Error> {
+    maintenance::ensure_writable(env)?;
+    payer.require_auth();
+    portability::ensure_active(env, meter_id)?;
+    let fee = read_config(env).connection_fee;
+    if fee <= 0 {
+        return Err(Error::InvalidConfig);
+    }
+    if connection_fee_paid(env, meter_id).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+    let amount = price_in(env, token_address, fee)?;
+
+    storage::write_persistent(
+        env,
+        &DepositKey::ConnectionFeePaid(meter_id.clone()),
+        &env.ledger().timestamp(),
+    );
+    token::Client::new(env, token_address).transfer(
+        payer,
+        &env.current_contract_address(),
+        &amount,
+    );
+    env.events().publish(
+        (Symbol::new(env, "connection_fee_paid"), meter_id.clone()),
+        (payer.clone(), token_address.clone(), amount, fee),
+    );
+    Ok(amount)
+}
+
+// Lodges the configured security deposit for the meter. Returns the token
+// amount held.
+pub fn pay_deposit(
+    env: &Env,
+    payer: &Address,
+    meter_id: &String,
+    token_address: &Address,
+) -> Result<i128, Error> {
+    maintenance::ensure_writable(env)?;
+    payer.require_auth();
+    portability::ensure_active(env, meter_id)?;
+    let value = read_config(env).security_deposit;
+    if value <= 0 {
+        return Err(Error::InvalidConfig);
+    }
+    if deposit(env, meter_id).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+    let amount = price_in(env, token_address, value)?;
+
+    let held = SecurityDeposit {
+        payer: payer.clone(),
+        token: token_address.clone(),
+        amount,
+        value,
+        paid_at: env.ledger().timestamp(),
+    };
+    storage::write_persistent(env, &DepositKey::SecurityDeposit(meter_id.clone()), &held);
+    add_held(env, token_address, amount)?;
+    token::Client::new(env, token_address).transfer(
+        payer,
+        &env.current_contract_address(),
+        &amount,
+    );
+    env.events().publish(
+        (Symbol::new(env, "deposit_paid"), meter_id.clone()),
+        (payer.clone(), token_address.clone(), amount, value),
+    );
+    Ok(amount)
+}
+
+// Moves the meter's deposit, and the tokens behind it, to the instance the
+// meter is leaving for.
+pub fn hand_over(env: &Env, meter_id: &String, destination: &Address) -> Result<(), Error> {
+    let Some(held) = deposit(env, meter_id) else {
+        return Ok(());
+    };
+    env.storage()
+        .persistent()
+        .remove(&DepositKey::SecurityDeposit(meter_id.clone()));
+    add_held(env, &held.token, -held.amount)?;
+    token::Client::new(env, &held.token).transfer(
+        &env.current_contract_address(),
+        destination,
+        &held.amount,
+    );
+    Ok(())
+}
+
+// Books a deposit handed over with a meter from another instance.
+pub fn take_over(env: &Env, meter_id: &String, held: &SecurityDeposit) -> Result<(), Error> {
+    if tokens::read_config(env, &held.token).is_none() {
+        return Err(Error::UnsupportedToken);
+    }
+    storage::write_persistent(env, &DepositKey::SecurityDeposit(meter_id.clone()), held);
+    add_held(env, &held.token, held.amount)
+}
+
+// NGN of debt the meter's deposit would settle at the current payment price;
+// 0 without one.
+pub fn coverage(env: &Env, meter_id: &String) -> Result<i128, Error> {
+    let Some(held) = deposit(env, meter_id) else {
+        return Ok(0);
+    };
+    let config = tokens::read_config(env, &held.token).ok_or(Error::UnsupportedToken)?;
+    let (feed, _) = peg::payment_price(env, &held.token, &config)?;
+    accounting::normalize(held.amount, config.decimals, &feed)
+}
+
+// Returns the deposit, first settling what the meter still owes out of it at
+// the current payment price. Only account closure calls this, once it has
+// checked the admin and holds the reentrancy guard: a deposit is security for
+// as long as the account is open. Returns the token amount refunded.
+pub fn release_held(env: &Env, meter_id: &String) -> Result<i128, Error> {
+    let held = deposit(env, meter_id).ok_or(Error::InvalidState)?;
+    let config = tokens::read_config(env, &held.token).ok_or(Error::UnsupportedToken)?;
+    let (feed, _) = peg::payment_price(env, &held.token, &config)?;
+
+    let owed = billing::balance(env, meter_id).max(0);
+    let deducted = accounting::denormalize(owed, config.decimals, &feed)?.min(held.amount);
+    let applied = if deducted < held.amount {
+        owed
+    } else {
+        accounting::normalize(deducted, config.decimals, &feed)?.min(owed)
+    };
+    let refund = held.amount - deducted;
+
+    env.storage()
+        .persistent()
+        .remove(&DepositKey::SecurityDeposit(meter_id.clone()));
+    add_held(env, &held.token, -held.amount)?;
+    if applied > 0 {
+        billing::adjust_balance(env, meter_id, -applied);
+        periods::on_payment(env, meter_id);
+    }
+    if refund > 0 {
+        token::Client::new(env, &held.token).transfer(
+            &env.current_contract_address(),
+            &held.payer,
+            &refund,
+        );
+        audit::record(
+            env,
+            AuditAction::Refund(held.payer.clone(), held.token.clone(), refund),
+        );
+    }
+    env.events().publish(
+        (Symbol::new(env, "deposit_released"), meter_id.clone()),
+        (held.payer, held.token, refund, applied),
+    );
+    Ok(refund)
+}
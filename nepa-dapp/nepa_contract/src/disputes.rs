@@ -0,0 +1,180 @@
+use soroban_sdk::{contractclient, contracttype, Address, BytesN, Env, String, Symbol};
+
+use crate::admin;
+use crate::audit;
+use crate::errors::Error;
+use crate::storage;
+
+// Providers have this long to resolve a complaint before it can be referred.
+pub const RESOLUTION_WINDOW_SECONDS: u64 = 14 * 24 * 60 * 60;
+
+// Interface of the regulator-operated contract that receives referrals.
+#[contractclient(name = "RegulatorClient")]
+pub trait RegulatorInterface {
+    fn refer_case(env: Env, source: Address, dispute_id: u64, case_hash: BytesN<32>);
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DisputeStatus {
+    Open,
+    Resolved,
+    Escalated,
+    Ruled,
+}
+
+// The case file itself stays off-chain; only its hash is recorded and referred.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dispute {
+    pub customer: Address,
+    pub meter_id: String,
+    pub case_hash: BytesN<32>,
+    pub opened_at: u64,
+    pub deadline: u64,
+    pub status: DisputeStatus,
+    // Set by the regulator's ruling.
+    pub upheld: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DisputeKey {
+    NextDisputeId,
+    Dispute(u64),
+    Regulator,
+    // Number of referrals awaiting a ruling; sweeps are frozen while non-zero.
+    OpenReferrals,
+}
+
+pub fn read(env: &Env, dispute_id: u64) -> Option<Dispute> {
+    env.storage()
+        .persistent()
+        .get(&DisputeKey::Dispute(dispute_id))
+}
+
+fn write(env: &Env, dispute_id: u64, dispute: &Dispute) {
+    storage::write_persistent(env, &DisputeKey::Dispute(dispute_id), dispute);
+}
+
+pub fn read_regulator(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DisputeKey::Regulator)
+}
+
+pub fn set_regulator(env: &Env, regulator: &Address) {
+    admin::require_admin(env);
+    env.storage()
+        .instance()
+        .set(&DisputeKey::Regulator, regulator);
+    audit::role_change(env, "regulator", regulator, true);
+}
+
+fn open_referrals(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DisputeKey::OpenReferrals)
+        .unwrap_or(0)
+}
+
+fn set_open_referrals(env: &Env, count: u32) {
+    env.storage()
+        .instance()
+        .set(&DisputeKey::OpenReferrals, &count);
+}
+
+pub fn ensure_settlements_unfrozen(env: &Env) -> Result<(), Error> {
+    if open_referrals(env) > 0 {
+        return Err(Error::SettlementsFrozen);
+    }
+    Ok(())
+}
+
+pub fn open(
+    env: &Env,
+    customer: &Address,
+    meter_id: &String,
+    case_hash: &BytesN<32>,
+) -> Result<u64, Error> {
+    customer.require_auth();
+    let dispute_id: u64 = env
+        .storage()
+        .instance()
+        .get(&DisputeKey::NextDisputeId)
+        .unwrap_or(1);
+    env.storage()
+        .instance()
+        .set(&DisputeKey::NextDisputeId, &(dispute_id + 1));
+
+    let now = env.ledger().timestamp();
+    let dispute = Dispute {
+        customer: customer.clone(),
+        meter_id: meter_id.clone(),
+        case_hash: case_hash.clone(),
+        opened_at: now,
+        deadline: now + RESOLUTION_WINDOW_SECONDS,
+        status: DisputeStatus::Open,
+        upheld: false,
+    };
+    write(env, dispute_id, &dispute);
+    env.events().publish(
+        (Symbol::new(env, "dispute_opened"), dispute_id),
+        (customer.clone(), meter_id.clone()),
+    );
+    Ok(dispute_id)
+}
+
+// The provider closes a complaint itself before it reaches the regulator.
+pub fn resolve(env: &Env, dispute_id: u64) -> Result<(), Error> {
+    admin::require_admin(env);
+    let mut dispute = read(env, dispute_id).ok_or(Error::InvalidInput)?;
+    if dispute.status != DisputeStatus::Open {
+        return Err(Error::InvalidState);
+    }
+    dispute.status = DisputeStatus::Resolved;
+    write(env, dispute_id, &dispute);
+    env.events()
+        .publish((Symbol::new(env, "dispute_resolved"), dispute_id), ());
+    Ok(())
+}
+
+// Anyone may refer a complaint left unresolved past its deadline.
+pub fn escalate(env: &Env, dispute_id: u64) -> Result<(), Error> {
+    let regulator = read_regulator(env).ok_or(Error::InvalidConfig)?;
+    let mut dispute = read(env, dispute_id).ok_or(Error::InvalidInput)?;
+    if dispute.status != DisputeStatus::Open || env.ledger().timestamp() < dispute.deadline {
+        return Err(Error::InvalidState);
+    }
+
+    dispute.status = DisputeStatus::Escalated;
+    write(env, dispute_id, &dispute);
+    set_open_referrals(env, open_referrals(env) + 1);
+
+    RegulatorClient::new(env, &regulator).refer_case(
+        &env.current_contract_address(),
+        &dispute_id,
+        &dispute.case_hash,
+    );
+    env.events().publish(
+        (Symbol::new(env, "dispute_escalated"), dispute_id),
+        (regulator, dispute.case_hash),
+    );
+    Ok(())
+}
+
+// The regulator's ruling closes the referral and releases settlements.
+pub fn record_ruling(env: &Env, dispute_id: u64, upheld: bool) -> Result<(), Error> {
+    let regulator = read_regulator(env).ok_or(Error::InvalidConfig)?;
+    regulator.require_auth();
+    let mut dispute = read(env, dispute_id).ok_or(Error::InvalidInput)?;
+    if dispute.status != DisputeStatus::Escalated {
+        return Err(Error::InvalidState);
+    }
+
+    dispute.status = DisputeStatus::Ruled;
+    dispute.upheld = upheld;
+    write(env, dispute_id, &dispute);
+    set_open_referrals(env, open_referrals(env).saturating_sub(1));
+    env.events()
+        .publish((Symbol::new(env, "dispute_ruled"), dispute_id), upheld);
+    Ok(())
+}
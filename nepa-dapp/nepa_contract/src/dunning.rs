@@ -0,0 +1,266 @@
+use soroban_sdk::{contracttype, Env, String, Symbol};
+
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::storage;
+
+const DEFAULT_NOTICE_PERIOD_SECONDS: u64 = 14 * 24 * 60 * 60;
+
+// Stable numeric codes; wallets and field teams render them in their own language.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum DisconnectionReason {
+    Arrears = 1,
+    MeterTampering = 2,
+    SafetyHazard = 3,
+    RegulatoryOrder = 4,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DunningConfig {
+    // Balance (NGN units) above which a meter is in arrears.
+    pub arrears_threshold: i128,
+    // Statutory notice the customer gets before disconnection.
+    pub notice_period_seconds: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DisconnectionNotice {
+    pub meter_id: String,
+    pub reason: DisconnectionReason,
+    pub notice_period_seconds: u64,
+    pub issued_at: u64,
+    pub earliest_disconnection: u64,
+    // NGN the customer must pay to avoid disconnection; 0 for non-arrears reasons.
+    pub amount_to_avoid: i128,
+    // Set once the arrears are paid or the provider withdraws the notice.
+    pub withdrawn: bool,
+}
+
+// How much debt a tariff band tolerates, and for how long, before the meter
+// is flagged for field disconnection.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DebtTolerance {
+    pub threshold: i128,
+    pub grace_period_seconds: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DebtStatus {
+    pub balance: i128,
+    // When the balance last went over the band's threshold, 0 if it is not.
+    pub over_threshold_since: u64,
+    pub disconnection_pending: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DunningKey {
+    DunningConfig,
+    Notice(String),
+    DebtTolerance(String),
+    // Meter id -> (over_threshold_since, disconnection_pending).
+    DebtFlag(String),
+}
+
+pub fn read_config(env: &Env) -> DunningConfig {
+    env.storage()
+        .instance()
+        .get(&DunningKey::DunningConfig)
+        .unwrap_or(DunningConfig {
+            arrears_threshold: 0,
+            notice_period_seconds: DEFAULT_NOTICE_PERIOD_SECONDS,
+        })
+}
+
+pub fn set_config(env: &Env, config: &DunningConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if config.arrears_threshold < 0 || config.notice_period_seconds == 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&DunningKey::DunningConfig, config);
+    Ok(())
+}
+
+pub fn read_notice(env: &Env, meter_id: &String) -> Option<DisconnectionNotice> {
+    env.storage()
+        .persistent()
+        .get(&DunningKey::Notice(meter_id.clone()))
+}
+
+fn active_notice(env: &Env, meter_id: &String) -> Option<DisconnectionNotice> {
+    read_notice(env, meter_id).filter(|notice| !notice.withdrawn)
+}
+
+fn issue(
+    env: &Env,
+    meter_id: &String,
+    reason: DisconnectionReason,
+    amount_to_avoid: i128,
+) -> DisconnectionNotice {
+    let config = read_config(env);
+    let now = env.ledger().timestamp();
+    let notice = DisconnectionNotice {
+        meter_id: meter_id.clone(),
+        reason,
+        notice_period_seconds: config.notice_period_seconds,
+        issued_at: now,
+        earliest_disconnection: now + config.notice_period_seconds,
+        amount_to_avoid,
+        withdrawn: false,
+    };
+    storage::write_persistent(env, &DunningKey::Notice(meter_id.clone()), &notice);
+    env.events().publish(
+        (Symbol::new(env, "disconnection_notice"), meter_id.clone()),
+        (
+            reason as u32,
+            notice.earliest_disconnection,
+            amount_to_avoid,
+        ),
+    );
+    notice
+}
+
+// Anyone may run dunning for a meter; it issues an arrears notice when the
+// balance is past the threshold and no notice is already active.
+pub fn run(env: &Env, meter_id: &String) -> Option<DisconnectionNotice> {
+    if let Some(notice) = active_notice(env, meter_id) {
+        return Some(notice);
+    }
+    let balance = billing::balance(env, meter_id);
+    if balance <= read_config(env).arrears_threshold {
+        return None;
+    }
+    Some(issue(env, meter_id, DisconnectionReason::Arrears, balance))
+}
+
+// Notices for reasons other than arrears come from the provider directly.
+pub fn issue_for_reason(
+    env: &Env,
+    meter_id: &String,
+    reason: DisconnectionReason,
+) -> Result<DisconnectionNotice, Error> {
+    admin::require_admin(env);
+    if reason == DisconnectionReason::Arrears || active_notice(env, meter_id).is_some() {
+        return Err(Error::InvalidState);
+    }
+    Ok(issue(env, meter_id, reason, 0))
+}
+
+pub fn withdraw(env: &Env, meter_id: &String) -> Result<(), Error> {
+    admin::require_admin(env);
+    let notice = active_notice(env, meter_id).ok_or(Error::InvalidState)?;
+    mark_withdrawn(env, notice);
+    Ok(())
+}
+
+fn mark_withdrawn(env: &Env, mut notice: DisconnectionNotice) {
+    notice.withdrawn = true;
+    storage::write_persistent(env, &DunningKey::Notice(notice.meter_id.clone()), &notice);
+    env.events().publish(
+        (
+            Symbol::new(env, "disconnection_notice_withdrawn"),
+            notice.meter_id,
+        ),
+        notice.reason as u32,
+    );
+}
+
+// Payments that bring the balance back under the threshold lift an arrears notice.
+pub fn on_payment(env: &Env, meter_id: &String) {
+    refresh_debt_flag(env, meter_id);
+    let Some(notice) = active_notice(env, meter_id) else {
+        return;
+    };
+    if notice.reason == DisconnectionReason::Arrears
+        && billing::balance(env, meter_id) <= read_config(env).arrears_threshold
+    {
+        mark_withdrawn(env, notice);
+    }
+}
+
+pub fn tolerance(env: &Env, band: &String) -> Option<DebtTolerance> {
+    env.storage()
+        .persistent()
+        .get(&DunningKey::DebtTolerance(band.clone()))
+}
+
+pub fn set_tolerance(env: &Env, band: &String, tolerance: &DebtTolerance) -> Result<(), Error> {
+    admin::require_admin(env);
+    if tolerance.threshold < 0 {
+        return Err(Error::InvalidConfig);
+    }
+    storage::write_persistent(env, &DunningKey::DebtTolerance(band.clone()), tolerance);
+    Ok(())
+}
+
+fn meter_tolerance(env: &Env, meter_id: &String) -> Option<DebtTolerance> {
+    tolerance(env, &billing::meter_band(env, meter_id)?)
+}
+
+// Sets the meter's debt clock as is; an unstarted clock is not stored.
+pub fn write_flag(env: &Env, meter_id: &String, since: u64, pending: bool) {
+    let key = DunningKey::DebtFlag(meter_id.clone());
+    if since == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        storage::write_persistent(env, &key, &(since, pending));
+    }
+}
+
+fn read_flag(env: &Env, meter_id: &String) -> (u64, bool) {
+    env.storage()
+        .persistent()
+        .get(&DunningKey::DebtFlag(meter_id.clone()))
+        .unwrap_or((0, false))
+}
+
+pub fn debt_status(env: &Env, meter_id: &String) -> DebtStatus {
+    let (over_threshold_since, disconnection_pending) = read_flag(env, meter_id);
+    DebtStatus {
+        balance: billing::balance(env, meter_id),
+        over_threshold_since,
+        disconnection_pending,
+    }
+}
+
+// Starts the grace clock when the balance goes over the band's threshold,
+// raises the disconnection flag once the grace period has run out, and clears
+// both when the balance is back under. Runs on every bill and payment, and
+// anyone may run it to pick up an expired grace period.
+pub fn refresh_debt_flag(env: &Env, meter_id: &String) -> DebtStatus {
+    let (since, pending) = read_flag(env, meter_id);
+    let balance = billing::balance(env, meter_id);
+    let now = env.ledger().timestamp();
+
+    let (new_since, new_pending) = match meter_tolerance(env, meter_id) {
+        Some(tolerance) if balance > tolerance.threshold => {
+            let since = if since == 0 { now } else { since };
+            (since, now >= since + tolerance.grace_period_seconds)
+        }
+        _ => (0, false),
+    };
+
+    if (new_since, new_pending) != (since, pending) {
+        write_flag(env, meter_id, new_since, new_pending);
+        if new_pending != pending {
+            env.events().publish(
+                (Symbol::new(env, "disconnection_pending"), meter_id.clone()),
+                (new_pending, balance),
+            );
+        }
+    }
+    DebtStatus {
+        balance,
+        over_threshold_since: new_since,
+        disconnection_pending: new_pending,
+    }
+}
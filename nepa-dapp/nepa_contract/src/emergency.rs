@@ -0,0 +1,135 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::escrow;
+use crate::portability;
+use crate::storage;
+use crate::vouchers;
+
+// Overdraft a prepaid meter may draw once its kWh balance runs out.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EmergencyCreditConfig {
+    pub kwh: i128,
+    // How long the meter may run on it before cutoff; 0 means until used up.
+    pub grace_seconds: u64,
+}
+
+// Emergency credit drawn and not yet recovered from top-ups.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EmergencyCredit {
+    pub voucher_id: u64,
+    pub kwh: i128,
+    // kWh still to recover.
+    pub outstanding: i128,
+    pub activated_at: u64,
+    // 0 when the config had no grace limit.
+    pub expires_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EmergencyKey {
+    EmergencyConfig,
+    EmergencyCredit(String),
+}
+
+pub fn read_config(env: &Env) -> Option<EmergencyCreditConfig> {
+    env.storage().instance().get(&EmergencyKey::EmergencyConfig)
+}
+
+pub fn set_config(env: &Env, config: &EmergencyCreditConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if config.kwh <= 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&EmergencyKey::EmergencyConfig, config);
+    Ok(())
+}
+
+pub fn credit(env: &Env, meter_id: &String) -> Option<EmergencyCredit> {
+    env.storage()
+        .persistent()
+        .get(&EmergencyKey::EmergencyCredit(meter_id.clone()))
+}
+
+// Called by the vending system (admin or vending oracle) once a prepaid
+// meter's kWh balance reaches zero. Issues a voucher for the configured
+// overdraft; only one may be outstanding per meter.
+pub fn activate(
+    env: &Env,
+    meter_id: &String,
+    reporter: &Address,
+) -> Result<EmergencyCredit, Error> {
+    let authorised = *reporter == admin::read_admin(env)
+        || escrow::vending_oracle(env).as_ref() == Some(reporter);
+    if !authorised {
+        return Err(Error::InvalidInput);
+    }
+    reporter.require_auth();
+    portability::ensure_active(env, meter_id)?;
+    if !vouchers::is_prepaid(env, meter_id) {
+        return Err(Error::InvalidState);
+    }
+    let config = read_config(env).ok_or(Error::InvalidConfig)?;
+    if credit(env, meter_id).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+
+    let now = env.ledger().timestamp();
+    let voucher = vouchers::issue(env, meter_id, config.kwh, now);
+    let drawn = EmergencyCredit {
+        voucher_id: voucher.voucher_id,
+        kwh: config.kwh,
+        outstanding: config.kwh,
+        activated_at: now,
+        expires_at: match config.grace_seconds {
+            0 => 0,
+            grace => now + grace,
+        },
+    };
+    storage::write_persistent(
+        env,
+        &EmergencyKey::EmergencyCredit(meter_id.clone()),
+        &drawn,
+    );
+    env.events().publish(
+        (
+            Symbol::new(env, "emergency_credit_activated"),
+            meter_id.clone(),
+        ),
+        (drawn.voucher_id, drawn.kwh, drawn.expires_at),
+    );
+    Ok(drawn)
+}
+
+// Takes outstanding emergency credit out of a top-up's kWh and returns what
+// is left to vend.
+pub fn recover(env: &Env, meter_id: &String, kwh: i128) -> i128 {
+    let Some(mut drawn) = credit(env, meter_id) else {
+        return kwh;
+    };
+    let recovered = kwh.min(drawn.outstanding);
+    if recovered <= 0 {
+        return kwh;
+    }
+    drawn.outstanding -= recovered;
+    let key = EmergencyKey::EmergencyCredit(meter_id.clone());
+    if drawn.outstanding == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        storage::write_persistent(env, &key, &drawn);
+    }
+    env.events().publish(
+        (
+            Symbol::new(env, "emergency_credit_repaid"),
+            meter_id.clone(),
+        ),
+        (recovered, drawn.outstanding),
+    );
+    kwh - recovered
+}
@@ -0,0 +1,39 @@
+use soroban_sdk::contracterror;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    InvalidConfig = 3,
+    UnsupportedToken = 4,
+    AmountTooSmall = 5,
+    PriceFeedNotFound = 6,
+    InvalidPrice = 7,
+    MaintenanceWindow = 8,
+    StalePriceFeed = 9,
+    InvalidTariff = 10,
+    RateNotFound = 11,
+    MissingTariffInput = 12,
+    InvalidInput = 13,
+    AlreadyExists = 14,
+    InvalidState = 15,
+    ReadOnlyMirror = 16,
+    MeterInactive = 17,
+    SettlementsFrozen = 18,
+    MultisigRequired = 19,
+    TimelockRequired = 20,
+    SignatureRequired = 21,
+    UnreliableFeed = 22,
+    BudgetExceeded = 23,
+    QuoteExpired = 24,
+    SpendingLimitExceeded = 25,
+    ArithmeticOverflow = 26,
+    ReentrantCall = 27,
+    OutOfBounds = 28,
+    RateFrozen = 29,
+    PegDeviation = 30,
+    UnitMismatch = 31,
+    Deprecated = 32,
+}
@@ -0,0 +1,267 @@
+use soroban_sdk::{contracttype, token, Address, Env, String, Symbol};
+
+use crate::accounting::{self, PaymentRecord};
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::errors::Error;
+use crate::guard;
+use crate::limits;
+use crate::math;
+use crate::payments;
+use crate::portability;
+use crate::storage;
+use crate::tokens;
+use crate::velocity;
+use crate::vendors;
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 24 * 60 * 60;
+// Furthest ahead a reservation may expire.
+pub const MAX_RESERVATION_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EscrowStatus {
+    Pending,
+    Confirmed,
+    Reclaimed,
+}
+
+// A payment held by the contract until vending is confirmed. It is valued when
+// escrowed and booked against the meter only on confirmation.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowedPayment {
+    pub meter_id: String,
+    pub record: PaymentRecord,
+    pub expires_at: u64,
+    pub status: EscrowStatus,
+    // Index in the meter's payment history once confirmed.
+    pub payment_index: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EscrowKey {
+    NextEscrowId,
+    Escrow(u64),
+    EscrowTimeout,
+    // Oracle allowed to attest that a meter was credited, besides the admin.
+    VendingOracle,
+    // Token held for pending escrows, owed back to payers if they expire.
+    PendingEscrowTotal(Address),
+}
+
+pub fn read(env: &Env, escrow_id: u64) -> Option<EscrowedPayment> {
+    env.storage()
+        .persistent()
+        .get(&EscrowKey::Escrow(escrow_id))
+}
+
+fn write(env: &Env, escrow_id: u64, escrow: &EscrowedPayment) {
+    storage::write_persistent(env, &EscrowKey::Escrow(escrow_id), escrow);
+}
+
+pub fn timeout(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&EscrowKey::EscrowTimeout)
+        .unwrap_or(DEFAULT_TIMEOUT_SECONDS)
+}
+
+pub fn set_timeout(env: &Env, seconds: u64) -> Result<(), Error> {
+    admin::require_admin(env);
+    if seconds == 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&EscrowKey::EscrowTimeout, &seconds);
+    Ok(())
+}
+
+pub fn next_id(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&EscrowKey::NextEscrowId)
+        .unwrap_or(1)
+}
+
+pub fn pending_total(env: &Env, token: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&EscrowKey::PendingEscrowTotal(token.clone()))
+        .unwrap_or(0)
+}
+
+fn adjust_pending(env: &Env, token: &Address, delta: i128) -> Result<(), Error> {
+    let key = EscrowKey::PendingEscrowTotal(token.clone());
+    storage::write_persistent(env, &key, &math::add(pending_total(env, token), delta)?);
+    Ok(())
+}
+
+pub fn vending_oracle(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&EscrowKey::VendingOracle)
+}
+
+pub fn set_vending_oracle(env: &Env, oracle: &Address) {
+    admin::require_admin(env);
+    env.storage()
+        .instance()
+        .set(&EscrowKey::VendingOracle, oracle);
+    audit::role_change(env, "vending_oracle", oracle, true);
+}
+
+// Takes the payer's funds into escrow and returns the escrow id.
+pub fn pay(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    amount: i128,
+) -> Result<u64, Error> {
+    let expires_at = env.ledger().timestamp() + timeout(env);
+    guard::non_reentrant(env, || {
+        pay_unguarded(env, from, token_address, meter_id, amount, expires_at)
+    })
+}
+
+// As `pay`, held until `expires_at` so an offline vending terminal can capture
+// it when it next connects. Returns the reservation's escrow id.
+pub fn reserve(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    amount: i128,
+    expires_at: u64,
+) -> Result<u64, Error> {
+    let now = env.ledger().timestamp();
+    if expires_at <= now || expires_at - now > MAX_RESERVATION_SECONDS {
+        return Err(Error::InvalidInput);
+    }
+    guard::non_reentrant(env, || {
+        pay_unguarded(env, from, token_address, meter_id, amount, expires_at)
+    })
+}
+
+fn pay_unguarded(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    amount: i128,
+    expires_at: u64,
+) -> Result<u64, Error> {
+    from.require_auth();
+    portability::ensure_active(env, meter_id)?;
+    let token_config = tokens::require_accepted(env, token_address, amount)?;
+    let record = accounting::quote(env, from, token_address, &token_config, amount)?;
+    velocity::require_attestation(env, from);
+    limits::spend(env, from, record.normalized_amount)?;
+
+    let escrow_id = next_id(env);
+    env.storage()
+        .instance()
+        .set(&EscrowKey::NextEscrowId, &(escrow_id + 1));
+
+    let escrow = EscrowedPayment {
+        meter_id: meter_id.clone(),
+        record,
+        expires_at,
+        status: EscrowStatus::Pending,
+        payment_index: 0,
+    };
+    write(env, escrow_id, &escrow);
+    adjust_pending(env, token_address, amount)?;
+    token::Client::new(env, token_address).transfer(from, &env.current_contract_address(), &amount);
+    storage::extend_instance(env);
+
+    env.events().publish(
+        (Symbol::new(env, "payment_escrowed"), meter_id.clone()),
+        (escrow_id, from.clone(), amount, escrow.expires_at),
+    );
+    Ok(escrow_id)
+}
+
+// The provider, or the vending oracle, confirms the meter was credited before
+// the escrow expires. Returns the payment index.
+pub fn confirm(env: &Env, escrow_id: u64, confirmer: &Address) -> Result<u32, Error> {
+    let authorised =
+        *confirmer == admin::read_admin(env) || vending_oracle(env).as_ref() == Some(confirmer);
+    if !authorised {
+        return Err(Error::InvalidInput);
+    }
+    confirmer.require_auth();
+    release(env, escrow_id)
+}
+
+// An active vending agent's terminal captures a reservation once it has
+// credited the meter; the admin and vending oracle may as well. Returns the
+// payment index.
+pub fn capture(env: &Env, reservation_id: u64, terminal: &Address) -> Result<u32, Error> {
+    let authorised = vendors::read_agent(env, terminal).is_some_and(|agent| agent.active)
+        || *terminal == admin::read_admin(env)
+        || vending_oracle(env).as_ref() == Some(terminal);
+    if !authorised {
+        return Err(Error::InvalidInput);
+    }
+    terminal.require_auth();
+    release(env, reservation_id)
+}
+
+// Books a pending, unexpired escrow against its meter.
+fn release(env: &Env, escrow_id: u64) -> Result<u32, Error> {
+    let mut escrow = read(env, escrow_id).ok_or(Error::InvalidInput)?;
+    if escrow.status != EscrowStatus::Pending || env.ledger().timestamp() >= escrow.expires_at {
+        return Err(Error::InvalidState);
+    }
+
+    escrow.payment_index = payments::settle(env, &escrow.meter_id, &escrow.record)?;
+    escrow.record.sequence = accounting::latest_sequence(env);
+    escrow.status = EscrowStatus::Confirmed;
+    write(env, escrow_id, &escrow);
+    adjust_pending(env, &escrow.record.token, -escrow.record.amount)?;
+    env.events().publish(
+        (
+            Symbol::new(env, "escrow_confirmed"),
+            escrow.meter_id.clone(),
+        ),
+        (escrow_id, escrow.payment_index),
+    );
+    Ok(escrow.payment_index)
+}
+
+// Returns unconfirmed funds to the payer once the escrow has expired.
+pub fn reclaim(env: &Env, escrow_id: u64) -> Result<(), Error> {
+    guard::non_reentrant(env, || reclaim_unguarded(env, escrow_id))
+}
+
+fn reclaim_unguarded(env: &Env, escrow_id: u64) -> Result<(), Error> {
+    let mut escrow = read(env, escrow_id).ok_or(Error::InvalidInput)?;
+    escrow.record.payer.require_auth();
+    if escrow.status != EscrowStatus::Pending || env.ledger().timestamp() < escrow.expires_at {
+        return Err(Error::InvalidState);
+    }
+
+    escrow.status = EscrowStatus::Reclaimed;
+    write(env, escrow_id, &escrow);
+    adjust_pending(env, &escrow.record.token, -escrow.record.amount)?;
+    token::Client::new(env, &escrow.record.token).transfer(
+        &env.current_contract_address(),
+        &escrow.record.payer,
+        &escrow.record.amount,
+    );
+    audit::record(
+        env,
+        AuditAction::Refund(
+            escrow.record.payer.clone(),
+            escrow.record.token.clone(),
+            escrow.record.amount,
+        ),
+    );
+    env.events().publish(
+        (Symbol::new(env, "escrow_reclaimed"), escrow.meter_id),
+        (escrow_id, escrow.record.amount),
+    );
+    Ok(())
+}
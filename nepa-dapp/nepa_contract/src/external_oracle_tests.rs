@@ -0,0 +1,52 @@
+// The contract as built without `embedded-oracle`, where prices come only from
+// SEP-40 oracles: cargo test --no-default-features
+
+use soroban_sdk::{vec, String};
+
+use crate::testutils::{Simulation, PRICE_DECIMALS, TOKEN_PAIR, TOKEN_PRICE};
+use crate::{Error, ExternalPriceSource, FallbackChain, PriceSource, Sep40Asset};
+
+#[test]
+fn push_feeds_are_refused() {
+    let sim = Simulation::new();
+    let pair = String::from_str(&sim.env, TOKEN_PAIR);
+    let pushed = sim
+        .client
+        .try_update_price_feed(&pair, &TOKEN_PRICE, &PRICE_DECIMALS);
+    assert_eq!(pushed, Err(Ok(Error::InvalidConfig)));
+    assert_eq!(sim.client.get_price_feed(&pair), None);
+
+    for source in [PriceSource::PushFeed, PriceSource::CachedPrice] {
+        let chain = FallbackChain {
+            sources: vec![&sim.env, source],
+            cache_max_age_seconds: 3_600,
+        };
+        let set = sim.client.try_set_fallback_chain(&pair, &chain);
+        assert_eq!(set, Err(Ok(Error::InvalidConfig)));
+    }
+}
+
+#[test]
+fn payments_are_priced_through_the_sep40_client() {
+    let sim = Simulation::new();
+    let oracle = sim.register_mock_oracle();
+    let asset = Sep40Asset::Stellar(sim.token.clone());
+    let pair = String::from_str(&sim.env, TOKEN_PAIR);
+    sim.client.set_external_price_source(
+        &pair,
+        &ExternalPriceSource {
+            oracle: oracle.address.clone(),
+            asset: asset.clone(),
+        },
+    );
+    oracle.set_price(&asset, &TOKEN_PRICE, &sim.env.ledger().timestamp());
+
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = String::from_str(&sim.env, "METER-1");
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    let record = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(record.price_source, PriceSource::ExternalOracle);
+    assert_eq!(record.rate, TOKEN_PRICE);
+}
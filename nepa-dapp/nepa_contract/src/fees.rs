@@ -0,0 +1,165 @@
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{contracttype, symbol_short, token, Address, Env, Symbol, Vec};
+
+use crate::accounting::{self, PaymentRecord};
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::errors::Error;
+use crate::guard;
+use crate::math;
+use crate::multisig;
+use crate::oracle::PriceFeed;
+use crate::storage;
+use crate::taxes::{LineItem, TaxKind};
+use crate::tokens::TokenConfig;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+
+// Fees taken out of every payment before it is credited, and accrued for
+// `collector`. The spread is charged on converting the token into NGN, the
+// service fee on the payment itself.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeConfig {
+    pub collector: Address,
+    pub fx_spread_bps: u32,
+    pub service_fee_bps: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FeeKey {
+    FeeConfig,
+    // token -> fees taken and not yet swept to the collector.
+    FeesAccrued(Address),
+}
+
+pub fn read_config(env: &Env) -> Option<FeeConfig> {
+    env.storage().instance().get(&FeeKey::FeeConfig)
+}
+
+// Once a signer set exists, only a multisig proposal may change where the
+// fees go; the admin may still retune the rates.
+pub fn set_config(env: &Env, config: &FeeConfig) -> Result<(), Error> {
+    if read_config(env).map(|current| current.collector) != Some(config.collector.clone()) {
+        multisig::ensure_not_required(env)?;
+    }
+    admin::require_admin(env);
+    store_config(env, config)
+}
+
+pub fn store_config(env: &Env, config: &FeeConfig) -> Result<(), Error> {
+    let total = i128::from(config.fx_spread_bps) + i128::from(config.service_fee_bps);
+    if total >= BPS_DENOMINATOR {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage().instance().set(&FeeKey::FeeConfig, config);
+    env.events().publish(
+        (Symbol::new(env, "fee_config_set"),),
+        (
+            config.collector.clone(),
+            config.fx_spread_bps,
+            config.service_fee_bps,
+        ),
+    );
+    Ok(())
+}
+
+fn total_bps(config: &FeeConfig) -> i128 {
+    i128::from(config.fx_spread_bps) + i128::from(config.service_fee_bps)
+}
+
+pub fn accrued_in(env: &Env, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&FeeKey::FeesAccrued(token_address.clone()))
+        .unwrap_or(0)
+}
+
+fn add_accrued(env: &Env, token_address: &Address, delta: i128) -> Result<(), Error> {
+    storage::write_persistent(
+        env,
+        &FeeKey::FeesAccrued(token_address.clone()),
+        &math::add(accrued_in(env, token_address), delta)?,
+    );
+    Ok(())
+}
+
+// Splits a payment of `amount` into the token fee and one NGN line item per
+// fee charged. Empty when no fees are configured.
+pub fn assess(
+    env: &Env,
+    amount: i128,
+    config: &TokenConfig,
+    feed: &PriceFeed,
+) -> Result<(i128, Vec<LineItem>), Error> {
+    let mut items = Vec::new(env);
+    let Some(fees) = read_config(env) else {
+        return Ok((0, items));
+    };
+    let mut taken: i128 = 0;
+    for (name, bps) in [
+        (symbol_short!("fx_spread"), fees.fx_spread_bps),
+        (Symbol::new(env, "service_fee"), fees.service_fee_bps),
+    ] {
+        if bps == 0 {
+            continue;
+        }
+        let fee = accounting::apply_bps(amount, i128::from(bps))?;
+        taken = math::add(taken, fee)?;
+        items.push_back(LineItem {
+            name,
+            kind: TaxKind::Fee,
+            bps,
+            amount: accounting::normalize(fee, config.decimals, feed)?,
+        });
+    }
+    Ok((taken, items))
+}
+
+// The token amount whose value after fees comes to `net`, so a payment sized
+// for a bill still covers it.
+pub fn gross_up(env: &Env, net: i128) -> Result<i128, Error> {
+    let Some(fees) = read_config(env) else {
+        return Ok(net);
+    };
+    net.fixed_mul_ceil(BPS_DENOMINATOR, BPS_DENOMINATOR - total_bps(&fees))
+        .ok_or(Error::ArithmeticOverflow)
+}
+
+// Sets aside a settled payment's fees for the collector.
+pub fn on_payment(env: &Env, record: &PaymentRecord) -> Result<(), Error> {
+    if record.fee_amount > 0 {
+        add_accrued(env, &record.token, record.fee_amount)?;
+    }
+    Ok(())
+}
+
+// Pays the fees accrued in `token_address` to the configured collector. Anyone
+// may trigger it. Returns the amount swept.
+pub fn sweep(env: &Env, token_address: &Address) -> Result<i128, Error> {
+    guard::non_reentrant(env, || sweep_unguarded(env, token_address))
+}
+
+fn sweep_unguarded(env: &Env, token_address: &Address) -> Result<i128, Error> {
+    let config = read_config(env).ok_or(Error::InvalidConfig)?;
+    let amount = accrued_in(env, token_address);
+    if amount <= 0 {
+        return Err(Error::InvalidState);
+    }
+    add_accrued(env, token_address, -amount)?;
+    token::Client::new(env, token_address).transfer(
+        &env.current_contract_address(),
+        &config.collector,
+        &amount,
+    );
+    audit::record(
+        env,
+        AuditAction::Withdrawal(config.collector.clone(), token_address.clone(), amount),
+    );
+    env.events().publish(
+        (Symbol::new(env, "fees_swept"), config.collector),
+        (token_address.clone(), amount),
+    );
+    Ok(amount)
+}
@@ -0,0 +1,180 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::errors::Error;
+use crate::maintenance;
+use crate::math;
+use crate::ownership;
+use crate::storage;
+use crate::tariff;
+use crate::units::MeteredUnit;
+
+// Green-attribute units are watt-hours of renewable supply.
+pub const GREEN_UNITS_PER_KWH: i128 = 1_000;
+
+// Renewable share of a region's supply, in bps of the energy delivered.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GenerationMix {
+    pub renewable_bps: u32,
+    pub updated_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GreenCredits {
+    pub balance: i128,
+    // Accrued from billed consumption, net of true-up corrections.
+    pub earned: i128,
+    // Claimed against renewable-consumption reports; no longer transferable.
+    pub retired: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GreenKey {
+    // Kept in instance storage.
+    GreenTransferable,
+    GenerationMix(String),
+    GreenCredits(Address),
+}
+
+pub fn mix(env: &Env, region: &String) -> Option<GenerationMix> {
+    env.storage()
+        .persistent()
+        .get(&GreenKey::GenerationMix(region.clone()))
+}
+
+// Applies to bills issued from now on; earlier bills keep what they accrued.
+pub fn set_mix(env: &Env, region: &String, renewable_bps: u32) -> Result<(), Error> {
+    admin::require_admin(env);
+    if !tariff::regions(env).contains(region) {
+        return Err(Error::InvalidInput);
+    }
+    if renewable_bps > 10_000 {
+        return Err(Error::InvalidConfig);
+    }
+    let mix = GenerationMix {
+        renewable_bps,
+        updated_at: env.ledger().timestamp(),
+    };
+    storage::write_persistent(env, &GreenKey::GenerationMix(region.clone()), &mix);
+    env.events().publish(
+        (Symbol::new(env, "generation_mix_set"), region.clone()),
+        renewable_bps,
+    );
+    Ok(())
+}
+
+pub fn is_transferable(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&GreenKey::GreenTransferable)
+        .unwrap_or(false)
+}
+
+pub fn set_transferable(env: &Env, transferable: bool) {
+    admin::require_admin(env);
+    env.storage()
+        .instance()
+        .set(&GreenKey::GreenTransferable, &transferable);
+    env.events()
+        .publish((Symbol::new(env, "green_transfer_set"),), transferable);
+}
+
+pub fn credits(env: &Env, holder: &Address) -> GreenCredits {
+    env.storage()
+        .persistent()
+        .get(&GreenKey::GreenCredits(holder.clone()))
+        .unwrap_or(GreenCredits {
+            balance: 0,
+            earned: 0,
+            retired: 0,
+        })
+}
+
+fn write_credits(env: &Env, holder: &Address, credits: &GreenCredits) {
+    storage::write_persistent(env, &GreenKey::GreenCredits(holder.clone()), credits);
+}
+
+// Accrues the renewable share of `kwh_delta` billed kWh to the meter's owner.
+// A true-up that lowers consumption takes back what it can of the excess.
+pub fn on_bill(env: &Env, meter_id: &String, rate_id: &String, unit: MeteredUnit, kwh_delta: i128) {
+    if unit != MeteredUnit::Kwh || kwh_delta == 0 {
+        return;
+    }
+    let Some(region) = tariff::key_for_rate(env, rate_id).map(|key| key.region) else {
+        return;
+    };
+    let (Some(mix), Some(owner)) = (mix(env, &region), ownership::owner(env, meter_id)) else {
+        return;
+    };
+    let Ok(units) = math::mul(kwh_delta, GREEN_UNITS_PER_KWH)
+        .and_then(|wh| accounting::apply_bps(wh, mix.renewable_bps as i128))
+    else {
+        return;
+    };
+    let mut credits = credits(env, &owner);
+    let units = units.max(-credits.balance);
+    if units == 0 {
+        return;
+    }
+    credits.balance += units;
+    credits.earned += units;
+    write_credits(env, &owner, &credits);
+    env.events().publish(
+        (
+            Symbol::new(env, "green_credits_accrued"),
+            owner,
+            meter_id.clone(),
+        ),
+        (region, units),
+    );
+}
+
+// Moves credits between holders while transfers are enabled.
+pub fn transfer(env: &Env, from: &Address, to: &Address, amount: i128) -> Result<(), Error> {
+    maintenance::ensure_writable(env)?;
+    from.require_auth();
+    if !is_transferable(env) {
+        return Err(Error::InvalidState);
+    }
+    let mut sender = credits(env, from);
+    if amount <= 0 || amount > sender.balance || from == to {
+        return Err(Error::InvalidInput);
+    }
+    let mut recipient = credits(env, to);
+    sender.balance -= amount;
+    recipient.balance = math::add(recipient.balance, amount)?;
+    write_credits(env, from, &sender);
+    write_credits(env, to, &recipient);
+    env.events().publish(
+        (
+            Symbol::new(env, "green_credits_transferred"),
+            from.clone(),
+            to.clone(),
+        ),
+        amount,
+    );
+    Ok(())
+}
+
+// Retires credits against a renewable-consumption claim, so the same units
+// cannot be reported twice. Always allowed, transferable or not.
+pub fn retire(env: &Env, holder: &Address, amount: i128) -> Result<GreenCredits, Error> {
+    maintenance::ensure_writable(env)?;
+    holder.require_auth();
+    let mut credits = credits(env, holder);
+    if amount <= 0 || amount > credits.balance {
+        return Err(Error::InvalidInput);
+    }
+    credits.balance -= amount;
+    credits.retired = math::add(credits.retired, amount)?;
+    write_credits(env, holder, &credits);
+    env.events().publish(
+        (Symbol::new(env, "green_credits_retired"), holder.clone()),
+        amount,
+    );
+    Ok(credits)
+}
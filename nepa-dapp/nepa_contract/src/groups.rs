@@ -0,0 +1,222 @@
+use soroban_sdk::{contracttype, Address, Env, Map, String, Symbol, Vec};
+
+use crate::billing;
+use crate::errors::Error;
+use crate::payments;
+use crate::storage;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+
+// How a group payment is divided between the member meters.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GroupSplit {
+    // In proportion to each member's outstanding balance.
+    Outstanding,
+    // By the members' configured shares, which must total 10_000 bps.
+    Shares,
+}
+
+// An estate or apartment block paid for as one. `members` maps each meter to
+// its share in bps, used under `GroupSplit::Shares`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BillingGroup {
+    pub admin: Address,
+    pub split: GroupSplit,
+    pub members: Map<String, u32>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GroupStatementLine {
+    pub meter_id: String,
+    pub kwh: i128,
+    pub billed: i128,
+    pub outstanding: i128,
+}
+
+// One period across the group's members, in NGN accounting units.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GroupStatement {
+    pub period: u32,
+    pub kwh: i128,
+    pub billed: i128,
+    pub outstanding: i128,
+    pub lines: Vec<GroupStatementLine>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GroupKey {
+    BillingGroup(String),
+}
+
+pub fn read(env: &Env, group_id: &String) -> Option<BillingGroup> {
+    env.storage()
+        .persistent()
+        .get(&GroupKey::BillingGroup(group_id.clone()))
+}
+
+fn write(env: &Env, group_id: &String, group: &BillingGroup) {
+    storage::write_persistent(env, &GroupKey::BillingGroup(group_id.clone()), group);
+}
+
+pub fn create(
+    env: &Env,
+    group_id: &String,
+    group_admin: &Address,
+    split: GroupSplit,
+) -> Result<(), Error> {
+    group_admin.require_auth();
+    if read(env, group_id).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+    let group = BillingGroup {
+        admin: group_admin.clone(),
+        split,
+        members: Map::new(env),
+    };
+    write(env, group_id, &group);
+    env.events().publish(
+        (Symbol::new(env, "billing_group_created"), group_id.clone()),
+        group_admin.clone(),
+    );
+    Ok(())
+}
+
+fn require_group_admin(env: &Env, group_id: &String) -> Result<BillingGroup, Error> {
+    let group = read(env, group_id).ok_or(Error::InvalidInput)?;
+    group.admin.require_auth();
+    Ok(group)
+}
+
+// Adds the meter, or updates its share if it is already a member.
+pub fn set_member(
+    env: &Env,
+    group_id: &String,
+    meter_id: &String,
+    share_bps: u32,
+) -> Result<(), Error> {
+    let mut group = require_group_admin(env, group_id)?;
+    if share_bps as i128 > BPS_DENOMINATOR {
+        return Err(Error::InvalidConfig);
+    }
+    if !group.members.contains_key(meter_id.clone())
+        && group.members.len() >= payments::MAX_BATCH_SIZE
+    {
+        return Err(Error::InvalidInput);
+    }
+    group.members.set(meter_id.clone(), share_bps);
+    write(env, group_id, &group);
+    env.events().publish(
+        (Symbol::new(env, "group_member_set"), group_id.clone()),
+        (meter_id.clone(), share_bps),
+    );
+    Ok(())
+}
+
+pub fn remove_member(env: &Env, group_id: &String, meter_id: &String) -> Result<(), Error> {
+    let mut group = require_group_admin(env, group_id)?;
+    if group.members.remove(meter_id.clone()).is_none() {
+        return Err(Error::InvalidInput);
+    }
+    write(env, group_id, &group);
+    env.events().publish(
+        (Symbol::new(env, "group_member_removed"), group_id.clone()),
+        meter_id.clone(),
+    );
+    Ok(())
+}
+
+// Divides `amount` by the group's split. The rounding remainder goes to the
+// last member with a non-zero weight; members allotted nothing are left out.
+fn allocate(env: &Env, group: &BillingGroup, amount: i128) -> Result<Vec<(String, i128)>, Error> {
+    let mut weights = Vec::new(env);
+    let mut total_weight: i128 = 0;
+    for (meter_id, share_bps) in group.members.iter() {
+        let weight = match group.split {
+            GroupSplit::Outstanding => billing::balance(env, &meter_id).max(0),
+            GroupSplit::Shares => share_bps as i128,
+        };
+        total_weight += weight;
+        weights.push_back((meter_id, weight));
+    }
+    match group.split {
+        GroupSplit::Outstanding if total_weight == 0 => return Err(Error::InvalidState),
+        GroupSplit::Shares if total_weight != BPS_DENOMINATOR => return Err(Error::InvalidConfig),
+        _ => {}
+    }
+
+    let mut bills = Vec::new(env);
+    let mut allotted: i128 = 0;
+    for (meter_id, weight) in weights.iter() {
+        let share = amount * weight / total_weight;
+        if weight > 0 {
+            allotted += share;
+            bills.push_back((meter_id, share));
+        }
+    }
+    let last = bills.len() - 1;
+    let (meter_id, share) = bills.get_unchecked(last);
+    bills.set(last, (meter_id, share + amount - allotted));
+
+    let mut paid = Vec::new(env);
+    for (meter_id, share) in bills.iter() {
+        if share > 0 {
+            paid.push_back((meter_id, share));
+        }
+    }
+    Ok(paid)
+}
+
+// Pays the group's members out of one payment. Returns what each member was
+// paid, in token units.
+pub fn pay(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    group_id: &String,
+    amount: i128,
+) -> Result<Vec<(String, i128)>, Error> {
+    let group = read(env, group_id).ok_or(Error::InvalidInput)?;
+    if amount <= 0 {
+        return Err(Error::AmountTooSmall);
+    }
+    let bills = allocate(env, &group, amount)?;
+    payments::pay_batch(env, from, token_address, &bills)?;
+    env.events().publish(
+        (Symbol::new(env, "group_bill_paid"), group_id.clone()),
+        (from.clone(), token_address.clone(), amount),
+    );
+    Ok(bills)
+}
+
+pub fn statement(env: &Env, group_id: &String, period: u32) -> Result<GroupStatement, Error> {
+    let group = read(env, group_id).ok_or(Error::InvalidInput)?;
+    let mut statement = GroupStatement {
+        period,
+        kwh: 0,
+        billed: 0,
+        outstanding: 0,
+        lines: Vec::new(env),
+    };
+    for (meter_id, _) in group.members.iter() {
+        let (kwh, billed) = match billing::read_bill(env, &meter_id, period) {
+            Some(bill) => (bill.kwh, bill.amount - bill.export_credit + bill.adjustment),
+            None => (0, 0),
+        };
+        let outstanding = billing::balance(env, &meter_id);
+        statement.kwh += kwh;
+        statement.billed += billed;
+        statement.outstanding += outstanding;
+        statement.lines.push_back(GroupStatementLine {
+            meter_id,
+            kwh,
+            billed,
+            outstanding,
+        });
+    }
+    Ok(statement)
+}
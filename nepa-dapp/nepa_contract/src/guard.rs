@@ -0,0 +1,25 @@
+use soroban_sdk::{contracttype, Env};
+
+use crate::errors::Error;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GuardKey {
+    CallInProgress,
+}
+
+// Runs `body` with the in-progress flag set, so a token contract calling back
+// into a payment function during its transfer is refused. The flag lives in
+// instance storage, which every call loads anyway, so it costs no extra
+// ledger entry; it is cleared on the way out and a failed call reverts it with
+// everything else.
+pub fn non_reentrant<T>(env: &Env, body: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    let storage = env.storage().instance();
+    if storage.has(&GuardKey::CallInProgress) {
+        return Err(Error::ReentrantCall);
+    }
+    storage.set(&GuardKey::CallInProgress, &true);
+    let result = body();
+    env.storage().instance().remove(&GuardKey::CallInProgress);
+    result
+}
@@ -0,0 +1,62 @@
+use soroban_sdk::{contracttype, vec, Address, Env, IntoVal, String, Symbol, Val};
+
+use crate::accounting::PaymentRecord;
+use crate::admin;
+
+// Contract function called after every settled payment with
+// `(meter_id, payment_index, record)`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentHook {
+    pub contract: Address,
+    pub function: Symbol,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HookKey {
+    PaymentHook,
+}
+
+pub fn read(env: &Env) -> Option<PaymentHook> {
+    env.storage().instance().get(&HookKey::PaymentHook)
+}
+
+pub fn set(env: &Env, hook: &PaymentHook) {
+    admin::require_admin(env);
+    env.storage().instance().set(&HookKey::PaymentHook, hook);
+    env.events().publish(
+        (Symbol::new(env, "payment_hook_set"),),
+        (hook.contract.clone(), hook.function.clone()),
+    );
+}
+
+pub fn remove(env: &Env) {
+    admin::require_admin(env);
+    env.storage().instance().remove(&HookKey::PaymentHook);
+    env.events()
+        .publish((Symbol::new(env, "payment_hook_removed"),), ());
+}
+
+// Best effort: a hook that fails or traps has its own changes rolled back and
+// never fails the payment. It runs inside the payment's reentrancy guard, so
+// it can read this contract but not pay through it.
+pub fn on_payment(env: &Env, meter_id: &String, index: u32, record: &PaymentRecord) {
+    let Some(hook) = read(env) else {
+        return;
+    };
+    let args = vec![
+        env,
+        meter_id.into_val(env),
+        index.into_val(env),
+        record.into_val(env),
+    ];
+    let result =
+        env.try_invoke_contract::<Val, soroban_sdk::Error>(&hook.contract, &hook.function, args);
+    if result.is_err() {
+        env.events().publish(
+            (Symbol::new(env, "payment_hook_failed"), meter_id.clone()),
+            (hook.contract, index),
+        );
+    }
+}
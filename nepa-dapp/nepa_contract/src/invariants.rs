@@ -0,0 +1,221 @@
+use soroban_sdk::{contracttype, token, Address, Env, Symbol, Vec};
+
+use crate::accounting;
+use crate::admin;
+use crate::deposits;
+use crate::errors::Error;
+use crate::escrow::{self, EscrowStatus};
+use crate::fees;
+use crate::keepers;
+use crate::receipts;
+use crate::retention;
+use crate::sessions;
+use crate::sponsorship;
+use crate::storage;
+use crate::tokens;
+use crate::vendors;
+use crate::wholesale;
+
+// Most receipts and escrows a single call inspects, each.
+pub const MAX_CHECKS_PER_CALL: u32 = 20;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Violation {
+    // A receipt does not match the payment it points at.
+    ReceiptMismatch(u64),
+    // A confirmed escrow has no matching payment in the meter's history.
+    EscrowMismatch(u64),
+    // The contract holds less of a token than it owes pending escrows and
+    // open metering sessions.
+    Insolvent(Address),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvariantCursor {
+    pub next_receipt: u64,
+    pub next_escrow: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BountyConfig {
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvariantReport {
+    pub checked: u32,
+    // Violations surfaced for the first time by this call.
+    pub new_violations: u32,
+    pub bounty_paid: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InvariantKey {
+    InvariantCursor,
+    Bounty,
+    Violation(Violation),
+}
+
+pub fn read_bounty(env: &Env) -> Option<BountyConfig> {
+    env.storage().instance().get(&InvariantKey::Bounty)
+}
+
+pub fn set_bounty(env: &Env, bounty: &BountyConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if bounty.amount < 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage().instance().set(&InvariantKey::Bounty, bounty);
+    Ok(())
+}
+
+pub fn is_recorded(env: &Env, violation: &Violation) -> bool {
+    env.storage()
+        .persistent()
+        .has(&InvariantKey::Violation(violation.clone()))
+}
+
+fn receipt_holds(env: &Env, receipt_id: u64) -> bool {
+    let Some(receipt) = receipts::read(env, receipt_id) else {
+        return true;
+    };
+    if retention::is_payment_pruned(env, &receipt.meter_id, receipt.payment_index) {
+        return true;
+    }
+    accounting::read_payment(env, &receipt.meter_id, receipt.payment_index).is_some_and(|p| {
+        p.payer == receipt.payer && p.token == receipt.token && p.amount == receipt.amount
+    })
+}
+
+fn escrow_holds(env: &Env, escrow_id: u64) -> bool {
+    let Some(escrow) = escrow::read(env, escrow_id) else {
+        return true;
+    };
+    if escrow.status != EscrowStatus::Confirmed
+        || retention::is_payment_pruned(env, &escrow.meter_id, escrow.payment_index)
+    {
+        return true;
+    }
+    accounting::read_payment(env, &escrow.meter_id, escrow.payment_index)
+        .is_some_and(|p| p == escrow.record)
+}
+
+// Walks `count` ids from `start`, wrapping back to 1 past the last issued id.
+// Returns the id to resume from.
+fn walk(start: u64, next_issued: u64, count: u32, mut check: impl FnMut(u64)) -> u64 {
+    let mut id = start;
+    for _ in 0..count.min((next_issued - 1) as u32) {
+        if id >= next_issued {
+            id = 1;
+        }
+        check(id);
+        id += 1;
+    }
+    id
+}
+
+// Anyone may run the checker. Each call covers the next `limit` receipts and
+// escrows and every accepted token's solvency; a caller surfacing a violation
+// no one has reported before earns the configured bounty.
+pub fn check(env: &Env, caller: &Address, limit: u32) -> Result<InvariantReport, Error> {
+    caller.require_auth();
+    if limit == 0 || limit > MAX_CHECKS_PER_CALL {
+        return Err(Error::InvalidInput);
+    }
+    let mut cursor: InvariantCursor = env
+        .storage()
+        .instance()
+        .get(&InvariantKey::InvariantCursor)
+        .unwrap_or(InvariantCursor {
+            next_receipt: 1,
+            next_escrow: 1,
+        });
+
+    let mut checked = 0;
+    let mut found = Vec::new(env);
+    cursor.next_receipt = walk(cursor.next_receipt, receipts::next_id(env), limit, |id| {
+        checked += 1;
+        if !receipt_holds(env, id) {
+            found.push_back(Violation::ReceiptMismatch(id));
+        }
+    });
+    cursor.next_escrow = walk(cursor.next_escrow, escrow::next_id(env), limit, |id| {
+        checked += 1;
+        if !escrow_holds(env, id) {
+            found.push_back(Violation::EscrowMismatch(id));
+        }
+    });
+    let contract = env.current_contract_address();
+    for token_address in tokens::list(env).iter() {
+        checked += 1;
+        let held = token::Client::new(env, &token_address).balance(&contract);
+        let owed =
+            escrow::pending_total(env, &token_address) + sessions::locked_in(env, &token_address);
+        if held < owed {
+            found.push_back(Violation::Insolvent(token_address));
+        }
+    }
+    env.storage()
+        .instance()
+        .set(&InvariantKey::InvariantCursor, &cursor);
+
+    let mut new_violations = 0;
+    for violation in found.iter() {
+        if is_recorded(env, &violation) {
+            continue;
+        }
+        new_violations += 1;
+        storage::write_persistent(env, &InvariantKey::Violation(violation.clone()), &true);
+        env.events().publish(
+            (Symbol::new(env, "invariant_violated"), caller.clone()),
+            violation,
+        );
+    }
+
+    let bounty_paid = match read_bounty(env) {
+        Some(bounty) if new_violations > 0 => pay_bounty(env, caller, &bounty),
+        _ => 0,
+    };
+    Ok(InvariantReport {
+        checked,
+        new_violations,
+        bounty_paid,
+    })
+}
+
+// What the contract holds in `token_address` beyond pending escrows, session
+// locks, the keeper reward pool, unclaimed agent commission, undrawn
+// wholesale pools, security deposits and fees owed to the collector.
+pub fn reserve(env: &Env, token_address: &Address) -> i128 {
+    token::Client::new(env, token_address).balance(&env.current_contract_address())
+        - escrow::pending_total(env, token_address)
+        - sessions::locked_in(env, token_address)
+        - keepers::pool_in(env, token_address)
+        - vendors::owed(env, token_address)
+        - wholesale::pooled_in(env, token_address)
+        - deposits::held_in(env, token_address)
+        - fees::accrued_in(env, token_address)
+        - sponsorship::held_in(env, token_address)
+}
+
+// Paid from the reserve.
+fn pay_bounty(env: &Env, caller: &Address, bounty: &BountyConfig) -> i128 {
+    let client = token::Client::new(env, &bounty.token);
+    let contract = env.current_contract_address();
+    let amount = bounty.amount.min(reserve(env, &bounty.token));
+    if amount <= 0 {
+        return 0;
+    }
+    client.transfer(&contract, caller, &amount);
+    env.events().publish(
+        (Symbol::new(env, "invariant_bounty_paid"), caller.clone()),
+        amount,
+    );
+    amount
+}
@@ -0,0 +1,306 @@
+use soroban_sdk::{contracttype, token, Address, BytesN, Env, String, Symbol};
+
+use crate::admin;
+use crate::budgets;
+use crate::errors::Error;
+use crate::math;
+use crate::oracle::OracleManager;
+use crate::storage;
+use crate::tariff;
+
+// Bounds on any refresh interval: at least a minute, at most thirty days.
+pub const MIN_UPDATE_INTERVAL: u64 = 60;
+pub const MAX_UPDATE_INTERVAL: u64 = 30 * 86_400;
+
+// Reward paid to whoever relays a signed price report for a feed that is due.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeeperConfig {
+    pub token: Address,
+    pub reward: i128,
+    // A feed is due once its price is at least this old.
+    pub update_interval_seconds: u64,
+}
+
+// How often prices and rates are expected to be refreshed; 0 where unset.
+// The price interval is kept in step with the keeper config's; utility rates
+// are due once older than `utility_rate_interval`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UpdateSchedule {
+    pub price_feed_interval: u64,
+    pub utility_rate_interval: u64,
+}
+
+// When one feed is next expected to update. `interval_seconds` is 0 and the
+// feed never due while no keeper config exists; `last_updated` is 0 for a
+// feed never pushed.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeedSchedule {
+    pub interval_seconds: u64,
+    pub last_updated: u64,
+    pub next_update_at: u64,
+    pub due: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeeperKey {
+    KeeperConfig,
+    // Reward token balance set aside for keepers.
+    KeeperPool,
+    RewardsEarned(Address),
+    // Kept in instance storage.
+    RateUpdateInterval,
+    PriceUpdateInterval,
+    // Overrides the schedule's price interval for one volatile feed.
+    FeedUpdateInterval(String),
+}
+
+pub fn read_config(env: &Env) -> Option<KeeperConfig> {
+    env.storage().instance().get(&KeeperKey::KeeperConfig)
+}
+
+pub fn set_config(env: &Env, config: &KeeperConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if config.reward <= 0 {
+        return Err(Error::InvalidConfig);
+    }
+    validate_interval(config.update_interval_seconds)?;
+    // The pool is held in one token; it must be drained before switching.
+    let switching = read_config(env).is_some_and(|current| current.token != config.token);
+    if switching && pool(env) > 0 {
+        return Err(Error::InvalidState);
+    }
+    let instance = env.storage().instance();
+    instance.set(&KeeperKey::KeeperConfig, config);
+    instance.set(
+        &KeeperKey::PriceUpdateInterval,
+        &config.update_interval_seconds,
+    );
+    Ok(())
+}
+
+pub fn pool(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&KeeperKey::KeeperPool)
+        .unwrap_or(0)
+}
+
+// Pool balance held in `token_address`, so other payouts leave it alone.
+pub fn pool_in(env: &Env, token_address: &Address) -> i128 {
+    match read_config(env) {
+        Some(config) if config.token == *token_address => pool(env),
+        _ => 0,
+    }
+}
+
+fn write_pool(env: &Env, amount: i128) {
+    env.storage()
+        .instance()
+        .set(&KeeperKey::KeeperPool, &amount);
+}
+
+pub fn earnings(env: &Env, keeper: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&KeeperKey::RewardsEarned(keeper.clone()))
+        .unwrap_or(0)
+}
+
+pub fn fund(env: &Env, from: &Address, amount: i128) -> Result<i128, Error> {
+    from.require_auth();
+    let config = read_config(env).ok_or(Error::InvalidConfig)?;
+    if amount <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    let balance = math::add(pool(env), amount)?;
+    write_pool(env, balance);
+    token::Client::new(env, &config.token).transfer(from, &env.current_contract_address(), &amount);
+    env.events().publish(
+        (Symbol::new(env, "keeper_pool_funded"), from.clone()),
+        (amount, balance),
+    );
+    Ok(balance)
+}
+
+// Every refresh interval, keeper or scheduled, per feed or global.
+fn validate_interval(interval: u64) -> Result<(), Error> {
+    if !(MIN_UPDATE_INTERVAL..=MAX_UPDATE_INTERVAL).contains(&interval) {
+        return Err(Error::InvalidConfig);
+    }
+    Ok(())
+}
+
+// None until a schedule or keeper config is set. Keeper configs set before
+// the schedule existed still supply its price interval.
+pub fn schedule(env: &Env) -> Option<UpdateSchedule> {
+    let instance = env.storage().instance();
+    let price_feed_interval = instance
+        .get(&KeeperKey::PriceUpdateInterval)
+        .or_else(|| read_config(env).map(|config| config.update_interval_seconds));
+    let utility_rate_interval = instance.get(&KeeperKey::RateUpdateInterval);
+    if price_feed_interval.is_none() && utility_rate_interval.is_none() {
+        return None;
+    }
+    Some(UpdateSchedule {
+        price_feed_interval: price_feed_interval.unwrap_or(0),
+        utility_rate_interval: utility_rate_interval.unwrap_or(0),
+    })
+}
+
+// Changes the refresh intervals without touching the keeper reward or pool,
+// and may be set before keepers are configured.
+pub fn set_schedule(
+    env: &Env,
+    price_feed_interval: u64,
+    utility_rate_interval: u64,
+) -> Result<(), Error> {
+    admin::require_admin(env);
+    validate_interval(price_feed_interval)?;
+    validate_interval(utility_rate_interval)?;
+    let instance = env.storage().instance();
+    if let Some(mut config) = read_config(env) {
+        config.update_interval_seconds = price_feed_interval;
+        instance.set(&KeeperKey::KeeperConfig, &config);
+    }
+    instance.set(&KeeperKey::PriceUpdateInterval, &price_feed_interval);
+    instance.set(&KeeperKey::RateUpdateInterval, &utility_rate_interval);
+    env.events().publish(
+        (Symbol::new(env, "update_schedule_set"),),
+        (price_feed_interval, utility_rate_interval),
+    );
+    Ok(())
+}
+
+pub fn feed_interval(env: &Env, feed_id: &String) -> Option<u64> {
+    env.storage()
+        .persistent()
+        .get(&KeeperKey::FeedUpdateInterval(feed_id.clone()))
+}
+
+// Gives a volatile feed its own refresh interval; 0 returns it to the schedule.
+pub fn set_feed_interval(env: &Env, feed_id: &String, interval: u64) -> Result<(), Error> {
+    admin::require_admin(env);
+    if OracleManager::get_price_feed(env, feed_id).is_none() {
+        return Err(Error::InvalidInput);
+    }
+    let key = KeeperKey::FeedUpdateInterval(feed_id.clone());
+    if interval == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        validate_interval(interval)?;
+        storage::write_persistent(env, &key, &interval);
+    }
+    env.events().publish(
+        (Symbol::new(env, "feed_interval_set"), feed_id.clone()),
+        interval,
+    );
+    Ok(())
+}
+
+pub fn feed_schedule(env: &Env, feed_id: &String) -> FeedSchedule {
+    let last_updated = OracleManager::get_price_feed(env, feed_id)
+        .map(|feed| feed.last_updated)
+        .unwrap_or(0);
+    let Some(config) = read_config(env) else {
+        return FeedSchedule {
+            interval_seconds: 0,
+            last_updated,
+            next_update_at: 0,
+            due: false,
+        };
+    };
+    let interval_seconds = feed_interval(env, feed_id).unwrap_or(config.update_interval_seconds);
+    FeedSchedule {
+        interval_seconds,
+        last_updated,
+        next_update_at: last_updated.saturating_add(interval_seconds),
+        due: feed_due(env, &config, feed_id),
+    }
+}
+
+fn feed_due(env: &Env, config: &KeeperConfig, feed_id: &String) -> bool {
+    let interval = feed_interval(env, feed_id).unwrap_or(config.update_interval_seconds);
+    match OracleManager::get_price_feed(env, feed_id) {
+        Some(feed) => env.ledger().timestamp().saturating_sub(feed.last_updated) >= interval,
+        None => true,
+    }
+}
+
+// True when any feed with a registered reporter is due for an update.
+pub fn should_update(env: &Env) -> bool {
+    let Some(config) = read_config(env) else {
+        return false;
+    };
+    OracleManager::get_price_feed_ids(env)
+        .iter()
+        .any(|feed_id| {
+            OracleManager::get_reporter_key(env, &feed_id).is_some()
+                && feed_due(env, &config, &feed_id)
+        })
+}
+
+// True when any registered rate is older than the schedule's rate interval.
+pub fn should_update_rates(env: &Env) -> bool {
+    let Some(interval) = schedule(env)
+        .map(|schedule| schedule.utility_rate_interval)
+        .filter(|interval| *interval > 0)
+    else {
+        return false;
+    };
+    let now = env.ledger().timestamp();
+    tariff::rate_ids(env).iter().any(|rate_id| {
+        tariff::read_rate(env, &rate_id)
+            .is_some_and(|rate| now.saturating_sub(rate.last_updated) >= interval)
+    })
+}
+
+// Anyone may relay a reporter-signed price. The update is kept whenever it
+// verifies; the keeper is rewarded only if the feed was due, the price was not
+// held by the deviation guard and the pool can cover it. Returns the reward paid.
+pub fn submit(
+    env: &Env,
+    keeper: &Address,
+    feed_id: &String,
+    price: i128,
+    decimals: u32,
+    timestamp: u64,
+    signature: &BytesN<64>,
+) -> Result<i128, Error> {
+    keeper.require_auth();
+    budgets::charge_caller(env, keeper)?;
+    let config = read_config(env);
+    let due = config
+        .as_ref()
+        .is_some_and(|config| feed_due(env, config, feed_id));
+    let applied = OracleManager::update_price_feed_signed(
+        env, feed_id, price, decimals, timestamp, signature,
+    )?;
+
+    let Some(config) = config.filter(|_| due && applied) else {
+        return Ok(0);
+    };
+    let balance = pool(env);
+    if balance < config.reward {
+        return Ok(0);
+    }
+    write_pool(env, balance - config.reward);
+    storage::write_persistent(
+        env,
+        &KeeperKey::RewardsEarned(keeper.clone()),
+        &math::add(earnings(env, keeper), config.reward)?,
+    );
+    token::Client::new(env, &config.token).transfer(
+        &env.current_contract_address(),
+        keeper,
+        &config.reward,
+    );
+    env.events().publish(
+        (Symbol::new(env, "keeper_rewarded"), keeper.clone()),
+        (feed_id.clone(), config.reward),
+    );
+    Ok(config.reward)
+}
@@ -0,0 +1,13 @@
+use soroban_sdk::{Env, Symbol};
+
+// Emitted by the original entry points so integrators can find callers that
+// still need to move to the typed API.
+pub fn deprecated_call(env: &Env, function: &str, replacement: &str) {
+    env.events().publish(
+        (
+            Symbol::new(env, "deprecated_call"),
+            Symbol::new(env, function),
+        ),
+        Symbol::new(env, replacement),
+    );
+}
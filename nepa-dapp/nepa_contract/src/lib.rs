@@ -1,29 +1,2523 @@
 #![no_std]
-// We added 'Address' and 'token' to the imports
-use soroban_sdk::{contract, contractimpl, Address, Env, String, token};
+use soroban_sdk::{
+    contract, contractimpl, panic_with_error, Address, BytesN, Env, Map, String, Symbol, Vec,
+};
+
+use oracle::OracleManager;
+
+mod accounting;
+mod admin;
+mod alerts;
+mod anomalies;
+mod audit;
+mod billing;
+mod bounds;
+mod budgets;
+mod capacity;
+mod closures;
+mod collections;
+mod credits;
+mod delegation;
+mod deposits;
+mod disputes;
+mod dunning;
+mod emergency;
+mod errors;
+mod escrow;
+mod fees;
+mod green;
+mod groups;
+mod guard;
+mod hooks;
+mod invariants;
+mod keepers;
+mod legacy;
+mod limits;
+mod loyalty;
+mod maintenance;
+mod math;
+mod meters;
+mod mirror;
+#[cfg(any(test, feature = "testutils"))]
+pub mod mock_oracle;
+mod multisig;
+mod netmetering;
+mod oracle;
+mod ownership;
+mod payments;
+mod peg;
+mod periods;
+mod plans;
+mod portability;
+#[cfg(all(test, feature = "proptest"))]
+mod proptests;
+mod quotes;
+mod readings;
+mod receipts;
+mod recovery;
+mod retention;
+mod rollups;
+mod sep40;
+mod sessions;
+mod settlement;
+#[cfg(not(target_family = "wasm"))]
+pub mod signing;
+mod sponsorship;
+mod storage;
+mod subsidy;
+mod swap;
+mod tariff;
+mod taxes;
+#[cfg(any(test, feature = "testutils"))]
+pub mod testutils;
+mod timelock;
+mod tokens;
+mod units;
+mod velocity;
+mod vendors;
+mod version;
+mod vouchers;
+mod wholesale;
+
+#[cfg(all(test, not(feature = "embedded-oracle")))]
+mod external_oracle_tests;
+#[cfg(all(test, feature = "embedded-oracle"))]
+mod tests;
+
+pub use accounting::{MeterSummary, PaymentRecord, SequencedPayment};
+pub use alerts::MonthlySpend;
+pub use anomalies::{Anomaly, AnomalyConfig, AnomalyKind, RollingAverage};
+pub use audit::{AuditAction, AuditEntry};
+pub use billing::{
+    BillBreakdown, BillingRecord, EffectiveRate, InvoicePreview, RateSnapshot, RoundingMode,
+    RoundingPolicy, RoundingUnit,
+};
+pub use bounds::InputBounds;
+pub use budgets::{OracleCostStats, ReporterStats};
+pub use capacity::{CapacityAgreement, CapacityStatement, CapacityTerms, UnusedBlockPolicy};
+pub use closures::ClosedAccount;
+pub use collections::CollectionMandate;
+pub use credits::CreditRefund;
+pub use delegation::{DelegatedPayment, DelegatedPoints};
+pub use deposits::{DepositConfig, SecurityDeposit};
+pub use disputes::{Dispute, DisputeStatus, RegulatorClient, RegulatorInterface};
+pub use dunning::{
+    DebtStatus, DebtTolerance, DisconnectionNotice, DisconnectionReason, DunningConfig,
+};
+pub use emergency::{EmergencyCredit, EmergencyCreditConfig};
+pub use errors::Error;
+pub use escrow::{EscrowStatus, EscrowedPayment};
+pub use fees::FeeConfig;
+pub use green::{GenerationMix, GreenCredits};
+pub use groups::{BillingGroup, GroupSplit, GroupStatement, GroupStatementLine};
+pub use hooks::PaymentHook;
+pub use invariants::{BountyConfig, InvariantCursor, InvariantReport, Violation};
+pub use keepers::{FeedSchedule, KeeperConfig, UpdateSchedule};
+pub use limits::SpendingLimit;
+pub use loyalty::{LoyaltyAccount, LoyaltyConfig};
+pub use maintenance::MaintenanceWindow;
+pub use meters::{BandChange, MeterMetadata, SupplyPhase};
+pub use mirror::{MirrorInfo, SnapshotEntry};
+pub use multisig::{AdminAction, MultisigConfig, Proposal};
+pub use oracle::{
+    DataFeed, FallbackChain, FeedListing, FeedReliability, OracleConfig, OracleStats, PriceFeed,
+    PricePoint, PriceSource, ReliabilitySummary, UpdateOutcome,
+};
+pub use ownership::MeterTransfer;
+pub use peg::PegGuard;
+pub use periods::BillingCycle;
+pub use plans::{PaymentPlan, PlanReport, PlanStatus};
+pub use portability::{MeterExport, MeterPortRecord, PortDirection};
+pub use quotes::{BillQuote, LockedQuote};
+pub use readings::MeterReading;
+pub use receipts::{PaymentProof, Receipt};
+pub use recovery::{RecoveryClaim, RecoveryConfig};
+pub use retention::{PruneState, RetentionConfig};
+pub use rollups::MonthlyStats;
+pub use sep40::{ExternalPriceSource, Sep40Asset, Sep40Client, Sep40Interface, Sep40PriceData};
+pub use sessions::MeteringSession;
+pub use settlement::{FiatReceipt, ReconciliationReport, ReconciliationStatus, SweepTotals};
+pub use sponsorship::{SponsoredMeter, SponsorshipConfig, SponsorshipPool};
+pub use storage::StorageEntry;
+pub use subsidy::SubsidyScheme;
+pub use swap::{SwapConfig, SwapRouterClient, SwapRouterInterface};
+pub use tariff::{
+    RateKey, RateListing, ScheduledRate, TariffOp, TariffTier, TouBand, TouSchedule, TouWindow,
+    UtilityRate, UtilityUsage,
+};
+pub use taxes::{LineItem, TaxComponent, TaxKind};
+pub use timelock::{ChangeStatus, QueuedChange, TimelockChange};
+pub use tokens::{TokenConfig, TokenMetadata};
+pub use units::{Consumption, MeteredUnit};
+pub use velocity::{PayerActivity, VelocityConfig};
+pub use vendors::VendingAgent;
+pub use version::VersionInfo;
+pub use vouchers::Voucher;
+pub use wholesale::{PoolShare, SplitConfig, WholesalePool};
 
 #[contract]
 pub struct NepaBillingContract;
 
 #[contractimpl]
 impl NepaBillingContract {
-    
-    pub fn pay_bill(env: Env, from: Address, token_address: Address, meter_id: String, amount: i128) {
-        // 1. Verify the user authorized this payment
-        from.require_auth();
+    pub fn initialize(env: Env, admin: Address) -> Result<(), Error> {
+        if admin::has_admin(&env) {
+            return Err(Error::AlreadyInitialized);
+        }
+        admin.require_auth();
+        admin::write_admin(&env, &admin);
+        storage::extend_instance(&env);
+        Ok(())
+    }
+
+    pub fn set_treasury(env: Env, treasury: Address) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        admin::require_admin(&env);
+        admin::write_treasury(&env, &treasury);
+        audit::role_change(&env, "treasury", &treasury, true);
+        Ok(())
+    }
+
+    pub fn get_treasury(env: Env) -> Address {
+        admin::read_treasury(&env)
+    }
+
+    // The hash is a top-level argument so signing devices can display it.
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        multisig::ensure_not_required(&env)?;
+        admin::require_admin(&env);
+        admin::upgrade(&env, &new_wasm_hash);
+        Ok(())
+    }
+
+    // --- Audit log of admin-sensitive operations ---
+
+    // Up to 50 entries from `from_seq` on; sequence numbers start at 1 and have no gaps.
+    pub fn get_audit_entries(env: Env, from_seq: u64, limit: u32) -> Vec<AuditEntry> {
+        audit::entries(&env, from_seq, limit)
+    }
+
+    // Sequence number of the latest entry, 0 while the log is empty.
+    pub fn get_audit_head(env: Env) -> u64 {
+        audit::head(&env)
+    }
+
+    // --- Multi-signature admin operations ---
+
+    // Sets the first signer set; afterwards sweeps, rate changes and upgrades need proposals.
+    pub fn configure_multisig(
+        env: Env,
+        signers: Vec<Address>,
+        threshold: u32,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        multisig::configure(&env, &signers, threshold)
+    }
+
+    pub fn get_multisig_config(env: Env) -> Option<MultisigConfig> {
+        multisig::read_config(&env)
+    }
+
+    pub fn propose_action(env: Env, proposer: Address, action: AdminAction) -> Result<u64, Error> {
+        maintenance::ensure_writable(&env)?;
+        multisig::propose(&env, &proposer, &action)
+    }
+
+    // Returns the number of approvals so far.
+    pub fn approve_action(env: Env, signer: Address, proposal_id: u64) -> Result<u32, Error> {
+        maintenance::ensure_writable(&env)?;
+        multisig::approve(&env, &signer, proposal_id)
+    }
+
+    pub fn execute_action(env: Env, proposal_id: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        multisig::execute(&env, proposal_id)
+    }
+
+    pub fn get_proposal(env: Env, proposal_id: u64) -> Option<Proposal> {
+        multisig::read_proposal(&env, proposal_id)
+    }
+
+    // --- Timelocked configuration changes ---
+
+    // With a delay in force, rate and oracle config changes must be queued here.
+    pub fn queue_change(env: Env, change: TimelockChange) -> Result<u64, Error> {
+        maintenance::ensure_writable(&env)?;
+        timelock::queue(&env, &change)
+    }
+
+    pub fn execute_change(env: Env, change_id: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        timelock::execute(&env, change_id)
+    }
+
+    pub fn cancel_change(env: Env, change_id: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        timelock::cancel(&env, change_id)
+    }
+
+    pub fn get_queued_change(env: Env, change_id: u64) -> Option<QueuedChange> {
+        timelock::read_change(&env, change_id)
+    }
+
+    pub fn get_timelock_delay(env: Env) -> u64 {
+        timelock::delay(&env)
+    }
+
+    // --- Admin recovery after inactivity ---
+
+    pub fn set_recovery_config(env: Env, config: RecoveryConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        recovery::set_config(&env, &config)
+    }
+
+    pub fn clear_recovery_config(env: Env) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        recovery::clear_config(&env)
+    }
+
+    pub fn get_recovery_config(env: Env) -> Option<RecoveryConfig> {
+        recovery::read_config(&env)
+    }
+
+    // Any admin-authorised call voids a pending claim; this one does nothing else.
+    pub fn cancel_admin_recovery(env: Env) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        admin::require_admin(&env);
+        Ok(())
+    }
+
+    // Returns when the claim may be completed.
+    pub fn claim_admin_recovery(env: Env) -> Result<u64, Error> {
+        recovery::open_claim(&env)
+    }
+
+    pub fn complete_admin_recovery(env: Env) -> Result<(), Error> {
+        recovery::complete_claim(&env)
+    }
+
+    pub fn get_recovery_claim(env: Env) -> Option<RecoveryClaim> {
+        recovery::claim(&env)
+    }
+
+    pub fn get_admin_last_active(env: Env) -> u64 {
+        recovery::last_active(&env)
+    }
+
+    // --- Meter ownership and transfers ---
+
+    pub fn set_meter_owner(env: Env, meter_id: String, owner: Address) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        ownership::set_owner(&env, &meter_id, &owner);
+        Ok(())
+    }
+
+    pub fn get_meter_owner(env: Env, meter_id: String) -> Option<Address> {
+        ownership::owner(&env, &meter_id)
+    }
+
+    pub fn initiate_meter_transfer(
+        env: Env,
+        current_owner: Address,
+        meter_id: String,
+        new_owner: Address,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        ownership::initiate(&env, &current_owner, &meter_id, &new_owner)
+    }
+
+    // Fails while the meter owes a balance unless `accept_debt` is set.
+    pub fn accept_meter_transfer(
+        env: Env,
+        new_owner: Address,
+        meter_id: String,
+        accept_debt: bool,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        ownership::accept(&env, &new_owner, &meter_id, accept_debt)
+    }
+
+    // `caller` is either party to the transfer.
+    pub fn cancel_meter_transfer(env: Env, caller: Address, meter_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        ownership::cancel(&env, &caller, &meter_id)
+    }
+
+    pub fn get_pending_meter_transfer(env: Env, meter_id: String) -> Option<MeterTransfer> {
+        ownership::pending(&env, &meter_id)
+    }
+
+    // --- Meter portability between provider instances ---
+
+    pub fn approve_meter_export(
+        env: Env,
+        meter_id: String,
+        destination: Address,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        portability::approve_export(&env, &meter_id, &destination)
+    }
+
+    // Called by the destination contract from `import_meter`.
+    pub fn finalize_meter_export(
+        env: Env,
+        meter_id: String,
+        destination: Address,
+    ) -> Result<MeterExport, Error> {
+        maintenance::ensure_writable(&env)?;
+        portability::finalize_export(&env, &meter_id, &destination)
+    }
+
+    pub fn import_meter(env: Env, source: Address, meter_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        portability::import(&env, &source, &meter_id)
+    }
+
+    pub fn get_meter_export(env: Env, meter_id: String) -> Option<MeterPortRecord> {
+        portability::read_outgoing(&env, &meter_id)
+    }
+
+    pub fn get_meter_import(env: Env, meter_id: String) -> Option<MeterPortRecord> {
+        portability::read_incoming(&env, &meter_id)
+    }
+
+    // --- Read-only mirrors for disaster recovery drills ---
+
+    pub fn export_snapshot(
+        env: Env,
+        entries: Vec<StorageEntry>,
+    ) -> Result<Vec<SnapshotEntry>, Error> {
+        mirror::export(&env, &entries)
+    }
+
+    pub fn initialize_mirror(
+        env: Env,
+        admin: Address,
+        source: Address,
+        snapshot_ledger: u32,
+    ) -> Result<(), Error> {
+        mirror::initialize(&env, &admin, &source, snapshot_ledger)
+    }
+
+    pub fn import_snapshot(env: Env, snapshot: Vec<SnapshotEntry>) -> Result<(), Error> {
+        mirror::import(&env, &snapshot)
+    }
+
+    pub fn seal_mirror(env: Env) -> Result<(), Error> {
+        mirror::seal(&env)
+    }
+
+    pub fn get_mirror_info(env: Env) -> Option<MirrorInfo> {
+        mirror::read_info(&env)
+    }
+
+    pub fn get_version(env: Env) -> VersionInfo {
+        version::version_info(&env)
+    }
+
+    // Extends the TTL of the given entries (and the contract instance).
+    pub fn bump_storage(env: Env, entries: Vec<StorageEntry>) -> Result<(), Error> {
+        storage::bump(&env, &entries)
+    }
+
+    // Returns the index of the new entry in the meter's payment history.
+    pub fn pay_bill_with_oracle(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        meter_id: String,
+        amount: i128,
+    ) -> Result<u32, Error> {
+        payments::pay(&env, &from, &token_address, &meter_id, amount)
+    }
+
+    // Pays the meter's bill for its owner; both parties are recorded against the payment.
+    pub fn pay_on_behalf(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        meter_id: String,
+        amount: i128,
+    ) -> Result<u32, Error> {
+        payments::pay_on_behalf(&env, &from, &token_address, &meter_id, amount)
+    }
+
+    pub fn get_delegated_payment(
+        env: Env,
+        meter_id: String,
+        index: u32,
+    ) -> Option<DelegatedPayment> {
+        delegation::read(&env, &meter_id, index)
+    }
+
+    // Called by the meter's owner.
+    pub fn set_third_party_opt_out(env: Env, meter_id: String, opt_out: bool) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        delegation::set_opt_out(&env, &meter_id, opt_out)
+    }
+
+    // Who earns loyalty points on `pay_on_behalf`; the beneficiary unless set.
+    pub fn set_delegated_points(env: Env, party: DelegatedPoints) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        delegation::set_points_to(&env, party);
+        Ok(())
+    }
+
+    pub fn get_delegated_points(env: Env) -> DelegatedPoints {
+        delegation::points_to(&env)
+    }
+
+    // Pays several meters with one authorization and one token transfer.
+    pub fn pay_bills_batch(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        bills: Vec<(String, i128)>,
+    ) -> Result<Vec<u32>, Error> {
+        payments::pay_batch(&env, &from, &token_address, &bills)
+    }
+
+    // Pays for `usage` priced at `rate_id`'s tariff, converted to the token at the oracle price.
+    pub fn pay_utility_bill(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        meter_id: String,
+        rate_id: String,
+        usage: UtilityUsage,
+    ) -> Result<u32, Error> {
+        payments::pay_utility(&env, &from, &token_address, &meter_id, &rate_id, &usage)
+    }
+
+    // Full tariff, tax and token conversion for `kwh`, without paying. The
+    // band is the meter's; `currency` is the payment token.
+    pub fn quote_utility_bill(
+        env: Env,
+        meter_id: String,
+        kwh: i128,
+        utility_type: String,
+        region: String,
+        currency: Address,
+    ) -> Result<BillQuote, Error> {
+        quotes::quote(&env, &meter_id, kwh, &utility_type, &region, &currency)
+    }
+
+    // Quotes as above and locks the rate for `payer` until the quote expires.
+    pub fn lock_bill_quote(
+        env: Env,
+        payer: Address,
+        meter_id: String,
+        kwh: i128,
+        utility_type: String,
+        region: String,
+        currency: Address,
+    ) -> Result<(u64, BillQuote), Error> {
+        maintenance::ensure_writable(&env)?;
+        quotes::lock(
+            &env,
+            &payer,
+            &meter_id,
+            kwh,
+            &utility_type,
+            &region,
+            &currency,
+        )
+    }
+
+    // Pays a locked quote at exactly its quoted rate; fails with QuoteExpired after expiry.
+    pub fn pay_with_quote(env: Env, quote_id: u64) -> Result<u32, Error> {
+        quotes::pay(&env, quote_id)
+    }
+
+    pub fn get_locked_quote(env: Env, quote_id: u64) -> Option<LockedQuote> {
+        quotes::read_locked(&env, quote_id)
+    }
+
+    // --- Escrowed payments, released once vending is confirmed ---
+
+    pub fn pay_bill_escrowed(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        meter_id: String,
+        amount: i128,
+    ) -> Result<u64, Error> {
+        maintenance::ensure_writable(&env)?;
+        escrow::pay(&env, &from, &token_address, &meter_id, amount)
+    }
+
+    // `confirmer` is the admin or the vending oracle. Returns the payment index.
+    pub fn confirm_escrow(env: Env, escrow_id: u64, confirmer: Address) -> Result<u32, Error> {
+        maintenance::ensure_writable(&env)?;
+        escrow::confirm(&env, escrow_id, &confirmer)
+    }
+
+    pub fn reclaim_escrow(env: Env, escrow_id: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        escrow::reclaim(&env, escrow_id)
+    }
+
+    pub fn get_escrow(env: Env, escrow_id: u64) -> Option<EscrowedPayment> {
+        escrow::read(&env, escrow_id)
+    }
+
+    pub fn set_escrow_timeout(env: Env, seconds: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        escrow::set_timeout(&env, seconds)
+    }
+
+    // Held until `expires_at`, at most seven days ahead; `reclaim_escrow` returns it after that.
+    pub fn reserve_payment(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        meter_id: String,
+        amount: i128,
+        expires_at: u64,
+    ) -> Result<u64, Error> {
+        maintenance::ensure_writable(&env)?;
+        escrow::reserve(&env, &from, &token_address, &meter_id, amount, expires_at)
+    }
+
+    // `terminal` is an active vending agent, the admin or the vending oracle. Returns the payment index.
+    pub fn capture_reservation(
+        env: Env,
+        reservation_id: u64,
+        terminal: Address,
+    ) -> Result<u32, Error> {
+        maintenance::ensure_writable(&env)?;
+        escrow::capture(&env, reservation_id, &terminal)
+    }
+
+    pub fn set_vending_oracle(env: Env, oracle: Address) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        escrow::set_vending_oracle(&env, &oracle);
+        Ok(())
+    }
+
+    pub fn get_meter_summary(env: Env, meter_id: String) -> MeterSummary {
+        accounting::summary(&env, &meter_id)
+    }
+
+    pub fn get_total_paid_ngn(env: Env, meter_id: String) -> i128 {
+        accounting::normalized_total(&env, &meter_id)
+    }
+
+    pub fn get_payment_count(env: Env, meter_id: String) -> u32 {
+        accounting::payment_count(&env, &meter_id)
+    }
+
+    pub fn get_payment(env: Env, meter_id: String, index: u32) -> Option<PaymentRecord> {
+        accounting::read_payment(&env, &meter_id, index)
+    }
+
+    pub fn get_latest_sequence(env: Env) -> u64 {
+        accounting::latest_sequence(&env)
+    }
+
+    pub fn get_payment_by_sequence(env: Env, sequence: u64) -> Option<SequencedPayment> {
+        accounting::payment_by_sequence(&env, sequence)
+    }
+
+    pub fn get_receipt(env: Env, receipt_id: u64) -> Option<Receipt> {
+        receipts::read(&env, receipt_id)
+    }
+
+    // Up to 50 of the payer's receipt ids from position `offset`, oldest first.
+    pub fn list_receipts(env: Env, payer: Address, offset: u32, limit: u32) -> Vec<u64> {
+        receipts::list(&env, &payer, offset, limit)
+    }
+
+    pub fn get_receipt_count(env: Env, payer: Address) -> u32 {
+        receipts::count(&env, &payer)
+    }
+
+    // The stored payment with a content hash third parties can check against.
+    pub fn verify_payment(
+        env: Env,
+        meter_id: String,
+        payment_id: u32,
+    ) -> Result<PaymentProof, Error> {
+        receipts::proof(&env, &meter_id, payment_id)
+    }
+
+    // --- Pay-as-you-go metering sessions ---
+
+    pub fn open_session(
+        env: Env,
+        payer: Address,
+        token_address: Address,
+        meter_id: String,
+        max_amount: i128,
+    ) -> Result<u64, Error> {
+        maintenance::ensure_writable(&env)?;
+        sessions::open(&env, &payer, &token_address, &meter_id, max_amount)
+    }
+
+    // `reporter` is the admin or a reading agent. Returns the token units drawn.
+    pub fn report_usage(
+        env: Env,
+        reporter: Address,
+        session_id: u64,
+        kwh: i128,
+    ) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        sessions::report_usage(&env, &reporter, session_id, kwh)
+    }
+
+    // `caller` is the payer or the admin. Returns the refund.
+    pub fn close_session(env: Env, session_id: u64, caller: Address) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        sessions::close(&env, session_id, &caller)
+    }
+
+    pub fn get_session(env: Env, session_id: u64) -> Option<MeteringSession> {
+        sessions::read(&env, session_id)
+    }
+
+    // --- Meter readings by authorized agents ---
+
+    pub fn set_reading_agent(env: Env, agent: Address, authorized: bool) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        readings::set_agent(&env, &agent, authorized);
+        Ok(())
+    }
+
+    pub fn is_reading_agent(env: Env, agent: Address) -> bool {
+        readings::is_agent(&env, &agent)
+    }
+
+    // `reading` is the cumulative register in kWh, `timestamp` when it was read.
+    pub fn submit_meter_reading(
+        env: Env,
+        agent: Address,
+        meter_id: String,
+        reading: i128,
+        timestamp: u64,
+    ) -> Result<u32, Error> {
+        maintenance::ensure_writable(&env)?;
+        readings::submit(
+            &env,
+            &agent,
+            &meter_id,
+            reading,
+            MeteredUnit::Kwh,
+            timestamp,
+        )
+    }
+
+    // As `submit_meter_reading`, for water and gas registers counting volume.
+    pub fn submit_metered_reading(
+        env: Env,
+        agent: Address,
+        meter_id: String,
+        reading: i128,
+        unit: MeteredUnit,
+        timestamp: u64,
+    ) -> Result<u32, Error> {
+        maintenance::ensure_writable(&env)?;
+        readings::submit(&env, &agent, &meter_id, reading, unit, timestamp)
+    }
+
+    pub fn get_reading_count(env: Env, meter_id: String) -> u32 {
+        readings::count(&env, &meter_id)
+    }
+
+    pub fn get_meter_reading(env: Env, meter_id: String, index: u32) -> Option<MeterReading> {
+        readings::read(&env, &meter_id, index)
+    }
+
+    // --- Consumption anomalies for field audit ---
+
+    pub fn set_anomaly_config(env: Env, config: AnomalyConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        anomalies::set_config(&env, &config)
+    }
+
+    pub fn get_anomaly_config(env: Env) -> Option<AnomalyConfig> {
+        anomalies::read_config(&env)
+    }
+
+    pub fn get_consumption_average(
+        env: Env,
+        meter_id: String,
+        kind: AnomalyKind,
+    ) -> RollingAverage {
+        anomalies::average(&env, &meter_id, kind)
+    }
+
+    // The meter's most recent anomalies, oldest first.
+    pub fn get_anomalies(env: Env, meter_id: String) -> Vec<Anomaly> {
+        anomalies::anomalies(&env, &meter_id)
+    }
+
+    pub fn list_flagged_meters(env: Env) -> Vec<String> {
+        anomalies::flagged_meters(&env)
+    }
+
+    // Clears the meter's anomalies once it has been inspected.
+    pub fn clear_anomalies(env: Env, meter_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        anomalies::clear(&env, &meter_id)
+    }
 
-        // 2. Initialize the Token client (for XLM or USDC)
-        let token_client = token::Client::new(&env, &token_address);
+    // --- Vending agents and commission ---
 
-        // 3. Move the tokens from the User to the Contract
-        token_client.transfer(&from, &env.current_contract_address(), &amount);
+    pub fn set_vending_agent(
+        env: Env,
+        agent: Address,
+        commission_bps: u32,
+        active: bool,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        vendors::set_agent(&env, &agent, commission_bps, active)
+    }
+
+    pub fn get_vending_agent(env: Env, agent: Address) -> Option<VendingAgent> {
+        vendors::read_agent(&env, &agent)
+    }
+
+    // The agent pays the meter in full and earns its commission on the sale.
+    pub fn pay_via_agent(
+        env: Env,
+        agent: Address,
+        token_address: Address,
+        customer_meter: String,
+        amount: i128,
+    ) -> Result<u32, Error> {
+        vendors::pay_via_agent(&env, &agent, &token_address, &customer_meter, amount)
+    }
+
+    pub fn get_commission_balance(env: Env, agent: Address, token_address: Address) -> i128 {
+        vendors::commission(&env, &agent, &token_address)
+    }
+
+    pub fn claim_commission(
+        env: Env,
+        agent: Address,
+        token_address: Address,
+    ) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        vendors::claim(&env, &agent, &token_address)
+    }
+
+    // --- Prepaid vending vouchers ---
+
+    pub fn set_prepaid_meter(env: Env, meter_id: String, prepaid: bool) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        vouchers::set_prepaid(&env, &meter_id, prepaid);
+        Ok(())
+    }
+
+    pub fn is_prepaid_meter(env: Env, meter_id: String) -> bool {
+        vouchers::is_prepaid(&env, &meter_id)
+    }
+
+    pub fn get_voucher(env: Env, voucher_id: u64) -> Option<Voucher> {
+        vouchers::read(&env, voucher_id)
+    }
 
-        // 4. Update the meter record (using i128 for larger money values)
-        let current_total: i128 = env.storage().persistent().get(&meter_id).unwrap_or(0);
-        env.storage().persistent().set(&meter_id, &(current_total + amount));
+    // `redeemer` is the admin or the vending oracle.
+    pub fn redeem_voucher(
+        env: Env,
+        meter_id: String,
+        voucher_id: u64,
+        redeemer: Address,
+    ) -> Result<Voucher, Error> {
+        maintenance::ensure_writable(&env)?;
+        vouchers::redeem(&env, &meter_id, voucher_id, &redeemer)
+    }
+
+    pub fn set_emergency_credit_config(
+        env: Env,
+        config: EmergencyCreditConfig,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        emergency::set_config(&env, &config)
+    }
+
+    pub fn get_emergency_credit_config(env: Env) -> Option<EmergencyCreditConfig> {
+        emergency::read_config(&env)
+    }
+
+    // `reporter` (admin or vending oracle) reports the meter's kWh balance has run out.
+    pub fn activate_emergency_credit(
+        env: Env,
+        meter_id: String,
+        reporter: Address,
+    ) -> Result<EmergencyCredit, Error> {
+        maintenance::ensure_writable(&env)?;
+        emergency::activate(&env, &meter_id, &reporter)
+    }
+
+    // Outstanding emergency credit, recovered from the meter's next top-ups.
+    pub fn get_emergency_credit(env: Env, meter_id: String) -> Option<EmergencyCredit> {
+        emergency::credit(&env, &meter_id)
+    }
+
+    // --- Legacy entry points, kept for existing integrators ---
+
+    pub fn pay_bill(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        meter_id: String,
+        amount: i128,
+    ) {
+        legacy::deprecated_call(&env, "pay_bill", "pay_bill_with_oracle");
+        if let Err(err) =
+            Self::pay_bill_with_oracle(env.clone(), from, token_address, meter_id, amount)
+        {
+            panic_with_error!(&env, err);
+        }
     }
 
     pub fn get_total_paid(env: Env, meter_id: String) -> i128 {
-        env.storage().persistent().get(&meter_id).unwrap_or(0)
+        legacy::deprecated_call(&env, "get_total_paid", "get_meter_summary");
+        Self::get_meter_summary(env, meter_id).total_paid
+    }
+
+    // --- Price feeds ---
+
+    // Returns false when the deviation guard held the price for review.
+    pub fn update_price_feed(
+        env: Env,
+        feed_id: String,
+        price: i128,
+        decimals: u32,
+    ) -> Result<bool, Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::update_price_feed(&env, &feed_id, price, decimals)
+    }
+
+    // Entries are (feed_id, price, observed_at) for existing feeds; each succeeds or fails on its own.
+    pub fn update_price_feeds_batch(
+        env: Env,
+        updates: Vec<(String, i128, u64)>,
+    ) -> Result<Vec<UpdateOutcome>, Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::update_price_feeds_batch(&env, &updates)
+    }
+
+    // Applies a report signed by the feed's reporter; anyone may relay it.
+    pub fn update_price_feed_signed(
+        env: Env,
+        feed_id: String,
+        price: i128,
+        decimals: u32,
+        timestamp: u64,
+        signature: BytesN<64>,
+    ) -> Result<bool, Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::update_price_feed_signed(
+            &env, &feed_id, price, decimals, timestamp, &signature,
+        )
+    }
+
+    // Ed25519 key of the feed's reporter. Once set, the feed only takes signed reports.
+    pub fn set_feed_reporter(
+        env: Env,
+        feed_id: String,
+        public_key: BytesN<32>,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::set_reporter_key(&env, &feed_id, &public_key);
+        Ok(())
+    }
+
+    pub fn get_feed_reporter(env: Env, feed_id: String) -> Option<BytesN<32>> {
+        OracleManager::get_reporter_key(&env, &feed_id)
+    }
+
+    pub fn get_flagged_price(env: Env, feed_id: String) -> Option<PriceFeed> {
+        OracleManager::get_flagged_price(&env, &feed_id)
+    }
+
+    pub fn accept_flagged_price(env: Env, feed_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::accept_flagged_price(&env, &feed_id)
+    }
+
+    pub fn reject_flagged_price(env: Env, feed_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::reject_flagged_price(&env, &feed_id)
+    }
+
+    pub fn get_feed_reliability(env: Env, feed_id: String) -> FeedReliability {
+        OracleManager::get_feed_reliability(&env, &feed_id)
+    }
+
+    pub fn get_data_feed_reliability(env: Env, feed_id: String) -> FeedReliability {
+        OracleManager::get_data_feed_reliability(&env, &feed_id)
+    }
+
+    // The feed keeps serving reads until `sunset_timestamp`; payments relying on it fail after.
+    pub fn deprecate_price_feed(
+        env: Env,
+        feed_id: String,
+        sunset_timestamp: u64,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::deprecate_feed(&env, &feed_id, sunset_timestamp)
+    }
+
+    // Only once a deprecated feed's sunset has passed.
+    pub fn remove_price_feed(env: Env, feed_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::remove_feed(&env, &feed_id)
+    }
+
+    pub fn get_feed_sunset(env: Env, feed_id: String) -> Option<u64> {
+        OracleManager::get_feed_sunset(&env, &feed_id)
+    }
+
+    // Seconds the feed may go without an update; 0 stops monitoring it.
+    pub fn set_feed_heartbeat(
+        env: Env,
+        feed_id: String,
+        heartbeat_seconds: u64,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::set_feed_heartbeat(&env, &feed_id, heartbeat_seconds)
+    }
+
+    pub fn get_feed_heartbeat(env: Env, feed_id: String) -> Option<u64> {
+        OracleManager::get_feed_heartbeat(&env, &feed_id)
+    }
+
+    // Feeds that have missed their heartbeat.
+    pub fn check_feed_health(env: Env) -> Vec<String> {
+        OracleManager::check_feed_health(&env)
+    }
+
+    // Daily update budget for the feed; 0 removes it.
+    pub fn set_feed_budget(env: Env, feed_id: String, daily_limit: u32) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        budgets::set_feed_budget(&env, &feed_id, daily_limit);
+        Ok(())
+    }
+
+    // Daily budget for reports relayed by `caller`; 0 removes it.
+    pub fn set_caller_budget(env: Env, caller: Address, daily_limit: u32) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        budgets::set_caller_budget(&env, &caller, daily_limit);
+        Ok(())
+    }
+
+    pub fn get_feed_cost_stats(env: Env, feed_id: String) -> OracleCostStats {
+        budgets::feed_stats(&env, &feed_id)
+    }
+
+    pub fn get_caller_cost_stats(env: Env, caller: Address) -> OracleCostStats {
+        budgets::caller_stats(&env, &caller)
+    }
+
+    // Signed updates any one reporter key may make per hour; 0 removes the cap.
+    pub fn set_reporter_rate_limit(env: Env, hourly_limit: u32) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        budgets::set_reporter_limit(&env, hourly_limit);
+        Ok(())
+    }
+
+    pub fn get_reporter_stats(env: Env, public_key: BytesN<32>) -> ReporterStats {
+        budgets::reporter_stats(&env, &public_key)
+    }
+
+    pub fn get_price_feed(env: Env, feed_id: String) -> Option<PriceFeed> {
+        OracleManager::get_price_feed(&env, &feed_id)
+    }
+
+    pub fn get_price_feed_ids(env: Env) -> Vec<String> {
+        OracleManager::get_price_feed_ids(&env)
+    }
+
+    pub fn list_price_feeds(env: Env, offset: u32, limit: u32) -> Vec<FeedListing> {
+        OracleManager::list_price_feeds(&env, offset, limit)
+    }
+
+    pub fn get_price_history(env: Env, feed_id: String) -> Vec<PricePoint> {
+        OracleManager::get_price_history(&env, &feed_id)
+    }
+
+    // The price that was in effect at `timestamp`, for settling billing disputes.
+    pub fn get_price_at(env: Env, feed_id: String, timestamp: u64) -> Result<PriceFeed, Error> {
+        OracleManager::get_price_at(&env, &feed_id, timestamp)
+    }
+
+    pub fn get_price_range(
+        env: Env,
+        feed_id: String,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<PriceFeed>, Error> {
+        OracleManager::get_price_range(&env, &feed_id, from, to)
+    }
+
+    pub fn get_twap(env: Env, feed_id: String, window_seconds: u64) -> Result<i128, Error> {
+        OracleManager::get_twap(&env, &feed_id, window_seconds)
+    }
+
+    pub fn set_oracle_config(env: Env, config: OracleConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::set_config(&env, &config)
+    }
+
+    pub fn get_oracle_config(env: Env) -> OracleConfig {
+        OracleManager::get_config(&env)
+    }
+
+    pub fn set_fallback_price(
+        env: Env,
+        feed_id: String,
+        price: i128,
+        decimals: u32,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::set_fallback_price(&env, &feed_id, price, decimals)
+    }
+
+    pub fn get_fallback_price(env: Env, feed_id: String) -> Option<PriceFeed> {
+        OracleManager::get_fallback_price(&env, &feed_id)
+    }
+
+    // Order in which the push feed, a SEP-40 oracle, the cached price and the
+    // fallback price are tried when pricing payments.
+    pub fn set_fallback_chain(
+        env: Env,
+        feed_id: String,
+        chain: FallbackChain,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::set_fallback_chain(&env, &feed_id, &chain)
+    }
+
+    pub fn get_fallback_chain(env: Env, feed_id: String) -> FallbackChain {
+        OracleManager::get_fallback_chain(&env, &feed_id)
+    }
+
+    pub fn set_external_price_source(
+        env: Env,
+        feed_id: String,
+        source: ExternalPriceSource,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::set_external_source(&env, &feed_id, &source);
+        Ok(())
+    }
+
+    pub fn get_external_price_source(env: Env, feed_id: String) -> Option<ExternalPriceSource> {
+        OracleManager::get_external_source(&env, &feed_id)
+    }
+
+    // The payment price for the feed and the chain source it resolved from.
+    pub fn resolve_payment_price(
+        env: Env,
+        feed_id: String,
+    ) -> Result<(PriceFeed, PriceSource), Error> {
+        OracleManager::resolve_payment_price(&env, &feed_id)
+    }
+
+    // Non-price observations (temperature, fuel spot prices) tariffs can reference.
+    pub fn update_data_feed(
+        env: Env,
+        feed_id: String,
+        value: i128,
+        decimals: u32,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        OracleManager::update_data_feed(&env, &feed_id, value, decimals)
+    }
+
+    pub fn get_data_feed(env: Env, feed_id: String) -> Option<DataFeed> {
+        OracleManager::get_data_feed(&env, &feed_id)
+    }
+
+    pub fn get_data_feed_ids(env: Env) -> Vec<String> {
+        OracleManager::get_data_feed_ids(&env)
+    }
+
+    // --- Oracle dashboards ---
+
+    // Each view reads only what it reports; `get_oracle_stats` combines them.
+    pub fn get_cost_summary(env: Env, feed_id: String) -> OracleCostStats {
+        budgets::feed_stats(&env, &feed_id)
+    }
+
+    pub fn get_reliability_summary(env: Env, feed_id: String) -> ReliabilitySummary {
+        OracleManager::reliability_summary(&env, &feed_id)
+    }
+
+    pub fn get_schedule(env: Env, feed_id: String) -> FeedSchedule {
+        keepers::feed_schedule(&env, &feed_id)
+    }
+
+    pub fn get_oracle_stats(env: Env, feed_id: String) -> OracleStats {
+        OracleManager::stats(&env, &feed_id)
+    }
+
+    // --- Keeper-relayed price reports ---
+
+    pub fn set_keeper_config(env: Env, config: KeeperConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        keepers::set_config(&env, &config)
+    }
+
+    pub fn get_keeper_config(env: Env) -> Option<KeeperConfig> {
+        keepers::read_config(&env)
+    }
+
+    pub fn fund_keeper_pool(env: Env, from: Address, amount: i128) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        keepers::fund(&env, &from, amount)
+    }
+
+    pub fn get_keeper_pool(env: Env) -> i128 {
+        keepers::pool(&env)
+    }
+
+    pub fn get_keeper_earnings(env: Env, keeper: Address) -> i128 {
+        keepers::earnings(&env, &keeper)
+    }
+
+    pub fn should_update_price_feeds(env: Env) -> bool {
+        keepers::should_update(&env)
+    }
+
+    pub fn set_update_schedule(
+        env: Env,
+        price_feed_interval: u64,
+        utility_rate_interval: u64,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        keepers::set_schedule(&env, price_feed_interval, utility_rate_interval)
+    }
+
+    pub fn get_update_schedule(env: Env) -> Option<UpdateSchedule> {
+        keepers::schedule(&env)
+    }
+
+    pub fn set_feed_update_interval(env: Env, feed_id: String, interval: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        keepers::set_feed_interval(&env, &feed_id, interval)
+    }
+
+    pub fn get_feed_update_interval(env: Env, feed_id: String) -> Option<u64> {
+        keepers::feed_interval(&env, &feed_id)
+    }
+
+    pub fn should_update_utility_rates(env: Env) -> bool {
+        keepers::should_update_rates(&env)
+    }
+
+    // Relays a reporter-signed price; returns the keeper reward paid, if any.
+    pub fn submit_price_report(
+        env: Env,
+        keeper: Address,
+        feed_id: String,
+        price: i128,
+        decimals: u32,
+        timestamp: u64,
+        signature: BytesN<64>,
+    ) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        keepers::submit(
+            &env, &keeper, &feed_id, price, decimals, timestamp, &signature,
+        )
+    }
+
+    // --- Utility rates ---
+
+    pub fn set_utility_rate(
+        env: Env,
+        rate_id: String,
+        formula: Vec<TariffOp>,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::set_rate(&env, &rate_id, &formula)
+    }
+
+    // The rate in effect now, including a scheduled formula that has taken effect.
+    pub fn get_utility_rate(env: Env, rate_id: String) -> Option<UtilityRate> {
+        tariff::read_rate(&env, &rate_id)
+    }
+
+    // Each (rate_id, formula) entry succeeds or fails on its own.
+    pub fn update_utility_rates_batch(
+        env: Env,
+        rates: Vec<(String, Vec<TariffOp>)>,
+    ) -> Result<Vec<UpdateOutcome>, Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::set_rates_batch(&env, &rates)
+    }
+
+    // Loads a tariff review in advance: `formula` applies from `effective_from`.
+    pub fn add_utility_rate_scheduled(
+        env: Env,
+        rate_id: String,
+        formula: Vec<TariffOp>,
+        effective_from: u64,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::schedule_rate(&env, &rate_id, &formula, effective_from)
+    }
+
+    // Unit the rate's quantity inputs are in; kWh unless set.
+    pub fn set_rate_unit(env: Env, rate_id: String, unit: MeteredUnit) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::set_rate_unit(&env, &rate_id, unit)
+    }
+
+    pub fn get_rate_unit(env: Env, rate_id: String) -> MeteredUnit {
+        tariff::rate_unit(&env, &rate_id)
+    }
+
+    pub fn cancel_scheduled_rate(
+        env: Env,
+        rate_id: String,
+        effective_from: u64,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::cancel_scheduled_rate(&env, &rate_id, effective_from)
+    }
+
+    pub fn get_scheduled_rates(env: Env, rate_id: String) -> Vec<ScheduledRate> {
+        tariff::scheduled_rates(&env, &rate_id)
+    }
+
+    // `caller` is the admin or the regulator; the rate keeps its formula until `until_timestamp`.
+    pub fn freeze_rate(
+        env: Env,
+        caller: Address,
+        rate_id: String,
+        until_timestamp: u64,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::freeze(&env, &caller, &rate_id, until_timestamp)
+    }
+
+    pub fn is_rate_frozen(env: Env, rate_id: String) -> bool {
+        tariff::is_frozen(&env, &rate_id)
+    }
+
+    // 0 when the rate was never frozen.
+    pub fn get_rate_frozen_until(env: Env, rate_id: String) -> u64 {
+        tariff::frozen_until(&env, &rate_id)
+    }
+
+    pub fn get_utility_rate_ids(env: Env) -> Vec<String> {
+        tariff::rate_ids(&env)
+    }
+
+    pub fn list_utility_rates(env: Env, offset: u32, limit: u32) -> Vec<RateListing> {
+        tariff::list_rates(&env, offset, limit)
+    }
+
+    // The rate keeps pricing until `sunset_timestamp`; bills and payments under it fail after.
+    pub fn deprecate_utility_rate(
+        env: Env,
+        rate_id: String,
+        sunset_timestamp: u64,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::deprecate(&env, &rate_id, sunset_timestamp)
+    }
+
+    // Only once a deprecated rate's sunset has passed.
+    pub fn remove_utility_rate(env: Env, rate_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::remove(&env, &rate_id)
+    }
+
+    pub fn get_rate_sunset(env: Env, rate_id: String) -> Option<u64> {
+        tariff::sunset(&env, &rate_id)
+    }
+
+    // Evaluates the rate's formula against the given inputs, e.g. {"kwh": 120}.
+    pub fn calculate_bill(
+        env: Env,
+        rate_id: String,
+        inputs: Map<Symbol, i128>,
+    ) -> Result<i128, Error> {
+        tariff::calculate(&env, &rate_id, &inputs)
+    }
+
+    // Registers a typed rate name and returns the rate id to set its formula under.
+    pub fn register_rate_key(env: Env, key: RateKey) -> Result<String, Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::register_key(&env, &key)
+    }
+
+    pub fn get_rate_id(env: Env, key: RateKey) -> Result<String, Error> {
+        tariff::rate_id_for(&env, &key)
+    }
+
+    // What one kWh costs under the rate at `timestamp`, with the feed, update time,
+    // reliability and fallback behind it.
+    pub fn get_effective_rate(
+        env: Env,
+        utility_type: String,
+        region: String,
+        band: String,
+        timestamp: u64,
+    ) -> Result<EffectiveRate, Error> {
+        billing::effective_rate(
+            &env,
+            &RateKey {
+                utility_type,
+                region,
+                band,
+            },
+            timestamp,
+        )
+    }
+
+    // Rate keys, quotes and effective-rate lookups must name registered regions and
+    // utility types.
+    pub fn register_region(env: Env, region: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::register_region(&env, &region)
+    }
+
+    pub fn register_utility_type(env: Env, utility_type: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::register_utility_type(&env, &utility_type)
+    }
+
+    pub fn list_regions(env: Env) -> Vec<String> {
+        tariff::regions(&env)
+    }
+
+    pub fn list_utility_types(env: Env) -> Vec<String> {
+        tariff::utility_types(&env)
+    }
+
+    pub fn list_rates_for_region(
+        env: Env,
+        region: String,
+    ) -> Result<Vec<(RateKey, UtilityRate)>, Error> {
+        tariff::rates_for_region(&env, &region)
+    }
+
+    pub fn set_tou_schedule(env: Env, rate_id: String, schedule: TouSchedule) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tariff::set_tou_schedule(&env, &rate_id, &schedule)
+    }
+
+    pub fn get_tou_schedule(env: Env, rate_id: String) -> Option<TouSchedule> {
+        tariff::read_tou_schedule(&env, &rate_id)
+    }
+
+    pub fn get_current_tou_window(env: Env, rate_id: String) -> TouWindow {
+        tariff::window_at(&env, &rate_id, env.ledger().timestamp())
+    }
+
+    // --- Meter metadata and tariff bands ---
+
+    pub fn set_meter_metadata(
+        env: Env,
+        meter_id: String,
+        metadata: MeterMetadata,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        meters::set_metadata(&env, &meter_id, &metadata)
+    }
+
+    pub fn get_meter_metadata(env: Env, meter_id: String) -> Option<MeterMetadata> {
+        meters::metadata(&env, &meter_id)
+    }
+
+    // Bills from `effective_from` on use the rate for `new_band` in the meter's
+    // region; bills already issued are left as they are.
+    pub fn reclassify_band(
+        env: Env,
+        meter_id: String,
+        new_band: String,
+        effective_from: u64,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        meters::reclassify(&env, &meter_id, &new_band, effective_from)
+    }
+
+    pub fn get_meter_band(env: Env, meter_id: String) -> Option<String> {
+        billing::meter_band(&env, &meter_id)
+    }
+
+    // --- Billing ---
+
+    // Bills the meter's consumption for `period` (YYYYMM). Without a utility rate
+    // for `rate_id`, the estimated flat rate is used and the bill marked estimated.
+    pub fn issue_bill(
+        env: Env,
+        meter_id: String,
+        period: u32,
+        rate_id: String,
+        kwh: i128,
+    ) -> Result<BillingRecord, Error> {
+        maintenance::ensure_writable(&env)?;
+        billing::issue(&env, &meter_id, period, &rate_id, kwh)
+    }
+
+    // Bills a consumption in any unit convertible to the rate's unit.
+    pub fn issue_metered_bill(
+        env: Env,
+        meter_id: String,
+        period: u32,
+        rate_id: String,
+        consumption: Consumption,
+    ) -> Result<BillingRecord, Error> {
+        maintenance::ensure_writable(&env)?;
+        billing::issue_metered(&env, &meter_id, period, &rate_id, &consumption)
+    }
+
+    // `actual_kwh` is in the bill's unit.
+    pub fn true_up(
+        env: Env,
+        meter_id: String,
+        period: u32,
+        actual_kwh: i128,
+    ) -> Result<BillingRecord, Error> {
+        maintenance::ensure_writable(&env)?;
+        billing::true_up(&env, &meter_id, period, actual_kwh)
+    }
+
+    pub fn assign_meter_rate(env: Env, meter_id: String, rate_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        billing::assign_rate(&env, &meter_id, &rate_id);
+        Ok(())
+    }
+
+    pub fn get_meter_rate(env: Env, meter_id: String) -> Option<String> {
+        billing::meter_rate(&env, &meter_id)
+    }
+
+    // What-if view of the current period's invoice at `assumed_kwh`, under the meter's assigned rate.
+    pub fn preview_next_invoice(
+        env: Env,
+        meter_id: String,
+        assumed_kwh: i128,
+    ) -> Result<InvoicePreview, Error> {
+        billing::preview(&env, &meter_id, assumed_kwh)
+    }
+
+    pub fn set_estimated_rate(env: Env, rate_id: String, per_kwh: i128) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        billing::set_estimated_rate(&env, &rate_id, per_kwh)
+    }
+
+    pub fn get_estimated_rate(env: Env, rate_id: String) -> Option<i128> {
+        billing::estimated_rate(&env, &rate_id)
+    }
+
+    // Rounding applied to the region's final bill amounts; exact when unset.
+    pub fn set_rounding_policy(
+        env: Env,
+        region: String,
+        policy: RoundingPolicy,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        billing::set_rounding_policy(&env, &region, &policy)
+    }
+
+    pub fn get_rounding_policy(env: Env, region: String) -> RoundingPolicy {
+        billing::rounding_policy(&env, &region)
+    }
+
+    pub fn get_bill(env: Env, meter_id: String, period: u32) -> Option<BillingRecord> {
+        billing::read_bill(&env, &meter_id, period)
+    }
+
+    // `meter_id` and `period` together key the bill.
+    pub fn get_bill_breakdown(
+        env: Env,
+        meter_id: String,
+        period: u32,
+    ) -> Result<Option<BillBreakdown>, Error> {
+        billing::breakdown(&env, &meter_id, period)
+    }
+
+    // Replaces the VAT and levy components applied to newly issued bills.
+    pub fn set_tax_components(env: Env, components: Vec<TaxComponent>) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        taxes::set_components(&env, &components)
+    }
+
+    pub fn get_tax_components(env: Env) -> Vec<TaxComponent> {
+        taxes::read_components(&env)
+    }
+
+    // Bills the period from the meter agents' readings at the meter's assigned rate.
+    pub fn compute_bill_from_readings(
+        env: Env,
+        meter_id: String,
+        period: u32,
+    ) -> Result<BillingRecord, Error> {
+        maintenance::ensure_writable(&env)?;
+        readings::bill_period(&env, &meter_id, period)
+    }
+
+    // NGN owed on the meter; negative when in credit.
+    pub fn get_meter_balance(env: Env, meter_id: String) -> i128 {
+        billing::balance(&env, &meter_id)
+    }
+
+    // --- Connection fees and security deposits ---
+
+    // NGN amounts charged on connection; either may be 0.
+    pub fn set_deposit_config(env: Env, config: DepositConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        deposits::set_config(&env, &config)
+    }
+
+    pub fn get_deposit_config(env: Env) -> DepositConfig {
+        deposits::read_config(&env)
+    }
+
+    // Returns the token amount paid.
+    pub fn pay_connection_fee(
+        env: Env,
+        payer: Address,
+        meter_id: String,
+        token_address: Address,
+    ) -> Result<i128, Error> {
+        deposits::pay_connection_fee(&env, &payer, &meter_id, &token_address)
+    }
+
+    pub fn get_connection_fee_paid(env: Env, meter_id: String) -> Option<u64> {
+        deposits::connection_fee_paid(&env, &meter_id)
+    }
+
+    // Returns the token amount held.
+    pub fn pay_deposit(
+        env: Env,
+        payer: Address,
+        meter_id: String,
+        token_address: Address,
+    ) -> Result<i128, Error> {
+        deposits::pay_deposit(&env, &payer, &meter_id, &token_address)
+    }
+
+    pub fn get_deposit(env: Env, meter_id: String) -> Option<SecurityDeposit> {
+        deposits::deposit(&env, &meter_id)
+    }
+
+    // --- Account closure ---
+
+    // Requires a balance the deposit covers; settles it from the deposit, refunds
+    // any credit and the deposit's remainder, then stops the meter.
+    pub fn close_account(env: Env, meter_id: String) -> Result<ClosedAccount, Error> {
+        closures::close(&env, &meter_id)
+    }
+
+    pub fn get_closed_account_summary(env: Env, meter_id: String) -> Option<ClosedAccount> {
+        closures::closed(&env, &meter_id)
+    }
+
+    // --- Meter credit and refunds ---
+
+    // NGN paid beyond the meter's bills; netted against its next invoice.
+    pub fn get_credit_balance(env: Env, meter_id: String) -> i128 {
+        credits::credit_balance(&env, &meter_id)
+    }
+
+    // The meter's owner asks for `amount` NGN of credit back in `token_address`.
+    pub fn request_credit_refund(
+        env: Env,
+        meter_id: String,
+        token_address: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        credits::request_refund(&env, &meter_id, &token_address, amount)
+    }
+
+    pub fn get_credit_refund_request(env: Env, meter_id: String) -> Option<CreditRefund> {
+        credits::pending_refund(&env, &meter_id)
+    }
+
+    // Admin approval pays the request out; returns the token amount refunded.
+    pub fn refund_credit(env: Env, meter_id: String) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        credits::approve_refund(&env, &meter_id)
+    }
+
+    pub fn reject_credit_refund(env: Env, meter_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        credits::reject_refund(&env, &meter_id)
+    }
+
+    // --- Billing periods ---
+
+    // Closes a region's ended month: freezes its rates, finalizes its invoices and
+    // rolls unpaid balances into arrears.
+    pub fn close_billing_period(
+        env: Env,
+        region: String,
+        period: u32,
+    ) -> Result<BillingCycle, Error> {
+        maintenance::ensure_writable(&env)?;
+        periods::close(&env, &region, period)
+    }
+
+    pub fn get_billing_cycle(env: Env, region: String, period: u32) -> Option<BillingCycle> {
+        periods::cycle(&env, &region, period)
+    }
+
+    // NGN carried out of closed billing periods and still unpaid.
+    pub fn get_meter_arrears(env: Env, meter_id: String) -> i128 {
+        periods::arrears(&env, &meter_id)
+    }
+
+    // --- Monthly budget alerts ---
+
+    // The meter's owner sets a monthly threshold in NGN units; 0 removes it.
+    pub fn set_budget_threshold(
+        env: Env,
+        owner: Address,
+        meter_id: String,
+        threshold: i128,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        alerts::set_threshold(&env, &owner, &meter_id, threshold)
+    }
+
+    pub fn get_budget_threshold(env: Env, meter_id: String) -> Option<i128> {
+        alerts::threshold(&env, &meter_id)
+    }
+
+    // Payments and billed consumption in `period` (YYYYMM), tracked while a threshold is set.
+    pub fn get_monthly_spend(env: Env, meter_id: String, period: u32) -> MonthlySpend {
+        alerts::month_spend(&env, &meter_id, period)
+    }
+
+    pub fn is_budget_exceeded(env: Env, meter_id: String) -> bool {
+        alerts::is_exceeded(&env, &meter_id)
+    }
+
+    // --- Net metering ---
+
+    // NGN units credited per kWh exported by meters billed under `rate_id`.
+    pub fn set_feed_in_tariff(env: Env, rate_id: String, per_kwh: i128) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        netmetering::set_feed_in_tariff(&env, &rate_id, per_kwh)
+    }
+
+    pub fn get_feed_in_tariff(env: Env, rate_id: String) -> Option<i128> {
+        netmetering::feed_in_tariff(&env, &rate_id)
+    }
+
+    pub fn record_export(env: Env, meter_id: String, kwh_exported: i128) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        netmetering::record_export(&env, &meter_id, kwh_exported)
+    }
+
+    // Export credit carried over, not yet netted against a bill.
+    pub fn get_export_credit(env: Env, meter_id: String) -> i128 {
+        netmetering::credit(&env, &meter_id)
+    }
+
+    pub fn get_period_exports(env: Env, meter_id: String, period: u32) -> i128 {
+        netmetering::period_exports(&env, &meter_id, period)
+    }
+
+    // --- Dunning and disconnection notices ---
+
+    pub fn set_dunning_config(env: Env, config: DunningConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        dunning::set_config(&env, &config)
+    }
+
+    pub fn get_dunning_config(env: Env) -> DunningConfig {
+        dunning::read_config(&env)
+    }
+
+    // Issues an arrears notice if the meter is past the threshold; returns the active notice, if any.
+    pub fn run_dunning(env: Env, meter_id: String) -> Result<Option<DisconnectionNotice>, Error> {
+        maintenance::ensure_writable(&env)?;
+        Ok(dunning::run(&env, &meter_id))
+    }
+
+    pub fn issue_disconnection_notice(
+        env: Env,
+        meter_id: String,
+        reason: DisconnectionReason,
+    ) -> Result<DisconnectionNotice, Error> {
+        maintenance::ensure_writable(&env)?;
+        dunning::issue_for_reason(&env, &meter_id, reason)
+    }
+
+    pub fn withdraw_disconnection_notice(env: Env, meter_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        dunning::withdraw(&env, &meter_id)
+    }
+
+    pub fn get_disconnection_notice(env: Env, meter_id: String) -> Option<DisconnectionNotice> {
+        dunning::read_notice(&env, &meter_id)
+    }
+
+    // Debt tolerated by meters on a tariff band before they are flagged.
+    pub fn set_debt_tolerance(
+        env: Env,
+        band: String,
+        tolerance: DebtTolerance,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        dunning::set_tolerance(&env, &band, &tolerance)
+    }
+
+    pub fn get_debt_tolerance(env: Env, band: String) -> Option<DebtTolerance> {
+        dunning::tolerance(&env, &band)
+    }
+
+    // Re-evaluates the meter's disconnection flag, e.g. once a grace period ends.
+    pub fn refresh_debt_status(env: Env, meter_id: String) -> Result<DebtStatus, Error> {
+        maintenance::ensure_writable(&env)?;
+        Ok(dunning::refresh_debt_flag(&env, &meter_id))
+    }
+
+    pub fn get_debt_status(env: Env, meter_id: String) -> DebtStatus {
+        dunning::debt_status(&env, &meter_id)
+    }
+
+    pub fn is_disconnection_pending(env: Env, meter_id: String) -> bool {
+        dunning::debt_status(&env, &meter_id).disconnection_pending
+    }
+
+    // --- Group billing for estates and apartment blocks ---
+
+    pub fn create_billing_group(
+        env: Env,
+        group_id: String,
+        group_admin: Address,
+        split: GroupSplit,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        groups::create(&env, &group_id, &group_admin, split)
+    }
+
+    // Adds the meter or updates its share; shares are in bps and used under `GroupSplit::Shares`.
+    pub fn set_group_member(
+        env: Env,
+        group_id: String,
+        meter_id: String,
+        share_bps: u32,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        groups::set_member(&env, &group_id, &meter_id, share_bps)
+    }
+
+    pub fn remove_group_member(env: Env, group_id: String, meter_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        groups::remove_member(&env, &group_id, &meter_id)
+    }
+
+    pub fn get_billing_group(env: Env, group_id: String) -> Option<BillingGroup> {
+        groups::read(&env, &group_id)
+    }
+
+    // Returns what each member meter was paid, in token units.
+    pub fn pay_group_bill(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        group_id: String,
+        amount: i128,
+    ) -> Result<Vec<(String, i128)>, Error> {
+        maintenance::ensure_writable(&env)?;
+        groups::pay(&env, &from, &token_address, &group_id, amount)
+    }
+
+    pub fn get_group_statement(
+        env: Env,
+        group_id: String,
+        period: u32,
+    ) -> Result<GroupStatement, Error> {
+        groups::statement(&env, &group_id, period)
+    }
+
+    // --- Installment payment plans ---
+
+    // Created active when `caller` is the admin, otherwise proposed for approval.
+    pub fn create_payment_plan(
+        env: Env,
+        caller: Address,
+        meter_id: String,
+        total_debt: i128,
+        num_installments: u32,
+        interval: u64,
+    ) -> Result<u64, Error> {
+        maintenance::ensure_writable(&env)?;
+        plans::create(
+            &env,
+            &caller,
+            &meter_id,
+            total_debt,
+            num_installments,
+            interval,
+        )
+    }
+
+    pub fn approve_payment_plan(env: Env, plan_id: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        plans::approve(&env, plan_id)
+    }
+
+    pub fn cancel_payment_plan(env: Env, plan_id: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        plans::cancel(&env, plan_id)
+    }
+
+    pub fn pay_installment(
+        env: Env,
+        from: Address,
+        token_address: Address,
+        plan_id: u64,
+    ) -> Result<u32, Error> {
+        plans::pay_installment(&env, &from, &token_address, plan_id)
+    }
+
+    pub fn get_payment_plan(env: Env, plan_id: u64) -> Option<PaymentPlan> {
+        plans::read(&env, plan_id)
+    }
+
+    pub fn get_meter_payment_plan(env: Env, meter_id: String) -> Option<u64> {
+        plans::meter_plan(&env, &meter_id)
+    }
+
+    pub fn get_payment_plan_status(env: Env, plan_id: u64) -> Result<PlanReport, Error> {
+        plans::report(&env, plan_id)
+    }
+
+    // --- Loyalty points ---
+
+    pub fn set_loyalty_config(env: Env, config: LoyaltyConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        loyalty::set_config(&env, &config)
+    }
+
+    pub fn get_loyalty_config(env: Env) -> Option<LoyaltyConfig> {
+        loyalty::read_config(&env)
+    }
+
+    pub fn get_loyalty_account(env: Env, customer: Address) -> LoyaltyAccount {
+        loyalty::account(&env, &customer)
+    }
+
+    // Credits `meter_id`'s balance for the points spent; returns the NGN credited.
+    pub fn redeem_points(
+        env: Env,
+        customer: Address,
+        meter_id: String,
+        points: i128,
+    ) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        loyalty::redeem(&env, &customer, &meter_id, points)
+    }
+
+    // --- Subsidies ---
+
+    pub fn set_subsidy_scheme(
+        env: Env,
+        scheme_id: String,
+        scheme: SubsidyScheme,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        subsidy::set_scheme(&env, &scheme_id, &scheme)
+    }
+
+    pub fn get_subsidy_scheme(env: Env, scheme_id: String) -> Option<SubsidyScheme> {
+        subsidy::read_scheme(&env, &scheme_id)
+    }
+
+    pub fn set_meter_subsidy(env: Env, meter_id: String, scheme_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        subsidy::tag_meter(&env, &meter_id, &scheme_id)
+    }
+
+    pub fn clear_meter_subsidy(env: Env, meter_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        subsidy::untag_meter(&env, &meter_id);
+        Ok(())
+    }
+
+    pub fn get_meter_subsidy(env: Env, meter_id: String) -> Option<String> {
+        subsidy::meter_scheme(&env, &meter_id)
+    }
+
+    // NGN subsidised under the scheme in `period`, for government reimbursement.
+    pub fn get_subsidy_total(env: Env, scheme_id: String, period: u32) -> i128 {
+        subsidy::total(&env, &scheme_id, period)
+    }
+
+    // --- Community bill sponsorship ---
+
+    pub fn set_sponsorship_config(env: Env, config: SponsorshipConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        sponsorship::set_config(&env, &config)
+    }
+
+    pub fn get_sponsorship_config(env: Env) -> SponsorshipConfig {
+        sponsorship::read_config(&env)
+    }
+
+    // Eligible meters have bills covered from their region's pools at payment time.
+    pub fn set_sponsorship_eligible(
+        env: Env,
+        meter_id: String,
+        eligible: bool,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        sponsorship::set_eligible(&env, &meter_id, eligible)
+    }
+
+    // Returns the pool's new balance.
+    pub fn fund_sponsorship(
+        env: Env,
+        donor: Address,
+        region: String,
+        token_address: Address,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        sponsorship::fund(&env, &donor, &region, &token_address, amount)
+    }
+
+    pub fn get_sponsorship_pool(
+        env: Env,
+        region: String,
+        token_address: Address,
+    ) -> SponsorshipPool {
+        sponsorship::pool(&env, &region, &token_address)
+    }
+
+    pub fn get_sponsored_meter(env: Env, meter_id: String) -> Option<SponsoredMeter> {
+        sponsorship::sponsored(&env, &meter_id)
+    }
+
+    // --- Green energy attributes ---
+
+    // Renewable share of the region's supply; bills issued there accrue it to the meter owner.
+    pub fn set_generation_mix(env: Env, region: String, renewable_bps: u32) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        green::set_mix(&env, &region, renewable_bps)
+    }
+
+    pub fn get_generation_mix(env: Env, region: String) -> Option<GenerationMix> {
+        green::mix(&env, &region)
+    }
+
+    // Balances are in Wh of renewable supply.
+    pub fn get_green_credits(env: Env, holder: Address) -> GreenCredits {
+        green::credits(&env, &holder)
+    }
+
+    pub fn set_green_credits_transferable(env: Env, transferable: bool) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        green::set_transferable(&env, transferable);
+        Ok(())
+    }
+
+    pub fn transfer_green_credits(
+        env: Env,
+        from: Address,
+        to: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        green::transfer(&env, &from, &to, amount)
+    }
+
+    pub fn retire_green_credits(
+        env: Env,
+        holder: Address,
+        amount: i128,
+    ) -> Result<GreenCredits, Error> {
+        green::retire(&env, &holder, amount)
+    }
+
+    // --- Estate capacity agreements ---
+
+    // Terms are flat arguments so the estate's signing device shows what it commits to.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_capacity_agreement(
+        env: Env,
+        agreement_id: String,
+        estate: Address,
+        block_kwh: i128,
+        block_rate: i128,
+        spot_rate_id: String,
+        unused_policy: UnusedBlockPolicy,
+        start_period: u32,
+        end_period: u32,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        let terms = CapacityTerms {
+            estate,
+            block_kwh,
+            block_rate,
+            spot_rate_id,
+            unused_policy,
+            start_period,
+            end_period,
+        };
+        capacity::create(&env, &agreement_id, &terms)
+    }
+
+    pub fn record_capacity_drawdown(
+        env: Env,
+        agreement_id: String,
+        period: u32,
+        kwh: i128,
+    ) -> Result<CapacityStatement, Error> {
+        maintenance::ensure_writable(&env)?;
+        capacity::record_drawdown(&env, &agreement_id, period, kwh)
+    }
+
+    pub fn close_capacity_period(
+        env: Env,
+        agreement_id: String,
+        period: u32,
+    ) -> Result<CapacityStatement, Error> {
+        maintenance::ensure_writable(&env)?;
+        capacity::close_period(&env, &agreement_id, period)
+    }
+
+    pub fn get_capacity_agreement(env: Env, agreement_id: String) -> Option<CapacityAgreement> {
+        capacity::read_agreement(&env, &agreement_id)
+    }
+
+    pub fn get_capacity_statement(
+        env: Env,
+        agreement_id: String,
+        period: u32,
+    ) -> CapacityStatement {
+        capacity::read_statement(&env, &agreement_id, period)
+    }
+
+    // --- Monthly consumption and revenue rollups ---
+
+    pub fn get_monthly_stats(
+        env: Env,
+        meter_id: String,
+        year: u32,
+        month: u32,
+    ) -> Result<MonthlyStats, Error> {
+        rollups::meter_stats(&env, &meter_id, year, month)
+    }
+
+    // Covers meters billed under the region's registered rate keys.
+    pub fn get_region_stats(
+        env: Env,
+        region: String,
+        year: u32,
+        month: u32,
+    ) -> Result<MonthlyStats, Error> {
+        rollups::region_stats(&env, &region, year, month)
+    }
+
+    // --- Data retention ---
+
+    pub fn set_retention_config(env: Env, config: RetentionConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        retention::set_config(&env, &config);
+        Ok(())
+    }
+
+    pub fn get_retention_config(env: Env) -> RetentionConfig {
+        retention::read_config(&env)
+    }
+
+    // Admin or meter owner. Removes old payment and reading records past retention;
+    // monthly rollups and the meter summary stay.
+    pub fn prune_billing_records(
+        env: Env,
+        caller: Address,
+        meter_id: String,
+        before_timestamp: u64,
+        max_entries: u32,
+    ) -> Result<PruneState, Error> {
+        retention::prune(&env, &caller, &meter_id, before_timestamp, max_entries)
+    }
+
+    pub fn get_prune_state(env: Env, meter_id: String) -> PruneState {
+        retention::state(&env, &meter_id)
+    }
+
+    // --- Settlement and reconciliation ---
+
+    // `period` is YYYYMM.
+    pub fn sweep_settlement(
+        env: Env,
+        provider: Address,
+        token_address: Address,
+        amount: i128,
+        period: u32,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        settlement::sweep(&env, &provider, &token_address, amount, period)
+    }
+
+    pub fn record_fiat_settlement(
+        env: Env,
+        provider: Address,
+        period: u32,
+        fiat_amount: i128,
+        bank_ref_hash: BytesN<32>,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        settlement::record_fiat(&env, &provider, period, fiat_amount, &bank_ref_hash)
+    }
+
+    pub fn get_fiat_receipts(env: Env, provider: Address, period: u32) -> Vec<FiatReceipt> {
+        settlement::read_receipts(&env, &provider, period)
+    }
+
+    pub fn get_reconciliation(env: Env, provider: Address, period: u32) -> ReconciliationReport {
+        settlement::reconcile(&env, &provider, period)
+    }
+
+    pub fn set_reconciliation_tolerance(env: Env, tolerance_bps: u32) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        settlement::set_tolerance(&env, tolerance_bps)
+    }
+
+    // --- Wholesale settlement pools ---
+
+    // Shares of distributed revenue per pool, in basis points summing to 10_000.
+    pub fn set_split_config(env: Env, config: SplitConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        wholesale::set_split(&env, &config)
+    }
+
+    pub fn get_split_config(env: Env) -> Option<SplitConfig> {
+        wholesale::read_split(&env)
+    }
+
+    // Treasury passes collected revenue on to the pools; returns each pool's part.
+    pub fn distribute_revenue(
+        env: Env,
+        token_address: Address,
+        amount: i128,
+    ) -> Result<Map<WholesalePool, i128>, Error> {
+        maintenance::ensure_writable(&env)?;
+        wholesale::distribute(&env, &token_address, amount)
+    }
+
+    pub fn get_pool_balance(env: Env, pool: WholesalePool, token_address: Address) -> i128 {
+        wholesale::pool_balance(&env, pool, &token_address)
+    }
+
+    pub fn claim_pool(
+        env: Env,
+        pool: WholesalePool,
+        token_address: Address,
+    ) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        wholesale::claim(&env, pool, &token_address)
+    }
+
+    // --- Customer disputes and regulator referral ---
+
+    pub fn set_regulator(env: Env, regulator: Address) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        disputes::set_regulator(&env, &regulator);
+        Ok(())
+    }
+
+    pub fn get_regulator(env: Env) -> Option<Address> {
+        disputes::read_regulator(&env)
+    }
+
+    pub fn open_dispute(
+        env: Env,
+        customer: Address,
+        meter_id: String,
+        case_hash: BytesN<32>,
+    ) -> Result<u64, Error> {
+        maintenance::ensure_writable(&env)?;
+        disputes::open(&env, &customer, &meter_id, &case_hash)
+    }
+
+    pub fn resolve_dispute(env: Env, dispute_id: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        disputes::resolve(&env, dispute_id)
+    }
+
+    // Refers a dispute unresolved past its deadline and freezes settlement sweeps.
+    pub fn escalate_dispute(env: Env, dispute_id: u64) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        disputes::escalate(&env, dispute_id)
+    }
+
+    // Called by the regulator once it has ruled on a referred case.
+    pub fn record_regulator_ruling(env: Env, dispute_id: u64, upheld: bool) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        disputes::record_ruling(&env, dispute_id, upheld)
+    }
+
+    pub fn get_dispute(env: Env, dispute_id: u64) -> Option<Dispute> {
+        disputes::read(&env, dispute_id)
+    }
+
+    // --- Accepted tokens ---
+
+    pub fn add_accepted_token(env: Env, token: Address, config: TokenConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tokens::add(&env, &token, &config)
+    }
+
+    pub fn set_min_payment(env: Env, token: Address, min_payment: i128) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tokens::set_min_payment(&env, &token, min_payment)
+    }
+
+    pub fn remove_accepted_token(env: Env, token: Address) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        tokens::remove(&env, &token)
+    }
+
+    pub fn list_accepted_tokens(env: Env) -> Vec<Address> {
+        tokens::list(&env)
+    }
+
+    pub fn get_accepted_token(env: Env, token: Address) -> Option<TokenConfig> {
+        tokens::read_config(&env, &token)
+    }
+
+    // Recorded from the token contract when it was accepted; kept after removal.
+    pub fn get_token_metadata(env: Env, token: Address) -> Option<TokenMetadata> {
+        tokens::metadata(&env, &token)
+    }
+
+    // Stablecoin payments are only converted while the token's "<symbol>/USD" feed
+    // is within `min_price..=max_price` (7 decimals, so 1.00 is 10_000_000).
+    pub fn set_peg_guard(
+        env: Env,
+        token: Address,
+        min_price: i128,
+        max_price: i128,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        peg::set_guard(&env, &token, min_price, max_price)
+    }
+
+    pub fn remove_peg_guard(env: Env, token: Address) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        peg::remove_guard(&env, &token);
+        Ok(())
+    }
+
+    pub fn get_peg_guard(env: Env, token: Address) -> Option<PegGuard> {
+        peg::read_guard(&env, &token)
+    }
+
+    // --- FX spread and service fees ---
+
+    pub fn set_fee_config(env: Env, config: FeeConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        fees::set_config(&env, &config)
+    }
+
+    pub fn get_fee_config(env: Env) -> Option<FeeConfig> {
+        fees::read_config(&env)
+    }
+
+    pub fn get_accrued_fees(env: Env, token_address: Address) -> i128 {
+        fees::accrued_in(&env, &token_address)
+    }
+
+    // Pays the fees accrued in the token to the collector; returns the amount.
+    pub fn sweep_fees(env: Env, token_address: Address) -> Result<i128, Error> {
+        maintenance::ensure_writable(&env)?;
+        fees::sweep(&env, &token_address)
+    }
+
+    // --- Payments swapped into the settlement token ---
+
+    pub fn set_swap_config(env: Env, config: SwapConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        swap::set_config(&env, &config)
+    }
+
+    pub fn get_swap_config(env: Env) -> Option<SwapConfig> {
+        swap::read_config(&env)
+    }
+
+    // Swaps `amount_in` of an accepted token into the settlement token through the
+    // configured AMM router and credits the meter with the proceeds.
+    pub fn pay_with_swap(
+        env: Env,
+        from: Address,
+        token_in: Address,
+        meter_id: String,
+        amount_in: i128,
+        max_slippage_bps: u32,
+    ) -> Result<u32, Error> {
+        swap::pay(
+            &env,
+            &from,
+            &token_in,
+            &meter_id,
+            amount_in,
+            max_slippage_bps,
+        )
+    }
+
+    // --- Bills collected under a standing token approval ---
+
+    // `cycle_cap` is the most NGN the utility may collect for the meter per billing period.
+    pub fn authorize_collection(
+        env: Env,
+        payer: Address,
+        meter_id: String,
+        token_address: Address,
+        cycle_cap: i128,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        collections::set_mandate(&env, &payer, &meter_id, &token_address, cycle_cap)
+    }
+
+    pub fn revoke_collection(env: Env, payer: Address, meter_id: String) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        collections::revoke_mandate(&env, &payer, &meter_id)
+    }
+
+    pub fn get_collection_mandate(env: Env, meter_id: String) -> Option<CollectionMandate> {
+        collections::read_mandate(&env, &meter_id)
+    }
+
+    // Pulls the meter's outstanding balance from the payer's token allowance.
+    pub fn collect_bill(env: Env, meter_id: String) -> Result<u32, Error> {
+        collections::collect(&env, &meter_id)
+    }
+
+    // --- Post-payment integration hook ---
+
+    // `function` is called on `contract` with (meter_id, payment_index, record) after each payment.
+    pub fn set_payment_hook(env: Env, contract: Address, function: Symbol) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        hooks::set(&env, &PaymentHook { contract, function });
+        Ok(())
+    }
+
+    pub fn remove_payment_hook(env: Env) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        hooks::remove(&env);
+        Ok(())
+    }
+
+    pub fn get_payment_hook(env: Env) -> Option<PaymentHook> {
+        hooks::read(&env)
+    }
+
+    // --- Payment velocity monitoring and spending limits ---
+
+    pub fn set_velocity_config(env: Env, config: VelocityConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        velocity::set_config(&env, &config)
+    }
+
+    pub fn get_velocity_config(env: Env) -> Option<VelocityConfig> {
+        velocity::read_config(&env)
+    }
+
+    pub fn get_payer_activity(env: Env, payer: Address) -> Option<PayerActivity> {
+        velocity::read_activity(&env, &payer)
+    }
+
+    pub fn is_payer_flagged(env: Env, payer: Address) -> bool {
+        velocity::is_flagged(&env, &payer)
+    }
+
+    pub fn clear_payer_flag(env: Env, payer: Address) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        velocity::clear_flag(&env, &payer);
+        Ok(())
+    }
+
+    // Limits are in NGN units. `address` may tighten its own; loosening them needs the admin too.
+    pub fn set_spending_limit(
+        env: Env,
+        address: Address,
+        max_per_day: i128,
+        max_per_tx: i128,
+    ) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        limits::set(
+            &env,
+            &address,
+            &SpendingLimit {
+                max_per_day,
+                max_per_tx,
+            },
+        )
+    }
+
+    // Needs both `address` and the admin.
+    pub fn reset_spending_limit(env: Env, address: Address) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        limits::reset(&env, &address);
+        Ok(())
+    }
+
+    pub fn get_spending_limit(env: Env, address: Address) -> Option<SpendingLimit> {
+        limits::read(&env, &address)
+    }
+
+    pub fn get_spent_today(env: Env, address: Address) -> i128 {
+        limits::spent_today(&env, &address)
+    }
+
+    // --- Invariant monitoring ---
+
+    // Checks the next `limit` receipts and escrows plus token solvency; new violations earn the bounty.
+    pub fn check_invariants(
+        env: Env,
+        caller: Address,
+        limit: u32,
+    ) -> Result<InvariantReport, Error> {
+        maintenance::ensure_writable(&env)?;
+        invariants::check(&env, &caller, limit)
+    }
+
+    pub fn set_invariant_bounty(env: Env, bounty: BountyConfig) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        invariants::set_bounty(&env, &bounty)
+    }
+
+    pub fn get_invariant_bounty(env: Env) -> Option<BountyConfig> {
+        invariants::read_bounty(&env)
+    }
+
+    pub fn is_violation_recorded(env: Env, violation: Violation) -> bool {
+        invariants::is_recorded(&env, &violation)
+    }
+
+    // --- Input bounds ---
+
+    // Ceilings on payment amounts, kWh, prices and rates; zero leaves one unbounded.
+    pub fn set_input_bounds(env: Env, bounds: InputBounds) -> Result<(), Error> {
+        maintenance::ensure_writable(&env)?;
+        bounds::set(&env, &bounds)
+    }
+
+    pub fn get_input_bounds(env: Env) -> InputBounds {
+        bounds::read(&env)
+    }
+
+    // --- Maintenance windows ---
+
+    pub fn schedule_maintenance(env: Env, start: u64, end: u64) -> Result<(), Error> {
+        maintenance::schedule(&env, start, end)
+    }
+
+    pub fn cancel_maintenance(env: Env) {
+        maintenance::cancel(&env)
+    }
+
+    pub fn get_maintenance_window(env: Env) -> Option<MaintenanceWindow> {
+        maintenance::read_window(&env)
+    }
+
+    pub fn is_in_maintenance(env: Env) -> bool {
+        maintenance::is_active(&env)
     }
-}
\ No newline at end of file
+}
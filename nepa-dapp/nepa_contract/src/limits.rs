@@ -0,0 +1,104 @@
+use soroban_sdk::{contracttype, Address, Env, Symbol};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::math;
+use crate::storage;
+
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+
+// Caps an address opts into, in NGN units (7 decimals). Payments from the
+// address are checked against both before any funds move.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpendingLimit {
+    pub max_per_day: i128,
+    pub max_per_tx: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpendWindow {
+    // Day number (timestamp / 86_400) `spent` counts.
+    pub day: u64,
+    pub spent: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LimitKey {
+    SpendingCap(Address),
+    SpendToday(Address),
+}
+
+pub fn read(env: &Env, address: &Address) -> Option<SpendingLimit> {
+    env.storage()
+        .persistent()
+        .get(&LimitKey::SpendingCap(address.clone()))
+}
+
+// What the address has spent today.
+pub fn spent_today(env: &Env, address: &Address) -> i128 {
+    let window: Option<SpendWindow> = env
+        .storage()
+        .persistent()
+        .get(&LimitKey::SpendToday(address.clone()));
+    match window {
+        Some(window) if window.day == env.ledger().timestamp() / DAY_SECONDS => window.spent,
+        _ => 0,
+    }
+}
+
+// The address may tighten its own limits at any time. Loosening them also
+// needs the admin, so a compromised key cannot lift the cap it is held to.
+pub fn set(env: &Env, address: &Address, limit: &SpendingLimit) -> Result<(), Error> {
+    address.require_auth();
+    if limit.max_per_tx <= 0 || limit.max_per_day < limit.max_per_tx {
+        return Err(Error::InvalidConfig);
+    }
+    if let Some(current) = read(env, address) {
+        if limit.max_per_day > current.max_per_day || limit.max_per_tx > current.max_per_tx {
+            admin::require_admin(env);
+        }
+    }
+    storage::write_persistent(env, &LimitKey::SpendingCap(address.clone()), limit);
+    env.events().publish(
+        (Symbol::new(env, "spending_limit_set"), address.clone()),
+        (limit.max_per_day, limit.max_per_tx),
+    );
+    Ok(())
+}
+
+// Admin-assisted reset: with both signatures the limit and today's spend are
+// cleared.
+pub fn reset(env: &Env, address: &Address) {
+    address.require_auth();
+    admin::require_admin(env);
+    env.storage()
+        .persistent()
+        .remove(&LimitKey::SpendingCap(address.clone()));
+    env.storage()
+        .persistent()
+        .remove(&LimitKey::SpendToday(address.clone()));
+    env.events().publish(
+        (Symbol::new(env, "spending_limit_reset"), address.clone()),
+        (),
+    );
+}
+
+// Counts a payment worth `value` NGN against the payer's limits, if any.
+pub fn spend(env: &Env, payer: &Address, value: i128) -> Result<(), Error> {
+    let Some(limit) = read(env, payer) else {
+        return Ok(());
+    };
+    let spent = math::add(spent_today(env, payer), value)?;
+    if value > limit.max_per_tx || spent > limit.max_per_day {
+        return Err(Error::SpendingLimitExceeded);
+    }
+    let window = SpendWindow {
+        day: env.ledger().timestamp() / DAY_SECONDS,
+        spent,
+    };
+    storage::write_persistent(env, &LimitKey::SpendToday(payer.clone()), &window);
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::accounting::{self, PaymentRecord};
+use crate::admin;
+use crate::billing;
+use crate::dunning;
+use crate::errors::Error;
+use crate::math;
+use crate::storage;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoyaltyConfig {
+    // Points earned per whole NGN paid.
+    pub points_per_ngn: i128,
+    // NGN units knocked off a bill per point redeemed.
+    pub redeem_value: i128,
+    // Points lapse after this long without earning any; 0 means never.
+    pub expiry_seconds: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoyaltyAccount {
+    pub points: i128,
+    pub last_earned_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LoyaltyKey {
+    LoyaltyConfig,
+    LoyaltyAccount(Address),
+}
+
+pub fn read_config(env: &Env) -> Option<LoyaltyConfig> {
+    env.storage().instance().get(&LoyaltyKey::LoyaltyConfig)
+}
+
+pub fn set_config(env: &Env, config: &LoyaltyConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if config.points_per_ngn < 0 || config.redeem_value < 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&LoyaltyKey::LoyaltyConfig, config);
+    Ok(())
+}
+
+// The customer's account with any lapsed points already written off.
+pub fn account(env: &Env, customer: &Address) -> LoyaltyAccount {
+    let mut account = env
+        .storage()
+        .persistent()
+        .get(&LoyaltyKey::LoyaltyAccount(customer.clone()))
+        .unwrap_or(LoyaltyAccount {
+            points: 0,
+            last_earned_at: 0,
+        });
+    let expiry = read_config(env).map_or(0, |config| config.expiry_seconds);
+    if expiry > 0 && env.ledger().timestamp() > account.last_earned_at + expiry {
+        account.points = 0;
+    }
+    account
+}
+
+fn write_account(env: &Env, customer: &Address, account: &LoyaltyAccount) {
+    storage::write_persistent(env, &LoyaltyKey::LoyaltyAccount(customer.clone()), account);
+}
+
+// Awards `earner`, normally the payer, points for a settled payment.
+pub fn on_payment(env: &Env, earner: &Address, record: &PaymentRecord) -> Result<(), Error> {
+    let Some(config) = read_config(env) else {
+        return Ok(());
+    };
+    // Rounded down to whole points.
+    let earned = record
+        .normalized_amount
+        .fixed_mul_floor(config.points_per_ngn, 10i128.pow(accounting::NGN_DECIMALS))
+        .unwrap_or(0);
+    if earned <= 0 {
+        return Ok(());
+    }
+    let mut account = account(env, earner);
+    account.points = math::add(account.points, earned)?;
+    account.last_earned_at = env.ledger().timestamp();
+    write_account(env, earner, &account);
+    env.events().publish(
+        (Symbol::new(env, "points_earned"), earner.clone()),
+        (earned, account.points),
+    );
+    Ok(())
+}
+
+// Spends points as a credit on any meter's bill. Returns the NGN credited.
+pub fn redeem(
+    env: &Env,
+    customer: &Address,
+    meter_id: &String,
+    points: i128,
+) -> Result<i128, Error> {
+    customer.require_auth();
+    let config = read_config(env).ok_or(Error::InvalidConfig)?;
+    let mut account = account(env, customer);
+    if points <= 0 || points > account.points {
+        return Err(Error::InvalidInput);
+    }
+    let credit = math::mul(points, config.redeem_value)?;
+    account.points -= points;
+    write_account(env, customer, &account);
+    billing::adjust_balance(env, meter_id, -credit);
+    dunning::on_payment(env, meter_id);
+    env.events().publish(
+        (Symbol::new(env, "points_redeemed"), customer.clone()),
+        (meter_id.clone(), points, credit),
+    );
+    Ok(credit)
+}
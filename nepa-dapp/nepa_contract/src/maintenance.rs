@@ -0,0 +1,65 @@
+use soroban_sdk::{contracttype, Env, Symbol};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::mirror;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaintenanceWindow {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MaintenanceKey {
+    MaintenanceWindow,
+}
+
+pub fn read_window(env: &Env) -> Option<MaintenanceWindow> {
+    env.storage()
+        .instance()
+        .get(&MaintenanceKey::MaintenanceWindow)
+}
+
+pub fn is_active(env: &Env) -> bool {
+    let now = env.ledger().timestamp();
+    read_window(env).is_some_and(|w| w.start <= now && now < w.end)
+}
+
+// Guard for every mutating entry point. Callers that get `MaintenanceWindow`
+// can read the end time from `get_maintenance_window`; read-only mirrors
+// refuse every mutation.
+pub fn ensure_writable(env: &Env) -> Result<(), Error> {
+    if mirror::is_mirror(env) {
+        return Err(Error::ReadOnlyMirror);
+    }
+    if is_active(env) {
+        return Err(Error::MaintenanceWindow);
+    }
+    Ok(())
+}
+
+pub fn schedule(env: &Env, start: u64, end: u64) -> Result<(), Error> {
+    admin::require_admin(env);
+    if end <= start || end <= env.ledger().timestamp() {
+        return Err(Error::InvalidConfig);
+    }
+    let window = MaintenanceWindow { start, end };
+    env.storage()
+        .instance()
+        .set(&MaintenanceKey::MaintenanceWindow, &window);
+    env.events()
+        .publish((Symbol::new(env, "maintenance_scheduled"),), (start, end));
+    Ok(())
+}
+
+pub fn cancel(env: &Env) {
+    admin::require_admin(env);
+    env.storage()
+        .instance()
+        .remove(&MaintenanceKey::MaintenanceWindow);
+    env.events()
+        .publish((Symbol::new(env, "maintenance_cancelled"),), ());
+}
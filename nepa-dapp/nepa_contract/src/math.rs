@@ -0,0 +1,22 @@
+use crate::errors::Error;
+
+// Checked i128 arithmetic for billing and oracle code: an overflow is
+// returned as `Error::ArithmeticOverflow` instead of trapping the host.
+
+pub fn add(a: i128, b: i128) -> Result<i128, Error> {
+    a.checked_add(b).ok_or(Error::ArithmeticOverflow)
+}
+
+pub fn sub(a: i128, b: i128) -> Result<i128, Error> {
+    a.checked_sub(b).ok_or(Error::ArithmeticOverflow)
+}
+
+pub fn mul(a: i128, b: i128) -> Result<i128, Error> {
+    a.checked_mul(b).ok_or(Error::ArithmeticOverflow)
+}
+
+pub fn pow10(exponent: u32) -> Result<i128, Error> {
+    10i128
+        .checked_pow(exponent)
+        .ok_or(Error::ArithmeticOverflow)
+}
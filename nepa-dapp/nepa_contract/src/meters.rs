@@ -0,0 +1,158 @@
+use soroban_sdk::{contracttype, BytesN, Env, String, Symbol, Vec};
+
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::storage;
+use crate::tariff::{self, RateKey};
+
+// Most reclassifications kept per meter, so its metadata stays one bounded entry.
+pub const MAX_BAND_CHANGES: u32 = 24;
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SupplyPhase {
+    Single,
+    Three,
+}
+
+// A band the meter moves to from `effective_from` on.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BandChange {
+    pub band: String,
+    pub effective_from: u64,
+    pub recorded_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MeterMetadata {
+    // Band the meter was connected under; see `band_changes` for later ones.
+    pub band: String,
+    pub phase: SupplyPhase,
+    pub max_load_watts: u32,
+    // Hash of the installation's coordinates; the location itself stays off-chain.
+    pub location_hash: BytesN<32>,
+    pub connected_at: u64,
+    // Reclassifications in effective order.
+    pub band_changes: Vec<BandChange>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MeterKey {
+    MeterInfo(String),
+}
+
+pub fn metadata(env: &Env, meter_id: &String) -> Option<MeterMetadata> {
+    env.storage()
+        .persistent()
+        .get(&MeterKey::MeterInfo(meter_id.clone()))
+}
+
+// Records a meter's installation details. Its band only changes afterwards
+// through `reclassify`, so history keeps the band each bill was issued under.
+pub fn set_metadata(env: &Env, meter_id: &String, info: &MeterMetadata) -> Result<(), Error> {
+    admin::require_admin(env);
+    tariff::check_key_part(&info.band)?;
+    if info.max_load_watts == 0 || info.connected_at > env.ledger().timestamp() {
+        return Err(Error::InvalidInput);
+    }
+    let mut stored = info.clone();
+    match metadata(env, meter_id) {
+        Some(existing) if existing.band != info.band => return Err(Error::InvalidState),
+        Some(existing) => stored.band_changes = existing.band_changes,
+        None => stored.band_changes = Vec::new(env),
+    }
+    storage::write_persistent(env, &MeterKey::MeterInfo(meter_id.clone()), &stored);
+    env.events().publish(
+        (Symbol::new(env, "meter_metadata_set"), meter_id.clone()),
+        stored.band,
+    );
+    Ok(())
+}
+
+fn band_in(metadata: &MeterMetadata, timestamp: u64) -> String {
+    let mut band = metadata.band.clone();
+    for change in metadata.band_changes.iter() {
+        if change.effective_from > timestamp {
+            break;
+        }
+        band = change.band;
+    }
+    band
+}
+
+// Band the meter is billed under at `timestamp`.
+pub fn band_at(env: &Env, meter_id: &String, timestamp: u64) -> Option<String> {
+    metadata(env, meter_id).map(|metadata| band_in(&metadata, timestamp))
+}
+
+// Moves the meter to `band` from `effective_from`, which may not be in the
+// past. Bills already issued keep the rate they were issued under.
+pub fn reclassify(
+    env: &Env,
+    meter_id: &String,
+    band: &String,
+    effective_from: u64,
+) -> Result<(), Error> {
+    admin::require_admin(env);
+    tariff::check_key_part(band)?;
+    let mut metadata = metadata(env, meter_id).ok_or(Error::InvalidInput)?;
+    let now = env.ledger().timestamp();
+    if effective_from < now || band_in(&metadata, effective_from) == *band {
+        return Err(Error::InvalidInput);
+    }
+    if let Some(last) = metadata.band_changes.last() {
+        if effective_from <= last.effective_from {
+            return Err(Error::InvalidState);
+        }
+    }
+    if metadata.band_changes.len() >= MAX_BAND_CHANGES {
+        return Err(Error::InvalidState);
+    }
+    // The meter's region must price the new band before it can move there.
+    if let Some(key) = billing::assigned_rate(env, meter_id)
+        .and_then(|rate_id| tariff::key_for_rate(env, &rate_id))
+    {
+        let rate_id = tariff::rate_id_for(env, &with_band(&key, band))?;
+        if tariff::read_rate(env, &rate_id).is_none() {
+            return Err(Error::RateNotFound);
+        }
+    }
+
+    metadata.band_changes.push_back(BandChange {
+        band: band.clone(),
+        effective_from,
+        recorded_at: now,
+    });
+    storage::write_persistent(env, &MeterKey::MeterInfo(meter_id.clone()), &metadata);
+    env.events().publish(
+        (Symbol::new(env, "band_reclassified"), meter_id.clone()),
+        (band.clone(), effective_from),
+    );
+    Ok(())
+}
+
+fn with_band(key: &RateKey, band: &String) -> RateKey {
+    RateKey {
+        utility_type: key.utility_type.clone(),
+        region: key.region.clone(),
+        band: band.clone(),
+    }
+}
+
+// The assigned rate, moved to the meter's current band when it has been
+// reclassified since the rate was assigned.
+pub fn rate_in_force(env: &Env, meter_id: &String, assigned: String) -> String {
+    let Some(band) = band_at(env, meter_id, env.ledger().timestamp()) else {
+        return assigned;
+    };
+    match tariff::key_for_rate(env, &assigned) {
+        Some(key) if key.band != band => {
+            tariff::rate_id_for(env, &with_band(&key, &band)).unwrap_or(assigned)
+        }
+        _ => assigned,
+    }
+}
@@ -0,0 +1,141 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol, Vec};
+
+use crate::accounting::{self, MeterSummary, PaymentRecord};
+use crate::admin;
+use crate::errors::Error;
+use crate::oracle::{OracleManager, PriceFeed};
+use crate::storage::StorageEntry;
+use crate::tariff::{self, UtilityRate};
+use crate::tokens::{self, TokenConfig};
+
+// Identifies the instance a read-only mirror was copied from.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MirrorInfo {
+    pub source: Address,
+    pub snapshot_ledger: u32,
+    // Once sealed, imports stop and the mirror only serves reads.
+    pub sealed: bool,
+}
+
+// A point-in-time copy of one piece of state, as exported by the source.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SnapshotEntry {
+    Meter(String, MeterSummary),
+    Payment(String, u32, PaymentRecord),
+    Feed(String, PriceFeed),
+    Rate(String, UtilityRate),
+    Token(Address, TokenConfig),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MirrorKey {
+    MirrorInfo,
+}
+
+// Upper bound on entries per export/import call, to keep its cost predictable.
+const MAX_SNAPSHOT_ENTRIES: u32 = 20;
+
+pub fn read_info(env: &Env) -> Option<MirrorInfo> {
+    env.storage().instance().get(&MirrorKey::MirrorInfo)
+}
+
+pub fn is_mirror(env: &Env) -> bool {
+    env.storage().instance().has(&MirrorKey::MirrorInfo)
+}
+
+// Reads the selected entries on the source instance; missing entries are skipped.
+pub fn export(env: &Env, entries: &Vec<StorageEntry>) -> Result<Vec<SnapshotEntry>, Error> {
+    if entries.len() > MAX_SNAPSHOT_ENTRIES {
+        return Err(Error::InvalidInput);
+    }
+    let mut snapshot = Vec::new(env);
+    for entry in entries.iter() {
+        let exported = match entry {
+            StorageEntry::Meter(meter_id) => {
+                let summary = accounting::summary(env, &meter_id);
+                Some(SnapshotEntry::Meter(meter_id, summary))
+            }
+            StorageEntry::MeterPayment(meter_id, index) => {
+                accounting::read_payment(env, &meter_id, index)
+                    .map(|record| SnapshotEntry::Payment(meter_id, index, record))
+            }
+            StorageEntry::PriceFeed(feed_id) => OracleManager::get_price_feed(env, &feed_id)
+                .map(|feed| SnapshotEntry::Feed(feed_id, feed)),
+            StorageEntry::UtilityRate(rate_id) => {
+                tariff::read_rate(env, &rate_id).map(|rate| SnapshotEntry::Rate(rate_id, rate))
+            }
+            StorageEntry::AcceptedToken(token) => {
+                tokens::read_config(env, &token).map(|config| SnapshotEntry::Token(token, config))
+            }
+        };
+        if let Some(exported) = exported {
+            snapshot.push_back(exported);
+        }
+    }
+    Ok(snapshot)
+}
+
+// Turns a fresh instance into an unsealed mirror of `source`.
+pub fn initialize(
+    env: &Env,
+    admin_address: &Address,
+    source: &Address,
+    snapshot_ledger: u32,
+) -> Result<(), Error> {
+    if admin::has_admin(env) {
+        return Err(Error::AlreadyInitialized);
+    }
+    admin_address.require_auth();
+    admin::write_admin(env, admin_address);
+
+    let info = MirrorInfo {
+        source: source.clone(),
+        snapshot_ledger,
+        sealed: false,
+    };
+    env.storage().instance().set(&MirrorKey::MirrorInfo, &info);
+    env.events().publish(
+        (Symbol::new(env, "mirror_initialized"), source.clone()),
+        snapshot_ledger,
+    );
+    Ok(())
+}
+
+pub fn import(env: &Env, snapshot: &Vec<SnapshotEntry>) -> Result<(), Error> {
+    admin::require_admin(env);
+    let info = read_info(env).ok_or(Error::InvalidState)?;
+    if info.sealed {
+        return Err(Error::ReadOnlyMirror);
+    }
+    if snapshot.len() > MAX_SNAPSHOT_ENTRIES {
+        return Err(Error::InvalidInput);
+    }
+
+    for entry in snapshot.iter() {
+        match entry {
+            SnapshotEntry::Meter(meter_id, summary) => {
+                accounting::store_summary(env, &meter_id, &summary)
+            }
+            SnapshotEntry::Payment(meter_id, index, record) => {
+                accounting::store_payment(env, &meter_id, index, &record)
+            }
+            SnapshotEntry::Feed(feed_id, feed) => OracleManager::store_feed(env, &feed_id, &feed),
+            SnapshotEntry::Rate(rate_id, rate) => tariff::store_rate(env, &rate_id, &rate),
+            SnapshotEntry::Token(token, config) => tokens::store(env, &token, &config),
+        }
+    }
+    Ok(())
+}
+
+pub fn seal(env: &Env) -> Result<(), Error> {
+    admin::require_admin(env);
+    let mut info = read_info(env).ok_or(Error::InvalidState)?;
+    info.sealed = true;
+    env.storage().instance().set(&MirrorKey::MirrorInfo, &info);
+    env.events()
+        .publish((Symbol::new(env, "mirror_sealed"), info.source), ());
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// A scriptable SEP-40 oracle for integration tests, behind the `testutils`
+// feature. Tests set each asset's price and timestamp, silence an asset, or
+// make every call fail, to drive the external leg of a fallback chain.
+use soroban_sdk::{contract, contracterror, contractimpl, contracttype, Env};
+
+use crate::sep40::{Sep40Asset, Sep40PriceData};
+
+const DEFAULT_DECIMALS: u32 = 7;
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum MockOracleError {
+    // Returned by every read while failures are scripted.
+    Unavailable = 1,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MockOracleKey {
+    Decimals,
+    ScriptedPrice(Sep40Asset),
+    Failing,
+}
+
+#[contract]
+pub struct MockPriceOracle;
+
+#[contractimpl]
+impl MockPriceOracle {
+    pub fn set_decimals(env: Env, decimals: u32) {
+        env.storage()
+            .instance()
+            .set(&MockOracleKey::Decimals, &decimals);
+    }
+
+    // `timestamp` may lie in the past to script a stale price.
+    pub fn set_price(env: Env, asset: Sep40Asset, price: i128, timestamp: u64) {
+        env.storage().instance().set(
+            &MockOracleKey::ScriptedPrice(asset),
+            &Sep40PriceData { price, timestamp },
+        );
+    }
+
+    // The asset then reads as having no price.
+    pub fn clear_price(env: Env, asset: Sep40Asset) {
+        env.storage()
+            .instance()
+            .remove(&MockOracleKey::ScriptedPrice(asset));
+    }
+
+    pub fn set_failing(env: Env, failing: bool) {
+        env.storage()
+            .instance()
+            .set(&MockOracleKey::Failing, &failing);
+    }
+
+    pub fn decimals(env: Env) -> Result<u32, MockOracleError> {
+        ensure_available(&env)?;
+        Ok(env
+            .storage()
+            .instance()
+            .get(&MockOracleKey::Decimals)
+            .unwrap_or(DEFAULT_DECIMALS))
+    }
+
+    pub fn lastprice(
+        env: Env,
+        asset: Sep40Asset,
+    ) -> Result<Option<Sep40PriceData>, MockOracleError> {
+        ensure_available(&env)?;
+        Ok(env
+            .storage()
+            .instance()
+            .get(&MockOracleKey::ScriptedPrice(asset)))
+    }
+}
+
+fn ensure_available(env: &Env) -> Result<(), MockOracleError> {
+    let failing: bool = env
+        .storage()
+        .instance()
+        .get(&MockOracleKey::Failing)
+        .unwrap_or(false);
+    if failing {
+        return Err(MockOracleError::Unavailable);
+    }
+    Ok(())
+}
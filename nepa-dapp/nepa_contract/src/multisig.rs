@@ -0,0 +1,248 @@
+use soroban_sdk::{contracttype, Address, BytesN, Env, String, Symbol, Vec};
+
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::errors::Error;
+use crate::fees::{self, FeeConfig};
+use crate::settlement;
+use crate::storage;
+use crate::tariff::{TariffOp, TouSchedule};
+use crate::timelock::{self, TimelockChange};
+use crate::units::MeteredUnit;
+
+// Proposals not executed within a week lapse.
+const PROPOSAL_LIFETIME_SECONDS: u64 = 7 * 24 * 60 * 60;
+const MAX_SIGNERS: u32 = 10;
+
+// Critical operations that need M-of-N approval once a signer set exists.
+// Arguments stay top-level so signing devices can show them.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdminAction {
+    // provider, token, amount, period
+    SweepSettlement(Address, Address, i128, u32),
+    SetUtilityRate(String, Vec<TariffOp>),
+    SetUtilityRates(Vec<(String, Vec<TariffOp>)>),
+    // rate_id, formula, effective_from
+    ScheduleUtilityRate(String, Vec<TariffOp>, u64),
+    // rate_id, sunset_at
+    DeprecateUtilityRate(String, u64),
+    RemoveUtilityRate(String),
+    SetTouSchedule(String, TouSchedule),
+    SetRateUnit(String, MeteredUnit),
+    // rate_id, NGN units per kWh
+    SetEstimatedRate(String, i128),
+    // collector, fx_spread_bps, service_fee_bps
+    SetFeeConfig(Address, u32, u32),
+    Upgrade(BytesN<32>),
+    // signers, threshold
+    SetSigners(Vec<Address>, u32),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MultisigConfig {
+    pub signers: Vec<Address>,
+    pub threshold: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Proposal {
+    pub action: AdminAction,
+    pub proposer: Address,
+    pub approvals: Vec<Address>,
+    pub expires_at: u64,
+    pub executed: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MultisigKey {
+    MultisigConfig,
+    NextProposalId,
+    Proposal(u64),
+}
+
+pub fn read_config(env: &Env) -> Option<MultisigConfig> {
+    env.storage().instance().get(&MultisigKey::MultisigConfig)
+}
+
+pub fn read_proposal(env: &Env, proposal_id: u64) -> Option<Proposal> {
+    env.storage()
+        .persistent()
+        .get(&MultisigKey::Proposal(proposal_id))
+}
+
+fn write_proposal(env: &Env, proposal_id: u64, proposal: &Proposal) {
+    storage::write_persistent(env, &MultisigKey::Proposal(proposal_id), proposal);
+}
+
+// Direct calls to critical operations are refused once a signer set is configured.
+pub fn ensure_not_required(env: &Env) -> Result<(), Error> {
+    if read_config(env).is_some() {
+        return Err(Error::MultisigRequired);
+    }
+    Ok(())
+}
+
+fn store_config(env: &Env, signers: &Vec<Address>, threshold: u32) -> Result<(), Error> {
+    let mut unique = Vec::new(env);
+    for signer in signers.iter() {
+        if !unique.contains(&signer) {
+            unique.push_back(signer);
+        }
+    }
+    if unique.is_empty() || unique.len() > MAX_SIGNERS || threshold == 0 || threshold > unique.len()
+    {
+        return Err(Error::InvalidConfig);
+    }
+    let config = MultisigConfig {
+        signers: unique,
+        threshold,
+    };
+    env.storage()
+        .instance()
+        .set(&MultisigKey::MultisigConfig, &config);
+    audit::record(
+        env,
+        AuditAction::SignersChanged(config.signers.clone(), threshold),
+    );
+    env.events().publish(
+        (Symbol::new(env, "multisig_configured"),),
+        (config.signers.len(), threshold),
+    );
+    Ok(())
+}
+
+// The admin appoints the first signer set; later changes go through proposals.
+pub fn configure(env: &Env, signers: &Vec<Address>, threshold: u32) -> Result<(), Error> {
+    ensure_not_required(env)?;
+    admin::require_admin(env);
+    store_config(env, signers, threshold)
+}
+
+fn require_signer(env: &Env, signer: &Address) -> Result<MultisigConfig, Error> {
+    let config = read_config(env).ok_or(Error::InvalidState)?;
+    if !config.signers.contains(signer) {
+        return Err(Error::InvalidInput);
+    }
+    signer.require_auth();
+    Ok(config)
+}
+
+// The proposer's own approval is counted.
+pub fn propose(env: &Env, proposer: &Address, action: &AdminAction) -> Result<u64, Error> {
+    require_signer(env, proposer)?;
+    let proposal_id: u64 = env
+        .storage()
+        .instance()
+        .get(&MultisigKey::NextProposalId)
+        .unwrap_or(1);
+    env.storage()
+        .instance()
+        .set(&MultisigKey::NextProposalId, &(proposal_id + 1));
+
+    let mut approvals = Vec::new(env);
+    approvals.push_back(proposer.clone());
+    let proposal = Proposal {
+        action: action.clone(),
+        proposer: proposer.clone(),
+        approvals,
+        expires_at: env.ledger().timestamp() + PROPOSAL_LIFETIME_SECONDS,
+        executed: false,
+    };
+    write_proposal(env, proposal_id, &proposal);
+    env.events().publish(
+        (Symbol::new(env, "action_proposed"), proposal_id),
+        (proposer.clone(), action.clone()),
+    );
+    Ok(proposal_id)
+}
+
+fn open_proposal(env: &Env, proposal_id: u64) -> Result<Proposal, Error> {
+    let proposal = read_proposal(env, proposal_id).ok_or(Error::InvalidInput)?;
+    if proposal.executed || env.ledger().timestamp() >= proposal.expires_at {
+        return Err(Error::InvalidState);
+    }
+    Ok(proposal)
+}
+
+pub fn approve(env: &Env, signer: &Address, proposal_id: u64) -> Result<u32, Error> {
+    require_signer(env, signer)?;
+    let mut proposal = open_proposal(env, proposal_id)?;
+    if proposal.approvals.contains(signer) {
+        return Err(Error::AlreadyExists);
+    }
+    proposal.approvals.push_back(signer.clone());
+    write_proposal(env, proposal_id, &proposal);
+    env.events().publish(
+        (Symbol::new(env, "action_approved"), proposal_id),
+        signer.clone(),
+    );
+    Ok(proposal.approvals.len())
+}
+
+// Anyone may execute once enough current signers have approved.
+pub fn execute(env: &Env, proposal_id: u64) -> Result<(), Error> {
+    let config = read_config(env).ok_or(Error::InvalidState)?;
+    let mut proposal = open_proposal(env, proposal_id)?;
+    // Approvals from signers removed since then no longer count.
+    let approvals = proposal
+        .approvals
+        .iter()
+        .filter(|signer| config.signers.contains(signer))
+        .count() as u32;
+    if approvals < config.threshold {
+        return Err(Error::InvalidState);
+    }
+
+    proposal.executed = true;
+    write_proposal(env, proposal_id, &proposal);
+
+    match proposal.action {
+        AdminAction::SweepSettlement(provider, token_address, amount, period) => {
+            settlement::transfer_sweep(env, &provider, &token_address, amount, period)?
+        }
+        AdminAction::SetUtilityRate(rate_id, formula) => {
+            timelock::apply_or_enqueue(env, &TimelockChange::UtilityRate(rate_id, formula))?
+        }
+        AdminAction::SetUtilityRates(rates) => {
+            timelock::apply_or_enqueue(env, &TimelockChange::UtilityRates(rates))?
+        }
+        AdminAction::ScheduleUtilityRate(rate_id, formula, effective_from) => {
+            timelock::apply_or_enqueue(
+                env,
+                &TimelockChange::ScheduledRate(rate_id, formula, effective_from),
+            )?
+        }
+        AdminAction::DeprecateUtilityRate(rate_id, sunset_at) => {
+            timelock::apply_or_enqueue(env, &TimelockChange::RateDeprecation(rate_id, sunset_at))?
+        }
+        AdminAction::RemoveUtilityRate(rate_id) => {
+            timelock::apply_or_enqueue(env, &TimelockChange::RateRemoval(rate_id))?
+        }
+        AdminAction::SetTouSchedule(rate_id, schedule) => {
+            timelock::apply_or_enqueue(env, &TimelockChange::TouSchedule(rate_id, schedule))?
+        }
+        AdminAction::SetRateUnit(rate_id, unit) => {
+            timelock::apply_or_enqueue(env, &TimelockChange::RateUnit(rate_id, unit))?
+        }
+        AdminAction::SetEstimatedRate(rate_id, per_kwh) => {
+            timelock::apply_or_enqueue(env, &TimelockChange::EstimatedRate(rate_id, per_kwh))?
+        }
+        AdminAction::SetFeeConfig(collector, fx_spread_bps, service_fee_bps) => fees::store_config(
+            env,
+            &FeeConfig {
+                collector,
+                fx_spread_bps,
+                service_fee_bps,
+            },
+        )?,
+        AdminAction::Upgrade(new_wasm_hash) => admin::upgrade(env, &new_wasm_hash),
+        AdminAction::SetSigners(signers, threshold) => store_config(env, &signers, threshold)?,
+    }
+    env.events()
+        .publish((Symbol::new(env, "action_executed"), proposal_id), ());
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use soroban_sdk::{contracttype, Env, String, Symbol};
+
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::billing;
+use crate::bounds;
+use crate::errors::Error;
+use crate::math;
+use crate::storage;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NetMeteringKey {
+    // NGN units credited per exported kWh, by utility rate.
+    FeedInTariff(String),
+    // Export credit not yet netted against a bill. Kept apart from the meter
+    // balance so it only ever offsets energy bills and carries over until used.
+    ExportCredit(String),
+    PeriodExports(String, u32),
+}
+
+pub fn feed_in_tariff(env: &Env, rate_id: &String) -> Option<i128> {
+    env.storage()
+        .persistent()
+        .get(&NetMeteringKey::FeedInTariff(rate_id.clone()))
+}
+
+pub fn set_feed_in_tariff(env: &Env, rate_id: &String, per_kwh: i128) -> Result<(), Error> {
+    admin::require_admin(env);
+    if per_kwh <= 0 {
+        return Err(Error::InvalidTariff);
+    }
+    bounds::rate(env, per_kwh)?;
+    storage::write_persistent(
+        env,
+        &NetMeteringKey::FeedInTariff(rate_id.clone()),
+        &per_kwh,
+    );
+    audit::record(env, AuditAction::FeedInTariffSet(rate_id.clone(), per_kwh));
+    env.events().publish(
+        (Symbol::new(env, "feed_in_tariff_updated"), rate_id.clone()),
+        per_kwh,
+    );
+    Ok(())
+}
+
+pub fn credit(env: &Env, meter_id: &String) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&NetMeteringKey::ExportCredit(meter_id.clone()))
+        .unwrap_or(0)
+}
+
+fn write_credit(env: &Env, meter_id: &String, credit: i128) {
+    storage::write_persistent(
+        env,
+        &NetMeteringKey::ExportCredit(meter_id.clone()),
+        &credit,
+    );
+}
+
+pub fn period_exports(env: &Env, meter_id: &String, period: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&NetMeteringKey::PeriodExports(meter_id.clone(), period))
+        .unwrap_or(0)
+}
+
+// Credits exported kWh at the feed-in tariff of the meter's assigned rate.
+// Returns the credit added.
+pub fn record_export(env: &Env, meter_id: &String, kwh_exported: i128) -> Result<i128, Error> {
+    admin::require_admin(env);
+    if kwh_exported <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    bounds::kwh(env, kwh_exported)?;
+    let rate_id = billing::meter_rate(env, meter_id).ok_or(Error::RateNotFound)?;
+    let tariff = feed_in_tariff(env, &rate_id).ok_or(Error::InvalidConfig)?;
+    let amount = math::mul(kwh_exported, tariff)?;
+
+    let period = billing::period_at(env.ledger().timestamp());
+    storage::write_persistent(
+        env,
+        &NetMeteringKey::PeriodExports(meter_id.clone(), period),
+        &math::add(period_exports(env, meter_id, period), kwh_exported)?,
+    );
+    write_credit(env, meter_id, math::add(credit(env, meter_id), amount)?);
+
+    env.events().publish(
+        (
+            Symbol::new(env, "export_recorded"),
+            meter_id.clone(),
+            period,
+        ),
+        (kwh_exported, amount),
+    );
+    Ok(amount)
+}
+
+// Nets carried-over export credit against a new bill; whatever the bill does
+// not absorb stays for the next period. Returns the credit applied.
+pub fn apply_credit(env: &Env, meter_id: &String, amount: i128) -> i128 {
+    let available = credit(env, meter_id);
+    let applied = available.min(amount).max(0);
+    if applied > 0 {
+        write_credit(env, meter_id, available - applied);
+    }
+    applied
+}
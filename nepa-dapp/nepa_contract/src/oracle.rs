@@ -0,0 +1,1213 @@
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{
+    contracttype, panic_with_error, Address, Bytes, BytesN, Env, String, Symbol, Vec,
+};
+
+use crate::admin;
+use crate::bounds;
+use crate::budgets::{self, OracleCostStats};
+use crate::errors::Error;
+use crate::keepers::{self, FeedSchedule};
+use crate::math;
+use crate::sep40::{ExternalPriceSource, Sep40Client};
+use crate::storage;
+use crate::timelock;
+
+// Price of one whole token in NGN, scaled by 10^decimals.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceFeed {
+    pub price: i128,
+    pub decimals: u32,
+    pub last_updated: u64,
+}
+
+// A non-price observation such as temperature or a fuel spot price, scaled by
+// 10^decimals. Governed and aged like price feeds.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataFeed {
+    pub value: i128,
+    pub decimals: u32,
+    pub last_updated: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PricePoint {
+    pub price: i128,
+    pub timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OracleConfig {
+    // Payments are valued at the TWAP over this window; 0 means spot price.
+    pub twap_window_seconds: u64,
+    // Feeds not updated within this many seconds are refused at payment time.
+    pub max_age_seconds: u64,
+    // Value payments at the admin-set fallback price instead of failing on a stale feed.
+    pub use_fallback: bool,
+    // Largest move one update may make, in bps of the previous price. Larger
+    // moves are held for admin review instead of applied; 0 disables the guard.
+    pub max_deviation_bps: u32,
+    // Payments refuse feeds whose reliability score is below this; 0 disables.
+    pub min_reliability_bps: u32,
+}
+
+// Track record of one feed. Updates held by the deviation guard count against
+// it unless the admin accepts them; counts are halved once their sum passes
+// RELIABILITY_WINDOW so old incidents fade.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeedReliability {
+    pub applied_updates: u32,
+    pub flagged_updates: u32,
+    pub score_bps: u32,
+}
+
+// A feed's update record alongside its heartbeat monitoring.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReliabilitySummary {
+    pub applied_updates: u32,
+    pub flagged_updates: u32,
+    pub score_bps: u32,
+    // 0 while the feed is not monitored.
+    pub heartbeat_seconds: u64,
+    pub missed_heartbeat: bool,
+}
+
+// The cost, reliability and schedule views of one feed in a single read, for
+// dashboards that show all three.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OracleStats {
+    pub cost: OracleCostStats,
+    pub reliability: ReliabilitySummary,
+    pub schedule: FeedSchedule,
+}
+
+// Where a payment price was resolved from.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PriceSource {
+    // The contract's own pushed feed, spot or TWAP.
+    PushFeed,
+    // A SEP-40 oracle contract.
+    ExternalOracle,
+    // The pushed feed's last price, past max age but within the chain's cache age.
+    CachedPrice,
+    // The admin-set fallback price.
+    StaticRate,
+}
+
+// What became of one entry of a batch update. Failed entries carry the
+// contract error code and leave the feed as it was.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UpdateOutcome {
+    Applied,
+    // Held back by the deviation guard for review.
+    Flagged,
+    Failed(u32),
+}
+
+impl UpdateOutcome {
+    pub fn of(result: Result<bool, Error>) -> Self {
+        match result {
+            Ok(true) => UpdateOutcome::Applied,
+            Ok(false) => UpdateOutcome::Flagged,
+            Err(error) => UpdateOutcome::Failed(error as u32),
+        }
+    }
+}
+
+// A registered feed as listed by `list_price_feeds`; `sunset` is 0 unless the
+// feed is deprecated.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeedListing {
+    pub feed_id: String,
+    pub feed: PriceFeed,
+    pub sunset: u64,
+}
+
+// Sources tried in order for a feed's payment price; the first usable one wins.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FallbackChain {
+    pub sources: Vec<PriceSource>,
+    // Oldest a cached price may be, in seconds.
+    pub cache_max_age_seconds: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OracleKey {
+    PriceFeed(String),
+    PriceFeedIndex,
+    PriceHistory(String),
+    OracleConfig,
+    FallbackPrice(String),
+    DataFeed(String),
+    DataFeedIndex,
+    // Ed25519 public key whose signature a relayed price report must carry.
+    ReporterKey(String),
+    // Latest update held back by the deviation guard.
+    FlaggedPrice(String),
+    // Price feeds only; data feeds tracked here before their own key existed
+    // are still read from it.
+    FeedReliability(String),
+    DataFeedReliability(String),
+    // Ring buffer of applied prices: slot = sequence % MAX_PRICE_SNAPSHOTS.
+    PriceSnapshot(String, u32),
+    SnapshotCount(String),
+    FallbackChain(String),
+    ExternalSource(String),
+    // Kept in instance storage: after this timestamp the deprecated feed no
+    // longer prices payments or takes updates.
+    FeedSunset(String),
+    // Kept in instance storage: the most seconds the feed may go without an
+    // update before its push price is treated as down.
+    FeedHeartbeat(String),
+    // Kept in instance storage while the feed is past its heartbeat and the
+    // contract has announced it.
+    FeedUnhealthy(String),
+}
+
+// Number of price points retained per feed for TWAP.
+const MAX_PRICE_HISTORY: u32 = 24;
+// Applied prices retained per feed for dispute lookups, and the most a single
+// range query returns.
+const MAX_PRICE_SNAPSHOTS: u32 = 200;
+const MAX_RANGE_POINTS: u32 = 50;
+const DEFAULT_MAX_AGE_SECONDS: u64 = 3600;
+const BPS_DENOMINATOR: i128 = 10_000;
+const RELIABILITY_WINDOW: u32 = 100;
+// Most entries one batch update may carry.
+pub const MAX_BATCH_UPDATES: u32 = 50;
+pub const MAX_FEED_PAGE: u32 = 50;
+
+// Whether this build keeps its own push feeds (the `embedded-oracle` feature).
+// Without them a feed's default chain starts at its SEP-40 oracle.
+pub const EMBEDDED: bool = cfg!(feature = "embedded-oracle");
+
+// The embedded oracle: push feeds kept in this contract's own storage and
+// called as a module. Prices from a separately deployed oracle come in
+// through `Sep40Client` as the ExternalOracle source of a fallback chain, the
+// default source when the contract is built without `embedded-oracle`.
+pub struct OracleManager;
+
+impl OracleManager {
+    pub fn get_price_feed(env: &Env, feed_id: &String) -> Option<PriceFeed> {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::PriceFeed(feed_id.clone()))
+    }
+
+    // Every feed id ever stored, in registration order.
+    pub fn get_price_feed_ids(env: &Env) -> Vec<String> {
+        storage::read_index(env, &OracleKey::PriceFeedIndex)
+    }
+
+    // Up to `limit` registered feeds starting at position `offset` of the
+    // index, in registration order.
+    pub fn list_price_feeds(env: &Env, offset: u32, limit: u32) -> Vec<FeedListing> {
+        let ids = Self::get_price_feed_ids(env);
+        let mut page = Vec::new(env);
+        let mut i = offset;
+        while i < ids.len() && page.len() < limit.min(MAX_FEED_PAGE) {
+            let feed_id = ids.get_unchecked(i);
+            if let Some(feed) = Self::get_price_feed(env, &feed_id) {
+                page.push_back(FeedListing {
+                    sunset: Self::get_feed_sunset(env, &feed_id).unwrap_or(0),
+                    feed_id,
+                    feed,
+                });
+            }
+            i += 1;
+        }
+        page
+    }
+
+    pub fn get_config(env: &Env) -> OracleConfig {
+        env.storage()
+            .instance()
+            .get(&OracleKey::OracleConfig)
+            .unwrap_or(OracleConfig {
+                twap_window_seconds: 0,
+                max_age_seconds: DEFAULT_MAX_AGE_SECONDS,
+                use_fallback: false,
+                max_deviation_bps: 0,
+                min_reliability_bps: 0,
+            })
+    }
+
+    pub fn set_config(env: &Env, config: &OracleConfig) -> Result<(), Error> {
+        timelock::ensure_not_required(env)?;
+        admin::require_admin(env);
+        Self::validate_config(config)?;
+        Self::store_config(env, config);
+        Ok(())
+    }
+
+    pub fn validate_config(config: &OracleConfig) -> Result<(), Error> {
+        if config.max_age_seconds == 0 || config.min_reliability_bps as i128 > BPS_DENOMINATOR {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(())
+    }
+
+    pub fn store_config(env: &Env, config: &OracleConfig) {
+        env.storage()
+            .instance()
+            .set(&OracleKey::OracleConfig, config);
+        env.events()
+            .publish((Symbol::new(env, "oracle_config_updated"),), config.clone());
+    }
+
+    pub fn get_fallback_price(env: &Env, feed_id: &String) -> Option<PriceFeed> {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::FallbackPrice(feed_id.clone()))
+    }
+
+    pub fn set_fallback_price(
+        env: &Env,
+        feed_id: &String,
+        price: i128,
+        decimals: u32,
+    ) -> Result<(), Error> {
+        admin::require_admin(env);
+        if price <= 0 {
+            return Err(Error::InvalidPrice);
+        }
+        bounds::price(env, price, decimals)?;
+        let fallback = PriceFeed {
+            price,
+            decimals,
+            last_updated: env.ledger().timestamp(),
+        };
+        storage::write_persistent(env, &OracleKey::FallbackPrice(feed_id.clone()), &fallback);
+        env.events().publish(
+            (Symbol::new(env, "fallback_price_set"), feed_id.clone()),
+            (price, decimals),
+        );
+        Ok(())
+    }
+
+    pub fn is_stale(env: &Env, feed: &PriceFeed, config: &OracleConfig) -> bool {
+        env.ledger().timestamp().saturating_sub(feed.last_updated) > config.max_age_seconds
+    }
+
+    pub fn get_price_history(env: &Env, feed_id: &String) -> Vec<PricePoint> {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::PriceHistory(feed_id.clone()))
+            .unwrap_or(Vec::new(env))
+    }
+
+    // Time-weighted average over the last `window_seconds`. Each point's price is
+    // held until the next point (or now); history older than the oldest retained
+    // point is not counted.
+    pub fn get_twap(env: &Env, feed_id: &String, window_seconds: u64) -> Result<i128, Error> {
+        let feed = Self::get_price_feed(env, feed_id).ok_or(Error::PriceFeedNotFound)?;
+        let history = Self::get_price_history(env, feed_id);
+        let now = env.ledger().timestamp();
+        let window_start = now.saturating_sub(window_seconds);
+
+        let mut weighted: i128 = 0;
+        let mut elapsed: u64 = 0;
+        for i in 0..history.len() {
+            let point = history.get_unchecked(i);
+            let until = match history.get(i + 1) {
+                Some(next) => next.timestamp,
+                None => now,
+            };
+            let from = point.timestamp.max(window_start);
+            if until > from {
+                let span = until - from;
+                weighted = math::add(weighted, math::mul(point.price, span as i128)?)?;
+                elapsed += span;
+            }
+        }
+
+        if elapsed == 0 {
+            return Ok(feed.price);
+        }
+        Ok(weighted / elapsed as i128)
+    }
+
+    // The price payments are valued at, resolved through the feed's fallback chain.
+    pub fn get_payment_price(env: &Env, feed_id: &String) -> Result<PriceFeed, Error> {
+        Self::resolve_payment_price(env, feed_id).map(|(feed, _)| feed)
+    }
+
+    // Feeds without a chain use their push feed, then the fallback price when
+    // the config enables it.
+    pub fn get_fallback_chain(env: &Env, feed_id: &String) -> FallbackChain {
+        Self::chain_for(env, feed_id, &Self::get_config(env))
+    }
+
+    fn chain_for(env: &Env, feed_id: &String, config: &OracleConfig) -> FallbackChain {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::FallbackChain(feed_id.clone()))
+            .unwrap_or_else(|| {
+                let first = if EMBEDDED {
+                    PriceSource::PushFeed
+                } else {
+                    PriceSource::ExternalOracle
+                };
+                let mut sources = Vec::from_array(env, [first]);
+                if config.use_fallback {
+                    sources.push_back(PriceSource::StaticRate);
+                }
+                FallbackChain {
+                    sources,
+                    cache_max_age_seconds: 0,
+                }
+            })
+    }
+
+    pub fn set_fallback_chain(
+        env: &Env,
+        feed_id: &String,
+        chain: &FallbackChain,
+    ) -> Result<(), Error> {
+        admin::require_admin(env);
+        let sources = &chain.sources;
+        if sources.is_empty() || sources.len() > 4 {
+            return Err(Error::InvalidConfig);
+        }
+        for (i, source) in sources.iter().enumerate() {
+            if sources.first_index_of(source) != Some(i as u32) {
+                return Err(Error::InvalidConfig);
+            }
+        }
+        // The cache is of the pushed feed, so both need the embedded oracle.
+        if !EMBEDDED
+            && (sources.contains(PriceSource::PushFeed)
+                || sources.contains(PriceSource::CachedPrice))
+        {
+            return Err(Error::InvalidConfig);
+        }
+        if sources.contains(PriceSource::ExternalOracle)
+            && Self::get_external_source(env, feed_id).is_none()
+        {
+            return Err(Error::InvalidConfig);
+        }
+        storage::write_persistent(env, &OracleKey::FallbackChain(feed_id.clone()), chain);
+        env.events().publish(
+            (Symbol::new(env, "fallback_chain_set"), feed_id.clone()),
+            chain.clone(),
+        );
+        Ok(())
+    }
+
+    pub fn get_external_source(env: &Env, feed_id: &String) -> Option<ExternalPriceSource> {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::ExternalSource(feed_id.clone()))
+    }
+
+    pub fn set_external_source(env: &Env, feed_id: &String, source: &ExternalPriceSource) {
+        admin::require_admin(env);
+        storage::write_persistent(env, &OracleKey::ExternalSource(feed_id.clone()), source);
+        env.events().publish(
+            (Symbol::new(env, "external_source_set"), feed_id.clone()),
+            source.oracle.clone(),
+        );
+    }
+
+    // Tries each source of the feed's chain in turn. Fails with the first
+    // source's error when none of them can price.
+    pub fn resolve_payment_price(
+        env: &Env,
+        feed_id: &String,
+    ) -> Result<(PriceFeed, PriceSource), Error> {
+        Self::ensure_not_sunset(env, feed_id)?;
+        let config = Self::get_config(env);
+        let chain = Self::chain_for(env, feed_id, &config);
+        let mut first_error = None;
+        for source in chain.sources.iter() {
+            let resolved = match source {
+                PriceSource::PushFeed => Self::push_price(env, feed_id, &config),
+                PriceSource::ExternalOracle => Self::external_price(env, feed_id, &config),
+                PriceSource::CachedPrice => {
+                    Self::cached_price(env, feed_id, &config, chain.cache_max_age_seconds)
+                }
+                PriceSource::StaticRate => {
+                    Self::get_fallback_price(env, feed_id).ok_or(Error::PriceFeedNotFound)
+                }
+            };
+            match resolved {
+                Ok(feed) => return Ok((feed, source)),
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+        Err(first_error.unwrap_or(Error::PriceFeedNotFound))
+    }
+
+    // Spot, or TWAP when configured, from a reliable and fresh push feed. A
+    // feed past its heartbeat is refused so the chain moves on.
+    fn push_price(env: &Env, feed_id: &String, config: &OracleConfig) -> Result<PriceFeed, Error> {
+        let mut feed = Self::get_price_feed(env, feed_id).ok_or(Error::PriceFeedNotFound)?;
+        Self::ensure_reliable(config, || Self::get_feed_reliability(env, feed_id))?;
+        if Self::is_stale(env, &feed, config) {
+            return Err(Error::StalePriceFeed);
+        }
+        if Self::missed_heartbeat(env, feed_id, &feed) {
+            return Err(Error::StalePriceFeed);
+        }
+        if config.twap_window_seconds > 0 {
+            feed.price = Self::get_twap(env, feed_id, config.twap_window_seconds)?;
+        }
+        Ok(feed)
+    }
+
+    // A failing or silent external oracle is skipped rather than trapping.
+    fn external_price(
+        env: &Env,
+        feed_id: &String,
+        config: &OracleConfig,
+    ) -> Result<PriceFeed, Error> {
+        let source = Self::get_external_source(env, feed_id).ok_or(Error::InvalidConfig)?;
+        let client = Sep40Client::new(env, &source.oracle);
+        let Ok(Ok(Some(data))) = client.try_lastprice(&source.asset) else {
+            return Err(Error::PriceFeedNotFound);
+        };
+        let Ok(Ok(decimals)) = client.try_decimals() else {
+            return Err(Error::PriceFeedNotFound);
+        };
+        if data.price <= 0 || bounds::price(env, data.price, decimals).is_err() {
+            return Err(Error::InvalidPrice);
+        }
+        let feed = PriceFeed {
+            price: data.price,
+            decimals,
+            last_updated: data.timestamp,
+        };
+        if data.timestamp > env.ledger().timestamp() || Self::is_stale(env, &feed, config) {
+            return Err(Error::StalePriceFeed);
+        }
+        Ok(feed)
+    }
+
+    fn cached_price(
+        env: &Env,
+        feed_id: &String,
+        config: &OracleConfig,
+        max_age_seconds: u64,
+    ) -> Result<PriceFeed, Error> {
+        let feed = Self::get_price_feed(env, feed_id).ok_or(Error::PriceFeedNotFound)?;
+        Self::ensure_reliable(config, || Self::get_feed_reliability(env, feed_id))?;
+        if env.ledger().timestamp().saturating_sub(feed.last_updated) > max_age_seconds {
+            return Err(Error::StalePriceFeed);
+        }
+        Ok(feed)
+    }
+
+    pub fn get_feed_sunset(env: &Env, feed_id: &String) -> Option<u64> {
+        env.storage()
+            .instance()
+            .get(&OracleKey::FeedSunset(feed_id.clone()))
+    }
+
+    fn ensure_not_sunset(env: &Env, feed_id: &String) -> Result<(), Error> {
+        match Self::get_feed_sunset(env, feed_id) {
+            Some(sunset) if env.ledger().timestamp() >= sunset => Err(Error::Deprecated),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_feed_heartbeat(env: &Env, feed_id: &String) -> Option<u64> {
+        env.storage()
+            .instance()
+            .get(&OracleKey::FeedHeartbeat(feed_id.clone()))
+    }
+
+    // Sets how often the feed is expected to update; 0 stops monitoring it.
+    pub fn set_feed_heartbeat(
+        env: &Env,
+        feed_id: &String,
+        heartbeat_seconds: u64,
+    ) -> Result<(), Error> {
+        admin::require_admin(env);
+        if Self::get_price_feed(env, feed_id).is_none() {
+            return Err(Error::PriceFeedNotFound);
+        }
+        let instance = env.storage().instance();
+        if heartbeat_seconds == 0 {
+            instance.remove(&OracleKey::FeedHeartbeat(feed_id.clone()));
+            instance.remove(&OracleKey::FeedUnhealthy(feed_id.clone()));
+        } else {
+            instance.set(
+                &OracleKey::FeedHeartbeat(feed_id.clone()),
+                &heartbeat_seconds,
+            );
+        }
+        env.events().publish(
+            (Symbol::new(env, "feed_heartbeat_set"), feed_id.clone()),
+            heartbeat_seconds,
+        );
+        Ok(())
+    }
+
+    fn missed_heartbeat(env: &Env, feed_id: &String, feed: &PriceFeed) -> bool {
+        Self::get_feed_heartbeat(env, feed_id).is_some_and(|heartbeat| {
+            env.ledger().timestamp().saturating_sub(feed.last_updated) > heartbeat
+        })
+    }
+
+    // Payments call this after pricing, so the first one to find a feed past
+    // its heartbeat announces it; pricing itself, which quotes and previews
+    // share, never writes. The flag clears on the feed's next update.
+    pub fn note_missed_heartbeat(env: &Env, feed_id: &String) {
+        if Self::get_feed_heartbeat(env, feed_id).is_none() {
+            return;
+        }
+        let Some(feed) = Self::get_price_feed(env, feed_id) else {
+            return;
+        };
+        if !Self::missed_heartbeat(env, feed_id, &feed) {
+            return;
+        }
+        let key = OracleKey::FeedUnhealthy(feed_id.clone());
+        if env.storage().instance().has(&key) {
+            return;
+        }
+        env.storage().instance().set(&key, &true);
+        env.events().publish(
+            (Symbol::new(env, "feed_unhealthy"), feed_id.clone()),
+            feed.last_updated,
+        );
+    }
+
+    // Monitored feeds that have missed their heartbeat, in registration order.
+    pub fn check_feed_health(env: &Env) -> Vec<String> {
+        let mut unhealthy = Vec::new(env);
+        for feed_id in Self::get_price_feed_ids(env).iter() {
+            let missed = Self::get_price_feed(env, &feed_id)
+                .is_some_and(|feed| Self::missed_heartbeat(env, &feed_id, &feed));
+            if missed {
+                unhealthy.push_back(feed_id);
+            }
+        }
+        unhealthy
+    }
+
+    // Retires a feed: it keeps serving reads and payments until `sunset`, and
+    // refuses both payments and updates from then on. A later call may move
+    // the sunset while it is still ahead.
+    pub fn deprecate_feed(env: &Env, feed_id: &String, sunset: u64) -> Result<(), Error> {
+        admin::require_admin(env);
+        if Self::get_price_feed(env, feed_id).is_none() {
+            return Err(Error::PriceFeedNotFound);
+        }
+        Self::ensure_not_sunset(env, feed_id)?;
+        if sunset < env.ledger().timestamp() {
+            return Err(Error::InvalidInput);
+        }
+        env.storage()
+            .instance()
+            .set(&OracleKey::FeedSunset(feed_id.clone()), &sunset);
+        env.events().publish(
+            (Symbol::new(env, "price_feed_deprecated"), feed_id.clone()),
+            sunset,
+        );
+        Ok(())
+    }
+
+    // Deletes a deprecated feed once its sunset has passed, along with its
+    // configuration. Applied-price snapshots stay for dispute lookups.
+    pub fn remove_feed(env: &Env, feed_id: &String) -> Result<(), Error> {
+        admin::require_admin(env);
+        match Self::get_feed_sunset(env, feed_id) {
+            Some(sunset) if env.ledger().timestamp() >= sunset => {}
+            _ => return Err(Error::InvalidState),
+        }
+        let persistent = env.storage().persistent();
+        for key in [
+            OracleKey::PriceFeed(feed_id.clone()),
+            OracleKey::PriceHistory(feed_id.clone()),
+            OracleKey::FallbackPrice(feed_id.clone()),
+            OracleKey::ReporterKey(feed_id.clone()),
+            OracleKey::FlaggedPrice(feed_id.clone()),
+            OracleKey::FeedReliability(feed_id.clone()),
+            OracleKey::FallbackChain(feed_id.clone()),
+            OracleKey::ExternalSource(feed_id.clone()),
+        ] {
+            persistent.remove(&key);
+        }
+        let mut ids = Self::get_price_feed_ids(env);
+        if let Some(position) = ids.first_index_of(feed_id) {
+            ids.remove(position);
+            storage::write_index(env, &OracleKey::PriceFeedIndex, &ids);
+        }
+        for key in [
+            OracleKey::FeedSunset(feed_id.clone()),
+            OracleKey::FeedHeartbeat(feed_id.clone()),
+            OracleKey::FeedUnhealthy(feed_id.clone()),
+        ] {
+            env.storage().instance().remove(&key);
+        }
+        env.events().publish(
+            (Symbol::new(env, "price_feed_removed"), feed_id.clone()),
+            (),
+        );
+        Ok(())
+    }
+
+    // Writes a feed and registers its id in the index on first sight.
+    pub fn store_feed(env: &Env, feed_id: &String, feed: &PriceFeed) {
+        if Self::get_price_feed(env, feed_id).is_none() {
+            let mut ids = Self::get_price_feed_ids(env);
+            ids.push_back(feed_id.clone());
+            storage::write_index(env, &OracleKey::PriceFeedIndex, &ids);
+        }
+        storage::write_persistent(env, &OracleKey::PriceFeed(feed_id.clone()), feed);
+        let unhealthy = OracleKey::FeedUnhealthy(feed_id.clone());
+        if env.storage().instance().has(&unhealthy) {
+            env.storage().instance().remove(&unhealthy);
+        }
+    }
+
+    fn push_history(env: &Env, feed_id: &String, point: PricePoint, reset: bool) {
+        let key = OracleKey::PriceHistory(feed_id.clone());
+        let mut history = if reset {
+            Vec::new(env)
+        } else {
+            Self::get_price_history(env, feed_id)
+        };
+        history.push_back(point);
+        while history.len() > MAX_PRICE_HISTORY {
+            history.pop_front();
+        }
+        storage::write_persistent(env, &key, &history);
+    }
+
+    pub fn get_data_feed(env: &Env, feed_id: &String) -> Option<DataFeed> {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::DataFeed(feed_id.clone()))
+    }
+
+    pub fn get_data_feed_ids(env: &Env) -> Vec<String> {
+        storage::read_index(env, &OracleKey::DataFeedIndex)
+    }
+
+    // A data feed fit to price a bill with: present and within max age.
+    pub fn get_fresh_data_feed(env: &Env, feed_id: &String) -> Result<DataFeed, Error> {
+        let feed = Self::get_data_feed(env, feed_id).ok_or(Error::PriceFeedNotFound)?;
+        let config = Self::get_config(env);
+        Self::ensure_reliable(&config, || Self::get_data_feed_reliability(env, feed_id))?;
+        if env.ledger().timestamp().saturating_sub(feed.last_updated) > config.max_age_seconds {
+            return Err(Error::StalePriceFeed);
+        }
+        Ok(feed)
+    }
+
+    // Unlike prices, data values may be zero or negative (e.g. temperatures).
+    pub fn update_data_feed(
+        env: &Env,
+        feed_id: &String,
+        value: i128,
+        decimals: u32,
+    ) -> Result<(), Error> {
+        admin::require_admin(env);
+        bounds::decimals(decimals)?;
+        budgets::charge_feed(env, feed_id)?;
+        if Self::get_data_feed(env, feed_id).is_none() {
+            let mut ids = Self::get_data_feed_ids(env);
+            ids.push_back(feed_id.clone());
+            storage::write_index(env, &OracleKey::DataFeedIndex, &ids);
+        }
+        let feed = DataFeed {
+            value,
+            decimals,
+            last_updated: env.ledger().timestamp(),
+        };
+        storage::write_persistent(env, &OracleKey::DataFeed(feed_id.clone()), &feed);
+        Self::tally_reliability(
+            env,
+            &OracleKey::DataFeedReliability(feed_id.clone()),
+            Self::get_data_feed_reliability(env, feed_id),
+            1,
+            0,
+        )?;
+        env.events().publish(
+            (Symbol::new(env, "data_feed_updated"), feed_id.clone()),
+            (value, decimals),
+        );
+        Ok(())
+    }
+
+    pub fn update_price_feed(
+        env: &Env,
+        feed_id: &String,
+        price: i128,
+        decimals: u32,
+    ) -> Result<bool, Error> {
+        admin::require_admin(env);
+        // Once a feed has a reporter, only data that reporter signed is accepted.
+        if Self::get_reporter_key(env, feed_id).is_some() {
+            return Err(Error::SignatureRequired);
+        }
+        if price <= 0 {
+            return Err(Error::InvalidPrice);
+        }
+        bounds::price(env, price, decimals)?;
+        budgets::charge_feed(env, feed_id)?;
+        Self::submit_price(env, feed_id, price, decimals, env.ledger().timestamp())
+    }
+
+    // Refreshes existing feeds at their current decimals, each observed at its
+    // own timestamp. Entries are applied independently: one that fails is
+    // reported and skipped. Returns one outcome per entry, in input order.
+    pub fn update_price_feeds_batch(
+        env: &Env,
+        updates: &Vec<(String, i128, u64)>,
+    ) -> Result<Vec<UpdateOutcome>, Error> {
+        admin::require_admin(env);
+        if updates.is_empty() || updates.len() > MAX_BATCH_UPDATES {
+            return Err(Error::InvalidInput);
+        }
+        let mut outcomes = Vec::new(env);
+        for (feed_id, price, timestamp) in updates.iter() {
+            let result = Self::batch_update(env, &feed_id, price, timestamp);
+            outcomes.push_back(UpdateOutcome::of(result));
+        }
+        Ok(outcomes)
+    }
+
+    // Every check runs before anything is written or charged.
+    fn batch_update(
+        env: &Env,
+        feed_id: &String,
+        price: i128,
+        timestamp: u64,
+    ) -> Result<bool, Error> {
+        let previous = Self::get_price_feed(env, feed_id).ok_or(Error::PriceFeedNotFound)?;
+        if Self::get_reporter_key(env, feed_id).is_some() {
+            return Err(Error::SignatureRequired);
+        }
+        if price <= 0 {
+            return Err(Error::InvalidPrice);
+        }
+        if timestamp > env.ledger().timestamp() || timestamp <= previous.last_updated {
+            return Err(Error::StalePriceFeed);
+        }
+        bounds::price(env, price, previous.decimals)?;
+        Self::ensure_not_sunset(env, feed_id)?;
+        budgets::charge_feed(env, feed_id)?;
+        Self::submit_price(env, feed_id, price, previous.decimals, timestamp)
+    }
+
+    // Anyone may relay a report signed by the feed's reporter.
+    pub fn update_price_feed_signed(
+        env: &Env,
+        feed_id: &String,
+        price: i128,
+        decimals: u32,
+        timestamp: u64,
+        signature: &BytesN<64>,
+    ) -> Result<bool, Error> {
+        let public_key = Self::verify_report(env, feed_id, price, decimals, timestamp, signature)?;
+        budgets::charge_reporter(env, &public_key)?;
+        budgets::charge_feed(env, feed_id)?;
+        Self::submit_price(env, feed_id, price, decimals, timestamp)
+    }
+
+    // Whether `price` moves more than `max_bps` away from `previous`, compared
+    // at the finer of the two scales.
+    fn deviates(
+        previous: &PriceFeed,
+        price: i128,
+        decimals: u32,
+        max_bps: u32,
+    ) -> Result<bool, Error> {
+        let scale = previous.decimals.max(decimals);
+        let old = math::mul(previous.price, math::pow10(scale - previous.decimals)?)?;
+        let new = math::mul(price, math::pow10(scale - decimals)?)?;
+        let moved = math::mul(math::sub(new, old)?.abs(), BPS_DENOMINATOR)?;
+        Ok(moved > math::mul(old, max_bps as i128)?)
+    }
+
+    // Applies the price unless the deviation guard holds it for review.
+    // Returns whether it was applied.
+    pub fn submit_price(
+        env: &Env,
+        feed_id: &String,
+        price: i128,
+        decimals: u32,
+        observed_at: u64,
+    ) -> Result<bool, Error> {
+        if !EMBEDDED {
+            return Err(Error::InvalidConfig);
+        }
+        Self::ensure_not_sunset(env, feed_id)?;
+        let max_bps = Self::get_config(env).max_deviation_bps;
+        if let Some(previous) = Self::get_price_feed(env, feed_id) {
+            if max_bps > 0 && Self::deviates(&previous, price, decimals, max_bps)? {
+                let flagged = PriceFeed {
+                    price,
+                    decimals,
+                    last_updated: observed_at,
+                };
+                storage::write_persistent(env, &OracleKey::FlaggedPrice(feed_id.clone()), &flagged);
+                env.events().publish(
+                    (Symbol::new(env, "price_deviation_flagged"), feed_id.clone()),
+                    (previous.price, price, decimals),
+                );
+                Self::track_reliability(env, feed_id, 0, 1)?;
+                return Ok(false);
+            }
+        }
+        Self::record_price(env, feed_id, price, decimals, observed_at);
+        Self::track_reliability(env, feed_id, 1, 0)?;
+        Ok(true)
+    }
+
+    fn reliability_at(env: &Env, key: &OracleKey) -> Option<FeedReliability> {
+        env.storage().persistent().get(key)
+    }
+
+    fn fresh_reliability() -> FeedReliability {
+        FeedReliability {
+            applied_updates: 0,
+            flagged_updates: 0,
+            score_bps: BPS_DENOMINATOR as u32,
+        }
+    }
+
+    pub fn get_feed_reliability(env: &Env, feed_id: &String) -> FeedReliability {
+        Self::reliability_at(env, &OracleKey::FeedReliability(feed_id.clone()))
+            .unwrap_or_else(Self::fresh_reliability)
+    }
+
+    // A legacy record under the shared key is the data feed's only when no
+    // price feed has the same id.
+    pub fn get_data_feed_reliability(env: &Env, feed_id: &String) -> FeedReliability {
+        Self::reliability_at(env, &OracleKey::DataFeedReliability(feed_id.clone()))
+            .or_else(|| match Self::get_price_feed(env, feed_id) {
+                Some(_) => None,
+                None => Self::reliability_at(env, &OracleKey::FeedReliability(feed_id.clone())),
+            })
+            .unwrap_or_else(Self::fresh_reliability)
+    }
+
+    pub fn reliability_summary(env: &Env, feed_id: &String) -> ReliabilitySummary {
+        let record = Self::get_feed_reliability(env, feed_id);
+        ReliabilitySummary {
+            applied_updates: record.applied_updates,
+            flagged_updates: record.flagged_updates,
+            score_bps: record.score_bps,
+            heartbeat_seconds: Self::get_feed_heartbeat(env, feed_id).unwrap_or(0),
+            missed_heartbeat: Self::get_price_feed(env, feed_id)
+                .is_some_and(|feed| Self::missed_heartbeat(env, feed_id, &feed)),
+        }
+    }
+
+    pub fn stats(env: &Env, feed_id: &String) -> OracleStats {
+        OracleStats {
+            cost: budgets::feed_stats(env, feed_id),
+            reliability: Self::reliability_summary(env, feed_id),
+            schedule: keepers::feed_schedule(env, feed_id),
+        }
+    }
+
+    fn track_reliability(
+        env: &Env,
+        feed_id: &String,
+        applied: u32,
+        flagged: i32,
+    ) -> Result<(), Error> {
+        Self::tally_reliability(
+            env,
+            &OracleKey::FeedReliability(feed_id.clone()),
+            Self::get_feed_reliability(env, feed_id),
+            applied,
+            flagged,
+        )
+    }
+
+    // Adds applied updates and (signed) flagged ones to the feed's record.
+    // Counts are halved once they exceed the window, so they stay small.
+    fn tally_reliability(
+        env: &Env,
+        key: &OracleKey,
+        mut record: FeedReliability,
+        applied: u32,
+        flagged: i32,
+    ) -> Result<(), Error> {
+        let overflow = Error::ArithmeticOverflow;
+        record.applied_updates = record
+            .applied_updates
+            .checked_add(applied)
+            .ok_or(overflow)?;
+        record.flagged_updates = record.flagged_updates.saturating_add_signed(flagged);
+        let mut total = record
+            .applied_updates
+            .checked_add(record.flagged_updates)
+            .ok_or(overflow)?;
+        if total > RELIABILITY_WINDOW {
+            record.applied_updates /= 2;
+            record.flagged_updates /= 2;
+            total = record.applied_updates + record.flagged_updates;
+        }
+        record.score_bps = if total == 0 {
+            BPS_DENOMINATOR as u32
+        } else {
+            let applied = math::mul(record.applied_updates as i128, BPS_DENOMINATOR)?;
+            (applied / total as i128) as u32
+        };
+        storage::write_persistent(env, key, &record);
+        Ok(())
+    }
+
+    // The feed's record is only read when a minimum score is configured.
+    fn ensure_reliable(
+        config: &OracleConfig,
+        record: impl FnOnce() -> FeedReliability,
+    ) -> Result<(), Error> {
+        if config.min_reliability_bps > 0 && record().score_bps < config.min_reliability_bps {
+            return Err(Error::UnreliableFeed);
+        }
+        Ok(())
+    }
+
+    pub fn get_flagged_price(env: &Env, feed_id: &String) -> Option<PriceFeed> {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::FlaggedPrice(feed_id.clone()))
+    }
+
+    // Admin override: applies a held-back price once it has been checked. Fails
+    // if a newer price has been applied since.
+    pub fn accept_flagged_price(env: &Env, feed_id: &String) -> Result<(), Error> {
+        admin::require_admin(env);
+        let flagged = Self::get_flagged_price(env, feed_id).ok_or(Error::InvalidState)?;
+        env.storage()
+            .persistent()
+            .remove(&OracleKey::FlaggedPrice(feed_id.clone()));
+        let current = Self::get_price_feed(env, feed_id);
+        if current.is_some_and(|feed| feed.last_updated >= flagged.last_updated) {
+            return Err(Error::InvalidState);
+        }
+        Self::record_price(
+            env,
+            feed_id,
+            flagged.price,
+            flagged.decimals,
+            flagged.last_updated,
+        );
+        // A confirmed move was real, so it no longer counts against the feed.
+        Self::track_reliability(env, feed_id, 1, -1)
+    }
+
+    pub fn reject_flagged_price(env: &Env, feed_id: &String) -> Result<(), Error> {
+        admin::require_admin(env);
+        let key = OracleKey::FlaggedPrice(feed_id.clone());
+        if !env.storage().persistent().has(&key) {
+            return Err(Error::InvalidState);
+        }
+        env.storage().persistent().remove(&key);
+        env.events().publish(
+            (Symbol::new(env, "flagged_price_rejected"), feed_id.clone()),
+            (),
+        );
+        Ok(())
+    }
+
+    // Stores a validated price observed at `observed_at` and extends its history.
+    pub fn record_price(env: &Env, feed_id: &String, price: i128, decimals: u32, observed_at: u64) {
+        let feed = PriceFeed {
+            price,
+            decimals,
+            last_updated: observed_at,
+        };
+        let previous = Self::get_price_feed(env, feed_id);
+        // Prices in different decimals cannot be averaged together.
+        let reset_history = previous.is_some_and(|previous| previous.decimals != decimals);
+        Self::store_feed(env, feed_id, &feed);
+
+        Self::push_history(
+            env,
+            feed_id,
+            PricePoint {
+                price,
+                timestamp: feed.last_updated,
+            },
+            reset_history,
+        );
+
+        Self::push_snapshot(env, feed_id, &feed);
+
+        env.events().publish(
+            (Symbol::new(env, "price_updated"), feed_id.clone()),
+            (price, decimals),
+        );
+    }
+
+    fn snapshot_count(env: &Env, feed_id: &String) -> u32 {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::SnapshotCount(feed_id.clone()))
+            .unwrap_or(0)
+    }
+
+    fn push_snapshot(env: &Env, feed_id: &String, feed: &PriceFeed) {
+        let count = Self::snapshot_count(env, feed_id);
+        let slot = count % MAX_PRICE_SNAPSHOTS;
+        storage::write_persistent(env, &OracleKey::PriceSnapshot(feed_id.clone(), slot), feed);
+        storage::write_persistent(
+            env,
+            &OracleKey::SnapshotCount(feed_id.clone()),
+            &(count + 1),
+        );
+    }
+
+    // Snapshot by sequence number; callers stay within the retained range.
+    fn snapshot(env: &Env, feed_id: &String, sequence: u32) -> PriceFeed {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::PriceSnapshot(
+                feed_id.clone(),
+                sequence % MAX_PRICE_SNAPSHOTS,
+            ))
+            .unwrap_or_else(|| panic_with_error!(env, Error::InvalidState))
+    }
+
+    // Snapshot timestamps never decrease, so retained snapshots split into a run
+    // where `before` holds followed by one where it does not. Returns the
+    // sequence the second run starts at, and the retained range's bounds.
+    fn search_snapshots(
+        env: &Env,
+        feed_id: &String,
+        before: impl Fn(u64) -> bool,
+    ) -> (u32, u32, u32) {
+        let count = Self::snapshot_count(env, feed_id);
+        let oldest = count.saturating_sub(MAX_PRICE_SNAPSHOTS);
+        let (mut low, mut high) = (oldest, count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if before(Self::snapshot(env, feed_id, mid).last_updated) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        (low, oldest, count)
+    }
+
+    // The price that was in effect at `timestamp`: the latest applied before or
+    // at it. Fails when that lies before the retained snapshots.
+    pub fn get_price_at(env: &Env, feed_id: &String, timestamp: u64) -> Result<PriceFeed, Error> {
+        let (after, oldest, _) = Self::search_snapshots(env, feed_id, |at| at <= timestamp);
+        if after == oldest {
+            return Err(Error::PriceFeedNotFound);
+        }
+        Ok(Self::snapshot(env, feed_id, after - 1))
+    }
+
+    // Prices applied between `from` and `to` inclusive, oldest first, up to
+    // MAX_RANGE_POINTS of them.
+    pub fn get_price_range(
+        env: &Env,
+        feed_id: &String,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<PriceFeed>, Error> {
+        if from > to {
+            return Err(Error::InvalidInput);
+        }
+        let (mut sequence, _, count) = Self::search_snapshots(env, feed_id, |at| at < from);
+        let mut points = Vec::new(env);
+        while sequence < count && points.len() < MAX_RANGE_POINTS {
+            let point = Self::snapshot(env, feed_id, sequence);
+            if point.last_updated > to {
+                break;
+            }
+            points.push_back(point);
+            sequence += 1;
+        }
+        Ok(points)
+    }
+
+    pub fn get_reporter_key(env: &Env, feed_id: &String) -> Option<BytesN<32>> {
+        env.storage()
+            .persistent()
+            .get(&OracleKey::ReporterKey(feed_id.clone()))
+    }
+
+    pub fn set_reporter_key(env: &Env, feed_id: &String, public_key: &BytesN<32>) {
+        admin::require_admin(env);
+        storage::write_persistent(env, &OracleKey::ReporterKey(feed_id.clone()), public_key);
+        env.events().publish(
+            (Symbol::new(env, "reporter_key_set"), feed_id.clone()),
+            public_key.clone(),
+        );
+    }
+
+    // The bytes a reporter signs: the XDR of (contract, feed_id, price,
+    // decimals, timestamp). Binding the contract stops reports being replayed
+    // against another deployment.
+    pub fn report_payload(
+        env: &Env,
+        contract: &Address,
+        feed_id: &String,
+        price: i128,
+        decimals: u32,
+        timestamp: u64,
+    ) -> Bytes {
+        (
+            contract.clone(),
+            feed_id.clone(),
+            price,
+            decimals,
+            timestamp,
+        )
+            .to_xdr(env)
+    }
+
+    // Checks a relayed report: signed by the feed's reporter, observed no later
+    // than now, newer than the stored price and not already stale. An invalid
+    // signature traps. Returns the reporter key that signed it.
+    pub fn verify_report(
+        env: &Env,
+        feed_id: &String,
+        price: i128,
+        decimals: u32,
+        timestamp: u64,
+        signature: &BytesN<64>,
+    ) -> Result<BytesN<32>, Error> {
+        let public_key = Self::get_reporter_key(env, feed_id).ok_or(Error::InvalidConfig)?;
+        if price <= 0 {
+            return Err(Error::InvalidPrice);
+        }
+        bounds::price(env, price, decimals)?;
+        let now = env.ledger().timestamp();
+        let previous = Self::get_price_feed(env, feed_id);
+        if timestamp > now || previous.is_some_and(|feed| timestamp <= feed.last_updated) {
+            return Err(Error::InvalidInput);
+        }
+        if now - timestamp > Self::get_config(env).max_age_seconds {
+            return Err(Error::StalePriceFeed);
+        }
+        let payload = Self::report_payload(
+            env,
+            &env.current_contract_address(),
+            feed_id,
+            price,
+            decimals,
+            timestamp,
+        );
+        env.crypto()
+            .ed25519_verify(&public_key, &payload, signature);
+        Ok(public_key)
+    }
+}
@@ -0,0 +1,149 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::portability;
+use crate::storage;
+
+// A change of ownership awaiting the new owner's acceptance.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MeterTransfer {
+    pub from: Address,
+    pub to: Address,
+    // Outstanding balance when the transfer was initiated.
+    pub debt_at_initiation: i128,
+    pub initiated_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OwnerKey {
+    MeterOwner(String),
+    PendingTransfer(String),
+}
+
+pub fn owner(env: &Env, meter_id: &String) -> Option<Address> {
+    env.storage()
+        .persistent()
+        .get(&OwnerKey::MeterOwner(meter_id.clone()))
+}
+
+pub fn pending(env: &Env, meter_id: &String) -> Option<MeterTransfer> {
+    env.storage()
+        .persistent()
+        .get(&OwnerKey::PendingTransfer(meter_id.clone()))
+}
+
+pub fn write_owner(env: &Env, meter_id: &String, owner: &Address) {
+    storage::write_persistent(env, &OwnerKey::MeterOwner(meter_id.clone()), owner);
+}
+
+// Forgets the meter's owner and any transfer in flight, e.g. once the meter
+// has moved to another instance.
+pub fn clear_owner(env: &Env, meter_id: &String) {
+    env.storage()
+        .persistent()
+        .remove(&OwnerKey::MeterOwner(meter_id.clone()));
+    env.storage()
+        .persistent()
+        .remove(&OwnerKey::PendingTransfer(meter_id.clone()));
+}
+
+// The provider records who owns a meter, e.g. when it is connected.
+pub fn set_owner(env: &Env, meter_id: &String, owner: &Address) {
+    admin::require_admin(env);
+    write_owner(env, meter_id, owner);
+    env.storage()
+        .persistent()
+        .remove(&OwnerKey::PendingTransfer(meter_id.clone()));
+    env.events().publish(
+        (Symbol::new(env, "meter_owner_set"), meter_id.clone()),
+        owner.clone(),
+    );
+}
+
+// First half of the handshake: the current owner names the new owner.
+pub fn initiate(
+    env: &Env,
+    current_owner: &Address,
+    meter_id: &String,
+    new_owner: &Address,
+) -> Result<(), Error> {
+    current_owner.require_auth();
+    portability::ensure_active(env, meter_id)?;
+    if owner(env, meter_id).as_ref() != Some(current_owner) || current_owner == new_owner {
+        return Err(Error::InvalidInput);
+    }
+
+    let transfer = MeterTransfer {
+        from: current_owner.clone(),
+        to: new_owner.clone(),
+        debt_at_initiation: billing::balance(env, meter_id).max(0),
+        initiated_at: env.ledger().timestamp(),
+    };
+    storage::write_persistent(env, &OwnerKey::PendingTransfer(meter_id.clone()), &transfer);
+    env.events().publish(
+        (
+            Symbol::new(env, "meter_transfer_initiated"),
+            meter_id.clone(),
+        ),
+        (
+            current_owner.clone(),
+            new_owner.clone(),
+            transfer.debt_at_initiation,
+        ),
+    );
+    Ok(())
+}
+
+// Second half: the new owner takes the meter. Outstanding debt blocks the
+// transfer unless the new owner explicitly accepts it.
+pub fn accept(
+    env: &Env,
+    new_owner: &Address,
+    meter_id: &String,
+    accept_debt: bool,
+) -> Result<(), Error> {
+    new_owner.require_auth();
+    let transfer = pending(env, meter_id).ok_or(Error::InvalidState)?;
+    if transfer.to != *new_owner {
+        return Err(Error::InvalidInput);
+    }
+    portability::ensure_active(env, meter_id)?;
+    let debt = billing::balance(env, meter_id).max(0);
+    if debt > 0 && !accept_debt {
+        return Err(Error::InvalidState);
+    }
+
+    write_owner(env, meter_id, new_owner);
+    env.storage()
+        .persistent()
+        .remove(&OwnerKey::PendingTransfer(meter_id.clone()));
+    env.events().publish(
+        (Symbol::new(env, "meter_transferred"), meter_id.clone()),
+        (transfer.from, new_owner.clone(), debt),
+    );
+    Ok(())
+}
+
+// Either party withdraws a pending transfer.
+pub fn cancel(env: &Env, caller: &Address, meter_id: &String) -> Result<(), Error> {
+    caller.require_auth();
+    let transfer = pending(env, meter_id).ok_or(Error::InvalidState)?;
+    if *caller != transfer.from && *caller != transfer.to {
+        return Err(Error::InvalidInput);
+    }
+    env.storage()
+        .persistent()
+        .remove(&OwnerKey::PendingTransfer(meter_id.clone()));
+    env.events().publish(
+        (
+            Symbol::new(env, "meter_transfer_cancelled"),
+            meter_id.clone(),
+        ),
+        caller.clone(),
+    );
+    Ok(())
+}
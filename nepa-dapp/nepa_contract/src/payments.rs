@@ -0,0 +1,282 @@
+use soroban_sdk::{token, Address, Env, String, Symbol, Vec};
+
+use crate::accounting::{self, PaymentRecord};
+use crate::alerts;
+use crate::anomalies::{self, AnomalyKind};
+use crate::billing;
+use crate::credits;
+use crate::delegation::{self, DelegatedPayment};
+use crate::dunning;
+use crate::errors::Error;
+use crate::fees;
+use crate::guard;
+use crate::hooks;
+use crate::limits;
+use crate::loyalty;
+use crate::maintenance;
+use crate::math;
+use crate::oracle::{OracleManager, PriceFeed, PriceSource};
+use crate::peg;
+use crate::periods;
+use crate::portability;
+use crate::receipts;
+use crate::rollups;
+use crate::sponsorship;
+use crate::storage;
+use crate::tariff::{self, UtilityUsage};
+use crate::tokens;
+use crate::velocity;
+use crate::vouchers;
+
+// Most meters a single batch may pay, to keep the invocation within budget.
+pub const MAX_BATCH_SIZE: u32 = 25;
+
+// Books a payment against the meter and announces it. Callers settle before
+// moving tokens so no state is left to update after the external call.
+pub fn settle(env: &Env, meter_id: &String, record: &PaymentRecord) -> Result<u32, Error> {
+    settle_earning(env, meter_id, record, &record.payer)
+}
+
+// As `settle`, with the payment's loyalty points going to `earner`.
+fn settle_earning(
+    env: &Env,
+    meter_id: &String,
+    record: &PaymentRecord,
+    earner: &Address,
+) -> Result<u32, Error> {
+    let mut record = record.clone();
+    let index = accounting::record_payment(env, meter_id, &mut record)?;
+    let record = &record;
+    fees::on_payment(env, record)?;
+    let owed = billing::balance(env, meter_id);
+    billing::adjust_balance(env, meter_id, -record.normalized_amount);
+    sponsorship::on_payment(env, meter_id, record)?;
+    credits::on_payment(env, meter_id, owed, record.normalized_amount);
+    dunning::on_payment(env, meter_id);
+    periods::on_payment(env, meter_id);
+    velocity::record_payment(env, &record.payer, meter_id);
+    loyalty::on_payment(env, earner, record)?;
+    alerts::on_payment(env, meter_id, record.timestamp, record.normalized_amount)?;
+    rollups::on_payment(env, meter_id, record)?;
+    anomalies::observe(
+        env,
+        meter_id,
+        AnomalyKind::Payment,
+        index,
+        record.normalized_amount,
+    );
+    receipts::issue(env, meter_id, index, record);
+    vouchers::issue_for_payment(env, meter_id, record);
+    env.events().publish(
+        (Symbol::new(env, "bill_paid"), meter_id.clone()),
+        (
+            record.payer.clone(),
+            record.token.clone(),
+            record.amount,
+            record.normalized_amount,
+            index,
+            record.sequence,
+        ),
+    );
+    // A failed token transfer later in the invocation also undoes the hook.
+    hooks::on_payment(env, meter_id, index, record);
+    Ok(index)
+}
+
+// Returns the index of the new entry in the meter's payment history.
+pub fn pay(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    amount: i128,
+) -> Result<u32, Error> {
+    pay_at(env, from, token_address, meter_id, amount, None)
+}
+
+// As `pay`, valued at `locked_price` instead of the current oracle price when
+// one is given.
+pub fn pay_at(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    amount: i128,
+    locked_price: Option<(&PriceFeed, PriceSource)>,
+) -> Result<u32, Error> {
+    maintenance::ensure_writable(env)?;
+    guard::non_reentrant(env, || {
+        pay_at_unguarded(
+            env,
+            from,
+            token_address,
+            meter_id,
+            amount,
+            locked_price,
+            from,
+        )
+    })
+}
+
+// Pays `meter_id`'s bill for its owner, recording both parties. Refused when
+// the owner has opted out of third-party payments. Returns the payment index.
+pub fn pay_on_behalf(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    amount: i128,
+) -> Result<u32, Error> {
+    maintenance::ensure_writable(env)?;
+    let beneficiary = delegation::beneficiary(env, from, meter_id)?;
+    let earner = delegation::earner(env, from, &beneficiary);
+    guard::non_reentrant(env, || {
+        let index = pay_at_unguarded(env, from, token_address, meter_id, amount, None, &earner)?;
+        delegation::record(
+            env,
+            meter_id,
+            &DelegatedPayment {
+                payer: from.clone(),
+                beneficiary,
+                payment_index: index,
+            },
+        );
+        Ok(index)
+    })
+}
+
+fn pay_at_unguarded(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    amount: i128,
+    locked_price: Option<(&PriceFeed, PriceSource)>,
+    earner: &Address,
+) -> Result<u32, Error> {
+    // 1. Verify the user authorized this payment
+    from.require_auth();
+    portability::ensure_active(env, meter_id)?;
+
+    // 2. Only tokens on the treasury allowlist are accepted
+    let token_config = tokens::require_accepted(env, token_address, amount)?;
+
+    // 3. Value the payment in NGN at the oracle price (spot or TWAP), or at
+    //    the price locked by a quote
+    let record = match locked_price {
+        Some((feed, source)) => accounting::build_record(
+            env,
+            from,
+            token_address,
+            &token_config,
+            feed,
+            source,
+            amount,
+        )?,
+        None => accounting::quote(env, from, token_address, &token_config, amount)?,
+    };
+
+    // 4. Payers flagged for unusual velocity may need a co-signature, and
+    //    opted-in spending limits apply
+    velocity::require_attestation(env, from);
+    limits::spend(env, from, record.normalized_amount)?;
+
+    // 5. Update the meter totals, its payment history and payer velocity,
+    //    and issue the payer a receipt, before any external call
+    let index = settle_earning(env, meter_id, &record, earner)?;
+
+    // 6. Move the tokens from the User to the Contract (XLM or USDC)
+    let token_client = token::Client::new(env, token_address);
+    token_client.transfer(from, &env.current_contract_address(), &amount);
+
+    storage::extend_instance(env);
+    Ok(index)
+}
+
+// Pays several meters with one authorization and a single token transfer.
+// Returns each meter's new payment index, in input order.
+pub fn pay_batch(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    bills: &Vec<(String, i128)>,
+) -> Result<Vec<u32>, Error> {
+    maintenance::ensure_writable(env)?;
+    guard::non_reentrant(env, || pay_batch_unguarded(env, from, token_address, bills))
+}
+
+fn pay_batch_unguarded(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    bills: &Vec<(String, i128)>,
+) -> Result<Vec<u32>, Error> {
+    if bills.is_empty() || bills.len() > MAX_BATCH_SIZE {
+        return Err(Error::InvalidInput);
+    }
+
+    from.require_auth();
+
+    // Price once and value every line item at the same rate.
+    let token_config = tokens::read_config(env, token_address).ok_or(Error::UnsupportedToken)?;
+    let (feed, source) = peg::payment_price(env, token_address, &token_config)?;
+    OracleManager::note_missed_heartbeat(env, &token_config.oracle_pair);
+    let mut records = Vec::new(env);
+    let mut total: i128 = 0;
+    let mut value: i128 = 0;
+    for (meter_id, amount) in bills.iter() {
+        portability::ensure_active(env, &meter_id)?;
+        tokens::require_accepted(env, token_address, amount)?;
+        let record = accounting::build_record(
+            env,
+            from,
+            token_address,
+            &token_config,
+            &feed,
+            source,
+            amount,
+        )?;
+        total = math::add(total, amount)?;
+        value = math::add(value, record.normalized_amount)?;
+        records.push_back(record);
+    }
+
+    velocity::require_attestation(env, from);
+    limits::spend(env, from, value)?;
+
+    let mut indices = Vec::new(env);
+    for (i, (meter_id, _)) in bills.iter().enumerate() {
+        let record = records.get_unchecked(i as u32);
+        indices.push_back(settle(env, &meter_id, &record)?);
+    }
+
+    token::Client::new(env, token_address).transfer(from, &env.current_contract_address(), &total);
+
+    storage::extend_instance(env);
+    Ok(indices)
+}
+
+// Prices the consumption with the region's tariff and pays the bill in the
+// given token at the current oracle price. Returns the payment index.
+pub fn pay_utility(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    rate_id: &String,
+    usage: &UtilityUsage,
+) -> Result<u32, Error> {
+    let inputs = tariff::usage_inputs(env, rate_id, usage)?;
+    let charge = tariff::calculate(env, rate_id, &inputs)?;
+    if charge <= 0 {
+        return Err(Error::AmountTooSmall);
+    }
+
+    let config = tokens::read_config(env, token_address).ok_or(Error::UnsupportedToken)?;
+    let feed = OracleManager::get_payment_price(env, &config.oracle_pair)?;
+    let amount = fees::gross_up(
+        env,
+        accounting::denormalize(charge, config.decimals, &feed)?,
+    )?;
+    pay(env, from, token_address, meter_id, amount)
+}
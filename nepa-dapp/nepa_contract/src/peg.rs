@@ -0,0 +1,117 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::oracle::{OracleManager, PriceFeed, PriceSource};
+use crate::tokens::{self, TokenConfig};
+
+// Quote currency of every peg feed: a token with symbol "USDC" is checked
+// against the "USDC/USD" feed.
+const PEG_QUOTE: &[u8] = b"/USD";
+const MAX_SYMBOL_LEN: usize = 32;
+
+// Band a stablecoin's USD price must stay within for it to be converted at
+// its pair's price. Bounds carry 7 decimals, so 1.00 is 10_000_000.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PegGuard {
+    pub peg_feed: String,
+    pub min_price: i128,
+    pub max_price: i128,
+}
+
+// Guards live in instance storage next to the other per-token settings read
+// on every payment, so checking one costs no extra ledger entry.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PegKey {
+    PegGuard(Address),
+}
+
+pub fn read_guard(env: &Env, token: &Address) -> Option<PegGuard> {
+    env.storage()
+        .instance()
+        .get(&PegKey::PegGuard(token.clone()))
+}
+
+fn peg_feed_for(env: &Env, token: &Address) -> Result<String, Error> {
+    let symbol = tokens::metadata(env, token)
+        .ok_or(Error::InvalidConfig)?
+        .symbol;
+    let len = symbol.len() as usize;
+    if len == 0 || len > MAX_SYMBOL_LEN {
+        return Err(Error::InvalidConfig);
+    }
+    let mut buf = [0u8; MAX_SYMBOL_LEN + PEG_QUOTE.len()];
+    symbol.copy_into_slice(&mut buf[..len]);
+    buf[len..len + PEG_QUOTE.len()].copy_from_slice(PEG_QUOTE);
+    Ok(String::from_bytes(env, &buf[..len + PEG_QUOTE.len()]))
+}
+
+pub fn set_guard(
+    env: &Env,
+    token: &Address,
+    min_price: i128,
+    max_price: i128,
+) -> Result<(), Error> {
+    admin::require_admin(env);
+    if tokens::read_config(env, token).is_none() {
+        return Err(Error::UnsupportedToken);
+    }
+    if min_price <= 0 || max_price < min_price {
+        return Err(Error::InvalidConfig);
+    }
+    let guard = PegGuard {
+        peg_feed: peg_feed_for(env, token)?,
+        min_price,
+        max_price,
+    };
+    env.storage()
+        .instance()
+        .set(&PegKey::PegGuard(token.clone()), &guard);
+    env.events().publish(
+        (Symbol::new(env, "peg_guard_set"), token.clone()),
+        (guard.peg_feed, min_price, max_price),
+    );
+    Ok(())
+}
+
+pub fn remove_guard(env: &Env, token: &Address) {
+    admin::require_admin(env);
+    env.storage()
+        .instance()
+        .remove(&PegKey::PegGuard(token.clone()));
+}
+
+fn within_peg(env: &Env, guard: &PegGuard) -> Result<bool, Error> {
+    let feed = OracleManager::get_payment_price(env, &guard.peg_feed)?;
+    let price = tokens::to_units(feed.price, feed.decimals)?;
+    Ok((guard.min_price..=guard.max_price).contains(&price))
+}
+
+// The price a payment in `token` is converted at. A guarded stablecoin off its
+// peg is valued at its pair's fallback price when fallbacks are enabled, and
+// refused otherwise.
+pub fn payment_price(
+    env: &Env,
+    token: &Address,
+    config: &TokenConfig,
+) -> Result<(PriceFeed, PriceSource), Error> {
+    let resolved = OracleManager::resolve_payment_price(env, &config.oracle_pair)?;
+    let Some(guard) = read_guard(env, token) else {
+        return Ok(resolved);
+    };
+    if within_peg(env, &guard)? {
+        return Ok(resolved);
+    }
+    env.events().publish(
+        (Symbol::new(env, "peg_breached"), token.clone()),
+        guard.peg_feed,
+    );
+    match OracleManager::get_fallback_price(env, &config.oracle_pair) {
+        Some(feed) if OracleManager::get_config(env).use_fallback => {
+            Ok((feed, PriceSource::StaticRate))
+        }
+        _ => Err(Error::PegDeviation),
+    }
+}
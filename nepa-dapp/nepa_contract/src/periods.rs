@@ -0,0 +1,156 @@
+use soroban_sdk::{contracttype, Env, Map, String, Symbol, Vec};
+
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::math;
+use crate::settlement;
+use crate::storage;
+use crate::tariff::{self, UtilityRate};
+
+// A region's closed monthly billing cycle.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BillingCycle {
+    pub region: String,
+    pub period: u32,
+    // The region's rates as they stood at close, by rate id.
+    pub rates: Map<String, UtilityRate>,
+    pub invoices: u32,
+    pub billed: i128,
+    // Unpaid balances rolled into arrears at close.
+    pub arrears: i128,
+    pub closed_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CycleKey {
+    // Meters billed in a region's open period.
+    CycleMeters(String, u32),
+    ClosedCycle(String, u32),
+    // NGN carried out of closed cycles and not yet paid.
+    MeterArrears(String),
+}
+
+// Region of a registered utility rate; bills under unregistered rates belong
+// to no cycle.
+fn region_of(env: &Env, rate_id: &String) -> Option<String> {
+    tariff::key_for_rate(env, rate_id).map(|key| key.region)
+}
+
+pub fn cycle(env: &Env, region: &String, period: u32) -> Option<BillingCycle> {
+    env.storage()
+        .persistent()
+        .get(&CycleKey::ClosedCycle(region.clone(), period))
+}
+
+fn cycle_meters(env: &Env, region: &String, period: u32) -> Vec<String> {
+    env.storage()
+        .persistent()
+        .get(&CycleKey::CycleMeters(region.clone(), period))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn arrears(env: &Env, meter_id: &String) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&CycleKey::MeterArrears(meter_id.clone()))
+        .unwrap_or(0)
+}
+
+fn write_arrears(env: &Env, meter_id: &String, amount: i128) {
+    let key = CycleKey::MeterArrears(meter_id.clone());
+    if amount > 0 {
+        storage::write_persistent(env, &key, &amount);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+}
+
+// Bills for a closed cycle can no longer be issued or trued up.
+pub fn ensure_open(env: &Env, rate_id: &String, period: u32) -> Result<(), Error> {
+    match region_of(env, rate_id) {
+        Some(region) if cycle(env, &region, period).is_some() => Err(Error::InvalidState),
+        _ => Ok(()),
+    }
+}
+
+pub fn on_bill(env: &Env, meter_id: &String, rate_id: &String, period: u32) {
+    let Some(region) = region_of(env, rate_id) else {
+        return;
+    };
+    let mut meters = cycle_meters(env, &region, period);
+    if !meters.contains(meter_id) {
+        meters.push_back(meter_id.clone());
+        storage::write_persistent(env, &CycleKey::CycleMeters(region, period), &meters);
+    }
+}
+
+// Payments clear arrears first: they never exceed what is still owed.
+pub fn on_payment(env: &Env, meter_id: &String) {
+    let carried = arrears(env, meter_id);
+    if carried > 0 {
+        let owed = billing::balance(env, meter_id).max(0);
+        if owed < carried {
+            write_arrears(env, meter_id, owed);
+        }
+    }
+}
+
+// Closes a region's month once it has ended: its rates are frozen into the
+// cycle, its invoices finalized and each meter's unpaid balance carried into
+// arrears. Returns the closed cycle.
+pub fn close(env: &Env, region: &String, period: u32) -> Result<BillingCycle, Error> {
+    admin::require_admin(env);
+    settlement::validate_period(period)?;
+    if tariff::region_rate_keys(env, region).is_empty() {
+        return Err(Error::InvalidInput);
+    }
+    if cycle(env, region, period).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+    if period >= billing::period_at(env.ledger().timestamp()) {
+        return Err(Error::InvalidState);
+    }
+
+    let mut rates = Map::new(env);
+    for (key, rate) in tariff::rates_for_region(env, region)?.iter() {
+        rates.set(tariff::rate_id_for(env, &key)?, rate);
+    }
+
+    let mut billed: i128 = 0;
+    let mut total_arrears: i128 = 0;
+    let meters = cycle_meters(env, region, period);
+    for meter_id in meters.iter() {
+        if let Some(amount) = billing::finalize(env, &meter_id, period) {
+            billed = math::add(billed, amount)?;
+        }
+        let unpaid = billing::balance(env, &meter_id).max(0);
+        write_arrears(env, &meter_id, unpaid);
+        total_arrears = math::add(total_arrears, unpaid)?;
+    }
+
+    let closed = BillingCycle {
+        region: region.clone(),
+        period,
+        rates,
+        invoices: meters.len(),
+        billed,
+        arrears: total_arrears,
+        closed_at: env.ledger().timestamp(),
+    };
+    storage::write_persistent(env, &CycleKey::ClosedCycle(region.clone(), period), &closed);
+    env.storage()
+        .persistent()
+        .remove(&CycleKey::CycleMeters(region.clone(), period));
+    env.events().publish(
+        (
+            Symbol::new(env, "billing_period_closed"),
+            region.clone(),
+            period,
+        ),
+        (closed.invoices, billed, total_arrears),
+    );
+    Ok(closed)
+}
@@ -0,0 +1,241 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::math;
+use crate::oracle::OracleManager;
+use crate::payments;
+use crate::storage;
+use crate::tokens;
+
+// Longest plan the provider will agree to.
+const MAX_INSTALLMENTS: u32 = 60;
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PlanStatus {
+    // Requested by the customer, awaiting the provider's approval.
+    Proposed,
+    Active,
+    Completed,
+    Cancelled,
+}
+
+// An agreement to clear `total_debt` NGN units of a meter's arrears in equal
+// installments, the k-th falling due `k * interval` seconds after activation.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentPlan {
+    pub meter_id: String,
+    // The customer who asked for the plan, or the admin who set it up.
+    pub requested_by: Address,
+    pub total_debt: i128,
+    pub num_installments: u32,
+    pub interval: u64,
+    // Rounded up; the last installment only covers what is left.
+    pub installment_amount: i128,
+    pub paid: i128,
+    pub installments_paid: u32,
+    pub activated_at: u64,
+    pub status: PlanStatus,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlanReport {
+    pub status: PlanStatus,
+    // Installments whose due date has passed.
+    pub installments_due: u32,
+    pub installments_paid: u32,
+    pub missed_installments: u32,
+    pub remaining_principal: i128,
+    // 0 once the plan is no longer active.
+    pub next_due_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PlanKey {
+    NextPlanId,
+    PaymentPlan(u64),
+    // Meter id -> its proposed or active plan.
+    MeterPlan(String),
+}
+
+pub fn read(env: &Env, plan_id: u64) -> Option<PaymentPlan> {
+    env.storage()
+        .persistent()
+        .get(&PlanKey::PaymentPlan(plan_id))
+}
+
+fn write(env: &Env, plan_id: u64, plan: &PaymentPlan) {
+    storage::write_persistent(env, &PlanKey::PaymentPlan(plan_id), plan);
+}
+
+pub fn meter_plan(env: &Env, meter_id: &String) -> Option<u64> {
+    env.storage()
+        .persistent()
+        .get(&PlanKey::MeterPlan(meter_id.clone()))
+}
+
+fn close(env: &Env, plan_id: u64, plan: &mut PaymentPlan, status: PlanStatus) {
+    plan.status = status;
+    write(env, plan_id, plan);
+    env.storage()
+        .persistent()
+        .remove(&PlanKey::MeterPlan(plan.meter_id.clone()));
+    env.events().publish(
+        (Symbol::new(env, "payment_plan_closed"), plan_id),
+        (plan.meter_id.clone(), status),
+    );
+}
+
+// The admin sets a plan up directly; a customer asks for one and it starts
+// once the admin approves. The debt may not exceed what the meter owes.
+pub fn create(
+    env: &Env,
+    caller: &Address,
+    meter_id: &String,
+    total_debt: i128,
+    num_installments: u32,
+    interval: u64,
+) -> Result<u64, Error> {
+    let by_admin = *caller == admin::read_admin(env);
+    caller.require_auth();
+    if total_debt <= 0
+        || total_debt > billing::balance(env, meter_id)
+        || num_installments == 0
+        || num_installments > MAX_INSTALLMENTS
+        || interval == 0
+    {
+        return Err(Error::InvalidInput);
+    }
+    if meter_plan(env, meter_id).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+
+    let plan_id: u64 = env
+        .storage()
+        .instance()
+        .get(&PlanKey::NextPlanId)
+        .unwrap_or(1);
+    env.storage()
+        .instance()
+        .set(&PlanKey::NextPlanId, &(plan_id + 1));
+
+    let n = num_installments as i128;
+    let plan = PaymentPlan {
+        meter_id: meter_id.clone(),
+        requested_by: caller.clone(),
+        total_debt,
+        num_installments,
+        interval,
+        installment_amount: (total_debt + n - 1) / n,
+        paid: 0,
+        installments_paid: 0,
+        activated_at: if by_admin {
+            env.ledger().timestamp()
+        } else {
+            0
+        },
+        status: if by_admin {
+            PlanStatus::Active
+        } else {
+            PlanStatus::Proposed
+        },
+    };
+    write(env, plan_id, &plan);
+    storage::write_persistent(env, &PlanKey::MeterPlan(meter_id.clone()), &plan_id);
+    env.events().publish(
+        (Symbol::new(env, "payment_plan_created"), plan_id),
+        (meter_id.clone(), total_debt, num_installments, plan.status),
+    );
+    Ok(plan_id)
+}
+
+pub fn approve(env: &Env, plan_id: u64) -> Result<(), Error> {
+    admin::require_admin(env);
+    let mut plan = read(env, plan_id).ok_or(Error::InvalidInput)?;
+    if plan.status != PlanStatus::Proposed {
+        return Err(Error::InvalidState);
+    }
+    plan.status = PlanStatus::Active;
+    plan.activated_at = env.ledger().timestamp();
+    write(env, plan_id, &plan);
+    env.events().publish(
+        (Symbol::new(env, "payment_plan_approved"), plan_id),
+        plan.meter_id,
+    );
+    Ok(())
+}
+
+pub fn cancel(env: &Env, plan_id: u64) -> Result<(), Error> {
+    admin::require_admin(env);
+    let mut plan = read(env, plan_id).ok_or(Error::InvalidInput)?;
+    if !matches!(plan.status, PlanStatus::Proposed | PlanStatus::Active) {
+        return Err(Error::InvalidState);
+    }
+    close(env, plan_id, &mut plan, PlanStatus::Cancelled);
+    Ok(())
+}
+
+// Pays the next installment in `token_address` at the current oracle price.
+// The payment is booked against the meter like any other. Returns its index.
+pub fn pay_installment(
+    env: &Env,
+    from: &Address,
+    token_address: &Address,
+    plan_id: u64,
+) -> Result<u32, Error> {
+    let mut plan = read(env, plan_id).ok_or(Error::InvalidInput)?;
+    if plan.status != PlanStatus::Active {
+        return Err(Error::InvalidState);
+    }
+    let due = plan
+        .installment_amount
+        .min(math::sub(plan.total_debt, plan.paid)?);
+
+    let config = tokens::read_config(env, token_address).ok_or(Error::UnsupportedToken)?;
+    let feed = OracleManager::get_payment_price(env, &config.oracle_pair)?;
+    let amount = accounting::denormalize(due, config.decimals, &feed)?;
+    let index = payments::pay(env, from, token_address, &plan.meter_id, amount)?;
+    let record = accounting::read_payment(env, &plan.meter_id, index).ok_or(Error::InvalidState)?;
+
+    plan.paid = math::add(plan.paid, record.normalized_amount)?;
+    plan.installments_paid += 1;
+    if plan.paid >= plan.total_debt {
+        close(env, plan_id, &mut plan, PlanStatus::Completed);
+    } else {
+        write(env, plan_id, &plan);
+    }
+    env.events().publish(
+        (Symbol::new(env, "installment_paid"), plan_id),
+        (plan.installments_paid, record.normalized_amount),
+    );
+    Ok(index)
+}
+
+pub fn report(env: &Env, plan_id: u64) -> Result<PlanReport, Error> {
+    let plan = read(env, plan_id).ok_or(Error::InvalidInput)?;
+    let active = plan.status == PlanStatus::Active;
+    let installments_due = if active {
+        let elapsed = env.ledger().timestamp() - plan.activated_at;
+        ((elapsed / plan.interval) as u32).min(plan.num_installments)
+    } else {
+        0
+    };
+    Ok(PlanReport {
+        status: plan.status,
+        installments_due,
+        installments_paid: plan.installments_paid,
+        missed_installments: installments_due.saturating_sub(plan.installments_paid),
+        remaining_principal: (plan.total_debt - plan.paid).max(0),
+        next_due_at: if active {
+            plan.activated_at + (plan.installments_paid as u64 + 1) * plan.interval
+        } else {
+            0
+        },
+    })
+}
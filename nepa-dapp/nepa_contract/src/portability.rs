@@ -0,0 +1,182 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol, Vec};
+
+use crate::accounting::{self, MeterSummary};
+use crate::admin;
+use crate::billing;
+use crate::closures;
+use crate::credits;
+use crate::deposits::{self, SecurityDeposit};
+use crate::dunning::{self, DebtStatus};
+use crate::errors::Error;
+use crate::ownership;
+use crate::storage;
+use crate::NepaBillingContractClient;
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PortDirection {
+    Outgoing,
+    Incoming,
+}
+
+// What a meter takes with it to another instance. Its payment history and
+// debt standing make up its credit record there.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MeterExport {
+    pub owner: Address,
+    pub summary: MeterSummary,
+    // Running balance, negative when in credit, and the debt clock behind it.
+    pub standing: DebtStatus,
+    // The security deposit held for the meter, if any; its tokens move too.
+    pub deposit: Vec<SecurityDeposit>,
+}
+
+// Audit record of a meter moving between provider instances, kept on both sides.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MeterPortRecord {
+    pub meter_id: String,
+    pub direction: PortDirection,
+    pub counterparty: Address,
+    pub export: MeterExport,
+    pub approved_at: u64,
+    // 0 until the destination has imported the meter.
+    pub completed_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PortKey {
+    OutgoingPort(String),
+    IncomingPort(String),
+}
+
+pub fn read_outgoing(env: &Env, meter_id: &String) -> Option<MeterPortRecord> {
+    env.storage()
+        .persistent()
+        .get(&PortKey::OutgoingPort(meter_id.clone()))
+}
+
+pub fn read_incoming(env: &Env, meter_id: &String) -> Option<MeterPortRecord> {
+    env.storage()
+        .persistent()
+        .get(&PortKey::IncomingPort(meter_id.clone()))
+}
+
+// Payments stop being accepted here once a meter has moved to another instance
+// or its account has been closed.
+pub fn ensure_active(env: &Env, meter_id: &String) -> Result<(), Error> {
+    match read_outgoing(env, meter_id) {
+        Some(record) if record.completed_at != 0 => Err(Error::MeterInactive),
+        _ if closures::closed(env, meter_id).is_some() => Err(Error::MeterInactive),
+        _ => Ok(()),
+    }
+}
+
+fn snapshot(env: &Env, meter_id: &String) -> Result<MeterExport, Error> {
+    Ok(MeterExport {
+        owner: ownership::owner(env, meter_id).ok_or(Error::InvalidState)?,
+        summary: accounting::summary(env, meter_id),
+        standing: dunning::debt_status(env, meter_id),
+        deposit: deposits::deposit(env, meter_id)
+            .map(|held| Vec::from_array(env, [held]))
+            .unwrap_or(Vec::new(env)),
+    })
+}
+
+// Source side: the provider agrees to hand the meter to `destination`.
+pub fn approve_export(env: &Env, meter_id: &String, destination: &Address) -> Result<(), Error> {
+    admin::require_admin(env);
+    ensure_active(env, meter_id)?;
+
+    let record = MeterPortRecord {
+        meter_id: meter_id.clone(),
+        direction: PortDirection::Outgoing,
+        counterparty: destination.clone(),
+        export: snapshot(env, meter_id)?,
+        approved_at: env.ledger().timestamp(),
+        completed_at: 0,
+    };
+    storage::write_persistent(env, &PortKey::OutgoingPort(meter_id.clone()), &record);
+    env.events().publish(
+        (Symbol::new(env, "meter_export_approved"), meter_id.clone()),
+        destination.clone(),
+    );
+    Ok(())
+}
+
+// Source side, invoked by the destination contract during `import_meter`. The
+// meter's owner, balance, debt clock and deposit leave this instance; only its
+// payment history stays behind.
+pub fn finalize_export(
+    env: &Env,
+    meter_id: &String,
+    destination: &Address,
+) -> Result<MeterExport, Error> {
+    destination.require_auth();
+    let mut record = read_outgoing(env, meter_id).ok_or(Error::InvalidState)?;
+    if record.counterparty != *destination || record.completed_at != 0 {
+        return Err(Error::InvalidState);
+    }
+
+    // Hand over the state as of now, not as of approval.
+    record.export = snapshot(env, meter_id)?;
+    deposits::hand_over(env, meter_id, destination)?;
+    billing::adjust_balance(env, meter_id, -record.export.standing.balance);
+    dunning::write_flag(env, meter_id, 0, false);
+    credits::clear_request(env, meter_id);
+    ownership::clear_owner(env, meter_id);
+    record.completed_at = env.ledger().timestamp();
+    storage::write_persistent(env, &PortKey::OutgoingPort(meter_id.clone()), &record);
+    env.events().publish(
+        (Symbol::new(env, "meter_exported"), meter_id.clone()),
+        destination.clone(),
+    );
+    Ok(record.export)
+}
+
+// Destination side: the receiving provider pulls the meter from `source`.
+pub fn import(env: &Env, source: &Address, meter_id: &String) -> Result<(), Error> {
+    admin::require_admin(env);
+    if accounting::payment_count(env, meter_id) > 0
+        || billing::balance(env, meter_id) != 0
+        || deposits::deposit(env, meter_id).is_some()
+        || read_incoming(env, meter_id).is_some()
+    {
+        return Err(Error::AlreadyExists);
+    }
+
+    let export = NepaBillingContractClient::new(env, source)
+        .try_finalize_meter_export(meter_id, &env.current_contract_address())
+        .map_err(|_| Error::InvalidState)?
+        .map_err(|_| Error::InvalidState)?;
+    accounting::store_summary(env, meter_id, &export.summary);
+    ownership::write_owner(env, meter_id, &export.owner);
+    billing::adjust_balance(env, meter_id, export.standing.balance);
+    dunning::write_flag(
+        env,
+        meter_id,
+        export.standing.over_threshold_since,
+        export.standing.disconnection_pending,
+    );
+    for held in export.deposit.iter() {
+        deposits::take_over(env, meter_id, &held)?;
+    }
+
+    let now = env.ledger().timestamp();
+    let record = MeterPortRecord {
+        meter_id: meter_id.clone(),
+        direction: PortDirection::Incoming,
+        counterparty: source.clone(),
+        export,
+        approved_at: now,
+        completed_at: now,
+    };
+    storage::write_persistent(env, &PortKey::IncomingPort(meter_id.clone()), &record);
+    env.events().publish(
+        (Symbol::new(env, "meter_imported"), meter_id.clone()),
+        source.clone(),
+    );
+    Ok(())
+}
@@ -0,0 +1,161 @@
+// Property-based checks of the billing math over generated tariffs,
+// consumptions, tax configurations and prices. Run with
+// `cargo test --features proptest`.
+extern crate std;
+
+use std::vec::Vec as StdVec;
+
+use proptest::prelude::*;
+use soroban_sdk::{Env, Map, Symbol, Vec};
+
+use crate::accounting;
+use crate::math;
+use crate::oracle::PriceFeed;
+use crate::tariff::{self, TariffOp, TariffTier};
+use crate::taxes::{TaxComponent, TaxKind};
+use crate::testutils::Simulation;
+
+// Up to 1,000 NGN per kWh, in NGN units.
+const MAX_RATE: i128 = 10_000_000_000;
+const MAX_KWH: i128 = 100_000;
+const TAX_NAMES: [&str; 4] = ["vat", "levy_a", "levy_b", "levy_c"];
+
+// A formula step before it is built in an Env. Tier widths are cumulated into
+// limits, so generated tiers are always ordered.
+#[derive(Clone, Debug)]
+enum Step {
+    PerUnit(i128),
+    Tiered(StdVec<(i128, i128)>),
+    FlatFee(i128),
+    Multiplier(u32),
+    Cap(i128),
+    Minimum(i128),
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        (0..MAX_RATE).prop_map(Step::PerUnit),
+        prop::collection::vec((1..MAX_KWH, 0..MAX_RATE), 1..4).prop_map(Step::Tiered),
+        (0..MAX_RATE).prop_map(Step::FlatFee),
+        (0u32..=20_000).prop_map(Step::Multiplier),
+        (0..MAX_RATE * MAX_KWH).prop_map(Step::Cap),
+        (0..MAX_RATE * MAX_KWH).prop_map(Step::Minimum),
+    ]
+}
+
+fn formula(env: &Env, steps: &[Step]) -> Vec<TariffOp> {
+    let kwh = Symbol::new(env, "kwh");
+    let mut formula = Vec::new(env);
+    for step in steps {
+        formula.push_back(match step {
+            Step::PerUnit(rate) => TariffOp::PerUnit(kwh.clone(), *rate),
+            Step::Tiered(bands) => {
+                let mut tiers = Vec::new(env);
+                let mut limit = 0;
+                for (width, rate) in bands {
+                    limit += width;
+                    tiers.push_back(TariffTier { limit, rate: *rate });
+                }
+                TariffOp::Tiered(kwh.clone(), tiers)
+            }
+            Step::FlatFee(fee) => TariffOp::FlatFee(*fee),
+            Step::Multiplier(bps) => TariffOp::Multiplier(*bps),
+            Step::Cap(cap) => TariffOp::Cap(*cap),
+            Step::Minimum(minimum) => TariffOp::Minimum(*minimum),
+        });
+    }
+    formula
+}
+
+fn charge(env: &Env, formula: &Vec<TariffOp>, kwh: i128) -> i128 {
+    let mut inputs = Map::new(env);
+    inputs.set(Symbol::new(env, "kwh"), kwh);
+    tariff::evaluate(env, formula, &inputs).unwrap()
+}
+
+// A price of at least one NGN per whole token, with a fractional part.
+fn feed() -> impl Strategy<Value = PriceFeed> {
+    (0u32..=12, 1i128..=10_000_000, any::<u64>()).prop_map(|(decimals, whole, fraction)| {
+        let scale = math::pow10(decimals).unwrap();
+        PriceFeed {
+            price: whole * scale + fraction as i128 % scale,
+            decimals,
+            last_updated: 0,
+        }
+    })
+}
+
+proptest! {
+    #[test]
+    fn bill_is_monotonic_in_kwh(
+        steps in prop::collection::vec(step(), 1..6),
+        a in 0..MAX_KWH,
+        b in 0..MAX_KWH,
+    ) {
+        let env = Env::default();
+        let formula = formula(&env, &steps);
+        prop_assume!(tariff::validate(&formula).is_ok());
+        let (low, high) = (a.min(b), a.max(b));
+        prop_assert!(charge(&env, &formula, low) <= charge(&env, &formula, high));
+    }
+
+    // Seven-decimal tokens priced at one NGN or more: a token amount valued in
+    // NGN and converted back is at most one stroop short, never over.
+    #[test]
+    fn conversion_round_trips_within_one_stroop(
+        amount in 0i128..1_000_000_000_000_000,
+        feed in feed(),
+    ) {
+        let value = accounting::normalize(amount, 7, &feed).unwrap();
+        let back = accounting::denormalize(value, 7, &feed).unwrap();
+        prop_assert!(back <= amount && amount - back <= 1);
+    }
+
+    // Whatever the decimals, a payment sized by `denormalize` covers the NGN
+    // value it was sized for, and a refund sized by `denormalize_floor` never
+    // exceeds it. Values reach 10M NGN, within range at every scale.
+    #[test]
+    fn conversions_round_in_the_contracts_favour(
+        value in 0i128..100_000_000_000_000,
+        token_decimals in 0u32..=18,
+        feed in feed(),
+    ) {
+        let charged = accounting::denormalize(value, token_decimals, &feed).unwrap();
+        prop_assert!(accounting::normalize(charged, token_decimals, &feed).unwrap() >= value);
+        let refunded = accounting::denormalize_floor(value, token_decimals, &feed).unwrap();
+        prop_assert!(accounting::normalize(refunded, token_decimals, &feed).unwrap() <= value);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn line_items_sum_to_total(
+        per_kwh in 1..MAX_RATE,
+        kwh in 1..MAX_KWH,
+        taxes in prop::collection::vec((0u32..=2_000, any::<bool>()), 0..=TAX_NAMES.len()),
+    ) {
+        let sim = Simulation::new();
+        let mut components = Vec::new(&sim.env);
+        for (i, (bps, levy)) in taxes.iter().enumerate() {
+            components.push_back(TaxComponent {
+                name: Symbol::new(&sim.env, TAX_NAMES[i]),
+                kind: if *levy { TaxKind::Levy } else { TaxKind::Tax },
+                bps: *bps,
+            });
+        }
+        sim.client.set_tax_components(&components);
+        let rate_id = sim.register_rate("electricity", "lagos", "a", per_kwh);
+        let owner = sim.customer(1);
+        let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+
+        let bill = sim.client.issue_bill(&meter_id, &202311, &rate_id, &kwh);
+        let items: i128 = bill.line_items.iter().map(|item| item.amount).sum();
+        prop_assert_eq!(bill.amount, per_kwh * kwh + items);
+        let breakdown = sim.client.get_bill_breakdown(&meter_id, &202311).unwrap();
+        prop_assert_eq!(breakdown.taxes + breakdown.levies, items);
+        prop_assert_eq!(breakdown.subtotal + items, breakdown.total);
+        prop_assert_eq!(breakdown.total, bill.amount);
+    }
+}
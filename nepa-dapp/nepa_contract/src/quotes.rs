@@ -0,0 +1,178 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol, Vec};
+
+use crate::accounting;
+use crate::billing;
+use crate::bounds;
+use crate::errors::Error;
+use crate::fees;
+use crate::oracle::{PriceFeed, PriceSource};
+use crate::payments;
+use crate::peg;
+use crate::storage;
+use crate::tariff::{self, RateKey};
+use crate::taxes::LineItem;
+use crate::tokens;
+
+// How long a quote is good for.
+pub const QUOTE_TTL_SECONDS: u64 = 5 * 60;
+
+// The full charge for a consumption, converted into the payment token.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BillQuote {
+    pub meter_id: String,
+    pub rate_id: String,
+    pub kwh: i128,
+    pub charge: i128,
+    pub subsidy: i128,
+    pub line_items: Vec<LineItem>,
+    // NGN units owed for the consumption: charge after subsidy, plus taxes.
+    pub total: i128,
+    pub estimated: bool,
+    pub token: Address,
+    pub token_amount: i128,
+    // Oracle price the conversion used, in its own decimals.
+    pub price: i128,
+    pub price_decimals: u32,
+    pub price_source: PriceSource,
+    pub quoted_at: u64,
+    pub expires_at: u64,
+}
+
+// A quote held for one payer, who may pay it at the quoted rate until expiry.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LockedQuote {
+    pub payer: Address,
+    pub quote: BillQuote,
+    pub used: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum QuoteKey {
+    NextQuoteId,
+    LockedQuote(u64),
+}
+
+// Prices `kwh` under the (utility_type, region) tariff for the meter's band,
+// with the meter's subsidy and current taxes, at the current payment price.
+// Writes nothing and needs no authorization.
+pub fn quote(
+    env: &Env,
+    meter_id: &String,
+    kwh: i128,
+    utility_type: &String,
+    region: &String,
+    token_address: &Address,
+) -> Result<BillQuote, Error> {
+    if kwh <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    bounds::kwh(env, kwh)?;
+    let band = billing::meter_band(env, meter_id).ok_or(Error::RateNotFound)?;
+    let key = RateKey {
+        utility_type: utility_type.clone(),
+        region: region.clone(),
+        band,
+    };
+    tariff::ensure_registered(env, &key)?;
+    let rate_id = tariff::rate_id_for(env, &key)?;
+    let assessment = billing::assess(env, meter_id, &rate_id, kwh)?;
+
+    let config = tokens::read_config(env, token_address).ok_or(Error::UnsupportedToken)?;
+    let (feed, source) = peg::payment_price(env, token_address, &config)?;
+    let total = assessment.net()?;
+    let now = env.ledger().timestamp();
+    Ok(BillQuote {
+        meter_id: meter_id.clone(),
+        rate_id,
+        kwh,
+        charge: assessment.gross,
+        subsidy: assessment.subsidy,
+        line_items: assessment.line_items.clone(),
+        total,
+        estimated: assessment.estimated,
+        token: token_address.clone(),
+        token_amount: fees::gross_up(env, accounting::denormalize(total, config.decimals, &feed)?)?,
+        price: feed.price,
+        price_decimals: feed.decimals,
+        price_source: source,
+        quoted_at: now,
+        expires_at: now + QUOTE_TTL_SECONDS,
+    })
+}
+
+pub fn read_locked(env: &Env, quote_id: u64) -> Option<LockedQuote> {
+    env.storage()
+        .persistent()
+        .get(&QuoteKey::LockedQuote(quote_id))
+}
+
+// Quotes as above and locks the result for `payer`. Returns the quote id.
+pub fn lock(
+    env: &Env,
+    payer: &Address,
+    meter_id: &String,
+    kwh: i128,
+    utility_type: &String,
+    region: &String,
+    token_address: &Address,
+) -> Result<(u64, BillQuote), Error> {
+    payer.require_auth();
+    let quote = quote(env, meter_id, kwh, utility_type, region, token_address)?;
+
+    let quote_id: u64 = env
+        .storage()
+        .instance()
+        .get(&QuoteKey::NextQuoteId)
+        .unwrap_or(1);
+    env.storage()
+        .instance()
+        .set(&QuoteKey::NextQuoteId, &(quote_id + 1));
+    let locked = LockedQuote {
+        payer: payer.clone(),
+        quote: quote.clone(),
+        used: false,
+    };
+    storage::write_persistent(env, &QuoteKey::LockedQuote(quote_id), &locked);
+    env.events().publish(
+        (Symbol::new(env, "quote_locked"), quote_id),
+        (
+            payer.clone(),
+            meter_id.clone(),
+            quote.token_amount,
+            quote.expires_at,
+        ),
+    );
+    Ok((quote_id, quote))
+}
+
+// Pays the quoted token amount, valued at the quoted price, once and only
+// before the quote expires. Returns the payment index.
+pub fn pay(env: &Env, quote_id: u64) -> Result<u32, Error> {
+    let mut locked = read_locked(env, quote_id).ok_or(Error::InvalidInput)?;
+    if locked.used {
+        return Err(Error::InvalidState);
+    }
+    let quote = &locked.quote;
+    if env.ledger().timestamp() > quote.expires_at {
+        return Err(Error::QuoteExpired);
+    }
+    let price = PriceFeed {
+        price: quote.price,
+        decimals: quote.price_decimals,
+        last_updated: quote.quoted_at,
+    };
+    let index = payments::pay_at(
+        env,
+        &locked.payer,
+        &quote.token,
+        &quote.meter_id,
+        quote.token_amount,
+        Some((&price, quote.price_source)),
+    )?;
+    locked.used = true;
+    storage::write_persistent(env, &QuoteKey::LockedQuote(quote_id), &locked);
+    Ok(index)
+}
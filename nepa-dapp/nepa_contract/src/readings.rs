@@ -0,0 +1,163 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::admin;
+use crate::anomalies::{self, AnomalyKind};
+use crate::audit;
+use crate::billing::{self, BillingRecord};
+use crate::errors::Error;
+use crate::math;
+use crate::storage;
+use crate::tariff;
+use crate::units::{self, MeteredUnit};
+
+const SECONDS_PER_DAY: i128 = 86_400;
+
+// A cumulative register value read off the meter by a field agent.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MeterReading {
+    pub reading: i128,
+    // A meter's register keeps one unit for its whole history.
+    pub unit: MeteredUnit,
+    // When the meter was read, as reported by the agent.
+    pub timestamp: u64,
+    pub agent: Address,
+    pub submitted_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReadingKey {
+    ReadingAgent(Address),
+    ReadingCount(String),
+    MeterReading(String, u32),
+}
+
+pub fn is_agent(env: &Env, agent: &Address) -> bool {
+    env.storage()
+        .persistent()
+        .has(&ReadingKey::ReadingAgent(agent.clone()))
+}
+
+pub fn set_agent(env: &Env, agent: &Address, authorized: bool) {
+    admin::require_admin(env);
+    let key = ReadingKey::ReadingAgent(agent.clone());
+    if authorized {
+        storage::write_persistent(env, &key, &true);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+    audit::role_change(env, "reading_agent", agent, authorized);
+    env.events().publish(
+        (Symbol::new(env, "reading_agent_updated"), agent.clone()),
+        authorized,
+    );
+}
+
+pub fn count(env: &Env, meter_id: &String) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&ReadingKey::ReadingCount(meter_id.clone()))
+        .unwrap_or(0)
+}
+
+pub fn read(env: &Env, meter_id: &String, index: u32) -> Option<MeterReading> {
+    env.storage()
+        .persistent()
+        .get(&ReadingKey::MeterReading(meter_id.clone(), index))
+}
+
+// Readings must be in time order and the register may never run backwards.
+// Returns the index of the reading in the meter's history.
+pub fn submit(
+    env: &Env,
+    agent: &Address,
+    meter_id: &String,
+    reading: i128,
+    unit: MeteredUnit,
+    timestamp: u64,
+) -> Result<u32, Error> {
+    agent.require_auth();
+    let now = env.ledger().timestamp();
+    if !is_agent(env, agent) || reading < 0 || timestamp > now {
+        return Err(Error::InvalidInput);
+    }
+
+    let index = count(env, meter_id);
+    let mut per_day = None;
+    if index > 0 {
+        let last = read(env, meter_id, index - 1).ok_or(Error::InvalidState)?;
+        if last.unit != unit {
+            return Err(Error::UnitMismatch);
+        }
+        if timestamp <= last.timestamp || reading < last.reading {
+            return Err(Error::InvalidInput);
+        }
+        let elapsed = i128::from(timestamp - last.timestamp);
+        per_day = Some(math::mul(reading - last.reading, SECONDS_PER_DAY)? / elapsed);
+    }
+
+    let entry = MeterReading {
+        reading,
+        unit,
+        timestamp,
+        agent: agent.clone(),
+        submitted_at: now,
+    };
+    storage::write_persistent(
+        env,
+        &ReadingKey::MeterReading(meter_id.clone(), index),
+        &entry,
+    );
+    storage::write_persistent(
+        env,
+        &ReadingKey::ReadingCount(meter_id.clone()),
+        &(index + 1),
+    );
+
+    if let Some(per_day) = per_day {
+        anomalies::observe(env, meter_id, AnomalyKind::Reading, index, per_day);
+    }
+
+    env.events().publish(
+        (Symbol::new(env, "meter_reading"), meter_id.clone()),
+        (agent.clone(), reading, timestamp, index),
+    );
+    Ok(index)
+}
+
+// The last reading taken in `period` and the last one taken before it.
+fn bracketing(env: &Env, meter_id: &String, period: u32) -> Option<(MeterReading, MeterReading)> {
+    let mut closing = None;
+    let mut index = count(env, meter_id);
+    while index > 0 {
+        index -= 1;
+        let entry = read(env, meter_id, index)?;
+        let entry_period = billing::period_at(entry.timestamp);
+        if entry_period > period {
+            continue;
+        }
+        if entry_period == period {
+            if closing.is_none() {
+                closing = Some(entry);
+            }
+            continue;
+        }
+        return closing.map(|closing| (entry, closing));
+    }
+    None
+}
+
+// Bills the period from the agents' readings instead of a supplied
+// consumption, at the meter's assigned rate and in its unit. Anyone may
+// trigger it.
+pub fn bill_period(env: &Env, meter_id: &String, period: u32) -> Result<BillingRecord, Error> {
+    let rate_id = billing::meter_rate(env, meter_id).ok_or(Error::RateNotFound)?;
+    let (opening, closing) = bracketing(env, meter_id, period).ok_or(Error::MissingTariffInput)?;
+    let quantity = units::convert(
+        closing.reading - opening.reading,
+        closing.unit,
+        tariff::rate_unit(env, &rate_id),
+    )?;
+    billing::bill_usage(env, meter_id, period, &rate_id, quantity)
+}
@@ -0,0 +1,175 @@
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{contracttype, Address, BytesN, Env, String, Symbol, Vec};
+
+use crate::accounting::{self, PaymentRecord};
+use crate::errors::Error;
+use crate::oracle::PriceSource;
+use crate::storage;
+
+pub const MAX_RECEIPT_PAGE: u32 = 50;
+
+// Proof of payment issued for every settled payment. Receipts are bound to the
+// payer and cannot be transferred.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Receipt {
+    pub receipt_id: u64,
+    pub meter_id: String,
+    // Index of the payment in the meter's history.
+    pub payment_index: u32,
+    pub payer: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub normalized_amount: i128,
+    pub issued_at: u64,
+}
+
+// A payment as stored on-chain, for crews and other contracts to check
+// without relying on an off-chain receipt.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentProof {
+    pub meter_id: String,
+    pub payment_index: u32,
+    pub payer: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub normalized_amount: i128,
+    // The feed price the payment was valued at, in the feed's own decimals.
+    pub rate: i128,
+    pub rate_decimals: u32,
+    pub price_source: PriceSource,
+    pub timestamp: u64,
+    // SHA-256 over this contract's address, the meter, the index and the
+    // stored record, so a proof cannot be replayed for another payment.
+    pub content_hash: BytesN<32>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReceiptKey {
+    NextReceiptId,
+    Receipt(u64),
+    // Whole list of a payer's receipt ids, as written before the indexed
+    // entries below. Still read as the first ids of payers that have one.
+    PayerReceipts(Address),
+    PayerReceiptCount(Address),
+    PayerReceipt(Address, u32),
+}
+
+pub fn read(env: &Env, receipt_id: u64) -> Option<Receipt> {
+    env.storage()
+        .persistent()
+        .get(&ReceiptKey::Receipt(receipt_id))
+}
+
+pub fn next_id(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&ReceiptKey::NextReceiptId)
+        .unwrap_or(1)
+}
+
+fn legacy_list(env: &Env, payer: &Address) -> Vec<u64> {
+    env.storage()
+        .persistent()
+        .get(&ReceiptKey::PayerReceipts(payer.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+// Number of receipts held by `payer`, counting any from the legacy list.
+pub fn count(env: &Env, payer: &Address) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&ReceiptKey::PayerReceiptCount(payer.clone()))
+        .unwrap_or_else(|| legacy_list(env, payer).len())
+}
+
+// Up to `limit` of the receipt ids held by `payer`, oldest first, starting at
+// position `offset`.
+pub fn list(env: &Env, payer: &Address, offset: u32, limit: u32) -> Vec<u64> {
+    let legacy = legacy_list(env, payer);
+    let count = count(env, payer);
+    let mut page = Vec::new(env);
+    let mut i = offset;
+    while i < count && page.len() < limit.min(MAX_RECEIPT_PAGE) {
+        let id = match legacy.get(i) {
+            Some(id) => Some(id),
+            None => env
+                .storage()
+                .persistent()
+                .get(&ReceiptKey::PayerReceipt(payer.clone(), i)),
+        };
+        if let Some(id) = id {
+            page.push_back(id);
+        }
+        i += 1;
+    }
+    page
+}
+
+// Ids start at 1 and are never reused.
+pub fn issue(env: &Env, meter_id: &String, payment_index: u32, record: &PaymentRecord) -> u64 {
+    let receipt_id = next_id(env);
+    env.storage()
+        .instance()
+        .set(&ReceiptKey::NextReceiptId, &(receipt_id + 1));
+
+    let receipt = Receipt {
+        receipt_id,
+        meter_id: meter_id.clone(),
+        payment_index,
+        payer: record.payer.clone(),
+        token: record.token.clone(),
+        amount: record.amount,
+        normalized_amount: record.normalized_amount,
+        issued_at: record.timestamp,
+    };
+    storage::write_persistent(env, &ReceiptKey::Receipt(receipt_id), &receipt);
+
+    let position = count(env, &record.payer);
+    storage::write_persistent(
+        env,
+        &ReceiptKey::PayerReceipt(record.payer.clone(), position),
+        &receipt_id,
+    );
+    storage::write_persistent(
+        env,
+        &ReceiptKey::PayerReceiptCount(record.payer.clone()),
+        &(position + 1),
+    );
+
+    env.events().publish(
+        (Symbol::new(env, "receipt_issued"), record.payer.clone()),
+        (receipt_id, meter_id.clone(), payment_index),
+    );
+    receipt_id
+}
+
+fn content_hash(env: &Env, meter_id: &String, index: u32, record: &PaymentRecord) -> BytesN<32> {
+    let preimage = (
+        env.current_contract_address(),
+        meter_id.clone(),
+        index,
+        record.clone(),
+    )
+        .to_xdr(env);
+    env.crypto().sha256(&preimage)
+}
+
+pub fn proof(env: &Env, meter_id: &String, index: u32) -> Result<PaymentProof, Error> {
+    let record = accounting::read_payment(env, meter_id, index).ok_or(Error::InvalidInput)?;
+    Ok(PaymentProof {
+        meter_id: meter_id.clone(),
+        payment_index: index,
+        content_hash: content_hash(env, meter_id, index, &record),
+        payer: record.payer,
+        token: record.token,
+        amount: record.amount,
+        normalized_amount: record.normalized_amount,
+        rate: record.rate,
+        rate_decimals: record.rate_decimals,
+        price_source: record.price_source,
+        timestamp: record.timestamp,
+    })
+}
@@ -0,0 +1,144 @@
+use soroban_sdk::{contracttype, Address, Env, Symbol};
+
+use crate::admin;
+use crate::audit;
+use crate::errors::Error;
+use crate::maintenance;
+
+// Shortest inactivity period the admin may configure, so a brief absence
+// never hands the contract over.
+pub const MIN_INACTIVITY_SECONDS: u64 = 30 * 86_400;
+
+// Who may take over an inactive admin, and when. `recovery` may claim once
+// the admin has been inactive for `inactivity_seconds`, and becomes admin
+// `challenge_seconds` later unless the admin acts in between.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryConfig {
+    pub recovery: Address,
+    pub inactivity_seconds: u64,
+    pub challenge_seconds: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryClaim {
+    pub claimed_at: u64,
+    pub available_at: u64,
+}
+
+// All kept in instance storage.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RecoveryKey {
+    RecoveryConfig,
+    AdminLastActive,
+    RecoveryClaim,
+}
+
+pub fn read_config(env: &Env) -> Option<RecoveryConfig> {
+    env.storage().instance().get(&RecoveryKey::RecoveryConfig)
+}
+
+pub fn claim(env: &Env) -> Option<RecoveryClaim> {
+    env.storage().instance().get(&RecoveryKey::RecoveryClaim)
+}
+
+pub fn last_active(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&RecoveryKey::AdminLastActive)
+        .unwrap_or(0)
+}
+
+// Called on every admin-authorised action: the admin is alive, so a pending
+// claim is void.
+pub fn on_admin_action(env: &Env) {
+    let instance = env.storage().instance();
+    instance.set(&RecoveryKey::AdminLastActive, &env.ledger().timestamp());
+    if instance.has(&RecoveryKey::RecoveryClaim) {
+        instance.remove(&RecoveryKey::RecoveryClaim);
+        env.events()
+            .publish((Symbol::new(env, "admin_recovery_cancelled"),), ());
+    }
+}
+
+pub fn set_config(env: &Env, config: &RecoveryConfig) -> Result<(), Error> {
+    let admin = admin::require_admin(env);
+    if config.recovery == admin
+        || config.inactivity_seconds < MIN_INACTIVITY_SECONDS
+        || config.challenge_seconds == 0
+    {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&RecoveryKey::RecoveryConfig, config);
+    audit::role_change(env, "recovery", &config.recovery, true);
+    Ok(())
+}
+
+pub fn clear_config(env: &Env) -> Result<(), Error> {
+    admin::require_admin(env);
+    let config = read_config(env).ok_or(Error::InvalidState)?;
+    env.storage()
+        .instance()
+        .remove(&RecoveryKey::RecoveryConfig);
+    audit::role_change(env, "recovery", &config.recovery, false);
+    Ok(())
+}
+
+// The recovery address opens its claim once the admin has been inactive for
+// the configured period. Returns when the claim may be completed.
+pub fn open_claim(env: &Env) -> Result<u64, Error> {
+    maintenance::ensure_writable(env)?;
+    let config = read_config(env).ok_or(Error::InvalidState)?;
+    config.recovery.require_auth();
+    if claim(env).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+    let now = env.ledger().timestamp();
+    if now.saturating_sub(last_active(env)) < config.inactivity_seconds {
+        return Err(Error::InvalidState);
+    }
+    let claim = RecoveryClaim {
+        claimed_at: now,
+        available_at: now + config.challenge_seconds,
+    };
+    env.storage()
+        .instance()
+        .set(&RecoveryKey::RecoveryClaim, &claim);
+    env.events().publish(
+        (
+            Symbol::new(env, "admin_recovery_claimed"),
+            config.recovery.clone(),
+        ),
+        claim.available_at,
+    );
+    Ok(claim.available_at)
+}
+
+// Hands admin rights to the recovery address once the challenge window has
+// passed without the admin acting. The recovery role is used up.
+pub fn complete_claim(env: &Env) -> Result<(), Error> {
+    maintenance::ensure_writable(env)?;
+    let config = read_config(env).ok_or(Error::InvalidState)?;
+    config.recovery.require_auth();
+    let claim = claim(env).ok_or(Error::InvalidState)?;
+    if env.ledger().timestamp() < claim.available_at {
+        return Err(Error::InvalidState);
+    }
+    let previous = admin::read_admin(env);
+    admin::write_admin(env, &config.recovery);
+    let instance = env.storage().instance();
+    instance.remove(&RecoveryKey::RecoveryClaim);
+    instance.remove(&RecoveryKey::RecoveryConfig);
+    instance.set(&RecoveryKey::AdminLastActive, &env.ledger().timestamp());
+    audit::role_change(env, "admin", &previous, false);
+    audit::role_change(env, "admin", &config.recovery, true);
+    env.events().publish(
+        (Symbol::new(env, "admin_recovered"), config.recovery),
+        previous,
+    );
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{contracttype, Address, Bytes, BytesN, Env, String, Symbol};
+
+use crate::accounting::{self, AccountingKey};
+use crate::admin;
+use crate::errors::Error;
+use crate::maintenance;
+use crate::ownership;
+use crate::readings::{self, ReadingKey};
+use crate::storage;
+
+pub const DEFAULT_RETENTION_SECONDS: u64 = 365 * 86_400;
+// Most records one prune call may remove, to keep it within budget.
+pub const MAX_PRUNE_ENTRIES: u32 = 50;
+
+// Detailed records younger than this are kept whatever a prune asks for.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetentionConfig {
+    pub retention_seconds: u64,
+}
+
+// How far a meter's detailed history has been pruned. Payments and readings
+// below the cursors are gone; each digest chains sha256(digest || record XDR)
+// over them in order, so an off-chain archive can be checked against it.
+// Monthly rollups and the meter summary are never pruned.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PruneState {
+    pub payments_pruned: u32,
+    pub readings_pruned: u32,
+    pub payments_digest: BytesN<32>,
+    pub readings_digest: BytesN<32>,
+    pub pruned_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RetentionKey {
+    // Kept in instance storage.
+    RetentionConfig,
+    PruneState(String),
+}
+
+pub fn read_config(env: &Env) -> RetentionConfig {
+    env.storage()
+        .instance()
+        .get(&RetentionKey::RetentionConfig)
+        .unwrap_or(RetentionConfig {
+            retention_seconds: DEFAULT_RETENTION_SECONDS,
+        })
+}
+
+pub fn set_config(env: &Env, config: &RetentionConfig) {
+    admin::require_admin(env);
+    env.storage()
+        .instance()
+        .set(&RetentionKey::RetentionConfig, config);
+    env.events().publish(
+        (Symbol::new(env, "retention_config_set"),),
+        config.retention_seconds,
+    );
+}
+
+pub fn state(env: &Env, meter_id: &String) -> PruneState {
+    env.storage()
+        .persistent()
+        .get(&RetentionKey::PruneState(meter_id.clone()))
+        .unwrap_or(PruneState {
+            payments_pruned: 0,
+            readings_pruned: 0,
+            payments_digest: BytesN::from_array(env, &[0; 32]),
+            readings_digest: BytesN::from_array(env, &[0; 32]),
+            pruned_at: 0,
+        })
+}
+
+pub fn is_payment_pruned(env: &Env, meter_id: &String, index: u32) -> bool {
+    index < state(env, meter_id).payments_pruned
+}
+
+fn chain(env: &Env, digest: &BytesN<32>, record: Bytes) -> BytesN<32> {
+    let mut preimage = Bytes::from_array(env, &digest.to_array());
+    preimage.append(&record);
+    env.crypto().sha256(&preimage)
+}
+
+// The admin or the meter's owner removes up to `max_entries` of the meter's
+// oldest payment records, then readings, taken before `before_timestamp` and
+// past the retention period. The latest payment and reading always stay.
+pub fn prune(
+    env: &Env,
+    caller: &Address,
+    meter_id: &String,
+    before_timestamp: u64,
+    max_entries: u32,
+) -> Result<PruneState, Error> {
+    maintenance::ensure_writable(env)?;
+    let authorised = *caller == admin::read_admin(env)
+        || ownership::owner(env, meter_id).as_ref() == Some(caller);
+    if !authorised {
+        return Err(Error::InvalidInput);
+    }
+    caller.require_auth();
+    if max_entries == 0 || max_entries > MAX_PRUNE_ENTRIES {
+        return Err(Error::InvalidInput);
+    }
+    let now = env.ledger().timestamp();
+    let cutoff = before_timestamp.min(now.saturating_sub(read_config(env).retention_seconds));
+
+    let mut state = state(env, meter_id);
+    let mut budget = max_entries;
+    let (first_payment, first_reading) = (state.payments_pruned, state.readings_pruned);
+    let persistent = env.storage().persistent();
+
+    let payments = accounting::payment_count(env, meter_id);
+    while budget > 0 && state.payments_pruned + 1 < payments {
+        let index = state.payments_pruned;
+        let Some(record) = accounting::read_payment(env, meter_id, index) else {
+            break;
+        };
+        if record.timestamp >= cutoff {
+            break;
+        }
+        state.payments_digest = chain(env, &state.payments_digest, record.to_xdr(env));
+        persistent.remove(&AccountingKey::Payment(meter_id.clone(), index));
+        state.payments_pruned += 1;
+        budget -= 1;
+    }
+
+    let count = readings::count(env, meter_id);
+    while budget > 0 && state.readings_pruned + 1 < count {
+        let index = state.readings_pruned;
+        let Some(reading) = readings::read(env, meter_id, index) else {
+            break;
+        };
+        if reading.timestamp >= cutoff {
+            break;
+        }
+        state.readings_digest = chain(env, &state.readings_digest, reading.to_xdr(env));
+        persistent.remove(&ReadingKey::MeterReading(meter_id.clone(), index));
+        state.readings_pruned += 1;
+        budget -= 1;
+    }
+
+    let pruned = (
+        state.payments_pruned - first_payment,
+        state.readings_pruned - first_reading,
+    );
+    if pruned != (0, 0) {
+        state.pruned_at = now;
+        storage::write_persistent(env, &RetentionKey::PruneState(meter_id.clone()), &state);
+        env.events().publish(
+            (Symbol::new(env, "billing_records_pruned"), meter_id.clone()),
+            pruned,
+        );
+    }
+    Ok(state)
+}
@@ -0,0 +1,137 @@
+use soroban_sdk::{contracttype, Env, String};
+
+use crate::accounting::PaymentRecord;
+use crate::billing;
+use crate::errors::Error;
+use crate::math;
+use crate::settlement;
+use crate::storage;
+use crate::tariff;
+
+// Monthly totals kept as payments and bills are booked, so reports need not
+// scan individual records. Amounts are in NGN units.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MonthlyStats {
+    // Billed consumption for the month.
+    pub kwh: i128,
+    pub paid: i128,
+    pub payment_count: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RollupKey {
+    MeterRollup(String, u32),
+    RegionRollup(String, u32),
+}
+
+fn read(env: &Env, key: &RollupKey) -> MonthlyStats {
+    env.storage().persistent().get(key).unwrap_or(MonthlyStats {
+        kwh: 0,
+        paid: 0,
+        payment_count: 0,
+    })
+}
+
+fn add(env: &Env, key: RollupKey, kwh: i128, paid: i128, payments: u32) -> Result<(), Error> {
+    let mut stats = read(env, &key);
+    stats.kwh = math::add(stats.kwh, kwh)?;
+    stats.paid = math::add(stats.paid, paid)?;
+    stats.payment_count = stats
+        .payment_count
+        .checked_add(payments)
+        .ok_or(Error::ArithmeticOverflow)?;
+    storage::write_persistent(env, &key, &stats);
+    Ok(())
+}
+
+fn period(year: u32, month: u32) -> Result<u32, Error> {
+    if !(1..=12).contains(&month) {
+        return Err(Error::InvalidInput);
+    }
+    let period = year * 100 + month;
+    settlement::validate_period(period)?;
+    Ok(period)
+}
+
+pub fn meter_stats(
+    env: &Env,
+    meter_id: &String,
+    year: u32,
+    month: u32,
+) -> Result<MonthlyStats, Error> {
+    let period = period(year, month)?;
+    Ok(read(env, &RollupKey::MeterRollup(meter_id.clone(), period)))
+}
+
+pub fn region_stats(
+    env: &Env,
+    region: &String,
+    year: u32,
+    month: u32,
+) -> Result<MonthlyStats, Error> {
+    let period = period(year, month)?;
+    Ok(read(env, &RollupKey::RegionRollup(region.clone(), period)))
+}
+
+// Region of a registered utility rate.
+fn region_of(env: &Env, rate_id: &String) -> Option<String> {
+    tariff::key_for_rate(env, rate_id).map(|key| key.region)
+}
+
+// Counts towards the meter's month and the region of the rate it is billed
+// under, if that rate is registered.
+fn book(
+    env: &Env,
+    meter_id: &String,
+    rate_id: Option<String>,
+    period: u32,
+    kwh: i128,
+    paid: i128,
+    payments: u32,
+) -> Result<(), Error> {
+    add(
+        env,
+        RollupKey::MeterRollup(meter_id.clone(), period),
+        kwh,
+        paid,
+        payments,
+    )?;
+    if let Some(region) = rate_id.and_then(|rate_id| region_of(env, &rate_id)) {
+        add(
+            env,
+            RollupKey::RegionRollup(region, period),
+            kwh,
+            paid,
+            payments,
+        )?;
+    }
+    Ok(())
+}
+
+pub fn on_payment(env: &Env, meter_id: &String, record: &PaymentRecord) -> Result<(), Error> {
+    let period = billing::period_at(record.timestamp);
+    let rate_id = billing::meter_rate(env, meter_id);
+    book(
+        env,
+        meter_id,
+        rate_id,
+        period,
+        0,
+        record.normalized_amount,
+        1,
+    )
+}
+
+// `kwh` is the change to the period's billed consumption: the bill's kWh
+// when issued, the difference when it is trued up.
+pub fn on_bill(
+    env: &Env,
+    meter_id: &String,
+    rate_id: &String,
+    period: u32,
+    kwh: i128,
+) -> Result<(), Error> {
+    book(env, meter_id, Some(rate_id.clone()), period, kwh, 0, 0)
+}
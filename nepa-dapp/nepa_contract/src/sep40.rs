@@ -0,0 +1,31 @@
+use soroban_sdk::{contractclient, contracttype, Address, Env, Symbol};
+
+// Asset identifier of the SEP-40 price oracle interface.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Sep40Asset {
+    Stellar(Address),
+    Other(Symbol),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sep40PriceData {
+    pub price: i128,
+    pub timestamp: u64,
+}
+
+// The subset of SEP-40 a fallback chain reads.
+#[contractclient(name = "Sep40Client")]
+pub trait Sep40Interface {
+    fn decimals(env: Env) -> u32;
+    fn lastprice(env: Env, asset: Sep40Asset) -> Option<Sep40PriceData>;
+}
+
+// An external oracle and the asset to read from it in place of a push feed.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExternalPriceSource {
+    pub oracle: Address,
+    pub asset: Sep40Asset,
+}
@@ -0,0 +1,289 @@
+use soroban_sdk::{contracttype, token, Address, Env, String, Symbol, Vec};
+
+use crate::accounting::{self, PaymentRecord};
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::billing;
+use crate::bounds;
+use crate::errors::Error;
+use crate::fees;
+use crate::guard;
+use crate::limits;
+use crate::math;
+use crate::oracle::PriceSource;
+use crate::payments;
+use crate::peg;
+use crate::portability;
+use crate::readings;
+use crate::storage;
+use crate::taxes::LineItem;
+use crate::tokens;
+use crate::velocity;
+
+// A pay-as-you-go session (EV charging, generator hire): funds are locked up
+// front and drawn down as usage is reported.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MeteringSession {
+    pub payer: Address,
+    pub meter_id: String,
+    pub rate_id: String,
+    pub token: Address,
+    // Token units locked when the session opened.
+    pub locked: i128,
+    // Token units drawn so far, and their NGN value after fees.
+    pub drawn: i128,
+    pub drawn_ngn: i128,
+    // Token units of `drawn` taken as fees, and each fee's NGN total.
+    pub fee_amount: i128,
+    pub fees: Vec<LineItem>,
+    pub kwh: i128,
+    // Feed price the latest usage was drawn at, and where it came from.
+    pub rate: i128,
+    pub rate_decimals: u32,
+    pub price_source: PriceSource,
+    pub opened_at: u64,
+    pub closed: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SessionKey {
+    NextSessionId,
+    MeteringSession(u64),
+    // token -> funds locked in open sessions.
+    SessionLocks(Address),
+}
+
+pub fn read(env: &Env, session_id: u64) -> Option<MeteringSession> {
+    env.storage()
+        .persistent()
+        .get(&SessionKey::MeteringSession(session_id))
+}
+
+fn write(env: &Env, session_id: u64, session: &MeteringSession) {
+    storage::write_persistent(env, &SessionKey::MeteringSession(session_id), session);
+}
+
+pub fn locked_in(env: &Env, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&SessionKey::SessionLocks(token_address.clone()))
+        .unwrap_or(0)
+}
+
+fn adjust_locks(env: &Env, token_address: &Address, delta: i128) -> Result<(), Error> {
+    storage::write_persistent(
+        env,
+        &SessionKey::SessionLocks(token_address.clone()),
+        &math::add(locked_in(env, token_address), delta)?,
+    );
+    Ok(())
+}
+
+// Locks `max_amount` of the token for a session at the meter's assigned rate.
+pub fn open(
+    env: &Env,
+    payer: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    max_amount: i128,
+) -> Result<u64, Error> {
+    guard::non_reentrant(env, || {
+        open_unguarded(env, payer, token_address, meter_id, max_amount)
+    })
+}
+
+fn open_unguarded(
+    env: &Env,
+    payer: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    max_amount: i128,
+) -> Result<u64, Error> {
+    payer.require_auth();
+    portability::ensure_active(env, meter_id)?;
+    let rate_id = billing::meter_rate(env, meter_id).ok_or(Error::RateNotFound)?;
+    let config = tokens::require_accepted(env, token_address, max_amount)?;
+    velocity::require_attestation(env, payer);
+    // The whole lock counts against the payer's spending limits.
+    let (feed, source) = peg::payment_price(env, token_address, &config)?;
+    limits::spend(
+        env,
+        payer,
+        accounting::normalize(max_amount, config.decimals, &feed)?,
+    )?;
+
+    adjust_locks(env, token_address, max_amount)?;
+
+    let session_id: u64 = env
+        .storage()
+        .instance()
+        .get(&SessionKey::NextSessionId)
+        .unwrap_or(1);
+    env.storage()
+        .instance()
+        .set(&SessionKey::NextSessionId, &(session_id + 1));
+    let session = MeteringSession {
+        payer: payer.clone(),
+        meter_id: meter_id.clone(),
+        rate_id,
+        token: token_address.clone(),
+        locked: max_amount,
+        drawn: 0,
+        drawn_ngn: 0,
+        fee_amount: 0,
+        fees: Vec::new(env),
+        kwh: 0,
+        rate: 0,
+        rate_decimals: 0,
+        price_source: source,
+        opened_at: env.ledger().timestamp(),
+        closed: false,
+    };
+    write(env, session_id, &session);
+    token::Client::new(env, token_address).transfer(
+        payer,
+        &env.current_contract_address(),
+        &max_amount,
+    );
+    env.events().publish(
+        (Symbol::new(env, "session_opened"), meter_id.clone()),
+        (session_id, payer.clone(), max_amount),
+    );
+    Ok(session_id)
+}
+
+fn require_reporter(env: &Env, reporter: &Address) -> Result<(), Error> {
+    if *reporter != admin::read_admin(env) && !readings::is_agent(env, reporter) {
+        return Err(Error::InvalidInput);
+    }
+    reporter.require_auth();
+    Ok(())
+}
+
+// Adds one draw's fee line items to the session's running totals by name.
+fn add_fees(totals: &mut Vec<LineItem>, items: &Vec<LineItem>) -> Result<(), Error> {
+    for item in items.iter() {
+        match totals.iter().position(|total| total.name == item.name) {
+            Some(i) => {
+                let mut total = totals.get_unchecked(i as u32);
+                total.amount = math::add(total.amount, item.amount)?;
+                totals.set(i as u32, total);
+            }
+            None => totals.push_back(item),
+        }
+    }
+    Ok(())
+}
+
+// Draws the usage, billed at the live rate and price, from the lock. Each draw
+// is grossed up so what is left after fees pays for the usage. Usage beyond
+// what is left draws the remainder. Returns the token units drawn.
+pub fn report_usage(
+    env: &Env,
+    reporter: &Address,
+    session_id: u64,
+    kwh: i128,
+) -> Result<i128, Error> {
+    require_reporter(env, reporter)?;
+    let mut session = read(env, session_id).ok_or(Error::InvalidInput)?;
+    if kwh <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    bounds::kwh(env, kwh)?;
+    // A drained session has to be closed and a new one opened.
+    if session.closed || session.drawn >= session.locked {
+        return Err(Error::InvalidState);
+    }
+
+    let cost = billing::assess(env, &session.meter_id, &session.rate_id, kwh)?.net()?;
+    let config = tokens::read_config(env, &session.token).ok_or(Error::UnsupportedToken)?;
+    let (feed, source) = peg::payment_price(env, &session.token, &config)?;
+    let remaining = session.locked - session.drawn;
+    let mut amount = fees::gross_up(env, accounting::denormalize(cost, config.decimals, &feed)?)?;
+    let capped = amount > remaining;
+    if capped {
+        amount = remaining;
+    }
+    let (fee_amount, fee_items) = fees::assess(env, amount, &config, &feed)?;
+    let value = if capped {
+        accounting::normalize(math::sub(amount, fee_amount)?, config.decimals, &feed)?
+    } else {
+        cost
+    };
+
+    session.drawn = math::add(session.drawn, amount)?;
+    session.drawn_ngn = math::add(session.drawn_ngn, value)?;
+    session.fee_amount = math::add(session.fee_amount, fee_amount)?;
+    add_fees(&mut session.fees, &fee_items)?;
+    session.kwh = math::add(session.kwh, kwh)?;
+    session.rate = feed.price;
+    session.rate_decimals = feed.decimals;
+    session.price_source = source;
+    write(env, session_id, &session);
+    env.events().publish(
+        (Symbol::new(env, "session_usage"), session_id),
+        (kwh, amount, session.locked - session.drawn),
+    );
+    Ok(amount)
+}
+
+// The payer or the provider ends the session: what was drawn is booked as one
+// payment for the consumption and the rest is refunded. Returns the refund.
+pub fn close(env: &Env, session_id: u64, caller: &Address) -> Result<i128, Error> {
+    guard::non_reentrant(env, || close_unguarded(env, session_id, caller))
+}
+
+fn close_unguarded(env: &Env, session_id: u64, caller: &Address) -> Result<i128, Error> {
+    let mut session = read(env, session_id).ok_or(Error::InvalidInput)?;
+    if *caller != session.payer && *caller != admin::read_admin(env) {
+        return Err(Error::InvalidInput);
+    }
+    caller.require_auth();
+    if session.closed {
+        return Err(Error::InvalidState);
+    }
+    session.closed = true;
+    write(env, session_id, &session);
+    adjust_locks(env, &session.token, -session.locked)?;
+
+    if session.drawn > 0 {
+        // The session is settled as it goes: the usage it paid for is billed
+        // against the same balance the payment credits.
+        billing::adjust_balance(env, &session.meter_id, session.drawn_ngn);
+        let record = PaymentRecord {
+            payer: session.payer.clone(),
+            token: session.token.clone(),
+            amount: session.drawn,
+            normalized_amount: session.drawn_ngn,
+            rate: session.rate,
+            rate_decimals: session.rate_decimals,
+            price_source: session.price_source,
+            timestamp: env.ledger().timestamp(),
+            // Settling accrues the fees taken on each draw for the collector.
+            fee_amount: session.fee_amount,
+            fees: session.fees.clone(),
+            sequence: 0,
+        };
+        payments::settle(env, &session.meter_id, &record)?;
+    }
+
+    let refund = session.locked - session.drawn;
+    if refund > 0 {
+        token::Client::new(env, &session.token).transfer(
+            &env.current_contract_address(),
+            &session.payer,
+            &refund,
+        );
+        audit::record(
+            env,
+            AuditAction::Refund(session.payer.clone(), session.token.clone(), refund),
+        );
+    }
+    env.events().publish(
+        (Symbol::new(env, "session_closed"), session_id),
+        (session.kwh, session.drawn, refund),
+    );
+    Ok(refund)
+}
@@ -0,0 +1,240 @@
+use soroban_sdk::{contracttype, token, Address, BytesN, Env, Symbol, Vec};
+
+use crate::accounting;
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::disputes;
+use crate::errors::Error;
+use crate::math;
+use crate::multisig;
+use crate::oracle::OracleManager;
+use crate::storage;
+use crate::tokens;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+const DEFAULT_TOLERANCE_BPS: u32 = 50;
+
+// On-chain sweeps to a provider within one period, valued in NGN at sweep time.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SweepTotals {
+    pub sweep_count: u32,
+    pub total_ngn: i128,
+}
+
+// A deposit the provider saw land on its bank statement.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FiatReceipt {
+    pub fiat_amount: i128,
+    pub bank_ref_hash: BytesN<32>,
+    pub recorded_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReconciliationStatus {
+    Pending,
+    Matched,
+    Mismatched,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReconciliationReport {
+    pub provider: Address,
+    pub period: u32,
+    pub swept_ngn: i128,
+    pub sweep_count: u32,
+    pub fiat_ngn: i128,
+    pub receipt_count: u32,
+    // fiat_ngn - swept_ngn
+    pub difference: i128,
+    pub status: ReconciliationStatus,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SettlementKey {
+    SweepTotals(Address, u32),
+    FiatReceipts(Address, u32),
+    ReconTolerance,
+}
+
+// Periods are YYYYMM, e.g. 202610.
+pub fn validate_period(period: u32) -> Result<(), Error> {
+    let (year, month) = (period / 100, period % 100);
+    if year < 2000 || !(1..=12).contains(&month) {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+pub fn read_sweeps(env: &Env, provider: &Address, period: u32) -> SweepTotals {
+    env.storage()
+        .persistent()
+        .get(&SettlementKey::SweepTotals(provider.clone(), period))
+        .unwrap_or(SweepTotals {
+            sweep_count: 0,
+            total_ngn: 0,
+        })
+}
+
+pub fn read_receipts(env: &Env, provider: &Address, period: u32) -> Vec<FiatReceipt> {
+    env.storage()
+        .persistent()
+        .get(&SettlementKey::FiatReceipts(provider.clone(), period))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn tolerance_bps(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&SettlementKey::ReconTolerance)
+        .unwrap_or(DEFAULT_TOLERANCE_BPS)
+}
+
+pub fn set_tolerance(env: &Env, bps: u32) -> Result<(), Error> {
+    admin::require_admin(env);
+    if bps as i128 > BPS_DENOMINATOR {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&SettlementKey::ReconTolerance, &bps);
+    Ok(())
+}
+
+// Treasury moves collected funds to the provider and books them against the period.
+pub fn sweep(
+    env: &Env,
+    provider: &Address,
+    token_address: &Address,
+    amount: i128,
+    period: u32,
+) -> Result<(), Error> {
+    multisig::ensure_not_required(env)?;
+    admin::require_treasury(env);
+    transfer_sweep(env, provider, token_address, amount, period)
+}
+
+// The sweep itself, once authorised directly or by a multisig proposal.
+pub fn transfer_sweep(
+    env: &Env,
+    provider: &Address,
+    token_address: &Address,
+    amount: i128,
+    period: u32,
+) -> Result<(), Error> {
+    // Nothing leaves the contract while the regulator is reviewing a case.
+    disputes::ensure_settlements_unfrozen(env)?;
+    validate_period(period)?;
+    if amount <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    let config = tokens::read_config(env, token_address).ok_or(Error::UnsupportedToken)?;
+    let feed = OracleManager::get_payment_price(env, &config.oracle_pair)?;
+    let value = accounting::normalize(amount, config.decimals, &feed)?;
+
+    token::Client::new(env, token_address).transfer(
+        &env.current_contract_address(),
+        provider,
+        &amount,
+    );
+
+    let mut totals = read_sweeps(env, provider, period);
+    totals.sweep_count = totals
+        .sweep_count
+        .checked_add(1)
+        .ok_or(Error::ArithmeticOverflow)?;
+    totals.total_ngn = math::add(totals.total_ngn, value)?;
+    storage::write_persistent(
+        env,
+        &SettlementKey::SweepTotals(provider.clone(), period),
+        &totals,
+    );
+    audit::record(
+        env,
+        AuditAction::Withdrawal(provider.clone(), token_address.clone(), amount),
+    );
+
+    env.events().publish(
+        (
+            Symbol::new(env, "settlement_swept"),
+            provider.clone(),
+            period,
+        ),
+        (token_address.clone(), amount, value),
+    );
+    Ok(())
+}
+
+pub fn record_fiat(
+    env: &Env,
+    provider: &Address,
+    period: u32,
+    fiat_amount: i128,
+    bank_ref_hash: &BytesN<32>,
+) -> Result<(), Error> {
+    provider.require_auth();
+    validate_period(period)?;
+    if fiat_amount <= 0 {
+        return Err(Error::InvalidInput);
+    }
+
+    let mut receipts = read_receipts(env, provider, period);
+    if receipts.iter().any(|r| r.bank_ref_hash == *bank_ref_hash) {
+        return Err(Error::AlreadyExists);
+    }
+    receipts.push_back(FiatReceipt {
+        fiat_amount,
+        bank_ref_hash: bank_ref_hash.clone(),
+        recorded_at: env.ledger().timestamp(),
+    });
+    storage::write_persistent(
+        env,
+        &SettlementKey::FiatReceipts(provider.clone(), period),
+        &receipts,
+    );
+
+    let report = reconcile(env, provider, period);
+    if report.status == ReconciliationStatus::Mismatched {
+        env.events().publish(
+            (
+                Symbol::new(env, "reconciliation_mismatch"),
+                provider.clone(),
+                period,
+            ),
+            report.difference,
+        );
+    }
+    Ok(())
+}
+
+pub fn reconcile(env: &Env, provider: &Address, period: u32) -> ReconciliationReport {
+    let sweeps = read_sweeps(env, provider, period);
+    let receipts = read_receipts(env, provider, period);
+    let fiat_ngn: i128 = receipts.iter().map(|r| r.fiat_amount).sum();
+    let difference = fiat_ngn - sweeps.total_ngn;
+
+    let allowed =
+        accounting::apply_bps(sweeps.total_ngn, tolerance_bps(env) as i128).unwrap_or(i128::MAX);
+    let status = if receipts.is_empty() {
+        ReconciliationStatus::Pending
+    } else if difference.abs() <= allowed {
+        ReconciliationStatus::Matched
+    } else {
+        ReconciliationStatus::Mismatched
+    };
+
+    ReconciliationReport {
+        provider: provider.clone(),
+        period,
+        swept_ngn: sweeps.total_ngn,
+        sweep_count: sweeps.sweep_count,
+        fiat_ngn,
+        receipt_count: receipts.len(),
+        difference,
+        status,
+    }
+}
@@ -0,0 +1,107 @@
+// Builds the exact Soroban authorization payloads for high-value calls, so
+// wallets and signing tools can show and sign them without simulating first.
+// Host-only: this module is not part of the contract Wasm.
+extern crate std;
+
+use std::vec::Vec as StdVec;
+
+use soroban_sdk::xdr::{
+    Error as XdrError, Hash, HashIdPreimage, HashIdPreimageSorobanAuthorization,
+    InvokeContractArgs, Limits, ScAddress, ScSymbol, ScVal, SorobanAuthorizedFunction,
+    SorobanAuthorizedInvocation, VecM, WriteXdr,
+};
+use soroban_sdk::{Address, Bytes, BytesN, Env, IntoVal, String, TryFromVal, Val};
+
+fn to_sc_address(env: &Env, address: &Address) -> Result<ScAddress, XdrError> {
+    match ScVal::try_from_val(env, &address.to_val()).map_err(|_| XdrError::Invalid)? {
+        ScVal::Address(address) => Ok(address),
+        _ => Err(XdrError::Invalid),
+    }
+}
+
+// The root invocation a signer authorizes when calling `function` directly.
+pub fn invocation(
+    env: &Env,
+    contract: &Address,
+    function: &str,
+    args: &[Val],
+) -> Result<SorobanAuthorizedInvocation, XdrError> {
+    let mut sc_args = StdVec::new();
+    for arg in args {
+        sc_args.push(ScVal::try_from_val(env, arg).map_err(|_| XdrError::Invalid)?);
+    }
+    Ok(SorobanAuthorizedInvocation {
+        function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+            contract_address: to_sc_address(env, contract)?,
+            function_name: ScSymbol(function.try_into()?),
+            args: sc_args.try_into()?,
+        }),
+        sub_invocations: VecM::default(),
+    })
+}
+
+pub fn sweep_settlement_invocation(
+    env: &Env,
+    contract: &Address,
+    provider: &Address,
+    token_address: &Address,
+    amount: i128,
+    period: u32,
+) -> Result<SorobanAuthorizedInvocation, XdrError> {
+    let args: [Val; 4] = [
+        provider.into_val(env),
+        token_address.into_val(env),
+        amount.into_val(env),
+        period.into_val(env),
+    ];
+    invocation(env, contract, "sweep_settlement", &args)
+}
+
+pub fn upgrade_invocation(
+    env: &Env,
+    contract: &Address,
+    new_wasm_hash: &BytesN<32>,
+) -> Result<SorobanAuthorizedInvocation, XdrError> {
+    invocation(env, contract, "upgrade", &[new_wasm_hash.into_val(env)])
+}
+
+// The bytes a signer's key signs for `invocation`: the SHA-256 of the
+// authorization preimage for the given network, nonce and expiry.
+pub fn signing_payload(
+    env: &Env,
+    network_passphrase: &String,
+    nonce: i64,
+    signature_expiration_ledger: u32,
+    invocation: SorobanAuthorizedInvocation,
+) -> Result<BytesN<32>, XdrError> {
+    let mut passphrase = [0u8; 64];
+    let len = network_passphrase.len() as usize;
+    if len > passphrase.len() {
+        return Err(XdrError::Invalid);
+    }
+    network_passphrase.copy_into_slice(&mut passphrase[..len]);
+    let network_id = env
+        .crypto()
+        .sha256(&Bytes::from_slice(env, &passphrase[..len]));
+
+    let preimage = HashIdPreimage::SorobanAuthorization(HashIdPreimageSorobanAuthorization {
+        network_id: Hash(network_id.to_array()),
+        nonce,
+        signature_expiration_ledger,
+        invocation,
+    });
+    let xdr = preimage.to_xdr(Limits::none())?;
+    Ok(env.crypto().sha256(&Bytes::from_slice(env, &xdr)))
+}
+
+// The bytes a feed's reporter signs for `submit_price_report`.
+pub fn price_report_payload(
+    env: &Env,
+    contract: &Address,
+    feed_id: &String,
+    price: i128,
+    decimals: u32,
+    timestamp: u64,
+) -> Bytes {
+    crate::oracle::OracleManager::report_payload(env, contract, feed_id, price, decimals, timestamp)
+}
@@ -0,0 +1,272 @@
+use soroban_sdk::{contracttype, token, Address, Env, Map, String, Symbol};
+
+use crate::accounting::{self, PaymentRecord};
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::maintenance;
+use crate::math;
+use crate::oracle::PriceFeed;
+use crate::storage;
+use crate::tariff;
+use crate::tokens;
+
+// Most NGN the pools cover for one eligible meter per calendar month; 0 turns
+// coverage off.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SponsorshipConfig {
+    pub monthly_cap: i128,
+}
+
+// Donations to one region in one token. Amounts are in token units.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SponsorshipPool {
+    pub balance: i128,
+    pub funded: i128,
+    pub used: i128,
+}
+
+// A low-income meter's eligibility and the sponsor funds spent on it. The
+// record outlives eligibility so its history stays readable.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SponsoredMeter {
+    // Region whose pools cover the meter, from its rate when flagged.
+    pub region: String,
+    pub eligible: bool,
+    // Month `period_covered` counts against the cap, as YYYYMM.
+    pub period: u32,
+    pub period_covered: i128,
+    pub total_covered: i128,
+    // token -> pool funds spent on the meter.
+    pub tokens_used: Map<Address, i128>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SponsorshipKey {
+    SponsorshipConfig,
+    // Kept in instance storage so payments for other meters skip the lookup
+    // while no meter is eligible.
+    EligibleCount,
+    SponsoredMeter(String),
+    SponsorPool(String, Address),
+    // token -> donations not yet spent, across regions.
+    SponsorFundsHeld(Address),
+}
+
+pub fn read_config(env: &Env) -> SponsorshipConfig {
+    env.storage()
+        .instance()
+        .get(&SponsorshipKey::SponsorshipConfig)
+        .unwrap_or(SponsorshipConfig { monthly_cap: 0 })
+}
+
+pub fn set_config(env: &Env, config: &SponsorshipConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if config.monthly_cap < 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&SponsorshipKey::SponsorshipConfig, config);
+    env.events().publish(
+        (Symbol::new(env, "sponsorship_config_set"),),
+        config.monthly_cap,
+    );
+    Ok(())
+}
+
+fn eligible_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&SponsorshipKey::EligibleCount)
+        .unwrap_or(0)
+}
+
+pub fn sponsored(env: &Env, meter_id: &String) -> Option<SponsoredMeter> {
+    env.storage()
+        .persistent()
+        .get(&SponsorshipKey::SponsoredMeter(meter_id.clone()))
+}
+
+pub fn pool(env: &Env, region: &String, token_address: &Address) -> SponsorshipPool {
+    env.storage()
+        .persistent()
+        .get(&SponsorshipKey::SponsorPool(
+            region.clone(),
+            token_address.clone(),
+        ))
+        .unwrap_or(SponsorshipPool {
+            balance: 0,
+            funded: 0,
+            used: 0,
+        })
+}
+
+fn write_pool(env: &Env, region: &String, token_address: &Address, pool: &SponsorshipPool) {
+    storage::write_persistent(
+        env,
+        &SponsorshipKey::SponsorPool(region.clone(), token_address.clone()),
+        pool,
+    );
+}
+
+pub fn held_in(env: &Env, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&SponsorshipKey::SponsorFundsHeld(token_address.clone()))
+        .unwrap_or(0)
+}
+
+fn add_held(env: &Env, token_address: &Address, delta: i128) -> Result<(), Error> {
+    storage::write_persistent(
+        env,
+        &SponsorshipKey::SponsorFundsHeld(token_address.clone()),
+        &math::add(held_in(env, token_address), delta)?,
+    );
+    Ok(())
+}
+
+// Admin flags a low-income meter for coverage by its region's pools, or
+// withdraws the flag. The meter needs a registered rate to place it.
+pub fn set_eligible(env: &Env, meter_id: &String, eligible: bool) -> Result<(), Error> {
+    admin::require_admin(env);
+    let existing = sponsored(env, meter_id);
+    if existing.as_ref().is_some_and(|meter| meter.eligible) == eligible {
+        return Ok(());
+    }
+    let mut meter = match existing {
+        Some(meter) => meter,
+        None => SponsoredMeter {
+            region: String::from_str(env, ""),
+            eligible: false,
+            period: 0,
+            period_covered: 0,
+            total_covered: 0,
+            tokens_used: Map::new(env),
+        },
+    };
+    if eligible {
+        let rate_id = billing::meter_rate(env, meter_id).ok_or(Error::InvalidInput)?;
+        meter.region = tariff::key_for_rate(env, &rate_id)
+            .ok_or(Error::InvalidInput)?
+            .region;
+    }
+    meter.eligible = eligible;
+    storage::write_persistent(
+        env,
+        &SponsorshipKey::SponsoredMeter(meter_id.clone()),
+        &meter,
+    );
+    let count = match eligible {
+        true => eligible_count(env) + 1,
+        false => eligible_count(env) - 1,
+    };
+    env.storage()
+        .instance()
+        .set(&SponsorshipKey::EligibleCount, &count);
+    env.events().publish(
+        (
+            Symbol::new(env, "sponsorship_eligibility_set"),
+            meter_id.clone(),
+        ),
+        (meter.region, eligible),
+    );
+    Ok(())
+}
+
+// A donor adds `amount` of an accepted token to the region's pool. Returns the
+// pool's new balance.
+pub fn fund(
+    env: &Env,
+    donor: &Address,
+    region: &String,
+    token_address: &Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    maintenance::ensure_writable(env)?;
+    donor.require_auth();
+    if !tariff::regions(env).contains(region) {
+        return Err(Error::InvalidInput);
+    }
+    tokens::require_accepted(env, token_address, amount)?;
+
+    let mut pool = pool(env, region, token_address);
+    pool.balance = math::add(pool.balance, amount)?;
+    pool.funded = math::add(pool.funded, amount)?;
+    write_pool(env, region, token_address, &pool);
+    add_held(env, token_address, amount)?;
+    token::Client::new(env, token_address).transfer(
+        donor,
+        &env.current_contract_address(),
+        &amount,
+    );
+    env.events().publish(
+        (Symbol::new(env, "sponsorship_funded"), region.clone()),
+        (donor.clone(), token_address.clone(), amount),
+    );
+    Ok(pool.balance)
+}
+
+// After a payment by an eligible meter, covers what it still owes, up to the
+// monthly cap, from its region's pool in the payment's token at the payment's
+// price. The tokens are already in the contract; only the books move.
+pub fn on_payment(env: &Env, meter_id: &String, record: &PaymentRecord) -> Result<(), Error> {
+    if eligible_count(env) == 0 {
+        return Ok(());
+    }
+    let Some(mut meter) = sponsored(env, meter_id).filter(|meter| meter.eligible) else {
+        return Ok(());
+    };
+    let cap = read_config(env).monthly_cap;
+    let owed = billing::balance(env, meter_id);
+    if cap <= 0 || owed <= 0 {
+        return Ok(());
+    }
+    let period = billing::period_at(record.timestamp);
+    if meter.period != period {
+        meter.period = period;
+        meter.period_covered = 0;
+    }
+
+    let mut pool = pool(env, &meter.region, &record.token);
+    let decimals = tokens::decimals(env, &record.token);
+    let feed = PriceFeed {
+        price: record.rate,
+        decimals: record.rate_decimals,
+        last_updated: record.timestamp,
+    };
+    let available = accounting::normalize(pool.balance, decimals, &feed)?;
+    let cover = owed
+        .min(math::sub(cap, meter.period_covered)?)
+        .min(available);
+    if cover <= 0 {
+        return Ok(());
+    }
+    let spent = accounting::denormalize(cover, decimals, &feed)?.min(pool.balance);
+
+    pool.balance = math::sub(pool.balance, spent)?;
+    pool.used = math::add(pool.used, spent)?;
+    write_pool(env, &meter.region, &record.token, &pool);
+    add_held(env, &record.token, -spent)?;
+    meter.period_covered = math::add(meter.period_covered, cover)?;
+    meter.total_covered = math::add(meter.total_covered, cover)?;
+    let used = meter.tokens_used.get(record.token.clone()).unwrap_or(0);
+    meter
+        .tokens_used
+        .set(record.token.clone(), math::add(used, spent)?);
+    storage::write_persistent(
+        env,
+        &SponsorshipKey::SponsoredMeter(meter_id.clone()),
+        &meter,
+    );
+    billing::adjust_balance(env, meter_id, -cover);
+    env.events().publish(
+        (Symbol::new(env, "sponsorship_applied"), meter_id.clone()),
+        (meter.region, record.token.clone(), spent, cover),
+    );
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use soroban_sdk::{contracttype, Address, Env, IntoVal, String, TryFromVal, Val, Vec};
+
+use crate::accounting::AccountingKey;
+use crate::errors::Error;
+use crate::oracle::OracleKey;
+use crate::tariff::TariffKey;
+use crate::tokens::TokenKey;
+
+// Keys shared across the contract. Feature modules keep their own key enums,
+// so variant names must stay unique across all of them.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DataKey {
+    Admin,
+    Treasury,
+}
+
+// Ledgers close roughly every five seconds.
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+pub const PERSISTENT_TTL_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+pub const PERSISTENT_TTL_THRESHOLD: u32 = PERSISTENT_TTL_EXTEND_TO - DAY_IN_LEDGERS;
+pub const INSTANCE_TTL_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+pub const INSTANCE_TTL_THRESHOLD: u32 = INSTANCE_TTL_EXTEND_TO - DAY_IN_LEDGERS;
+
+// Writes a persistent entry and keeps it alive for another month, so data
+// that is still being written never gets archived.
+pub fn write_persistent<K, V>(env: &Env, key: &K, value: &V)
+where
+    K: IntoVal<Env, Val>,
+    V: IntoVal<Env, Val>,
+{
+    env.storage().persistent().set(key, value);
+    extend_persistent(env, key);
+}
+
+pub fn extend_persistent<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND_TO);
+}
+
+// Reads an unbounded list kept under its own persistent key, so it is not
+// loaded with the instance on every call. Lists written before the move to
+// persistent storage are still found in the instance.
+pub fn read_index<K, T>(env: &Env, key: &K) -> Vec<T>
+where
+    K: IntoVal<Env, Val>,
+    T: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    env.storage()
+        .persistent()
+        .get(key)
+        .or_else(|| env.storage().instance().get(key))
+        .unwrap_or(Vec::new(env))
+}
+
+// Writes a list read with `read_index`, dropping any copy left in the instance.
+pub fn write_index<K, T>(env: &Env, key: &K, index: &Vec<T>)
+where
+    K: IntoVal<Env, Val>,
+    T: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    write_persistent(env, key, index);
+    if env.storage().instance().has(key) {
+        env.storage().instance().remove(key);
+    }
+}
+
+pub fn extend_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_TTL_THRESHOLD, INSTANCE_TTL_EXTEND_TO);
+}
+
+// Entries an operator can ask `bump_storage` to keep alive.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StorageEntry {
+    Meter(String),
+    MeterPayment(String, u32),
+    PriceFeed(String),
+    UtilityRate(String),
+    AcceptedToken(Address),
+}
+
+// Upper bound on entries per `bump_storage` call, to keep its cost predictable.
+const MAX_BUMP_ENTRIES: u32 = 20;
+
+fn extend_if_present<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    if env.storage().persistent().has(key) {
+        extend_persistent(env, key);
+    }
+}
+
+// Anyone may pay the rent to keep hot entries from being archived.
+pub fn bump(env: &Env, entries: &Vec<StorageEntry>) -> Result<(), Error> {
+    if entries.len() > MAX_BUMP_ENTRIES {
+        return Err(Error::InvalidInput);
+    }
+    extend_instance(env);
+    for entry in entries.iter() {
+        match entry {
+            StorageEntry::Meter(meter_id) => {
+                extend_if_present(env, &meter_id);
+                extend_if_present(env, &AccountingKey::PaymentCount(meter_id.clone()));
+                extend_if_present(env, &AccountingKey::NormalizedTotal(meter_id));
+            }
+            StorageEntry::MeterPayment(meter_id, index) => {
+                extend_if_present(env, &AccountingKey::Payment(meter_id, index));
+            }
+            StorageEntry::PriceFeed(feed_id) => {
+                extend_if_present(env, &OracleKey::PriceFeed(feed_id.clone()));
+                extend_if_present(env, &OracleKey::PriceHistory(feed_id.clone()));
+                extend_if_present(env, &OracleKey::FallbackPrice(feed_id));
+            }
+            StorageEntry::UtilityRate(rate_id) => {
+                extend_if_present(env, &TariffKey::UtilityRate(rate_id));
+            }
+            StorageEntry::AcceptedToken(token) => {
+                extend_if_present(env, &TokenKey::AcceptedToken(token));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use soroban_sdk::{contracttype, Env, String, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::math;
+use crate::storage;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SubsidyScheme {
+    // Share of the bill (bps) covered.
+    Percentage(u32),
+    // Flat NGN amount off each bill, never more than the bill.
+    FixedDiscount(i128),
+    // The first `kwh` of each bill at a reduced NGN rate per kWh.
+    Lifeline(i128, i128),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SubsidyKey {
+    Scheme(String),
+    MeterSubsidy(String),
+    // NGN subsidised under a scheme in a period, for reimbursement claims.
+    SubsidyTotal(String, u32),
+}
+
+pub fn read_scheme(env: &Env, scheme_id: &String) -> Option<SubsidyScheme> {
+    env.storage()
+        .persistent()
+        .get(&SubsidyKey::Scheme(scheme_id.clone()))
+}
+
+pub fn set_scheme(env: &Env, scheme_id: &String, scheme: &SubsidyScheme) -> Result<(), Error> {
+    admin::require_admin(env);
+    let valid = match scheme {
+        SubsidyScheme::Percentage(bps) => *bps as i128 <= BPS_DENOMINATOR,
+        SubsidyScheme::FixedDiscount(discount) => *discount > 0,
+        SubsidyScheme::Lifeline(kwh, rate) => *kwh > 0 && *rate >= 0,
+    };
+    if !valid {
+        return Err(Error::InvalidConfig);
+    }
+    storage::write_persistent(env, &SubsidyKey::Scheme(scheme_id.clone()), scheme);
+    env.events().publish(
+        (Symbol::new(env, "subsidy_scheme_set"), scheme_id.clone()),
+        scheme.clone(),
+    );
+    Ok(())
+}
+
+pub fn meter_scheme(env: &Env, meter_id: &String) -> Option<String> {
+    env.storage()
+        .persistent()
+        .get(&SubsidyKey::MeterSubsidy(meter_id.clone()))
+}
+
+pub fn tag_meter(env: &Env, meter_id: &String, scheme_id: &String) -> Result<(), Error> {
+    admin::require_admin(env);
+    if read_scheme(env, scheme_id).is_none() {
+        return Err(Error::InvalidInput);
+    }
+    storage::write_persistent(env, &SubsidyKey::MeterSubsidy(meter_id.clone()), scheme_id);
+    env.events().publish(
+        (Symbol::new(env, "meter_subsidised"), meter_id.clone()),
+        scheme_id.clone(),
+    );
+    Ok(())
+}
+
+pub fn untag_meter(env: &Env, meter_id: &String) {
+    admin::require_admin(env);
+    env.storage()
+        .persistent()
+        .remove(&SubsidyKey::MeterSubsidy(meter_id.clone()));
+}
+
+// NGN of `gross` a scheme covers for a bill of `kwh` under `rate_id`.
+pub fn amount(
+    env: &Env,
+    scheme: &SubsidyScheme,
+    rate_id: &String,
+    kwh: i128,
+    gross: i128,
+) -> Result<i128, Error> {
+    let covered = match scheme {
+        SubsidyScheme::Percentage(bps) => accounting::apply_bps(gross, *bps as i128)?,
+        SubsidyScheme::FixedDiscount(discount) => *discount,
+        SubsidyScheme::Lifeline(band_kwh, reduced_rate) => {
+            // Usage past the band keeps its normal marginal price.
+            let band = kwh.min(*band_kwh);
+            let (band_charge, _) = billing::price(env, rate_id, band)?;
+            math::sub(band_charge, math::mul(band, *reduced_rate)?)?
+        }
+    };
+    Ok(covered.clamp(0, gross))
+}
+
+pub fn total(env: &Env, scheme_id: &String, period: u32) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&SubsidyKey::SubsidyTotal(scheme_id.clone(), period))
+        .unwrap_or(0)
+}
+
+pub fn record(env: &Env, scheme_id: &String, period: u32, delta: i128) -> Result<(), Error> {
+    let key = SubsidyKey::SubsidyTotal(scheme_id.clone(), period);
+    storage::write_persistent(env, &key, &math::add(total(env, scheme_id, period), delta)?);
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use soroban_sdk::auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation};
+use soroban_sdk::{
+    contractclient, contracttype, token, vec, Address, Env, IntoVal, String, Symbol, Vec,
+};
+
+use crate::accounting;
+use crate::admin;
+use crate::errors::Error;
+use crate::guard;
+use crate::limits;
+use crate::maintenance;
+use crate::payments;
+use crate::peg;
+use crate::portability;
+use crate::storage;
+use crate::tokens;
+use crate::velocity;
+
+const BPS_DENOMINATOR: u32 = 10_000;
+
+// The subset of the Soroswap router a swapped payment uses.
+#[contractclient(name = "SwapRouterClient")]
+pub trait SwapRouterInterface {
+    fn router_pair_for(env: Env, token_a: Address, token_b: Address) -> Address;
+    fn router_get_amounts_out(env: Env, amount_in: i128, path: Vec<Address>) -> Vec<i128>;
+    fn swap_exact_tokens_for_tokens(
+        env: Env,
+        amount_in: i128,
+        amount_out_min: i128,
+        path: Vec<Address>,
+        to: Address,
+        deadline: u64,
+    ) -> Vec<i128>;
+}
+
+// AMM router payments in other tokens are swapped through, and the asset the
+// utility settles in.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SwapConfig {
+    pub router: Address,
+    pub settlement_token: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SwapKey {
+    SwapConfig,
+}
+
+pub fn read_config(env: &Env) -> Option<SwapConfig> {
+    env.storage().instance().get(&SwapKey::SwapConfig)
+}
+
+pub fn set_config(env: &Env, config: &SwapConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if tokens::read_config(env, &config.settlement_token).is_none() {
+        return Err(Error::UnsupportedToken);
+    }
+    env.storage().instance().set(&SwapKey::SwapConfig, config);
+    env.events().publish(
+        (Symbol::new(env, "swap_config_set"),),
+        (config.router.clone(), config.settlement_token.clone()),
+    );
+    Ok(())
+}
+
+// Pays the meter in any accepted token: `amount_in` is swapped into the
+// settlement token, accepting at most `max_slippage_bps` less than the
+// router's quote, and the bill is credited with what the swap returned.
+// Returns the payment index.
+pub fn pay(
+    env: &Env,
+    from: &Address,
+    token_in: &Address,
+    meter_id: &String,
+    amount_in: i128,
+    max_slippage_bps: u32,
+) -> Result<u32, Error> {
+    maintenance::ensure_writable(env)?;
+    guard::non_reentrant(env, || {
+        pay_unguarded(env, from, token_in, meter_id, amount_in, max_slippage_bps)
+    })
+}
+
+fn pay_unguarded(
+    env: &Env,
+    from: &Address,
+    token_in: &Address,
+    meter_id: &String,
+    amount_in: i128,
+    max_slippage_bps: u32,
+) -> Result<u32, Error> {
+    from.require_auth();
+    portability::ensure_active(env, meter_id)?;
+    let config = read_config(env).ok_or(Error::InvalidConfig)?;
+    if *token_in == config.settlement_token || max_slippage_bps > BPS_DENOMINATOR {
+        return Err(Error::InvalidInput);
+    }
+    tokens::require_accepted(env, token_in, amount_in)?;
+    let settlement_config =
+        tokens::read_config(env, &config.settlement_token).ok_or(Error::UnsupportedToken)?;
+    velocity::require_attestation(env, from);
+
+    let contract = env.current_contract_address();
+    token::Client::new(env, token_in).transfer(from, &contract, &amount_in);
+    let received = swap(env, &config, token_in, amount_in, max_slippage_bps)?;
+
+    let (feed, source) = peg::payment_price(env, &config.settlement_token, &settlement_config)?;
+    let record = accounting::build_record(
+        env,
+        from,
+        &config.settlement_token,
+        &settlement_config,
+        &feed,
+        source,
+        received,
+    )?;
+    limits::spend(env, from, record.normalized_amount)?;
+    let index = payments::settle(env, meter_id, &record)?;
+    env.events().publish(
+        (Symbol::new(env, "payment_swapped"), meter_id.clone()),
+        (token_in.clone(), amount_in, received),
+    );
+
+    storage::extend_instance(env);
+    Ok(index)
+}
+
+// Swaps tokens the contract already holds. Returns the settlement tokens received.
+fn swap(
+    env: &Env,
+    config: &SwapConfig,
+    token_in: &Address,
+    amount_in: i128,
+    max_slippage_bps: u32,
+) -> Result<i128, Error> {
+    let router = SwapRouterClient::new(env, &config.router);
+    let path = vec![env, token_in.clone(), config.settlement_token.clone()];
+    let quoted = router
+        .router_get_amounts_out(&amount_in, &path)
+        .last()
+        .ok_or(Error::InvalidState)?;
+    let slippage = accounting::apply_bps(quoted, max_slippage_bps as i128)?;
+    let min_out = quoted - slippage;
+    if min_out <= 0 {
+        return Err(Error::AmountTooSmall);
+    }
+
+    // The router moves the input from this contract into the pair.
+    let contract = env.current_contract_address();
+    let pair = router.router_pair_for(token_in, &config.settlement_token);
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token_in.clone(),
+                fn_name: Symbol::new(env, "transfer"),
+                args: (contract.clone(), pair, amount_in).into_val(env),
+            },
+            sub_invocations: Vec::new(env),
+        }),
+    ]);
+    let amounts = router.swap_exact_tokens_for_tokens(
+        &amount_in,
+        &min_out,
+        &path,
+        &contract,
+        &env.ledger().timestamp(),
+    );
+    let received = amounts.last().ok_or(Error::InvalidState)?;
+    if received < min_out {
+        return Err(Error::InvalidState);
+    }
+    Ok(received)
+}
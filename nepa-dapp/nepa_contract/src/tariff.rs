@@ -0,0 +1,886 @@
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{contracttype, Address, Env, Map, String, Symbol, Vec};
+
+use crate::accounting;
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::bounds;
+use crate::disputes;
+use crate::errors::Error;
+use crate::math;
+use crate::multisig;
+use crate::oracle::{OracleManager, UpdateOutcome, MAX_BATCH_UPDATES};
+use crate::storage;
+use crate::timelock;
+use crate::units::MeteredUnit;
+
+// Longest formula accepted, to keep evaluation cost bounded.
+const MAX_FORMULA_OPS: u32 = 16;
+const MAX_TOU_BANDS: u32 = 8;
+const MAX_KEY_PART_LEN: usize = 32;
+// Most future formulas a rate may have queued at once.
+const MAX_SCHEDULED_RATES: u32 = 12;
+const RATE_ID_SEPARATOR: u8 = b'/';
+pub const MAX_RATE_PAGE: u32 = 50;
+
+// One band of a tiered charge. `limit` is the cumulative upper bound of the
+// band in input units; usage past the last band is charged at its rate.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TariffTier {
+    pub limit: i128,
+    pub rate: i128,
+}
+
+// A step of a tariff formula. Steps run in order over a running total in NGN
+// accounting units; unit rates are NGN units per input unit.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TariffOp {
+    // total += inputs[name] * rate
+    PerUnit(Symbol, i128),
+    // total += inputs[name] charged band by band
+    Tiered(Symbol, Vec<TariffTier>),
+    // total += fee
+    FlatFee(i128),
+    // total = total * bps / 10_000
+    Multiplier(u32),
+    // total = min(total, cap)
+    Cap(i128),
+    // total = max(total, minimum)
+    Minimum(i128),
+    // total += total * (feed - baseline) * bps / 10_000, with feed and baseline
+    // in whole feed units: a pass-through of bps per unit of deviation.
+    FeedAdjust(String, i128, i32),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UtilityRate {
+    pub formula: Vec<TariffOp>,
+    pub last_updated: u64,
+}
+
+// A registered rate as listed by `list_utility_rates`. `last_updated` is 0
+// while only scheduled formulas exist; `sunset` is 0 unless deprecated.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateListing {
+    pub rate_id: String,
+    pub unit: MeteredUnit,
+    pub last_updated: u64,
+    pub frozen_until: u64,
+    pub sunset: u64,
+}
+
+// Time-of-use window. Formulas price each window through its own input:
+// `kwh_peak`, `kwh_shoulder` and `kwh_off_peak`; `kwh` always carries the total.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum TouWindow {
+    OffPeak,
+    Shoulder,
+    Peak,
+}
+
+// Hours `start_hour..end_hour` of the region's local day fall in `window`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TouBand {
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub window: TouWindow,
+}
+
+// Hours not covered by any band are off-peak.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TouSchedule {
+    // Local time offset from UTC, e.g. 60 for WAT.
+    pub utc_offset_minutes: i32,
+    pub bands: Vec<TouBand>,
+}
+
+// Consumption to bill: either already split per window, or a single total
+// attributed to the window the ledger is currently in.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UtilityUsage {
+    Total(i128),
+    ByWindow(Map<TouWindow, i128>),
+}
+
+// Typed name of a rate. Registering it derives the canonical rate id
+// "utility_type/region/band" under which the rate itself is stored.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateKey {
+    pub utility_type: String,
+    pub region: String,
+    pub band: String,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TariffKey {
+    UtilityRate(String),
+    UtilityRateIndex,
+    TouSchedule(String),
+    Regions,
+    RegionRateKeys(String),
+    // Typed key a registered rate id was derived from.
+    RateKeyFor(String),
+    // Timestamp until which the rate may not change.
+    RateFrozenUntil(String),
+    // Future formulas for the rate, in effective order.
+    ScheduledRates(String),
+    UtilityTypes,
+    // Unit the rate's quantity inputs are in; kWh when unset.
+    RateUnit(String),
+    // Kept in instance storage: after this timestamp the deprecated rate no
+    // longer prices bills or payments.
+    RateSunset(String),
+}
+
+// A formula loaded ahead of a tariff review, applying from `effective_from`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScheduledRate {
+    pub formula: Vec<TariffOp>,
+    pub effective_from: u64,
+    pub scheduled_at: u64,
+}
+
+fn stored_rate(env: &Env, rate_id: &String) -> Option<UtilityRate> {
+    env.storage()
+        .persistent()
+        .get(&TariffKey::UtilityRate(rate_id.clone()))
+}
+
+pub fn scheduled_rates(env: &Env, rate_id: &String) -> Vec<ScheduledRate> {
+    env.storage()
+        .persistent()
+        .get(&TariffKey::ScheduledRates(rate_id.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+// The rate whose effective window covers `timestamp`: the latest scheduled
+// formula in effect by then, unless the rate was set directly since. Only the
+// stored rate and the queue are kept, not earlier formulas.
+fn rate_at(env: &Env, rate_id: &String, timestamp: u64) -> Option<UtilityRate> {
+    let current = stored_rate(env, rate_id);
+    let mut due = None;
+    for scheduled in scheduled_rates(env, rate_id).iter() {
+        if scheduled.effective_from > timestamp {
+            break;
+        }
+        due = Some(scheduled);
+    }
+    match (current, due) {
+        (Some(current), Some(due)) if due.effective_from <= current.last_updated => Some(current),
+        (_, Some(due)) => Some(UtilityRate {
+            formula: due.formula,
+            last_updated: due.effective_from,
+        }),
+        (current, None) => current,
+    }
+}
+
+pub fn read_rate(env: &Env, rate_id: &String) -> Option<UtilityRate> {
+    rate_at(env, rate_id, env.ledger().timestamp())
+}
+
+pub fn rate_unit(env: &Env, rate_id: &String) -> MeteredUnit {
+    env.storage()
+        .persistent()
+        .get(&TariffKey::RateUnit(rate_id.clone()))
+        .unwrap_or(MeteredUnit::Kwh)
+}
+
+// Water and gas rates price volumes. Bills already issued keep the unit they
+// were issued in.
+pub fn set_rate_unit(env: &Env, rate_id: &String, unit: MeteredUnit) -> Result<(), Error> {
+    multisig::ensure_not_required(env)?;
+    timelock::ensure_not_required(env)?;
+    admin::require_admin(env);
+    apply_rate_unit(env, rate_id, unit)
+}
+
+// Stores the unit once authorised directly or through the timelock.
+pub fn apply_rate_unit(env: &Env, rate_id: &String, unit: MeteredUnit) -> Result<(), Error> {
+    if is_frozen(env, rate_id) {
+        return Err(Error::RateFrozen);
+    }
+    storage::write_persistent(env, &TariffKey::RateUnit(rate_id.clone()), &unit);
+    env.events()
+        .publish((Symbol::new(env, "rate_unit_set"), rate_id.clone()), unit);
+    Ok(())
+}
+
+pub fn rate_ids(env: &Env) -> Vec<String> {
+    storage::read_index(env, &TariffKey::UtilityRateIndex)
+}
+
+// Up to `limit` registered rates starting at position `offset` of the index,
+// in registration order.
+pub fn list_rates(env: &Env, offset: u32, limit: u32) -> Vec<RateListing> {
+    let ids = rate_ids(env);
+    let mut page = Vec::new(env);
+    let mut i = offset;
+    while i < ids.len() && page.len() < limit.min(MAX_RATE_PAGE) {
+        let rate_id = ids.get_unchecked(i);
+        page.push_back(RateListing {
+            unit: rate_unit(env, &rate_id),
+            last_updated: read_rate(env, &rate_id).map_or(0, |rate| rate.last_updated),
+            frozen_until: frozen_until(env, &rate_id),
+            sunset: sunset(env, &rate_id).unwrap_or(0),
+            rate_id,
+        });
+        i += 1;
+    }
+    page
+}
+
+pub fn validate(formula: &Vec<TariffOp>) -> Result<(), Error> {
+    if formula.is_empty() || formula.len() > MAX_FORMULA_OPS {
+        return Err(Error::InvalidTariff);
+    }
+    for op in formula.iter() {
+        let valid = match op {
+            TariffOp::PerUnit(_, rate) => rate >= 0,
+            TariffOp::Tiered(_, tiers) => {
+                let mut previous = 0;
+                let mut ascending = !tiers.is_empty();
+                for tier in tiers.iter() {
+                    ascending &= tier.limit > previous && tier.rate >= 0;
+                    previous = tier.limit;
+                }
+                ascending
+            }
+            TariffOp::FlatFee(fee) => fee >= 0,
+            TariffOp::Multiplier(_) => true,
+            TariffOp::Cap(cap) => cap >= 0,
+            TariffOp::Minimum(minimum) => minimum >= 0,
+            TariffOp::FeedAdjust(feed_id, _, _) => feed_id.len() > 0,
+        };
+        if !valid {
+            return Err(Error::InvalidTariff);
+        }
+    }
+    Ok(())
+}
+
+fn tiered_charge(quantity: i128, tiers: &Vec<TariffTier>) -> Result<i128, Error> {
+    let mut charge = 0;
+    let mut billed = 0;
+    let mut last_rate = 0;
+    for tier in tiers.iter() {
+        if quantity <= billed {
+            return Ok(charge);
+        }
+        let band = math::sub(quantity.min(tier.limit), billed)?;
+        charge = math::add(charge, math::mul(band, tier.rate)?)?;
+        billed = math::add(billed, band)?;
+        last_rate = tier.rate;
+    }
+    let overflow = math::sub(quantity, billed)?;
+    math::add(charge, math::mul(overflow, last_rate)?)
+}
+
+fn feed_adjustment(
+    env: &Env,
+    total: i128,
+    feed_id: &String,
+    baseline: i128,
+    bps: i32,
+) -> Result<i128, Error> {
+    let feed = OracleManager::get_fresh_data_feed(env, feed_id)?;
+    let scale = math::pow10(feed.decimals)?;
+    let deviation = baseline
+        .checked_mul(scale)
+        .and_then(|baseline| feed.value.checked_sub(baseline))
+        .ok_or(Error::ArithmeticOverflow)?;
+    // Rounded down: the pass-through is floored whichever way the feed moved.
+    let adjustment = total
+        .fixed_mul_floor(deviation, scale)
+        .ok_or(Error::ArithmeticOverflow)?;
+    accounting::apply_bps(adjustment, bps as i128)
+}
+
+pub fn evaluate(
+    env: &Env,
+    formula: &Vec<TariffOp>,
+    inputs: &Map<Symbol, i128>,
+) -> Result<i128, Error> {
+    let input = |name: &Symbol| inputs.get(name.clone()).ok_or(Error::MissingTariffInput);
+
+    let mut total: i128 = 0;
+    for op in formula.iter() {
+        total = match op {
+            TariffOp::PerUnit(name, rate) => math::add(total, math::mul(input(&name)?, rate)?)?,
+            TariffOp::Tiered(name, tiers) => {
+                math::add(total, tiered_charge(input(&name)?, &tiers)?)?
+            }
+            TariffOp::FlatFee(fee) => math::add(total, fee)?,
+            TariffOp::Multiplier(bps) => accounting::apply_bps(total, bps as i128)?,
+            TariffOp::Cap(cap) => total.min(cap),
+            TariffOp::Minimum(minimum) => total.max(minimum),
+            TariffOp::FeedAdjust(feed_id, baseline, bps) => {
+                math::add(total, feed_adjustment(env, total, &feed_id, baseline, bps)?)?
+            }
+        };
+    }
+    Ok(total)
+}
+
+fn index_rate(env: &Env, rate_id: &String) {
+    let mut ids = rate_ids(env);
+    if !ids.contains(rate_id) {
+        ids.push_back(rate_id.clone());
+        storage::write_index(env, &TariffKey::UtilityRateIndex, &ids);
+    }
+}
+
+// Writes a rate and registers its id in the index on first sight.
+pub fn store_rate(env: &Env, rate_id: &String, rate: &UtilityRate) {
+    if stored_rate(env, rate_id).is_none() {
+        index_rate(env, rate_id);
+    }
+    storage::write_persistent(env, &TariffKey::UtilityRate(rate_id.clone()), rate);
+}
+
+fn write_schedule(env: &Env, rate_id: &String, schedule: &Vec<ScheduledRate>) {
+    let key = TariffKey::ScheduledRates(rate_id.clone());
+    if schedule.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        storage::write_persistent(env, &key, schedule);
+    }
+}
+
+// Formulas still to come; those already in effect are superseded by `since`.
+fn pending_schedule(env: &Env, rate_id: &String, since: u64) -> Vec<ScheduledRate> {
+    let mut pending = Vec::new(env);
+    for scheduled in scheduled_rates(env, rate_id).iter() {
+        if scheduled.effective_from > since {
+            pending.push_back(scheduled);
+        }
+    }
+    pending
+}
+
+// Queues `formula` to take over the rate from `effective_from`, replacing any
+// formula already queued for that moment. Formulas in effect by now are
+// written through as the stored rate first.
+pub fn schedule_rate(
+    env: &Env,
+    rate_id: &String,
+    formula: &Vec<TariffOp>,
+    effective_from: u64,
+) -> Result<(), Error> {
+    multisig::ensure_not_required(env)?;
+    timelock::ensure_not_required(env)?;
+    admin::require_admin(env);
+    apply_scheduled_rate(env, rate_id, formula, effective_from)
+}
+
+// Queues the formula once authorised directly or through the timelock.
+pub fn apply_scheduled_rate(
+    env: &Env,
+    rate_id: &String,
+    formula: &Vec<TariffOp>,
+    effective_from: u64,
+) -> Result<(), Error> {
+    let now = env.ledger().timestamp();
+    if effective_from <= now {
+        return Err(Error::InvalidInput);
+    }
+    ensure_rate_registered(env, rate_id)?;
+    if effective_from < frozen_until(env, rate_id) {
+        return Err(Error::RateFrozen);
+    }
+    validate(formula)?;
+    bounds::formula(env, formula)?;
+
+    if let Some(current) = read_rate(env, rate_id) {
+        if stored_rate(env, rate_id).as_ref() != Some(&current) {
+            store_rate(env, rate_id, &current);
+        }
+    }
+    let mut schedule = Vec::new(env);
+    let mut queued = false;
+    for scheduled in pending_schedule(env, rate_id, now).iter() {
+        if !queued && scheduled.effective_from >= effective_from {
+            schedule.push_back(ScheduledRate {
+                formula: formula.clone(),
+                effective_from,
+                scheduled_at: now,
+            });
+            queued = true;
+        }
+        if scheduled.effective_from != effective_from {
+            schedule.push_back(scheduled);
+        }
+    }
+    if !queued {
+        schedule.push_back(ScheduledRate {
+            formula: formula.clone(),
+            effective_from,
+            scheduled_at: now,
+        });
+    }
+    if schedule.len() > MAX_SCHEDULED_RATES {
+        return Err(Error::InvalidState);
+    }
+    write_schedule(env, rate_id, &schedule);
+    index_rate(env, rate_id);
+    audit::record(env, AuditAction::RateChanged(rate_id.clone()));
+    env.events().publish(
+        (Symbol::new(env, "utility_rate_scheduled"), rate_id.clone()),
+        effective_from,
+    );
+    Ok(())
+}
+
+pub fn cancel_scheduled_rate(
+    env: &Env,
+    rate_id: &String,
+    effective_from: u64,
+) -> Result<(), Error> {
+    admin::require_admin(env);
+    let mut schedule = pending_schedule(env, rate_id, env.ledger().timestamp());
+    let Some(index) = schedule
+        .iter()
+        .position(|scheduled| scheduled.effective_from == effective_from)
+    else {
+        return Err(Error::InvalidInput);
+    };
+    schedule.remove(index as u32);
+    write_schedule(env, rate_id, &schedule);
+    env.events().publish(
+        (
+            Symbol::new(env, "scheduled_rate_cancelled"),
+            rate_id.clone(),
+        ),
+        effective_from,
+    );
+    Ok(())
+}
+
+pub fn set_rate(env: &Env, rate_id: &String, formula: &Vec<TariffOp>) -> Result<(), Error> {
+    multisig::ensure_not_required(env)?;
+    timelock::ensure_not_required(env)?;
+    admin::require_admin(env);
+    apply_rate(env, rate_id, formula)
+}
+
+pub fn sunset(env: &Env, rate_id: &String) -> Option<u64> {
+    env.storage()
+        .instance()
+        .get(&TariffKey::RateSunset(rate_id.clone()))
+}
+
+fn ensure_not_sunset(env: &Env, rate_id: &String) -> Result<(), Error> {
+    match sunset(env, rate_id) {
+        Some(at) if env.ledger().timestamp() >= at => Err(Error::Deprecated),
+        _ => Ok(()),
+    }
+}
+
+// Retires a rate: it keeps pricing until `sunset_at` and refuses bills and
+// payments from then on. Reads are unaffected.
+pub fn deprecate(env: &Env, rate_id: &String, sunset_at: u64) -> Result<(), Error> {
+    multisig::ensure_not_required(env)?;
+    timelock::ensure_not_required(env)?;
+    admin::require_admin(env);
+    apply_deprecation(env, rate_id, sunset_at)
+}
+
+// Sets the sunset once authorised directly or through the timelock.
+pub fn apply_deprecation(env: &Env, rate_id: &String, sunset_at: u64) -> Result<(), Error> {
+    if stored_rate(env, rate_id).is_none() {
+        return Err(Error::RateNotFound);
+    }
+    if is_frozen(env, rate_id) {
+        return Err(Error::RateFrozen);
+    }
+    ensure_not_sunset(env, rate_id)?;
+    if sunset_at < env.ledger().timestamp() {
+        return Err(Error::InvalidInput);
+    }
+    env.storage()
+        .instance()
+        .set(&TariffKey::RateSunset(rate_id.clone()), &sunset_at);
+    audit::record(env, AuditAction::RateChanged(rate_id.clone()));
+    env.events().publish(
+        (Symbol::new(env, "utility_rate_deprecated"), rate_id.clone()),
+        sunset_at,
+    );
+    Ok(())
+}
+
+// Deletes a deprecated rate's formula, schedule and settings once its sunset
+// has passed. A registered rate key keeps its name and may be given a new rate.
+pub fn remove(env: &Env, rate_id: &String) -> Result<(), Error> {
+    multisig::ensure_not_required(env)?;
+    timelock::ensure_not_required(env)?;
+    admin::require_admin(env);
+    apply_removal(env, rate_id)
+}
+
+// Deletes the rate once authorised directly or through the timelock.
+pub fn apply_removal(env: &Env, rate_id: &String) -> Result<(), Error> {
+    match sunset(env, rate_id) {
+        Some(at) if env.ledger().timestamp() >= at => {}
+        _ => return Err(Error::InvalidState),
+    }
+    let persistent = env.storage().persistent();
+    for key in [
+        TariffKey::UtilityRate(rate_id.clone()),
+        TariffKey::TouSchedule(rate_id.clone()),
+        TariffKey::RateFrozenUntil(rate_id.clone()),
+        TariffKey::ScheduledRates(rate_id.clone()),
+        TariffKey::RateUnit(rate_id.clone()),
+    ] {
+        persistent.remove(&key);
+    }
+    let mut ids = rate_ids(env);
+    if let Some(position) = ids.first_index_of(rate_id) {
+        ids.remove(position);
+        storage::write_index(env, &TariffKey::UtilityRateIndex, &ids);
+    }
+    env.storage()
+        .instance()
+        .remove(&TariffKey::RateSunset(rate_id.clone()));
+    audit::record(env, AuditAction::RateChanged(rate_id.clone()));
+    env.events().publish(
+        (Symbol::new(env, "utility_rate_removed"), rate_id.clone()),
+        (),
+    );
+    Ok(())
+}
+
+// Sets several formulas under one authorization. Entries are applied
+// independently; one that fails is reported and skipped. Returns one outcome
+// per entry, in input order.
+pub fn set_rates_batch(
+    env: &Env,
+    rates: &Vec<(String, Vec<TariffOp>)>,
+) -> Result<Vec<UpdateOutcome>, Error> {
+    multisig::ensure_not_required(env)?;
+    timelock::ensure_not_required(env)?;
+    admin::require_admin(env);
+    apply_rates_batch(env, rates)
+}
+
+pub fn validate_batch(rates: &Vec<(String, Vec<TariffOp>)>) -> Result<(), Error> {
+    if rates.is_empty() || rates.len() > MAX_BATCH_UPDATES {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+// Applies the batch once authorised directly or through the timelock.
+pub fn apply_rates_batch(
+    env: &Env,
+    rates: &Vec<(String, Vec<TariffOp>)>,
+) -> Result<Vec<UpdateOutcome>, Error> {
+    validate_batch(rates)?;
+    let mut outcomes = Vec::new(env);
+    for (rate_id, formula) in rates.iter() {
+        let result = apply_rate(env, &rate_id, &formula).map(|_| true);
+        outcomes.push_back(UpdateOutcome::of(result));
+    }
+    Ok(outcomes)
+}
+
+pub fn frozen_until(env: &Env, rate_id: &String) -> u64 {
+    env.storage()
+        .persistent()
+        .get(&TariffKey::RateFrozenUntil(rate_id.clone()))
+        .unwrap_or(0)
+}
+
+pub fn is_frozen(env: &Env, rate_id: &String) -> bool {
+    env.ledger().timestamp() < frozen_until(env, rate_id)
+}
+
+// The admin or the regulator pins a rate at its current formula during a
+// review. A freeze can be extended but not shortened, so the provider cannot
+// lift one the regulator placed.
+pub fn freeze(env: &Env, caller: &Address, rate_id: &String, until: u64) -> Result<(), Error> {
+    let authorised =
+        *caller == admin::read_admin(env) || disputes::read_regulator(env).as_ref() == Some(caller);
+    if !authorised {
+        return Err(Error::InvalidInput);
+    }
+    caller.require_auth();
+    if read_rate(env, rate_id).is_none() {
+        return Err(Error::RateNotFound);
+    }
+    if until <= env.ledger().timestamp() || until < frozen_until(env, rate_id) {
+        return Err(Error::InvalidInput);
+    }
+    storage::write_persistent(env, &TariffKey::RateFrozenUntil(rate_id.clone()), &until);
+    env.events().publish(
+        (Symbol::new(env, "rate_frozen"), rate_id.clone()),
+        (caller.clone(), until),
+    );
+    Ok(())
+}
+
+// Validates and stores a formula, once authorised directly or by a multisig
+// proposal. Frozen rates are refused; a timelocked change stays queued until
+// the freeze ends.
+pub fn apply_rate(env: &Env, rate_id: &String, formula: &Vec<TariffOp>) -> Result<(), Error> {
+    ensure_rate_registered(env, rate_id)?;
+    if is_frozen(env, rate_id) {
+        return Err(Error::RateFrozen);
+    }
+    validate(formula)?;
+    bounds::formula(env, formula)?;
+
+    let rate = UtilityRate {
+        formula: formula.clone(),
+        last_updated: env.ledger().timestamp(),
+    };
+    store_rate(env, rate_id, &rate);
+    // Formulas that took effect before this one are superseded by it.
+    let pending = pending_schedule(env, rate_id, rate.last_updated);
+    if pending.len() != scheduled_rates(env, rate_id).len() {
+        write_schedule(env, rate_id, &pending);
+    }
+    audit::record(env, AuditAction::RateChanged(rate_id.clone()));
+
+    env.events().publish(
+        (Symbol::new(env, "utility_rate_updated"), rate_id.clone()),
+        rate.last_updated,
+    );
+    Ok(())
+}
+
+pub fn calculate(env: &Env, rate_id: &String, inputs: &Map<Symbol, i128>) -> Result<i128, Error> {
+    let rate = read_rate(env, rate_id).ok_or(Error::RateNotFound)?;
+    ensure_not_sunset(env, rate_id)?;
+    evaluate(env, &rate.formula, inputs)
+}
+
+pub fn read_tou_schedule(env: &Env, rate_id: &String) -> Option<TouSchedule> {
+    env.storage()
+        .persistent()
+        .get(&TariffKey::TouSchedule(rate_id.clone()))
+}
+
+pub fn set_tou_schedule(env: &Env, rate_id: &String, schedule: &TouSchedule) -> Result<(), Error> {
+    multisig::ensure_not_required(env)?;
+    timelock::ensure_not_required(env)?;
+    admin::require_admin(env);
+    apply_tou_schedule(env, rate_id, schedule)
+}
+
+pub fn validate_tou_schedule(schedule: &TouSchedule) -> Result<(), Error> {
+    if schedule.bands.len() > MAX_TOU_BANDS || schedule.utc_offset_minutes.abs() > 14 * 60 {
+        return Err(Error::InvalidTariff);
+    }
+    for band in schedule.bands.iter() {
+        if band.start_hour >= band.end_hour || band.end_hour > 24 {
+            return Err(Error::InvalidTariff);
+        }
+    }
+    Ok(())
+}
+
+// Stores the schedule once authorised directly or through the timelock.
+pub fn apply_tou_schedule(
+    env: &Env,
+    rate_id: &String,
+    schedule: &TouSchedule,
+) -> Result<(), Error> {
+    validate_tou_schedule(schedule)?;
+    storage::write_persistent(env, &TariffKey::TouSchedule(rate_id.clone()), schedule);
+    env.events().publish(
+        (Symbol::new(env, "tou_schedule_updated"), rate_id.clone()),
+        schedule.bands.len(),
+    );
+    Ok(())
+}
+
+// The region's window at `timestamp`; off-peak when it has no schedule.
+pub fn window_at(env: &Env, rate_id: &String, timestamp: u64) -> TouWindow {
+    let Some(schedule) = read_tou_schedule(env, rate_id) else {
+        return TouWindow::OffPeak;
+    };
+    let local = timestamp as i64 + schedule.utc_offset_minutes as i64 * 60;
+    let hour = local.rem_euclid(86_400) as u32 / 3600;
+    schedule
+        .bands
+        .iter()
+        .find(|band| band.start_hour <= hour && hour < band.end_hour)
+        .map_or(TouWindow::OffPeak, |band| band.window)
+}
+
+fn window_input(env: &Env, window: TouWindow) -> Symbol {
+    match window {
+        TouWindow::OffPeak => Symbol::new(env, "kwh_off_peak"),
+        TouWindow::Shoulder => Symbol::new(env, "kwh_shoulder"),
+        TouWindow::Peak => Symbol::new(env, "kwh_peak"),
+    }
+}
+
+// Builds formula inputs from consumption, with every window present.
+pub fn usage_inputs(
+    env: &Env,
+    rate_id: &String,
+    usage: &UtilityUsage,
+) -> Result<Map<Symbol, i128>, Error> {
+    let mut by_window = Map::new(env);
+    for window in [TouWindow::OffPeak, TouWindow::Shoulder, TouWindow::Peak] {
+        by_window.set(window, 0);
+    }
+    match usage {
+        UtilityUsage::Total(kwh) => {
+            by_window.set(window_at(env, rate_id, env.ledger().timestamp()), *kwh);
+        }
+        UtilityUsage::ByWindow(split) => {
+            for (window, kwh) in split.iter() {
+                by_window.set(window, kwh);
+            }
+        }
+    }
+
+    let mut inputs = Map::new(env);
+    let mut total: i128 = 0;
+    for (window, kwh) in by_window.iter() {
+        bounds::kwh(env, kwh)?;
+        inputs.set(window_input(env, window), kwh);
+        total = math::add(total, kwh)?;
+    }
+    inputs.set(Symbol::new(env, "kwh"), total);
+    Ok(inputs)
+}
+
+// Whether `part` can be used as one part of a rate key.
+pub fn check_key_part(part: &String) -> Result<(), Error> {
+    copy_part(part, &mut [0u8; MAX_KEY_PART_LEN]).map(|_| ())
+}
+
+fn copy_part(part: &String, buf: &mut [u8]) -> Result<usize, Error> {
+    let len = part.len() as usize;
+    if len == 0 || len > MAX_KEY_PART_LEN {
+        return Err(Error::InvalidInput);
+    }
+    part.copy_into_slice(&mut buf[..len]);
+    if buf[..len].contains(&RATE_ID_SEPARATOR) {
+        return Err(Error::InvalidInput);
+    }
+    Ok(len)
+}
+
+pub fn rate_id_for(env: &Env, key: &RateKey) -> Result<String, Error> {
+    let mut buf = [0u8; 3 * MAX_KEY_PART_LEN + 2];
+    let mut len = 0;
+    for (i, part) in [&key.utility_type, &key.region, &key.band]
+        .iter()
+        .enumerate()
+    {
+        if i > 0 {
+            buf[len] = RATE_ID_SEPARATOR;
+            len += 1;
+        }
+        len += copy_part(part, &mut buf[len..])?;
+    }
+    Ok(String::from_bytes(env, &buf[..len]))
+}
+
+// Registered by the admin; regions that had rate keys before the registry
+// existed were entered into it with their first key.
+pub fn regions(env: &Env) -> Vec<String> {
+    storage::read_index(env, &TariffKey::Regions)
+}
+
+pub fn region_rate_keys(env: &Env, region: &String) -> Vec<RateKey> {
+    env.storage()
+        .persistent()
+        .get(&TariffKey::RegionRateKeys(region.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn utility_types(env: &Env) -> Vec<String> {
+    storage::read_index(env, &TariffKey::UtilityTypes)
+}
+
+fn add_entry(env: &Env, key: &TariffKey, entry: &String, event: &str) -> Result<(), Error> {
+    admin::require_admin(env);
+    check_key_part(entry)?;
+    let mut entries: Vec<String> = storage::read_index(env, key);
+    if entries.contains(entry) {
+        return Err(Error::AlreadyExists);
+    }
+    entries.push_back(entry.clone());
+    storage::write_index(env, key, &entries);
+    env.events()
+        .publish((Symbol::new(env, event), entry.clone()), entries.len());
+    Ok(())
+}
+
+pub fn register_region(env: &Env, region: &String) -> Result<(), Error> {
+    add_entry(env, &TariffKey::Regions, region, "region_registered")
+}
+
+// e.g. "electricity", "water", "gas".
+pub fn register_utility_type(env: &Env, utility_type: &String) -> Result<(), Error> {
+    add_entry(
+        env,
+        &TariffKey::UtilityTypes,
+        utility_type,
+        "utility_type_registered",
+    )
+}
+
+// Rate keys may only name registered regions and utility types.
+pub fn ensure_registered(env: &Env, key: &RateKey) -> Result<(), Error> {
+    if !regions(env).contains(&key.region) || !utility_types(env).contains(&key.utility_type) {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+// Rates may only be set under an id `register_key` issued, for a region and
+// utility type still registered.
+pub fn ensure_rate_registered(env: &Env, rate_id: &String) -> Result<(), Error> {
+    let key = key_for_rate(env, rate_id).ok_or(Error::InvalidInput)?;
+    ensure_registered(env, &key)
+}
+
+// Adds the key to its region's registry and returns its rate id. The rate is
+// then set under that id through the usual (multisig/timelock-aware) paths.
+pub fn register_key(env: &Env, key: &RateKey) -> Result<String, Error> {
+    admin::require_admin(env);
+    ensure_registered(env, key)?;
+    let rate_id = rate_id_for(env, key)?;
+
+    let mut keys = region_rate_keys(env, &key.region);
+    if !keys.contains(key) {
+        keys.push_back(key.clone());
+        storage::write_persistent(env, &TariffKey::RegionRateKeys(key.region.clone()), &keys);
+        storage::write_persistent(env, &TariffKey::RateKeyFor(rate_id.clone()), key);
+    }
+    Ok(rate_id)
+}
+
+pub fn key_for_rate(env: &Env, rate_id: &String) -> Option<RateKey> {
+    env.storage()
+        .persistent()
+        .get(&TariffKey::RateKeyFor(rate_id.clone()))
+}
+
+// The region's full tariff table: every registered key that has a rate set.
+pub fn rates_for_region(env: &Env, region: &String) -> Result<Vec<(RateKey, UtilityRate)>, Error> {
+    let mut table = Vec::new(env);
+    for key in region_rate_keys(env, region).iter() {
+        if let Some(rate) = read_rate(env, &rate_id_for(env, &key)?) {
+            table.push_back((key, rate));
+        }
+    }
+    Ok(table)
+}
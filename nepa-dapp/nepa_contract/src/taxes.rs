@@ -0,0 +1,98 @@
+use soroban_sdk::{contracttype, Env, Symbol, Vec};
+
+use crate::accounting;
+use crate::admin;
+use crate::errors::Error;
+use crate::math;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+const MAX_TAX_COMPONENTS: u32 = 8;
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TaxKind {
+    Tax,
+    Levy,
+    // Taken out of a payment for the fee collector.
+    Fee,
+    // What the region's rounding policy added to or took off the bill.
+    Rounding,
+}
+
+// A percentage charged on the bill's subtotal (after subsidy), e.g. VAT at 750 bps.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaxComponent {
+    pub name: Symbol,
+    pub kind: TaxKind,
+    pub bps: u32,
+}
+
+// A component as charged on one bill.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LineItem {
+    pub name: Symbol,
+    pub kind: TaxKind,
+    pub bps: u32,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TaxKey {
+    TaxComponents,
+}
+
+pub fn read_components(env: &Env) -> Vec<TaxComponent> {
+    env.storage()
+        .instance()
+        .get(&TaxKey::TaxComponents)
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn set_components(env: &Env, components: &Vec<TaxComponent>) -> Result<(), Error> {
+    admin::require_admin(env);
+    if components.len() > MAX_TAX_COMPONENTS
+        || components.iter().any(|c| c.bps as i128 > BPS_DENOMINATOR)
+    {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&TaxKey::TaxComponents, components);
+    env.events()
+        .publish((Symbol::new(env, "tax_components_set"),), components.len());
+    Ok(())
+}
+
+// Line items for `subtotal` under the current tax configuration.
+pub fn line_items(env: &Env, subtotal: i128) -> Result<Vec<LineItem>, Error> {
+    let mut items = Vec::new(env);
+    for component in read_components(env).iter() {
+        items.push_back(LineItem {
+            amount: accounting::apply_bps(subtotal, component.bps as i128)?,
+            name: component.name,
+            kind: component.kind,
+            bps: component.bps,
+        });
+    }
+    Ok(items)
+}
+
+// Re-applies a bill's own rates to a new subtotal, ignoring later config changes.
+pub fn reprice(env: &Env, items: &Vec<LineItem>, subtotal: i128) -> Result<Vec<LineItem>, Error> {
+    let mut repriced = Vec::new(env);
+    for mut item in items.iter() {
+        item.amount = accounting::apply_bps(subtotal, item.bps as i128)?;
+        repriced.push_back(item);
+    }
+    Ok(repriced)
+}
+
+pub fn sum(items: &Vec<LineItem>, kind: TaxKind) -> Result<i128, Error> {
+    items
+        .iter()
+        .filter(|item| item.kind == kind)
+        .try_fold(0, |sum, item| math::add(sum, item.amount))
+}
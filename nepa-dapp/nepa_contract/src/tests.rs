@@ -0,0 +1,5069 @@
+extern crate std;
+
+use std::rc::Rc;
+
+use ed25519_dalek::{Signer, SigningKey};
+
+use soroban_sdk::testutils::{Address as _, Events, Ledger};
+use soroban_sdk::xdr::{
+    ContractDataDurability, LedgerKey, LedgerKeyContractData, ScAddress, ScVal, ToXdr,
+};
+use soroban_sdk::{
+    contract, contractimpl, map, token, vec, Address, Bytes, BytesN, Env, IntoVal, Map, String,
+    Symbol, TryFromVal, Val, Vec,
+};
+
+use crate::accounting::AccountingKey;
+use crate::billing::BillingKey;
+use crate::mock_oracle::MockPriceOracleClient;
+use crate::oracle::{OracleKey, OracleManager};
+use crate::storage::PERSISTENT_TTL_EXTEND_TO;
+use crate::testutils::{Simulation, PRICE_DECIMALS, START_TIMESTAMP, TOKEN_PAIR, TOKEN_PRICE};
+use crate::version::STORAGE_SCHEMA_VERSION;
+use crate::{
+    AdminAction, AnomalyConfig, AnomalyKind, AuditAction, BountyConfig, Consumption, DebtTolerance,
+    DelegatedPayment, DelegatedPoints, DepositConfig, DisconnectionReason, DisputeStatus,
+    DunningConfig, EmergencyCreditConfig, Error, EscrowStatus, ExternalPriceSource, FallbackChain,
+    FeeConfig, GroupSplit, InputBounds, KeeperConfig, LineItem, LoyaltyConfig, MeteredUnit,
+    NepaBillingContract, NepaBillingContractClient, OracleConfig, PaymentRecord, PlanStatus,
+    PoolShare, PriceFeed, PriceSource, RateKey, ReconciliationStatus, RecoveryConfig,
+    RetentionConfig, RoundingMode, RoundingPolicy, RoundingUnit, Sep40Asset, SplitConfig,
+    SponsorshipConfig, StorageEntry, SubsidyScheme, SupplyPhase, SwapConfig, TariffOp, TariffTier,
+    TaxComponent, TaxKind, TimelockChange, TokenConfig, TouBand, TouSchedule, TouWindow,
+    UnusedBlockPolicy, UpdateOutcome, UpdateSchedule, UtilityUsage, VelocityConfig, Violation,
+    WholesalePool,
+};
+
+// Ceilings for a repeat `pay_bill_with_oracle` on a plain meter. They leave
+// some headroom over the measured cost; a change that crosses them adds reads
+// to the hot path and should say why. The footprint includes the closed-account
+// check every payment makes, the payment's entry in the sequence index and the
+// payer's receipt count beside the indexed receipt id.
+const PAYMENT_CPU_CEILING: u64 = 1_100_000;
+const PAYMENT_FOOTPRINT_CEILING: usize = 28;
+
+struct Setup {
+    env: Env,
+    contract: Address,
+    client: NepaBillingContractClient<'static>,
+    token: Address,
+    payer: Address,
+}
+
+fn setup() -> Setup {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger()
+        .with_mut(|ledger| ledger.timestamp = 1_700_000_000);
+    let contract = env.register_contract(None, NepaBillingContract);
+    let client = NepaBillingContractClient::new(&env, &contract);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+
+    let token = env.register_stellar_asset_contract(admin);
+    let payer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&payer, &1_000_000_000_000);
+    let pair = String::from_str(&env, "USDC/NGN");
+    client.update_price_feed(&pair, &15_000_000_000, &7);
+    client.add_accepted_token(
+        &token,
+        &TokenConfig {
+            decimals: 7,
+            oracle_pair: pair,
+            min_payment: 1,
+        },
+    );
+    Setup {
+        env,
+        contract,
+        client,
+        token,
+        payer,
+    }
+}
+
+fn reset_footprint(env: &Env) {
+    env.host()
+        .with_mut_storage(|storage| {
+            storage.footprint = Default::default();
+            Ok(())
+        })
+        .unwrap();
+}
+
+fn footprint_len(env: &Env) -> usize {
+    env.host()
+        .with_mut_storage(|storage| Ok(storage.footprint.0.len()))
+        .unwrap()
+}
+
+#[test]
+fn repeat_payment_stays_within_budget() {
+    let s = setup();
+    let meter = String::from_str(&s.env, "METER-1");
+    s.client
+        .pay_bill_with_oracle(&s.payer, &s.token, &meter, &10_000_000);
+
+    reset_footprint(&s.env);
+    s.env.budget().reset_default();
+    s.client
+        .pay_bill_with_oracle(&s.payer, &s.token, &meter, &10_000_000);
+
+    let cpu = s.env.budget().cpu_instruction_cost();
+    assert!(
+        cpu <= PAYMENT_CPU_CEILING,
+        "payment used {cpu} instructions"
+    );
+    let entries = footprint_len(&s.env);
+    assert!(
+        entries <= PAYMENT_FOOTPRINT_CEILING,
+        "payment touched {entries} ledger entries"
+    );
+}
+
+#[test]
+fn indexes_stay_out_of_instance_storage() {
+    let s = setup();
+    let ids = s.client.get_price_feed_ids();
+    assert_eq!(ids, vec![&s.env, String::from_str(&s.env, "USDC/NGN")]);
+    let in_instance = s.env.as_contract(&s.contract, || {
+        s.env.storage().instance().has(&OracleKey::PriceFeedIndex)
+    });
+    assert!(!in_instance);
+}
+
+#[test]
+fn legacy_instance_index_is_moved_on_next_write() {
+    let s = setup();
+    let legacy = String::from_str(&s.env, "XLM/NGN");
+    s.env.as_contract(&s.contract, || {
+        let storage = s.env.storage();
+        storage.persistent().remove(&OracleKey::PriceFeedIndex);
+        storage
+            .instance()
+            .set(&OracleKey::PriceFeedIndex, &vec![&s.env, legacy.clone()]);
+    });
+    assert_eq!(s.client.get_price_feed_ids(), vec![&s.env, legacy.clone()]);
+
+    let added = String::from_str(&s.env, "EURC/NGN");
+    s.client.update_price_feed(&added, &16_000_000_000, &7);
+    assert_eq!(s.client.get_price_feed_ids(), vec![&s.env, legacy, added]);
+    let in_instance = s.env.as_contract(&s.contract, || {
+        s.env.storage().instance().has(&OracleKey::PriceFeedIndex)
+    });
+    assert!(!in_instance);
+}
+
+// A payment as stored before fees were charged.
+fn schema_2_record(env: &Env, payer: &Address, token: &Address) -> Map<Symbol, Val> {
+    map![
+        env,
+        (Symbol::new(env, "payer"), payer.into_val(env)),
+        (Symbol::new(env, "token"), token.into_val(env)),
+        (Symbol::new(env, "amount"), 10_000_000_i128.into_val(env)),
+        (
+            Symbol::new(env, "normalized_amount"),
+            15_000_000_000_i128.into_val(env)
+        ),
+        (Symbol::new(env, "rate"), TOKEN_PRICE.into_val(env)),
+        (Symbol::new(env, "rate_decimals"), 7_u32.into_val(env)),
+        (
+            Symbol::new(env, "price_source"),
+            PriceSource::StaticRate.into_val(env)
+        ),
+        (Symbol::new(env, "timestamp"), START_TIMESTAMP.into_val(env)),
+    ]
+}
+
+#[test]
+fn payments_stored_in_older_layouts_still_read() {
+    let s = setup();
+    let meter_id = String::from_str(&s.env, "METER-1");
+    let record = schema_2_record(&s.env, &s.payer, &s.token);
+    let mut oldest = record.clone();
+    oldest.remove(Symbol::new(&s.env, "price_source"));
+    s.env.as_contract(&s.contract, || {
+        let persistent = s.env.storage().persistent();
+        persistent.set(&AccountingKey::Payment(meter_id.clone(), 0), &oldest);
+        persistent.set(&AccountingKey::Payment(meter_id.clone(), 1), &record);
+    });
+
+    let expected = PaymentRecord {
+        payer: s.payer.clone(),
+        token: s.token.clone(),
+        amount: 10_000_000,
+        normalized_amount: 15_000_000_000,
+        rate: TOKEN_PRICE,
+        rate_decimals: 7,
+        price_source: PriceSource::StaticRate,
+        timestamp: START_TIMESTAMP,
+        fee_amount: 0,
+        fees: vec![&s.env],
+        sequence: 0,
+    };
+    assert_eq!(s.client.get_payment(&meter_id, &1), Some(expected.clone()));
+    let mut unsequenced = record;
+    unsequenced.set(Symbol::new(&s.env, "fee_amount"), 0_i128.into_val(&s.env));
+    unsequenced.set(
+        Symbol::new(&s.env, "fees"),
+        Vec::<LineItem>::new(&s.env).into_val(&s.env),
+    );
+    s.env.as_contract(&s.contract, || {
+        s.env
+            .storage()
+            .persistent()
+            .set(&AccountingKey::Payment(meter_id.clone(), 2), &unsequenced);
+    });
+    assert_eq!(s.client.get_payment(&meter_id, &2), Some(expected.clone()));
+    assert_eq!(
+        s.client.get_payment(&meter_id, &0),
+        Some(PaymentRecord {
+            price_source: PriceSource::PushFeed,
+            ..expected
+        })
+    );
+}
+
+#[test]
+fn bills_keep_the_rate_context_they_were_issued_under() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let feed_id = sim.string("LAGOS-TEMP");
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 1_000),
+        TariffOp::FeedAdjust(feed_id.clone(), 30, 100),
+    ];
+    sim.client.set_utility_rate(&rate_id, &formula);
+    sim.client.update_data_feed(&feed_id, &35, &0);
+    let meter_id = sim.string("METER-1");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &100);
+
+    // Later rate, feed and price changes leave the issued bill as it was.
+    sim.advance_and_refresh(60);
+    sim.client.set_utility_rate(
+        &rate_id,
+        &vec![
+            &sim.env,
+            TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 2_000),
+        ],
+    );
+    sim.client.update_data_feed(&feed_id, &40, &0);
+    sim.set_price(TOKEN_PAIR, TOKEN_PRICE * 2);
+
+    let snapshot = sim
+        .client
+        .get_bill(&meter_id, &202_311)
+        .unwrap()
+        .rate_snapshot;
+    assert_eq!(snapshot.formula, formula);
+    assert_eq!(snapshot.rate_updated, START_TIMESTAMP);
+    assert_eq!(snapshot.estimated_per_kwh, 0);
+    assert_eq!(snapshot.inputs.get(Symbol::new(&sim.env, "kwh")), Some(100));
+    let feed = snapshot.feeds.get(feed_id).unwrap();
+    assert_eq!((feed.value, feed.last_updated), (35, START_TIMESTAMP));
+    let price = snapshot.fx_rates.get(sim.token.clone()).unwrap();
+    assert_eq!(
+        (price.price, price.last_updated),
+        (TOKEN_PRICE, START_TIMESTAMP)
+    );
+    assert_eq!(snapshot.taken_at, START_TIMESTAMP);
+}
+
+#[test]
+fn bills_issued_before_rate_snapshots_still_read() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let bill = sim.client.issue_bill(&meter_id, &202311, &rate_id, &150);
+
+    let key = BillingKey::Bill(meter_id.clone(), 202311);
+    sim.env.as_contract(&sim.contract, || {
+        let persistent = sim.env.storage().persistent();
+        let mut stored: Map<Symbol, Val> = persistent.get(&key).unwrap();
+        for field in ["rate_snapshot", "rounding", "unit"] {
+            stored.remove(Symbol::new(&sim.env, field));
+        }
+        persistent.set(&key, &stored);
+    });
+
+    let read = sim.client.get_bill(&meter_id, &202311).unwrap();
+    assert_eq!(read.amount, bill.amount);
+    assert_eq!(read.line_items, bill.line_items);
+    assert_eq!(read.unit, MeteredUnit::Kwh);
+    assert_eq!(read.rate_snapshot.taken_at, 0);
+    assert!(read.rate_snapshot.formula.is_empty());
+}
+
+#[test]
+fn oracle_stats_combine_the_targeted_views() {
+    let sim = Simulation::new();
+    let feed_id = String::from_str(&sim.env, TOKEN_PAIR);
+    let unscheduled = sim.client.get_schedule(&feed_id);
+    assert_eq!(unscheduled.interval_seconds, 0);
+    assert!(!unscheduled.due);
+
+    sim.client.set_keeper_config(&KeeperConfig {
+        token: sim.token.clone(),
+        reward: 1_000_000,
+        update_interval_seconds: 600,
+    });
+    sim.client.set_feed_heartbeat(&feed_id, &900);
+    sim.advance(1_000);
+
+    let schedule = sim.client.get_schedule(&feed_id);
+    assert_eq!(schedule.interval_seconds, 600);
+    assert_eq!(schedule.next_update_at, START_TIMESTAMP + 600);
+    assert!(schedule.due);
+    let reliability = sim.client.get_reliability_summary(&feed_id);
+    assert_eq!(reliability.heartbeat_seconds, 900);
+    assert!(reliability.missed_heartbeat);
+    let stats = sim.client.get_oracle_stats(&feed_id);
+    assert_eq!(stats.cost, sim.client.get_cost_summary(&feed_id));
+    assert_eq!(stats.reliability, reliability);
+    assert_eq!(stats.schedule, schedule);
+}
+
+#[test]
+fn update_schedule_stands_without_keepers() {
+    let sim = Simulation::new();
+    assert_eq!(sim.client.get_update_schedule(), None);
+    sim.client.set_update_schedule(&3_600, &86_400);
+    assert_eq!(
+        sim.client.get_update_schedule(),
+        Some(UpdateSchedule {
+            price_feed_interval: 3_600,
+            utility_rate_interval: 86_400,
+        })
+    );
+
+    let config = KeeperConfig {
+        token: sim.token.clone(),
+        reward: 1_000_000,
+        update_interval_seconds: 30,
+    };
+    let too_short = sim.client.try_set_keeper_config(&config);
+    assert_eq!(too_short, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_keeper_config(&KeeperConfig {
+        update_interval_seconds: 600,
+        ..config
+    });
+    let schedule = sim.client.get_update_schedule().unwrap();
+    assert_eq!(schedule.price_feed_interval, 600);
+    assert_eq!(schedule.utility_rate_interval, 86_400);
+}
+
+#[test]
+fn only_payments_flag_a_missed_heartbeat() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let feed_id = String::from_str(&sim.env, TOKEN_PAIR);
+    sim.client.set_fallback_price(&feed_id, &TOKEN_PRICE, &7);
+    sim.client.set_fallback_chain(
+        &feed_id,
+        &FallbackChain {
+            sources: vec![&sim.env, PriceSource::PushFeed, PriceSource::StaticRate],
+            cache_max_age_seconds: 0,
+        },
+    );
+    sim.client.set_feed_heartbeat(&feed_id, &600);
+    sim.advance(700);
+    let flagged = || {
+        sim.env.as_contract(&sim.contract, || {
+            sim.env
+                .storage()
+                .instance()
+                .has(&OracleKey::FeedUnhealthy(feed_id.clone()))
+        })
+    };
+
+    let (_, source) = sim.client.resolve_payment_price(&feed_id);
+    assert_eq!(source, PriceSource::StaticRate);
+    assert!(!flagged());
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000_000);
+    assert!(flagged());
+}
+
+#[test]
+fn data_feeds_keep_their_own_reliability() {
+    let sim = Simulation::new();
+    let feed_id = String::from_str(&sim.env, TOKEN_PAIR);
+    sim.client.update_data_feed(&feed_id, &31, &0);
+    assert_eq!(
+        sim.client
+            .get_data_feed_reliability(&feed_id)
+            .applied_updates,
+        1
+    );
+    let price = sim.client.get_feed_reliability(&feed_id);
+    sim.client.update_data_feed(&feed_id, &32, &0);
+    assert_eq!(sim.client.get_feed_reliability(&feed_id), price);
+}
+
+#[test]
+fn simulation_bills_and_pays_a_sample_meter() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    assert_eq!(sim.client.get_meter_owner(&meter_id), Some(owner.clone()));
+
+    let bill = sim.client.issue_bill(&meter_id, &202311, &rate_id, &150);
+    assert_eq!(bill.amount, 150_000_000_000);
+    // 15,000 NGN at 1,500 NGN per token.
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &100_000_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+    assert_eq!(sim.token_balance(&owner), 900_000_000);
+}
+
+#[test]
+fn rates_need_a_registered_rate_id() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let formula = sim.client.get_utility_rate(&rate_id).unwrap().formula;
+
+    let unregistered = String::from_str(&sim.env, "electricity/abuja/a");
+    let set = sim.client.try_set_utility_rate(&unregistered, &formula);
+    assert_eq!(set, Err(Ok(Error::InvalidInput)));
+    let scheduled = sim.client.try_add_utility_rate_scheduled(
+        &unregistered,
+        &formula,
+        &(START_TIMESTAMP + 86_400),
+    );
+    assert_eq!(scheduled, Err(Ok(Error::InvalidInput)));
+    let batch = sim
+        .client
+        .update_utility_rates_batch(&vec![&sim.env, (unregistered.clone(), formula)]);
+    assert_eq!(
+        batch,
+        vec![&sim.env, UpdateOutcome::Failed(Error::InvalidInput as u32)]
+    );
+}
+
+#[test]
+fn tariff_settings_wait_for_the_timelock() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let delay = sim.client.queue_change(&TimelockChange::Delay(86_400));
+    sim.client.execute_change(&delay);
+
+    let schedule = TouSchedule {
+        utc_offset_minutes: 60,
+        bands: vec![
+            &sim.env,
+            TouBand {
+                start_hour: 18,
+                end_hour: 22,
+                window: TouWindow::Peak,
+            },
+        ],
+    };
+    let direct = sim.client.try_set_tou_schedule(&rate_id, &schedule);
+    assert_eq!(direct, Err(Ok(Error::TimelockRequired)));
+    let unit = sim
+        .client
+        .try_set_rate_unit(&rate_id, &MeteredUnit::CubicMetre);
+    assert_eq!(unit, Err(Ok(Error::TimelockRequired)));
+    let estimated = sim.client.try_set_estimated_rate(&rate_id, &1_000_000_000);
+    assert_eq!(estimated, Err(Ok(Error::TimelockRequired)));
+
+    let change_id = sim.client.queue_change(&TimelockChange::TouSchedule(
+        rate_id.clone(),
+        schedule.clone(),
+    ));
+    let early = sim.client.try_execute_change(&change_id);
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+    sim.advance(86_400);
+    sim.client.execute_change(&change_id);
+    assert_eq!(sim.client.get_tou_schedule(&rate_id), Some(schedule));
+}
+
+#[test]
+fn batch_updates_apply_each_entry_on_its_own() {
+    let sim = Simulation::new();
+    sim.set_price("XLM/NGN", 2_000_000_000);
+    sim.client.set_oracle_config(&OracleConfig {
+        max_deviation_bps: 1_000,
+        ..sim.client.get_oracle_config()
+    });
+    sim.advance(60);
+    let now = sim.env.ledger().timestamp();
+    let calm = TOKEN_PRICE * 105 / 100;
+    let updates = vec![
+        &sim.env,
+        (sim.string(TOKEN_PAIR), calm, now),
+        (sim.string("XLM/NGN"), 4_000_000_000, now),
+        (sim.string("EUR/NGN"), 16_000_000_000, now),
+        (sim.string("XLM/NGN"), 2_000_000_000, now + 1),
+    ];
+    let outcomes = sim.client.update_price_feeds_batch(&updates);
+    assert_eq!(
+        outcomes,
+        vec![
+            &sim.env,
+            UpdateOutcome::Applied,
+            UpdateOutcome::Flagged,
+            UpdateOutcome::Failed(Error::PriceFeedNotFound as u32),
+            UpdateOutcome::Failed(Error::StalePriceFeed as u32),
+        ]
+    );
+    assert_eq!(
+        sim.client
+            .get_price_feed(&sim.string(TOKEN_PAIR))
+            .unwrap()
+            .price,
+        calm
+    );
+    assert_eq!(
+        sim.client
+            .get_price_feed(&sim.string("XLM/NGN"))
+            .unwrap()
+            .price,
+        2_000_000_000
+    );
+
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 1_200_000_000),
+    ];
+    let rates = vec![
+        &sim.env,
+        (rate_id.clone(), Vec::new(&sim.env)),
+        (rate_id.clone(), formula.clone()),
+    ];
+    let outcomes = sim.client.update_utility_rates_batch(&rates);
+    assert_eq!(
+        outcomes,
+        vec![
+            &sim.env,
+            UpdateOutcome::Failed(Error::InvalidTariff as u32),
+            UpdateOutcome::Applied,
+        ]
+    );
+    assert_eq!(
+        sim.client.get_utility_rate(&rate_id).unwrap().formula,
+        formula
+    );
+}
+
+#[test]
+fn rate_batches_go_through_multisig() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let signer = Address::generate(&sim.env);
+    sim.client
+        .configure_multisig(&vec![&sim.env, signer.clone()], &1);
+
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 1_200_000_000),
+    ];
+    let rates = vec![&sim.env, (rate_id.clone(), formula.clone())];
+    let direct = sim.client.try_update_utility_rates_batch(&rates);
+    assert_eq!(direct, Err(Ok(Error::MultisigRequired)));
+
+    let proposal_id = sim
+        .client
+        .propose_action(&signer, &AdminAction::SetUtilityRates(rates));
+    sim.client.execute_action(&proposal_id);
+    assert_eq!(
+        sim.client.get_utility_rate(&rate_id).unwrap().formula,
+        formula
+    );
+}
+
+#[test]
+fn scheduled_rates_can_be_queued() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let delay = sim.client.queue_change(&TimelockChange::Delay(86_400));
+    sim.client.execute_change(&delay);
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 1_200_000_000),
+    ];
+
+    // Due before the delay runs out, it could never be executed.
+    let too_soon = sim.client.try_queue_change(&TimelockChange::ScheduledRate(
+        rate_id.clone(),
+        formula.clone(),
+        START_TIMESTAMP + 86_400,
+    ));
+    assert_eq!(too_soon, Err(Ok(Error::InvalidInput)));
+
+    let effective_from = START_TIMESTAMP + 2 * 86_400;
+    let change_id = sim.client.queue_change(&TimelockChange::ScheduledRate(
+        rate_id.clone(),
+        formula.clone(),
+        effective_from,
+    ));
+    sim.advance(86_400);
+    sim.client.execute_change(&change_id);
+    let scheduled = sim.client.get_scheduled_rates(&rate_id);
+    assert_eq!(scheduled.len(), 1);
+    assert_eq!(scheduled.get_unchecked(0).formula, formula);
+    assert_eq!(scheduled.get_unchecked(0).effective_from, effective_from);
+}
+
+#[test]
+fn deprecated_feeds_and_rates_serve_until_their_sunset_then_go() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &payer, &rate_id, "a");
+    let feed_id = sim.string(TOKEN_PAIR);
+    let sunset = START_TIMESTAMP + 600;
+    let past = sim
+        .client
+        .try_deprecate_price_feed(&feed_id, &(START_TIMESTAMP - 1));
+    assert_eq!(past, Err(Ok(Error::InvalidInput)));
+    sim.client.deprecate_price_feed(&feed_id, &sunset);
+    sim.client.deprecate_utility_rate(&rate_id, &sunset);
+    assert_eq!(sim.client.get_feed_sunset(&feed_id), Some(sunset));
+
+    // Both keep working until the sunset, and neither can be removed yet.
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    let early = sim.client.try_remove_price_feed(&feed_id);
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+    let early = sim.client.try_remove_utility_rate(&rate_id);
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+
+    sim.advance(600);
+    let updated = sim
+        .client
+        .try_update_price_feed(&feed_id, &TOKEN_PRICE, &PRICE_DECIMALS);
+    assert_eq!(updated, Err(Ok(Error::Deprecated)));
+    let billed = sim
+        .client
+        .try_issue_bill(&meter_id, &202_312, &rate_id, &10);
+    assert_eq!(billed, Err(Ok(Error::Deprecated)));
+    let paid = sim
+        .client
+        .try_pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    assert_eq!(paid, Err(Ok(Error::Deprecated)));
+
+    sim.client.remove_price_feed(&feed_id);
+    assert_eq!(sim.client.get_price_feed(&feed_id), None);
+    assert!(!sim.client.get_price_feed_ids().contains(&feed_id));
+    sim.client.remove_utility_rate(&rate_id);
+    assert_eq!(sim.client.get_utility_rate(&rate_id), None);
+}
+
+#[test]
+fn rates_are_retired_through_multisig() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let signer = Address::generate(&sim.env);
+    sim.client
+        .configure_multisig(&vec![&sim.env, signer.clone()], &1);
+
+    let direct = sim
+        .client
+        .try_deprecate_utility_rate(&rate_id, &START_TIMESTAMP);
+    assert_eq!(direct, Err(Ok(Error::MultisigRequired)));
+    let deprecate = sim.client.propose_action(
+        &signer,
+        &AdminAction::DeprecateUtilityRate(rate_id.clone(), START_TIMESTAMP),
+    );
+    sim.client.execute_action(&deprecate);
+
+    let direct = sim.client.try_remove_utility_rate(&rate_id);
+    assert_eq!(direct, Err(Ok(Error::MultisigRequired)));
+    let remove = sim
+        .client
+        .propose_action(&signer, &AdminAction::RemoveUtilityRate(rate_id.clone()));
+    sim.client.execute_action(&remove);
+    assert_eq!(sim.client.get_utility_rate(&rate_id), None);
+}
+
+#[test]
+fn feeds_and_rates_are_listed_a_page_at_a_time() {
+    let sim = Simulation::new();
+    sim.set_price("XLM/NGN", 2_000_000_000);
+    sim.set_price("EUR/NGN", 16_000_000_000);
+    let sunset = START_TIMESTAMP + 1_000;
+    sim.client
+        .deprecate_price_feed(&sim.string("XLM/NGN"), &sunset);
+
+    let feeds = sim.client.list_price_feeds(&0, &10);
+    assert_eq!(feeds.len(), 3);
+    assert_eq!(feeds.get_unchecked(0).feed_id, sim.string(TOKEN_PAIR));
+    let page = sim.client.list_price_feeds(&1, &1);
+    assert_eq!(page.len(), 1);
+    let listed = page.get_unchecked(0);
+    assert_eq!(listed.feed_id, sim.string("XLM/NGN"));
+    assert_eq!((listed.feed.price, listed.sunset), (2_000_000_000, sunset));
+    assert_eq!(feeds.get_unchecked(2).sunset, 0);
+    assert!(sim.client.list_price_feeds(&3, &10).is_empty());
+
+    let first = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let second = sim.register_rate("electricity", "lagos", "b", 900_000_000);
+    sim.client.deprecate_utility_rate(&second, &sunset);
+    let rates = sim.client.list_utility_rates(&0, &10);
+    assert_eq!(rates.len(), 2);
+    let listed = rates.get_unchecked(0);
+    assert_eq!(listed.rate_id, first);
+    assert_eq!(
+        (listed.unit, listed.last_updated),
+        (MeteredUnit::Kwh, START_TIMESTAMP)
+    );
+    assert_eq!(listed.sunset, 0);
+    let page = sim.client.list_utility_rates(&1, &5);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get_unchecked(0).sunset, sunset);
+    assert!(sim.client.list_utility_rates(&0, &0).is_empty());
+}
+
+#[test]
+fn fee_collector_changes_need_multisig() {
+    let sim = Simulation::new();
+    let collector = Address::generate(&sim.env);
+    let config = FeeConfig {
+        collector: collector.clone(),
+        fx_spread_bps: 50,
+        service_fee_bps: 25,
+    };
+    sim.client.set_fee_config(&config);
+    let signer = Address::generate(&sim.env);
+    sim.client
+        .configure_multisig(&vec![&sim.env, signer.clone()], &1);
+
+    // The admin may still retune the rates, but not redirect the fees.
+    let retuned = FeeConfig {
+        fx_spread_bps: 40,
+        ..config
+    };
+    sim.client.set_fee_config(&retuned);
+    let redirected = FeeConfig {
+        collector: Address::generate(&sim.env),
+        ..retuned
+    };
+    let direct = sim.client.try_set_fee_config(&redirected);
+    assert_eq!(direct, Err(Ok(Error::MultisigRequired)));
+
+    let proposal_id = sim.client.propose_action(
+        &signer,
+        &AdminAction::SetFeeConfig(redirected.collector.clone(), 40, 25),
+    );
+    sim.client.execute_action(&proposal_id);
+    assert_eq!(sim.client.get_fee_config(), Some(redirected));
+}
+
+#[test]
+fn bill_breakdown_itemises_rounding() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_001);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.set_rounding_policy(
+        &String::from_str(&sim.env, "lagos"),
+        &RoundingPolicy {
+            mode: RoundingMode::Up,
+            unit: RoundingUnit::Naira,
+        },
+    );
+
+    let bill = sim.client.issue_bill(&meter_id, &202311, &rate_id, &150);
+    assert_eq!(bill.amount, 150_010_000_000);
+    let breakdown = sim.client.get_bill_breakdown(&meter_id, &202311).unwrap();
+    assert_eq!(breakdown.rounding, 9_999_850);
+    assert_eq!(breakdown.subtotal, 150_000_000_150);
+    assert_eq!(
+        breakdown.subtotal + breakdown.taxes + breakdown.levies + breakdown.rounding,
+        breakdown.total
+    );
+    let rounding = breakdown.line_items.last().unwrap();
+    assert_eq!(rounding.kind, TaxKind::Rounding);
+    assert_eq!(rounding.amount, 9_999_850);
+}
+
+#[test]
+fn simulation_advance_ages_feeds_until_refreshed() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+
+    sim.advance(7_200);
+    assert_eq!(sim.env.ledger().timestamp(), START_TIMESTAMP + 7_200);
+    let stale = sim
+        .client
+        .try_pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(stale, Err(Ok(Error::StalePriceFeed)));
+
+    sim.advance_and_refresh(60);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), -15_000_000_000);
+}
+
+// USDC/NGN priced by the push feed first and the mock oracle second, with a
+// static rate of 1,000 NGN as the last resort.
+fn external_chain(sim: &Simulation) -> (MockPriceOracleClient<'static>, Sep40Asset) {
+    let oracle = sim.register_mock_oracle();
+    let asset = Sep40Asset::Stellar(sim.token.clone());
+    let pair = String::from_str(&sim.env, TOKEN_PAIR);
+    sim.client.set_external_price_source(
+        &pair,
+        &ExternalPriceSource {
+            oracle: oracle.address.clone(),
+            asset: asset.clone(),
+        },
+    );
+    sim.client.set_fallback_price(&pair, &10_000_000_000, &7);
+    sim.client.set_fallback_chain(
+        &pair,
+        &FallbackChain {
+            sources: vec![
+                &sim.env,
+                PriceSource::PushFeed,
+                PriceSource::ExternalOracle,
+                PriceSource::StaticRate,
+            ],
+            cache_max_age_seconds: 0,
+        },
+    );
+    (oracle, asset)
+}
+
+fn pay_once(sim: &Simulation, meter_id: &str) -> PaymentRecord {
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = String::from_str(&sim.env, meter_id);
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    sim.client.get_payment(&meter_id, &index).unwrap()
+}
+
+#[test]
+fn stale_push_feed_falls_back_to_mock_oracle() {
+    let sim = Simulation::new();
+    let (oracle, asset) = external_chain(&sim);
+    sim.advance(7_200);
+    oracle.set_price(&asset, &16_000_000_000, &sim.env.ledger().timestamp());
+
+    let record = pay_once(&sim, "METER-1");
+    assert_eq!(record.price_source, PriceSource::ExternalOracle);
+    assert_eq!(record.rate, 16_000_000_000);
+}
+
+#[test]
+fn failing_or_stale_mock_oracle_falls_through_to_static_rate() {
+    let sim = Simulation::new();
+    let (oracle, asset) = external_chain(&sim);
+    sim.advance(7_200);
+
+    oracle.set_price(&asset, &16_000_000_000, &sim.env.ledger().timestamp());
+    oracle.set_failing(&true);
+    assert_eq!(
+        pay_once(&sim, "METER-1").price_source,
+        PriceSource::StaticRate
+    );
+
+    oracle.set_failing(&false);
+    oracle.set_price(&asset, &16_000_000_000, &START_TIMESTAMP);
+    assert_eq!(
+        pay_once(&sim, "METER-2").price_source,
+        PriceSource::StaticRate
+    );
+
+    oracle.clear_price(&asset);
+    let record = pay_once(&sim, "METER-3");
+    assert_eq!(record.price_source, PriceSource::StaticRate);
+    assert_eq!(record.rate, 10_000_000_000);
+}
+
+#[test]
+fn fresh_push_feed_is_preferred_over_mock_oracle() {
+    let sim = Simulation::new();
+    let (oracle, asset) = external_chain(&sim);
+    oracle.set_price(&asset, &16_000_000_000, &sim.env.ledger().timestamp());
+
+    let record = pay_once(&sim, "METER-1");
+    assert_eq!(record.price_source, PriceSource::PushFeed);
+    assert_eq!(record.rate, TOKEN_PRICE);
+}
+
+#[test]
+fn payers_crossing_the_velocity_thresholds_are_flagged() {
+    let sim = Simulation::new();
+    sim.client.set_velocity_config(&VelocityConfig {
+        max_payments_per_hour: 5,
+        max_distinct_meters: 2,
+        require_attestation: false,
+        attestor: sim.admin.clone(),
+    });
+    let payer = sim.customer(1_000_000_000);
+    for meter in ["METER-1", "METER-2"] {
+        let meter_id = sim.string(meter);
+        sim.client
+            .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    }
+    assert!(!sim.client.is_payer_flagged(&payer));
+
+    let meter_id = sim.string("METER-3");
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    assert!(sim.client.is_payer_flagged(&payer));
+    let activity = sim.client.get_payer_activity(&payer).unwrap();
+    assert_eq!(activity.payment_count, 3);
+
+    sim.client.clear_payer_flag(&payer);
+    assert!(!sim.client.is_payer_flagged(&payer));
+}
+
+#[test]
+fn only_allowlisted_tokens_are_accepted() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let other = sim.env.register_stellar_asset_contract(sim.admin.clone());
+    let unlisted = sim
+        .client
+        .try_pay_bill_with_oracle(&payer, &other, &meter_id, &10_000_000);
+    assert_eq!(unlisted, Err(Ok(Error::UnsupportedToken)));
+
+    // The configured decimals must be the token's own.
+    let mismatched = TokenConfig {
+        decimals: 6,
+        oracle_pair: sim.string(TOKEN_PAIR),
+        min_payment: 1,
+    };
+    let added = sim.client.try_add_accepted_token(&other, &mismatched);
+    assert_eq!(added, Err(Ok(Error::InvalidConfig)));
+    assert_eq!(
+        sim.client.list_accepted_tokens(),
+        vec![&sim.env, sim.token.clone()]
+    );
+
+    sim.client.remove_accepted_token(&sim.token);
+    let removed = sim
+        .client
+        .try_pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(removed, Err(Ok(Error::UnsupportedToken)));
+    assert_eq!(sim.client.get_accepted_token(&sim.token), None);
+}
+
+#[test]
+fn payments_are_valued_in_ngn_at_the_price_paid() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    let record = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(record.rate, TOKEN_PRICE);
+    assert_eq!(record.normalized_amount, 15_000_000_000);
+
+    sim.set_price(TOKEN_PAIR, 16_000_000_000);
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(sim.client.get_total_paid_ngn(&meter_id), 31_000_000_000);
+}
+
+#[test]
+fn writes_wait_out_a_maintenance_window() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let backwards = sim
+        .client
+        .try_schedule_maintenance(&(START_TIMESTAMP + 600), &START_TIMESTAMP);
+    assert_eq!(backwards, Err(Ok(Error::InvalidConfig)));
+    sim.client
+        .schedule_maintenance(&(START_TIMESTAMP + 600), &(START_TIMESTAMP + 1_200));
+    assert!(!sim.client.is_in_maintenance());
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+
+    sim.advance(600);
+    assert!(sim.client.is_in_maintenance());
+    let paid = sim
+        .client
+        .try_pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(paid, Err(Ok(Error::MaintenanceWindow)));
+    assert_eq!(sim.client.get_meter_summary(&meter_id).payment_count, 1);
+
+    sim.advance(600);
+    assert!(!sim.client.is_in_maintenance());
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(sim.client.get_meter_summary(&meter_id).payment_count, 2);
+}
+
+#[test]
+fn payments_can_be_valued_at_the_twap() {
+    let sim = Simulation::new();
+    let pair = sim.string(TOKEN_PAIR);
+    sim.advance(600);
+    sim.set_price(TOKEN_PAIR, 16_000_000_000);
+    sim.advance(600);
+    assert_eq!(sim.client.get_twap(&pair, &1_200), 15_500_000_000);
+    // A window reaching past the first price starts at it.
+    assert_eq!(sim.client.get_twap(&pair, &7_200), 15_500_000_000);
+
+    let mut config = sim.client.get_oracle_config();
+    config.twap_window_seconds = 1_200;
+    sim.client.set_oracle_config(&config);
+    let record = pay_once(&sim, "METER-1");
+    assert_eq!(record.rate, 15_500_000_000);
+    assert_eq!(record.normalized_amount, 15_500_000_000);
+}
+
+#[test]
+fn version_reports_the_build() {
+    let sim = Simulation::new();
+    let info = sim.client.get_version();
+    assert_eq!(info.version, sim.string(env!("CARGO_PKG_VERSION")));
+    assert_eq!(info.schema_version, STORAGE_SCHEMA_VERSION);
+    assert!(info
+        .features
+        .contains(String::from_str(&sim.env, "embedded-oracle")));
+    assert_eq!(info.git_commit, sim.string(env!("NEPA_GIT_COMMIT")));
+}
+
+fn deprecated_calls(env: &Env) -> std::vec::Vec<(Symbol, Symbol)> {
+    let name = Symbol::new(env, "deprecated_call");
+    env.events()
+        .all()
+        .iter()
+        .filter_map(|(_, topics, data)| {
+            let topic = Symbol::try_from_val(env, &topics.get(0)?).ok()?;
+            let function = Symbol::try_from_val(env, &topics.get(1)?).ok()?;
+            let replacement = Symbol::try_from_val(env, &data).ok()?;
+            (topic == name).then_some((function, replacement))
+        })
+        .collect()
+}
+
+#[test]
+fn legacy_entry_points_still_work_and_announce_their_replacement() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    sim.client
+        .pay_bill(&payer, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(
+        deprecated_calls(&sim.env),
+        std::vec![(
+            Symbol::new(&sim.env, "pay_bill"),
+            Symbol::new(&sim.env, "pay_bill_with_oracle")
+        )]
+    );
+
+    assert_eq!(sim.client.get_total_paid(&meter_id), 10_000_000);
+    assert_eq!(
+        deprecated_calls(&sim.env).last(),
+        Some(&(
+            Symbol::new(&sim.env, "get_total_paid"),
+            Symbol::new(&sim.env, "get_meter_summary")
+        ))
+    );
+}
+
+#[test]
+fn stale_feeds_are_refused_unless_the_fallback_is_enabled() {
+    let sim = Simulation::new();
+    let mut config = sim.client.get_oracle_config();
+    sim.advance(config.max_age_seconds + 1);
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let stale = sim
+        .client
+        .try_pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(stale, Err(Ok(Error::StalePriceFeed)));
+
+    let pair = sim.string(TOKEN_PAIR);
+    sim.client.set_fallback_price(&pair, &14_000_000_000, &7);
+    config.use_fallback = true;
+    sim.client.set_oracle_config(&config);
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    let record = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(record.price_source, PriceSource::StaticRate);
+    assert_eq!(record.rate, 14_000_000_000);
+}
+
+#[test]
+fn each_price_feed_is_stored_on_its_own() {
+    let sim = Simulation::new();
+    let usdc = sim.string(TOKEN_PAIR);
+    let xlm = sim.string("XLM/NGN");
+    sim.set_price("XLM/NGN", 2_000_000_000);
+    assert_eq!(
+        sim.client.get_price_feed_ids(),
+        vec![&sim.env, usdc.clone(), xlm.clone()]
+    );
+
+    let stored = |feed_id: &String| {
+        sim.env.as_contract(&sim.contract, || {
+            sim.env
+                .storage()
+                .persistent()
+                .get::<_, PriceFeed>(&OracleKey::PriceFeed(feed_id.clone()))
+        })
+    };
+    let before = stored(&usdc);
+    sim.advance(60);
+    sim.set_price("XLM/NGN", 2_100_000_000);
+    assert_eq!(stored(&usdc), before);
+    assert_eq!(stored(&xlm).unwrap().price, 2_100_000_000);
+    assert_eq!(sim.client.get_price_feed(&xlm), stored(&xlm));
+}
+
+#[test]
+fn tariff_formulas_run_their_steps_in_order() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1);
+    let kwh = Symbol::new(&sim.env, "kwh");
+    let tiers = vec![
+        &sim.env,
+        TariffTier {
+            limit: 50,
+            rate: 10,
+        },
+        TariffTier {
+            limit: 100,
+            rate: 20,
+        },
+    ];
+    sim.client.set_utility_rate(
+        &rate_id,
+        &vec![
+            &sim.env,
+            TariffOp::Tiered(kwh.clone(), tiers),
+            TariffOp::FlatFee(500),
+            TariffOp::Multiplier(15_000),
+            TariffOp::Cap(3_000),
+        ],
+    );
+
+    // 50 * 10 + 50 * 20 + 20 * 20 + 500, times 1.5, capped.
+    let inputs = map![&sim.env, (kwh.clone(), 120_i128)];
+    assert_eq!(sim.client.calculate_bill(&rate_id, &inputs), 3_000);
+    let inputs = map![&sim.env, (kwh, 60_i128)];
+    assert_eq!(sim.client.calculate_bill(&rate_id, &inputs), 1_800);
+    let missing = map![&sim.env, (Symbol::new(&sim.env, "kvar"), 1_i128)];
+    let missing = sim.client.try_calculate_bill(&rate_id, &missing);
+    assert_eq!(missing, Err(Ok(Error::MissingTariffInput)));
+}
+
+#[test]
+fn sweeps_reconcile_against_recorded_fiat() {
+    let sim = Simulation::new();
+    pay_once(&sim, "METER-1");
+    let provider = Address::generate(&sim.env);
+    sim.client
+        .sweep_settlement(&provider, &sim.token, &10_000_000, &202_610);
+    assert_eq!(sim.token_balance(&provider), 10_000_000);
+    let pending = sim.client.get_reconciliation(&provider, &202_610);
+    assert_eq!(pending.swept_ngn, 15_000_000_000);
+    assert_eq!(pending.status, ReconciliationStatus::Pending);
+
+    let reference = BytesN::from_array(&sim.env, &[1; 32]);
+    sim.client
+        .record_fiat_settlement(&provider, &202_610, &14_950_000_000, &reference);
+    let report = sim.client.get_reconciliation(&provider, &202_610);
+    assert_eq!(report.difference, -50_000_000);
+    assert_eq!(report.status, ReconciliationStatus::Matched);
+    let again = sim
+        .client
+        .try_record_fiat_settlement(&provider, &202_610, &1, &reference);
+    assert_eq!(again, Err(Ok(Error::AlreadyExists)));
+
+    let extra = BytesN::from_array(&sim.env, &[2; 32]);
+    sim.client
+        .record_fiat_settlement(&provider, &202_610, &1_000_000_000, &extra);
+    let report = sim.client.get_reconciliation(&provider, &202_610);
+    assert_eq!(report.receipt_count, 2);
+    assert_eq!(report.status, ReconciliationStatus::Mismatched);
+}
+
+#[test]
+fn bumps_extend_an_entry_for_another_month() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+
+    let mut entries = Vec::new(&sim.env);
+    for _ in 0..21 {
+        entries.push_back(StorageEntry::Meter(meter_id.clone()));
+    }
+    let oversized = sim.client.try_bump_storage(&entries);
+    assert_eq!(oversized, Err(Ok(Error::InvalidInput)));
+
+    let key = AccountingKey::Payment(meter_id.clone(), index);
+    let written_at = sim.env.ledger().sequence();
+    assert_eq!(
+        live_until(&sim, &key),
+        written_at + PERSISTENT_TTL_EXTEND_TO
+    );
+    sim.advance(20 * 86_400);
+    let entries = vec![
+        &sim.env,
+        StorageEntry::MeterPayment(meter_id.clone(), index),
+    ];
+    sim.client.bump_storage(&entries);
+    let bumped_at = sim.env.ledger().sequence();
+    assert!(bumped_at > written_at);
+    assert_eq!(live_until(&sim, &key), bumped_at + PERSISTENT_TTL_EXTEND_TO);
+}
+
+// Last ledger the contract's persistent entry under `key` is live for.
+fn live_until(sim: &Simulation, key: &impl IntoVal<Env, Val>) -> u32 {
+    let key = LedgerKey::ContractData(LedgerKeyContractData {
+        contract: ScAddress::try_from(&sim.contract).unwrap(),
+        key: ScVal::try_from_val(&sim.env, &key.into_val(&sim.env)).unwrap(),
+        durability: ContractDataDurability::Persistent,
+    });
+    let budget = sim.env.host().budget_cloned();
+    sim.env
+        .host()
+        .with_mut_storage(|storage| {
+            let entry = storage.map.get::<Rc<LedgerKey>>(&Rc::new(key), &budget)?;
+            Ok(entry.and_then(|entry| entry.as_ref()?.1))
+        })
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn batches_pay_each_meter_with_one_transfer() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let first = sim.string("METER-1");
+    let second = sim.string("METER-2");
+    let bills = vec![
+        &sim.env,
+        (first.clone(), 10_000_000_i128),
+        (second.clone(), 30_000_000_i128),
+    ];
+    let indices = sim.client.pay_bills_batch(&payer, &sim.token, &bills);
+    assert_eq!(indices, vec![&sim.env, 0, 0]);
+    assert_eq!(sim.token_balance(&payer), 960_000_000);
+    assert_eq!(sim.client.get_total_paid_ngn(&first), 15_000_000_000);
+    assert_eq!(sim.client.get_total_paid_ngn(&second), 45_000_000_000);
+
+    let empty = sim
+        .client
+        .try_pay_bills_batch(&payer, &sim.token, &Vec::new(&sim.env));
+    assert_eq!(empty, Err(Ok(Error::InvalidInput)));
+    let mut oversized = Vec::new(&sim.env);
+    for _ in 0..26 {
+        oversized.push_back((first.clone(), 1_i128));
+    }
+    let oversized = sim
+        .client
+        .try_pay_bills_batch(&payer, &sim.token, &oversized);
+    assert_eq!(oversized, Err(Ok(Error::InvalidInput)));
+}
+
+#[test]
+fn mirrors_serve_imported_state_read_only() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    let pair = sim.string(TOKEN_PAIR);
+    let snapshot = sim.client.export_snapshot(&vec![
+        &sim.env,
+        StorageEntry::Meter(meter_id.clone()),
+        StorageEntry::MeterPayment(meter_id.clone(), index),
+        StorageEntry::PriceFeed(pair.clone()),
+    ]);
+    assert_eq!(snapshot.len(), 3);
+
+    let mirror_id = sim.env.register_contract(None, NepaBillingContract);
+    let mirror = NepaBillingContractClient::new(&sim.env, &mirror_id);
+    let ledger = sim.env.ledger().sequence();
+    mirror.initialize_mirror(&sim.admin, &sim.contract, &ledger);
+    mirror.import_snapshot(&snapshot);
+    assert_eq!(
+        mirror.get_meter_summary(&meter_id),
+        sim.client.get_meter_summary(&meter_id)
+    );
+    assert_eq!(
+        mirror.get_payment(&meter_id, &index),
+        sim.client.get_payment(&meter_id, &index)
+    );
+    assert_eq!(
+        mirror.get_price_feed(&pair),
+        sim.client.get_price_feed(&pair)
+    );
+
+    let paid = mirror.try_pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(paid, Err(Ok(Error::ReadOnlyMirror)));
+    mirror.seal_mirror();
+    assert!(mirror.get_mirror_info().unwrap().sealed);
+    let imported = mirror.try_import_snapshot(&snapshot);
+    assert_eq!(imported, Err(Ok(Error::ReadOnlyMirror)));
+}
+
+#[test]
+fn exported_meters_take_their_owner_credit_and_deposit_along() {
+    let sim = Simulation::new();
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    sim.client.set_meter_owner(&meter_id, &owner);
+    sim.client.set_deposit_config(&DepositConfig {
+        connection_fee: 0,
+        security_deposit: 15_000_000_000,
+    });
+    sim.client.pay_deposit(&owner, &meter_id, &sim.token);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000_000);
+    let summary = sim.client.get_meter_summary(&meter_id);
+    assert_eq!(sim.client.get_credit_balance(&meter_id), 15_000_000_000);
+
+    let destination_id = sim.env.register_contract(None, NepaBillingContract);
+    let destination = NepaBillingContractClient::new(&sim.env, &destination_id);
+    destination.initialize(&sim.admin);
+    destination.add_accepted_token(
+        &sim.token,
+        &TokenConfig {
+            decimals: 7,
+            oracle_pair: sim.string(TOKEN_PAIR),
+            min_payment: 1,
+        },
+    );
+    let unapproved = destination.try_import_meter(&sim.contract, &meter_id);
+    assert_eq!(unapproved, Err(Ok(Error::InvalidState)));
+
+    sim.client.approve_meter_export(&meter_id, &destination_id);
+    destination.import_meter(&sim.contract, &meter_id);
+
+    assert_eq!(destination.get_meter_owner(&meter_id), Some(owner.clone()));
+    assert_eq!(destination.get_meter_summary(&meter_id), summary);
+    assert_eq!(destination.get_credit_balance(&meter_id), 15_000_000_000);
+    let held = destination.get_deposit(&meter_id).unwrap();
+    assert_eq!((held.payer, held.amount), (owner.clone(), 10_000_000));
+    assert_eq!(sim.token_balance(&destination_id), 10_000_000);
+
+    assert_eq!(sim.client.get_meter_owner(&meter_id), None);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+    assert_eq!(sim.client.get_deposit(&meter_id), None);
+    assert_eq!(sim.token_balance(&sim.contract), 10_000_000);
+    let export = sim.client.get_meter_export(&meter_id).unwrap();
+    let import = destination.get_meter_import(&meter_id).unwrap();
+    assert_eq!(export.export, import.export);
+    assert_eq!(export.export.deposit.len(), 1);
+
+    let paid = sim
+        .client
+        .try_pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(paid, Err(Ok(Error::MeterInactive)));
+    let again = destination.try_import_meter(&sim.contract, &meter_id);
+    assert_eq!(again, Err(Ok(Error::AlreadyExists)));
+}
+
+#[test]
+fn every_payment_issues_a_receipt_to_its_payer() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let other = sim.customer(1_000_000_000);
+    let first = pay_once(&sim, "METER-1");
+    let meter_id = sim.string("METER-1");
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &20_000_000);
+    sim.client
+        .pay_bill_with_oracle(&other, &sim.token, &meter_id, &10_000_000);
+
+    let ids = sim.client.list_receipts(&payer, &0, &10);
+    assert_eq!(sim.client.get_receipt_count(&payer), 1);
+    assert_eq!(ids.len(), 1);
+    let receipt = sim.client.get_receipt(&ids.get(0).unwrap()).unwrap();
+    assert_eq!(receipt.receipt_id, 2);
+    assert_eq!(receipt.payment_index, 1);
+    assert_eq!(receipt.payer, payer);
+    assert_eq!(receipt.amount, 20_000_000);
+    assert_eq!(receipt.normalized_amount, 30_000_000_000);
+
+    let earlier = sim.client.get_receipt(&1).unwrap();
+    assert_eq!(earlier.payer, first.payer);
+    assert_eq!(sim.client.list_receipts(&other, &0, &10), vec![&sim.env, 3]);
+    assert_eq!(sim.client.list_receipts(&other, &1, &10).len(), 0);
+    assert_eq!(sim.client.get_receipt(&4), None);
+}
+
+// Stands in for the regulator's contract and keeps the last case referred.
+#[contract]
+struct RecordingRegulator;
+
+#[contractimpl]
+impl RecordingRegulator {
+    pub fn refer_case(env: Env, source: Address, dispute_id: u64, case_hash: BytesN<32>) {
+        env.storage().instance().set(
+            &Symbol::new(&env, "referred"),
+            &(source, dispute_id, case_hash),
+        );
+    }
+}
+
+#[test]
+fn unresolved_complaints_are_referred_and_freeze_sweeps_until_ruled() {
+    let sim = Simulation::new();
+    let customer = sim.customer(0);
+    let meter_id = sim.string("METER-1");
+    let case_hash = BytesN::from_array(&sim.env, &[7; 32]);
+    let regulator = sim.env.register_contract(None, RecordingRegulator);
+    let provider = Address::generate(&sim.env);
+    pay_once(&sim, "METER-2");
+
+    let dispute_id = sim.client.open_dispute(&customer, &meter_id, &case_hash);
+    let unconfigured = sim.client.try_escalate_dispute(&dispute_id);
+    assert_eq!(unconfigured, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_regulator(&regulator);
+    let early = sim.client.try_escalate_dispute(&dispute_id);
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+
+    // Only the clock moves, so no entry outlives its TTL meanwhile.
+    sim.env.ledger().with_mut(|ledger| {
+        ledger.timestamp += crate::disputes::RESOLUTION_WINDOW_SECONDS;
+    });
+    sim.set_price(TOKEN_PAIR, TOKEN_PRICE);
+    sim.client.escalate_dispute(&dispute_id);
+    let referred: (Address, u64, BytesN<32>) = sim.env.as_contract(&regulator, || {
+        sim.env
+            .storage()
+            .instance()
+            .get(&Symbol::new(&sim.env, "referred"))
+            .unwrap()
+    });
+    assert_eq!(referred, (sim.contract.clone(), dispute_id, case_hash));
+    let frozen = sim
+        .client
+        .try_sweep_settlement(&provider, &sim.token, &10_000_000, &202_610);
+    assert_eq!(frozen, Err(Ok(Error::SettlementsFrozen)));
+
+    sim.client.record_regulator_ruling(&dispute_id, &true);
+    let dispute = sim.client.get_dispute(&dispute_id).unwrap();
+    assert_eq!(dispute.status, DisputeStatus::Ruled);
+    assert!(dispute.upheld);
+    sim.client
+        .sweep_settlement(&provider, &sim.token, &10_000_000, &202_610);
+    assert_eq!(sim.token_balance(&provider), 10_000_000);
+}
+
+#[test]
+fn regions_without_a_rate_are_billed_an_estimate_and_trued_up() {
+    let sim = Simulation::new();
+    let key = RateKey {
+        utility_type: sim.string("electricity"),
+        region: sim.string("lagos"),
+        band: sim.string("a"),
+    };
+    sim.client.register_region(&key.region);
+    sim.client.register_utility_type(&key.utility_type);
+    let rate_id = sim.client.register_rate_key(&key);
+    let meter_id = sim.string("METER-1");
+
+    let unpriced = sim
+        .client
+        .try_issue_bill(&meter_id, &202_311, &rate_id, &100);
+    assert_eq!(unpriced, Err(Ok(Error::RateNotFound)));
+    let invalid = sim.client.try_set_estimated_rate(&rate_id, &0);
+    assert_eq!(invalid, Err(Ok(Error::InvalidTariff)));
+    sim.client.set_estimated_rate(&rate_id, &1_000);
+    let bill = sim.client.issue_bill(&meter_id, &202_311, &rate_id, &100);
+    assert!(bill.estimated);
+    assert_eq!(bill.amount, 100_000);
+    assert_eq!(bill.rate_snapshot.estimated_per_kwh, 1_000);
+    let early = sim.client.try_true_up(&meter_id, &202_311, &120);
+    assert_eq!(early, Err(Ok(Error::RateNotFound)));
+
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 900),
+    ];
+    sim.client.set_utility_rate(&rate_id, &formula);
+    let trued = sim.client.true_up(&meter_id, &202_311, &120);
+    assert_eq!((trued.amount, trued.adjustment), (108_000, 8_000));
+    assert!(trued.trued_up);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 108_000);
+    let again = sim.client.try_true_up(&meter_id, &202_311, &120);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn capacity_blocks_roll_over_and_bill_overage_at_spot() {
+    let sim = Simulation::new();
+    let spot = sim.register_rate("electricity", "lagos", "estate", 80);
+    let estate = sim.customer(0);
+    let rolling = sim.string("ESTATE-1");
+    let refunding = sim.string("ESTATE-2");
+    sim.client.create_capacity_agreement(
+        &rolling,
+        &estate,
+        &100,
+        &50,
+        &spot,
+        &UnusedBlockPolicy::RollOver,
+        &202_401,
+        &202_412,
+    );
+    sim.client.create_capacity_agreement(
+        &refunding,
+        &estate,
+        &100,
+        &50,
+        &spot,
+        &UnusedBlockPolicy::Refund(5_000),
+        &202_401,
+        &202_412,
+    );
+    let unpriced = sim.client.try_create_capacity_agreement(
+        &sim.string("ESTATE-3"),
+        &estate,
+        &100,
+        &50,
+        &sim.string("missing"),
+        &UnusedBlockPolicy::Forfeit,
+        &202_401,
+        &202_412,
+    );
+    assert_eq!(unpriced, Err(Ok(Error::RateNotFound)));
+
+    sim.client.record_capacity_drawdown(&rolling, &202_401, &60);
+    let first = sim.client.close_capacity_period(&rolling, &202_401);
+    assert_eq!((first.block_charge, first.unused_kwh), (5_000, 40));
+    sim.client
+        .record_capacity_drawdown(&rolling, &202_402, &150);
+    let second = sim.client.close_capacity_period(&rolling, &202_402);
+    assert_eq!(second.carried_in_kwh, 40);
+    assert_eq!((second.overage_kwh, second.overage_charge), (10, 800));
+    assert_eq!(sim.client.get_meter_balance(&rolling), 10_800);
+    let reclosed = sim.client.try_close_capacity_period(&rolling, &202_402);
+    assert_eq!(reclosed, Err(Ok(Error::InvalidState)));
+
+    sim.client
+        .record_capacity_drawdown(&refunding, &202_401, &60);
+    let refunded = sim.client.close_capacity_period(&refunding, &202_401);
+    assert_eq!(refunded.refund, 1_000);
+    assert_eq!(sim.client.get_meter_balance(&refunding), 4_000);
+}
+
+#[test]
+fn time_of_use_rates_price_each_window_on_its_own() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 0);
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh_off_peak"), 1_500_000),
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh_peak"), 3_000_000),
+    ];
+    sim.client.set_utility_rate(&rate_id, &formula);
+    let peak = TouBand {
+        start_hour: 22,
+        end_hour: 24,
+        window: TouWindow::Peak,
+    };
+    let schedule = TouSchedule {
+        utc_offset_minutes: 60,
+        bands: vec![&sim.env, peak],
+    };
+    let backwards = TouSchedule {
+        utc_offset_minutes: 60,
+        bands: vec![
+            &sim.env,
+            TouBand {
+                start_hour: 22,
+                end_hour: 18,
+                window: TouWindow::Peak,
+            },
+        ],
+    };
+    let invalid = sim.client.try_set_tou_schedule(&rate_id, &backwards);
+    assert_eq!(invalid, Err(Ok(Error::InvalidTariff)));
+    sim.client.set_tou_schedule(&rate_id, &schedule);
+
+    // START_TIMESTAMP is 23:13 in Lagos.
+    assert_eq!(sim.client.get_current_tou_window(&rate_id), TouWindow::Peak);
+    let meter_id = sim.string("METER-1");
+    let bill = sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    assert_eq!(bill.amount, 30_000_000);
+    sim.advance_and_refresh(2 * 3600);
+    assert_eq!(
+        sim.client.get_current_tou_window(&rate_id),
+        TouWindow::OffPeak
+    );
+    let later = sim.client.issue_bill(&meter_id, &202_312, &rate_id, &10);
+    assert_eq!(later.amount, 15_000_000);
+
+    let payer = sim.customer(1_000_000_000);
+    let split = map![&sim.env, (TouWindow::OffPeak, 10), (TouWindow::Peak, 5)];
+    let index = sim.client.pay_utility_bill(
+        &payer,
+        &sim.token,
+        &meter_id,
+        &rate_id,
+        &UtilityUsage::ByWindow(split),
+    );
+    // 30,000,000 NGN units at 1,500 NGN per token.
+    let payment = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(payment.amount, 20_000);
+}
+
+#[test]
+fn weather_feeds_adjust_tariffs_on_their_own_reliability() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let feed_id = sim.string("LAGOS-TEMP");
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 1_000),
+        // 1% per degree above 30C.
+        TariffOp::FeedAdjust(feed_id.clone(), 30, 100),
+    ];
+    sim.client.set_utility_rate(&rate_id, &formula);
+    let meter_id = sim.string("METER-1");
+    let unfed = sim
+        .client
+        .try_issue_bill(&meter_id, &202_311, &rate_id, &100);
+    assert_eq!(unfed, Err(Ok(Error::PriceFeedNotFound)));
+
+    sim.client.update_data_feed(&feed_id, &35, &0);
+    // A price feed under the same id that keeps being held back does not
+    // count against the weather readings.
+    sim.client.update_price_feed(&feed_id, &1_000_000_000, &7);
+    let mut config = sim.client.get_oracle_config();
+    config.max_deviation_bps = 1_000;
+    config.min_reliability_bps = 9_000;
+    sim.client.set_oracle_config(&config);
+    sim.client.update_price_feed(&feed_id, &3_000_000_000, &7);
+    assert!(sim.client.get_feed_reliability(&feed_id).score_bps < 9_000);
+
+    let bill = sim.client.issue_bill(&meter_id, &202_311, &rate_id, &100);
+    assert_eq!(bill.amount, 105_000);
+    assert_eq!(
+        bill.rate_snapshot.feeds.get(feed_id.clone()).unwrap().value,
+        35
+    );
+
+    sim.advance(config.max_age_seconds + 1);
+    let stale = sim
+        .client
+        .try_issue_bill(&meter_id, &202_312, &rate_id, &100);
+    assert_eq!(stale, Err(Ok(Error::StalePriceFeed)));
+}
+
+#[test]
+fn invoice_previews_run_the_billing_engine_without_writing() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let unassigned = sim
+        .client
+        .try_preview_next_invoice(&sim.string("METER-2"), &10);
+    assert_eq!(unassigned, Err(Ok(Error::RateNotFound)));
+    sim.client.issue_bill(&meter_id, &202_310, &rate_id, &10);
+
+    let preview = sim.client.preview_next_invoice(&meter_id, &20);
+    assert_eq!(preview.period, 202_311);
+    assert_eq!((preview.charge, preview.taxes), (30_000_000, 0));
+    assert_eq!(preview.balance, 15_000_000);
+    assert_eq!(preview.amount_due, 45_000_000);
+    // 45,000,000 NGN units at 1,500 NGN per token.
+    assert_eq!(preview.token_amounts.get(sim.token.clone()), Some(30_000));
+    assert!(!preview.estimated);
+
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 15_000_000);
+    assert_eq!(sim.client.get_bill(&meter_id, &202_311), None);
+}
+
+#[test]
+fn lifeline_meters_are_billed_the_reduced_band_and_claimed_per_period() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let lifeline = sim.string("LIFELINE");
+    let share = sim.string("QUARTER");
+    let unknown = sim
+        .client
+        .try_set_meter_subsidy(&sim.string("METER-1"), &lifeline);
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+    let invalid = sim
+        .client
+        .try_set_subsidy_scheme(&share, &SubsidyScheme::Percentage(10_001));
+    assert_eq!(invalid, Err(Ok(Error::InvalidConfig)));
+    sim.client
+        .set_subsidy_scheme(&lifeline, &SubsidyScheme::Lifeline(50, 200));
+    sim.client
+        .set_subsidy_scheme(&share, &SubsidyScheme::Percentage(2_500));
+
+    let first = sim.string("METER-1");
+    let second = sim.string("METER-2");
+    sim.client.set_meter_subsidy(&first, &lifeline);
+    sim.client.set_meter_subsidy(&second, &share);
+    let bill = sim.client.issue_bill(&first, &202_311, &rate_id, &80);
+    assert_eq!((bill.subsidy, bill.amount), (40_000, 40_000));
+    assert_eq!(bill.subsidy_scheme, lifeline);
+    let shared = sim.client.issue_bill(&second, &202_311, &rate_id, &100);
+    assert_eq!((shared.subsidy, shared.amount), (25_000, 75_000));
+
+    assert_eq!(sim.client.get_subsidy_total(&lifeline, &202_311), 40_000);
+    assert_eq!(sim.client.get_subsidy_total(&share, &202_311), 25_000);
+    sim.client.clear_meter_subsidy(&first);
+    let full = sim.client.issue_bill(&first, &202_312, &rate_id, &80);
+    assert_eq!((full.subsidy, full.amount), (0, 80_000));
+}
+
+#[test]
+fn disconnection_notices_carry_stable_reason_codes() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.set_dunning_config(&DunningConfig {
+        arrears_threshold: 0,
+        notice_period_seconds: 7 * 86_400,
+    });
+    assert_eq!(sim.client.run_dunning(&meter_id), None);
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+
+    let notice = sim.client.run_dunning(&meter_id).unwrap();
+    assert_eq!(notice.reason, DisconnectionReason::Arrears);
+    assert_eq!(notice.amount_to_avoid, 15_000_000);
+    assert_eq!(notice.earliest_disconnection, START_TIMESTAMP + 7 * 86_400);
+    let (_, topics, data) = sim.env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (
+            Symbol::new(&sim.env, "disconnection_notice"),
+            meter_id.clone()
+        )
+            .into_val(&sim.env)
+    );
+    let (code, _, _): (u32, u64, i128) = data.into_val(&sim.env);
+    assert_eq!(code, 1);
+    let arrears = sim
+        .client
+        .try_issue_disconnection_notice(&meter_id, &DisconnectionReason::Arrears);
+    assert_eq!(arrears, Err(Ok(Error::InvalidState)));
+
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000);
+    assert!(
+        sim.client
+            .get_disconnection_notice(&meter_id)
+            .unwrap()
+            .withdrawn
+    );
+    let hazard = sim
+        .client
+        .issue_disconnection_notice(&meter_id, &DisconnectionReason::SafetyHazard);
+    assert_eq!((hazard.reason as u32, hazard.amount_to_avoid), (3, 0));
+    sim.client.withdraw_disconnection_notice(&meter_id);
+    let again = sim.client.try_withdraw_disconnection_notice(&meter_id);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn vat_and_levies_are_itemised_on_the_subsidised_subtotal() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let scheme = sim.string("FIFTH");
+    sim.client
+        .set_subsidy_scheme(&scheme, &SubsidyScheme::Percentage(2_000));
+    sim.client.set_meter_subsidy(&meter_id, &scheme);
+    let vat = TaxComponent {
+        name: Symbol::new(&sim.env, "vat"),
+        kind: TaxKind::Tax,
+        bps: 750,
+    };
+    let levy = TaxComponent {
+        name: Symbol::new(&sim.env, "nerc"),
+        kind: TaxKind::Levy,
+        bps: 200,
+    };
+    let excessive = TaxComponent {
+        bps: 10_001,
+        ..vat.clone()
+    };
+    let invalid = sim
+        .client
+        .try_set_tax_components(&vec![&sim.env, excessive]);
+    assert_eq!(invalid, Err(Ok(Error::InvalidConfig)));
+    sim.client
+        .set_tax_components(&vec![&sim.env, vat.clone(), levy]);
+
+    let bill = sim.client.issue_bill(&meter_id, &202_311, &rate_id, &100);
+    assert_eq!(bill.amount, 87_600);
+    let breakdown = sim.client.get_bill_breakdown(&meter_id, &202_311).unwrap();
+    assert_eq!(breakdown.subtotal, 80_000);
+    assert_eq!((breakdown.taxes, breakdown.levies), (6_000, 1_600));
+    assert_eq!(breakdown.line_items.get(0).unwrap().name, vat.name);
+
+    // Bills keep the rates they were issued at.
+    sim.client.set_tax_components(&vec![&sim.env]);
+    let kept = sim.client.get_bill_breakdown(&meter_id, &202_311).unwrap();
+    assert_eq!(kept.total, 87_600);
+}
+
+#[test]
+fn escrows_settle_or_refund_within_their_window() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let settled = sim
+        .client
+        .pay_bill_escrowed(&payer, &sim.token, &meter_id, &10_000_000);
+    let escrow_id = sim
+        .client
+        .pay_bill_escrowed(&payer, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(sim.token_balance(&sim.contract), 20_000_000);
+    assert_eq!(sim.client.get_payment_count(&meter_id), 0);
+
+    let stranger = Address::generate(&sim.env);
+    let confirmed = sim.client.try_confirm_escrow(&escrow_id, &stranger);
+    assert_eq!(confirmed, Err(Ok(Error::InvalidInput)));
+    let unknown = sim.client.try_confirm_escrow(&(escrow_id + 1), &sim.admin);
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+    let early = sim.client.try_reclaim_escrow(&escrow_id);
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+
+    let index = sim.client.confirm_escrow(&settled, &sim.admin);
+    let escrow = sim.client.get_escrow(&settled).unwrap();
+    assert_eq!(escrow.status, EscrowStatus::Confirmed);
+    assert_eq!(escrow.payment_index, index);
+    assert_eq!(sim.client.get_total_paid_ngn(&meter_id), 15_000_000_000);
+    let twice = sim.client.try_confirm_escrow(&settled, &sim.admin);
+    assert_eq!(twice, Err(Ok(Error::InvalidState)));
+
+    let expires_at = sim.client.get_escrow(&escrow_id).unwrap().expires_at;
+    sim.advance(expires_at - START_TIMESTAMP);
+    let late = sim.client.try_confirm_escrow(&escrow_id, &sim.admin);
+    assert_eq!(late, Err(Ok(Error::InvalidState)));
+    sim.client.reclaim_escrow(&escrow_id);
+    assert_eq!(sim.token_balance(&payer), 990_000_000);
+    assert_eq!(sim.token_balance(&sim.contract), 10_000_000);
+    let again = sim.client.try_reclaim_escrow(&escrow_id);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn signing_payloads_cover_the_exact_call() {
+    use crate::signing;
+    use soroban_sdk::xdr::SorobanAuthorizedFunction;
+
+    let sim = Simulation::new();
+    let provider = Address::generate(&sim.env);
+    let passphrase = sim.string("Test SDF Network ; September 2015");
+    let sweep = |amount: i128| {
+        signing::sweep_settlement_invocation(
+            &sim.env,
+            &sim.contract,
+            &provider,
+            &sim.token,
+            amount,
+            202_311,
+        )
+        .unwrap()
+    };
+    let SorobanAuthorizedFunction::ContractFn(call) = sweep(10_000_000).function else {
+        panic!("sweeps are contract calls");
+    };
+    assert_eq!(
+        call.function_name.to_utf8_string().unwrap(),
+        "sweep_settlement"
+    );
+    assert_eq!(call.args.len(), 4);
+    let amount: Val = 10_000_000_i128.into_val(&sim.env);
+    assert_eq!(
+        call.args[2],
+        ScVal::try_from_val(&sim.env, &amount).unwrap()
+    );
+
+    let payload = |nonce: i64, amount: i128| {
+        signing::signing_payload(&sim.env, &passphrase, nonce, 1_000, sweep(amount)).unwrap()
+    };
+    assert_eq!(payload(1, 10_000_000), payload(1, 10_000_000));
+    assert_ne!(payload(1, 10_000_000), payload(2, 10_000_000));
+    assert_ne!(payload(1, 10_000_000), payload(1, 10_000_001));
+    let other_network = signing::signing_payload(
+        &sim.env,
+        &sim.string("Public Global Stellar Network ; September 2015"),
+        1,
+        1_000,
+        sweep(10_000_000),
+    )
+    .unwrap();
+    assert_ne!(other_network, payload(1, 10_000_000));
+
+    let oversized = sim.string(&"x".repeat(65));
+    assert!(signing::signing_payload(&sim.env, &oversized, 1, 1_000, sweep(1)).is_err());
+}
+
+#[test]
+fn invariant_checkers_earn_the_bounty_once_per_violation() {
+    let sim = Simulation::new();
+    let checker = Address::generate(&sim.env);
+    pay_once(&sim, "METER-1");
+    let invalid = sim.client.try_set_invariant_bounty(&BountyConfig {
+        token: sim.token.clone(),
+        amount: -1,
+    });
+    assert_eq!(invalid, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_invariant_bounty(&BountyConfig {
+        token: sim.token.clone(),
+        amount: 1_000,
+    });
+    let oversized = sim.client.try_check_invariants(&checker, &21);
+    assert_eq!(oversized, Err(Ok(Error::InvalidInput)));
+    let clean = sim.client.check_invariants(&checker, &20);
+    assert_eq!((clean.checked, clean.new_violations), (2, 0));
+
+    // A receipt whose payment has vanished.
+    sim.env.as_contract(&sim.contract, || {
+        sim.env
+            .storage()
+            .persistent()
+            .remove(&AccountingKey::Payment(sim.string("METER-1"), 0));
+    });
+    let report = sim.client.check_invariants(&checker, &20);
+    assert_eq!((report.new_violations, report.bounty_paid), (1, 1_000));
+    assert_eq!(sim.token_balance(&checker), 1_000);
+    let repeat = sim.client.check_invariants(&checker, &20);
+    assert_eq!((repeat.new_violations, repeat.bounty_paid), (0, 0));
+    assert!(sim
+        .client
+        .is_violation_recorded(&Violation::ReceiptMismatch(1)));
+}
+
+#[test]
+fn prepaid_top_ups_issue_a_voucher_for_the_units_paid() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_400_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000_000);
+    assert_eq!(sim.client.get_voucher(&1), None);
+
+    sim.client.set_prepaid_meter(&meter_id, &true);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000_000);
+    // 15,000,000,000 NGN units buy 10,714 whole kWh at 1,400,000 each.
+    let voucher = sim.client.get_voucher(&1).unwrap();
+    assert_eq!(
+        (voucher.meter_id.clone(), voucher.kwh),
+        (meter_id.clone(), 10_714)
+    );
+    assert!(!voucher.redeemed);
+
+    let stranger = Address::generate(&sim.env);
+    let unauthorised = sim.client.try_redeem_voucher(&meter_id, &1, &stranger);
+    assert_eq!(unauthorised, Err(Ok(Error::InvalidInput)));
+    let elsewhere = sim
+        .client
+        .try_redeem_voucher(&sim.string("METER-2"), &1, &sim.admin);
+    assert_eq!(elsewhere, Err(Ok(Error::InvalidState)));
+    assert!(
+        sim.client
+            .redeem_voucher(&meter_id, &1, &sim.admin)
+            .redeemed
+    );
+    let twice = sim.client.try_redeem_voucher(&meter_id, &1, &sim.admin);
+    assert_eq!(twice, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn proposals_execute_once_with_enough_approvals() {
+    let sim = Simulation::new();
+    let first = Address::generate(&sim.env);
+    let second = Address::generate(&sim.env);
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    sim.client
+        .configure_multisig(&vec![&sim.env, first.clone(), second.clone()], &2);
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 1_200),
+    ];
+    let direct = sim.client.try_set_utility_rate(&rate_id, &formula);
+    assert_eq!(direct, Err(Ok(Error::MultisigRequired)));
+    let action = AdminAction::SetUtilityRate(rate_id.clone(), formula.clone());
+
+    let stranger = Address::generate(&sim.env);
+    let proposed = sim.client.try_propose_action(&stranger, &action);
+    assert_eq!(proposed, Err(Ok(Error::InvalidInput)));
+    let proposal_id = sim.client.propose_action(&first, &action);
+    let short = sim.client.try_execute_action(&proposal_id);
+    assert_eq!(short, Err(Ok(Error::InvalidState)));
+    let twice = sim.client.try_approve_action(&first, &proposal_id);
+    assert_eq!(twice, Err(Ok(Error::AlreadyExists)));
+
+    sim.client.approve_action(&second, &proposal_id);
+    sim.client.execute_action(&proposal_id);
+    assert_eq!(
+        sim.client.get_utility_rate(&rate_id).unwrap().formula,
+        formula
+    );
+    let again = sim.client.try_execute_action(&proposal_id);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+    let unknown = sim.client.try_execute_action(&(proposal_id + 1));
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+
+    let stale_id = sim.client.propose_action(&first, &action);
+    sim.advance(7 * 86_400);
+    let expired = sim.client.try_approve_action(&second, &stale_id);
+    assert_eq!(expired, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn queued_changes_execute_once_after_their_eta() {
+    let sim = Simulation::new();
+    let delay = sim.client.queue_change(&TimelockChange::Delay(86_400));
+    sim.client.execute_change(&delay);
+    let unknown = sim.client.try_execute_change(&(delay + 1));
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+
+    let cancelled = sim.client.queue_change(&TimelockChange::Delay(3_600));
+    sim.client.cancel_change(&cancelled);
+    sim.advance(86_400);
+    let executed = sim.client.try_execute_change(&cancelled);
+    assert_eq!(executed, Err(Ok(Error::InvalidState)));
+    let recancelled = sim.client.try_cancel_change(&cancelled);
+    assert_eq!(recancelled, Err(Ok(Error::InvalidState)));
+
+    let change_id = sim.client.queue_change(&TimelockChange::Delay(3_600));
+    let early = sim.client.try_execute_change(&change_id);
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+    sim.advance(86_400);
+    sim.client.execute_change(&change_id);
+    assert_eq!(sim.client.get_timelock_delay(), 3_600);
+    let again = sim.client.try_execute_change(&change_id);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn regions_list_their_full_tariff_table_by_typed_key() {
+    let sim = Simulation::new();
+    let residential = sim.register_rate("electricity", "lagos", "a", 1_000);
+    sim.register_rate("water", "lagos", "a", 300);
+    sim.register_rate("electricity", "abuja", "a", 900);
+    assert_eq!(residential, sim.string("electricity/lagos/a"));
+
+    let unpriced = RateKey {
+        utility_type: sim.string("electricity"),
+        region: sim.string("lagos"),
+        band: sim.string("b"),
+    };
+    sim.client.register_rate_key(&unpriced);
+    let table = sim.client.list_rates_for_region(&sim.string("lagos"));
+    assert_eq!(table.len(), 2);
+    let (key, rate) = table.get(0).unwrap();
+    assert_eq!(sim.client.get_rate_id(&key), residential);
+    assert_eq!(
+        rate.formula,
+        vec![
+            &sim.env,
+            TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 1_000)
+        ]
+    );
+    assert_eq!(
+        sim.client.list_rates_for_region(&sim.string("abuja")).len(),
+        1
+    );
+
+    let ambiguous = RateKey {
+        band: sim.string("a/b"),
+        ..unpriced.clone()
+    };
+    let rejected = sim.client.try_register_rate_key(&ambiguous);
+    assert_eq!(rejected, Err(Ok(Error::InvalidInput)));
+    let unknown_region = RateKey {
+        region: sim.string("kano"),
+        ..unpriced
+    };
+    let unregistered = sim.client.try_register_rate_key(&unknown_region);
+    assert_eq!(unregistered, Err(Ok(Error::InvalidInput)));
+    let duplicate = sim.client.try_register_region(&sim.string("lagos"));
+    assert_eq!(duplicate, Err(Ok(Error::AlreadyExists)));
+}
+
+#[test]
+fn bills_are_computed_from_consecutive_agent_readings() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let agent = Address::generate(&sim.env);
+    let october = START_TIMESTAMP - 30 * 86_400;
+
+    let unauthorised = sim
+        .client
+        .try_submit_meter_reading(&agent, &meter_id, &1_000, &october);
+    assert_eq!(unauthorised, Err(Ok(Error::InvalidInput)));
+    sim.client.set_reading_agent(&agent, &true);
+    sim.client
+        .submit_meter_reading(&agent, &meter_id, &1_000, &october);
+    let backwards = sim
+        .client
+        .try_submit_meter_reading(&agent, &meter_id, &900, &START_TIMESTAMP);
+    assert_eq!(backwards, Err(Ok(Error::InvalidInput)));
+    let future =
+        sim.client
+            .try_submit_meter_reading(&agent, &meter_id, &1_200, &(START_TIMESTAMP + 1));
+    assert_eq!(future, Err(Ok(Error::InvalidInput)));
+    let index = sim
+        .client
+        .submit_meter_reading(&agent, &meter_id, &1_150, &START_TIMESTAMP);
+    assert_eq!(index, 1);
+    assert_eq!(sim.client.get_reading_count(&meter_id), 2);
+
+    let opening = sim
+        .client
+        .try_compute_bill_from_readings(&meter_id, &202_310);
+    assert_eq!(opening, Err(Ok(Error::MissingTariffInput)));
+    let bill = sim.client.compute_bill_from_readings(&meter_id, &202_311);
+    assert_eq!((bill.kwh, bill.amount), (150, 150_000));
+    let twice = sim
+        .client
+        .try_compute_bill_from_readings(&meter_id, &202_311);
+    assert_eq!(twice, Err(Ok(Error::AlreadyExists)));
+}
+
+#[test]
+fn solar_exports_are_netted_against_bills_and_carry_over() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let untariffed = sim.client.try_record_export(&meter_id, &100);
+    assert_eq!(untariffed, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_feed_in_tariff(&rate_id, &500);
+    assert_eq!(sim.client.record_export(&meter_id, &100), 50_000);
+    assert_eq!(sim.client.get_period_exports(&meter_id, &202_311), 100);
+
+    let covered = sim.client.issue_bill(&meter_id, &202_311, &rate_id, &30);
+    assert_eq!((covered.amount, covered.export_credit), (30_000, 30_000));
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+    assert_eq!(sim.client.get_export_credit(&meter_id), 20_000);
+
+    let next = sim.client.issue_bill(&meter_id, &202_312, &rate_id, &40);
+    assert_eq!(next.export_credit, 20_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 20_000);
+    assert_eq!(sim.client.get_export_credit(&meter_id), 0);
+}
+
+#[test]
+fn meters_over_their_band_tolerance_are_flagged_after_the_grace_period() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let invalid = sim.client.try_set_debt_tolerance(
+        &sim.string("a"),
+        &DebtTolerance {
+            threshold: -1,
+            grace_period_seconds: 0,
+        },
+    );
+    assert_eq!(invalid, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_debt_tolerance(
+        &sim.string("a"),
+        &DebtTolerance {
+            threshold: 10_000_000,
+            grace_period_seconds: 86_400,
+        },
+    );
+
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    let status = sim.client.get_debt_status(&meter_id);
+    assert_eq!(status.over_threshold_since, START_TIMESTAMP);
+    assert!(!status.disconnection_pending);
+    sim.advance_and_refresh(86_400);
+    assert!(!sim.client.is_disconnection_pending(&meter_id));
+    assert!(
+        sim.client
+            .refresh_debt_status(&meter_id)
+            .disconnection_pending
+    );
+    assert!(sim.client.is_disconnection_pending(&meter_id));
+
+    // 9,000,000 NGN units bring the meter back under its threshold.
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &6_000);
+    let cleared = sim.client.get_debt_status(&meter_id);
+    assert_eq!(cleared.balance, 6_000_000);
+    assert_eq!(cleared.over_threshold_since, 0);
+    assert!(!cleared.disconnection_pending);
+}
+
+#[test]
+fn payment_plans_track_due_and_missed_installments() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &20);
+    let excessive = sim
+        .client
+        .try_create_payment_plan(&owner, &meter_id, &30_000_001, &3, &86_400);
+    assert_eq!(excessive, Err(Ok(Error::InvalidInput)));
+
+    let plan_id = sim
+        .client
+        .create_payment_plan(&owner, &meter_id, &30_000_000, &3, &86_400);
+    let proposed = sim.client.try_pay_installment(&owner, &sim.token, &plan_id);
+    assert_eq!(proposed, Err(Ok(Error::InvalidState)));
+    let second = sim
+        .client
+        .try_create_payment_plan(&owner, &meter_id, &1_000, &1, &86_400);
+    assert_eq!(second, Err(Ok(Error::AlreadyExists)));
+    sim.client.approve_payment_plan(&plan_id);
+
+    sim.advance_and_refresh(2 * 86_400);
+    let behind = sim.client.get_payment_plan_status(&plan_id);
+    assert_eq!(
+        (behind.installments_due, behind.missed_installments),
+        (2, 2)
+    );
+    assert_eq!(behind.next_due_at, START_TIMESTAMP + 86_400);
+    sim.client.pay_installment(&owner, &sim.token, &plan_id);
+    let report = sim.client.get_payment_plan_status(&plan_id);
+    assert_eq!(
+        (report.installments_paid, report.missed_installments),
+        (1, 1)
+    );
+    // 10,000,000 NGN units rounds up to 6,667 stroops, worth 10,000,500.
+    assert_eq!(report.remaining_principal, 19_999_500);
+
+    sim.client.pay_installment(&owner, &sim.token, &plan_id);
+    sim.client.pay_installment(&owner, &sim.token, &plan_id);
+    let done = sim.client.get_payment_plan_status(&plan_id);
+    assert_eq!(done.status, PlanStatus::Completed);
+    assert_eq!((done.remaining_principal, done.next_due_at), (0, 0));
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+}
+
+// A reporter key registered for TOKEN_PAIR.
+fn register_reporter(sim: &Simulation) -> SigningKey {
+    let reporter = SigningKey::from_bytes(&[7; 32]);
+    let public_key = BytesN::from_array(&sim.env, &reporter.verifying_key().to_bytes());
+    sim.client
+        .set_feed_reporter(&sim.string(TOKEN_PAIR), &public_key);
+    reporter
+}
+
+// `reporter`'s signature over a TOKEN_PAIR report observed now.
+fn sign_report(sim: &Simulation, reporter: &SigningKey, price: i128) -> BytesN<64> {
+    let payload = OracleManager::report_payload(
+        &sim.env,
+        &sim.contract,
+        &sim.string(TOKEN_PAIR),
+        price,
+        PRICE_DECIMALS,
+        sim.env.ledger().timestamp(),
+    );
+    let signature = reporter.sign(&payload.iter().collect::<std::vec::Vec<u8>>());
+    BytesN::from_array(&sim.env, &signature.to_bytes())
+}
+
+#[test]
+fn keepers_are_rewarded_for_relaying_due_reports() {
+    let sim = Simulation::new();
+    let feed_id = sim.string(TOKEN_PAIR);
+    let reporter = register_reporter(&sim);
+    let funder = sim.customer(10_000);
+    let keeper = Address::generate(&sim.env);
+    let config = KeeperConfig {
+        token: sim.token.clone(),
+        reward: 4_000,
+        update_interval_seconds: 600,
+    };
+    let unconfigured = sim.client.try_fund_keeper_pool(&funder, &10_000);
+    assert_eq!(unconfigured, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_keeper_config(&config);
+    assert_eq!(sim.client.fund_keeper_pool(&funder, &10_000), 10_000);
+    assert!(!sim.client.should_update_price_feeds());
+
+    // A report for a feed that is not yet due is applied but earns nothing.
+    sim.advance(60);
+    let early = sign_report(&sim, &reporter, TOKEN_PRICE);
+    let now = sim.env.ledger().timestamp();
+    let unpaid = sim.client.submit_price_report(
+        &keeper,
+        &feed_id,
+        &TOKEN_PRICE,
+        &PRICE_DECIMALS,
+        &now,
+        &early,
+    );
+    assert_eq!(unpaid, 0);
+    assert_eq!(
+        sim.client.get_price_feed(&feed_id).unwrap().last_updated,
+        now
+    );
+
+    for _ in 0..3 {
+        sim.advance(600);
+        assert!(sim.client.should_update_price_feeds());
+        let signature = sign_report(&sim, &reporter, TOKEN_PRICE);
+        let now = sim.env.ledger().timestamp();
+        sim.client.submit_price_report(
+            &keeper,
+            &feed_id,
+            &TOKEN_PRICE,
+            &PRICE_DECIMALS,
+            &now,
+            &signature,
+        );
+    }
+    // The third due report finds the pool short of a reward.
+    assert_eq!(sim.client.get_keeper_earnings(&keeper), 8_000);
+    assert_eq!(sim.client.get_keeper_pool(), 2_000);
+    assert_eq!(sim.token_balance(&keeper), 8_000);
+    assert!(!sim.client.should_update_price_feeds());
+}
+
+#[test]
+fn feeds_with_a_reporter_only_take_its_signed_reports() {
+    let sim = Simulation::new();
+    let feed_id = sim.string(TOKEN_PAIR);
+    let reporter = register_reporter(&sim);
+    let unsigned = sim
+        .client
+        .try_update_price_feed(&feed_id, &TOKEN_PRICE, &PRICE_DECIMALS);
+    assert_eq!(unsigned, Err(Ok(Error::SignatureRequired)));
+
+    sim.advance(60);
+    let price = TOKEN_PRICE + 1_000_000;
+    let now = sim.env.ledger().timestamp();
+    let signature = sign_report(&sim, &reporter, price);
+    let early = sim.client.try_update_price_feed_signed(
+        &feed_id,
+        &price,
+        &PRICE_DECIMALS,
+        &(now + 1),
+        &signature,
+    );
+    assert_eq!(early, Err(Ok(Error::InvalidInput)));
+    let applied =
+        sim.client
+            .update_price_feed_signed(&feed_id, &price, &PRICE_DECIMALS, &now, &signature);
+    assert!(applied);
+    assert_eq!(sim.client.get_price_feed(&feed_id).unwrap().price, price);
+
+    let replayed = sim.client.try_update_price_feed_signed(
+        &feed_id,
+        &price,
+        &PRICE_DECIMALS,
+        &now,
+        &signature,
+    );
+    assert_eq!(replayed, Err(Ok(Error::InvalidInput)));
+}
+
+#[test]
+fn each_reporter_key_is_capped_per_hour() {
+    let sim = Simulation::new();
+    let feed_id = sim.string(TOKEN_PAIR);
+    let reporter = register_reporter(&sim);
+    let public_key = BytesN::from_array(&sim.env, &reporter.verifying_key().to_bytes());
+    sim.client.set_reporter_rate_limit(&2);
+    let report = |price: i128| {
+        sim.advance(60);
+        let signature = sign_report(&sim, &reporter, price);
+        let now = sim.env.ledger().timestamp();
+        sim.client
+            .try_update_price_feed_signed(&feed_id, &price, &PRICE_DECIMALS, &now, &signature)
+    };
+
+    assert_eq!(report(TOKEN_PRICE), Ok(Ok(true)));
+    assert_eq!(report(TOKEN_PRICE), Ok(Ok(true)));
+    let capped = report(TOKEN_PRICE + 1_000_000);
+    assert_eq!(capped, Err(Ok(Error::BudgetExceeded)));
+    assert_eq!(
+        sim.client.get_price_feed(&feed_id).unwrap().price,
+        TOKEN_PRICE
+    );
+    let stats = sim.client.get_reporter_stats(&public_key);
+    assert_eq!(
+        (
+            stats.hourly_limit,
+            stats.updates_this_hour,
+            stats.total_updates
+        ),
+        (2, 2, 2)
+    );
+
+    // The count starts again each hour, and a limit of 0 lifts the cap.
+    sim.advance(60 * 60);
+    assert_eq!(
+        sim.client.get_reporter_stats(&public_key).updates_this_hour,
+        0
+    );
+    assert_eq!(report(TOKEN_PRICE), Ok(Ok(true)));
+    assert_eq!(report(TOKEN_PRICE), Ok(Ok(true)));
+    sim.client.set_reporter_rate_limit(&0);
+    assert_eq!(report(TOKEN_PRICE), Ok(Ok(true)));
+    let stats = sim.client.get_reporter_stats(&public_key);
+    assert_eq!(
+        (
+            stats.hourly_limit,
+            stats.updates_this_hour,
+            stats.total_updates
+        ),
+        (0, 3, 5)
+    );
+}
+
+#[test]
+fn large_price_moves_are_held_for_review() {
+    let sim = Simulation::new();
+    let feed_id = sim.string(TOKEN_PAIR);
+    sim.client.set_oracle_config(&OracleConfig {
+        max_deviation_bps: 1_000,
+        ..sim.client.get_oracle_config()
+    });
+    let calm = TOKEN_PRICE * 105 / 100;
+    assert!(sim
+        .client
+        .update_price_feed(&feed_id, &calm, &PRICE_DECIMALS));
+
+    // Rescaling the price does not get a 20% move past the guard.
+    sim.advance(60);
+    let jump = calm * 12;
+    assert!(!sim
+        .client
+        .update_price_feed(&feed_id, &jump, &(PRICE_DECIMALS + 1)));
+    assert_eq!(sim.client.get_price_feed(&feed_id).unwrap().price, calm);
+    assert_eq!(sim.client.get_flagged_price(&feed_id).unwrap().price, jump);
+    assert_eq!(sim.client.get_feed_reliability(&feed_id).flagged_updates, 1);
+    sim.client.accept_flagged_price(&feed_id);
+    let accepted = sim.client.get_price_feed(&feed_id).unwrap();
+    assert_eq!(
+        (accepted.price, accepted.decimals),
+        (jump, PRICE_DECIMALS + 1)
+    );
+    assert_eq!(sim.client.get_feed_reliability(&feed_id).flagged_updates, 0);
+    assert_eq!(sim.client.get_flagged_price(&feed_id), None);
+
+    // A held price is not accepted over a newer one already applied.
+    sim.advance(60);
+    sim.client
+        .update_price_feed(&feed_id, &(jump * 2), &(PRICE_DECIMALS + 1));
+    sim.advance(60);
+    sim.client
+        .update_price_feed(&feed_id, &jump, &(PRICE_DECIMALS + 1));
+    let superseded = sim.client.try_accept_flagged_price(&feed_id);
+    assert_eq!(superseded, Err(Ok(Error::InvalidState)));
+    sim.client.reject_flagged_price(&feed_id);
+    assert_eq!(sim.client.get_flagged_price(&feed_id), None);
+    let cleared = sim.client.try_reject_flagged_price(&feed_id);
+    assert_eq!(cleared, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn feeds_and_callers_each_get_their_own_daily_budget() {
+    let sim = Simulation::new();
+    let feed_id = sim.string("EUR/NGN");
+    sim.client.set_feed_budget(&feed_id, &2);
+    sim.set_price("EUR/NGN", 16_000_000_000);
+    sim.set_price("EUR/NGN", 16_100_000_000);
+    let over = sim
+        .client
+        .try_update_price_feed(&feed_id, &16_200_000_000, &PRICE_DECIMALS);
+    assert_eq!(over, Err(Ok(Error::BudgetExceeded)));
+    // Other feeds are not held to it.
+    sim.set_price(TOKEN_PAIR, TOKEN_PRICE);
+    let stats = sim.client.get_feed_cost_stats(&feed_id);
+    assert_eq!(
+        (stats.daily_limit, stats.updates_today, stats.total_updates),
+        (2, 2, 2)
+    );
+
+    sim.advance(86_400);
+    sim.set_price("EUR/NGN", 16_200_000_000);
+    let next_day = sim.client.get_feed_cost_stats(&feed_id);
+    assert_eq!((next_day.updates_today, next_day.total_updates), (1, 3));
+
+    let reporter = register_reporter(&sim);
+    let keeper = Address::generate(&sim.env);
+    sim.client.set_caller_budget(&keeper, &1);
+    let pair = sim.string(TOKEN_PAIR);
+    let relay = || {
+        sim.advance(60);
+        let signature = sign_report(&sim, &reporter, TOKEN_PRICE);
+        let now = sim.env.ledger().timestamp();
+        sim.client.try_submit_price_report(
+            &keeper,
+            &pair,
+            &TOKEN_PRICE,
+            &PRICE_DECIMALS,
+            &now,
+            &signature,
+        )
+    };
+    assert!(relay().is_ok());
+    assert_eq!(relay(), Err(Ok(Error::BudgetExceeded)));
+    let caller = sim.client.get_caller_cost_stats(&keeper);
+    assert_eq!((caller.daily_limit, caller.updates_today), (1, 1));
+}
+
+#[test]
+fn applied_prices_can_be_looked_up_by_time() {
+    let sim = Simulation::new();
+    let feed_id = sim.string(TOKEN_PAIR);
+    sim.advance(100);
+    sim.set_price(TOKEN_PAIR, TOKEN_PRICE + 1);
+    sim.advance(100);
+    // A decimals change keeps the earlier snapshots.
+    sim.client
+        .update_price_feed(&feed_id, &(TOKEN_PRICE * 10), &(PRICE_DECIMALS + 1));
+
+    let before = sim
+        .client
+        .try_get_price_at(&feed_id, &(START_TIMESTAMP - 1));
+    assert_eq!(before, Err(Ok(Error::PriceFeedNotFound)));
+    let at = sim.client.get_price_at(&feed_id, &(START_TIMESTAMP + 150));
+    assert_eq!(
+        (at.price, at.last_updated),
+        (TOKEN_PRICE + 1, START_TIMESTAMP + 100)
+    );
+    let range =
+        sim.client
+            .get_price_range(&feed_id, &(START_TIMESTAMP + 50), &(START_TIMESTAMP + 200));
+    let prices: std::vec::Vec<_> = range
+        .iter()
+        .map(|feed| (feed.price, feed.decimals))
+        .collect();
+    assert_eq!(
+        prices,
+        [
+            (TOKEN_PRICE + 1, PRICE_DECIMALS),
+            (TOKEN_PRICE * 10, PRICE_DECIMALS + 1)
+        ]
+    );
+    let backwards =
+        sim.client
+            .try_get_price_range(&feed_id, &(START_TIMESTAMP + 1), &START_TIMESTAMP);
+    assert_eq!(backwards, Err(Ok(Error::InvalidInput)));
+
+    // Once the buffer wraps, the oldest prices fall out of the window.
+    // The test budget is shared by every call below.
+    sim.env.budget().reset_unlimited();
+    for _ in 0..200 {
+        sim.advance(10);
+        sim.set_price(TOKEN_PAIR, TOKEN_PRICE);
+    }
+    let evicted = sim
+        .client
+        .try_get_price_at(&feed_id, &(START_TIMESTAMP + 150));
+    assert_eq!(evicted, Err(Ok(Error::PriceFeedNotFound)));
+    let latest = sim.env.ledger().timestamp();
+    assert_eq!(
+        sim.client.get_price_at(&feed_id, &latest).last_updated,
+        latest
+    );
+    let capped = sim.client.get_price_range(&feed_id, &0, &latest);
+    assert_eq!(capped.len(), 50);
+}
+
+#[test]
+fn bill_quotes_price_the_charge_in_the_payment_token() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let (electricity, lagos) = (sim.string("electricity"), sim.string("lagos"));
+    let quote = sim
+        .client
+        .quote_utility_bill(&meter_id, &10, &electricity, &lagos, &sim.token);
+    assert_eq!(quote.rate_id, rate_id);
+    assert_eq!((quote.charge, quote.total), (15_000_000, 15_000_000));
+    // 15,000,000 NGN units at 1,500 per stroop.
+    assert_eq!(quote.token_amount, 10_000);
+    assert_eq!(
+        (quote.price, quote.price_source),
+        (TOKEN_PRICE, PriceSource::PushFeed)
+    );
+    assert_eq!(quote.expires_at, START_TIMESTAMP + 5 * 60);
+    assert!(!quote.estimated);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+
+    let empty = sim
+        .client
+        .try_quote_utility_bill(&meter_id, &0, &electricity, &lagos, &sim.token);
+    assert_eq!(empty, Err(Ok(Error::InvalidInput)));
+    let unquoted_token = Address::generate(&sim.env);
+    let unsupported =
+        sim.client
+            .try_quote_utility_bill(&meter_id, &10, &electricity, &lagos, &unquoted_token);
+    assert_eq!(unsupported, Err(Ok(Error::UnsupportedToken)));
+    let elsewhere = sim.client.try_quote_utility_bill(
+        &meter_id,
+        &10,
+        &electricity,
+        &sim.string("abuja"),
+        &sim.token,
+    );
+    assert_eq!(elsewhere, Err(Ok(Error::InvalidInput)));
+}
+
+#[test]
+fn locked_quotes_pay_at_the_quoted_rate_until_they_expire() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let (electricity, lagos) = (sim.string("electricity"), sim.string("lagos"));
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    let (quote_id, quote) =
+        sim.client
+            .lock_bill_quote(&owner, &meter_id, &10, &electricity, &lagos, &sim.token);
+    assert_eq!(quote.token_amount, 10_000);
+
+    // The token doubling in value does not change what the quote charges.
+    sim.set_price(TOKEN_PAIR, TOKEN_PRICE * 2);
+    let before = sim.token_balance(&owner);
+    let index = sim.client.pay_with_quote(&quote_id);
+    assert_eq!(before - sim.token_balance(&owner), 10_000);
+    let payment = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(
+        (payment.normalized_amount, payment.rate),
+        (15_000_000, TOKEN_PRICE)
+    );
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+    assert!(sim.client.get_locked_quote(&quote_id).unwrap().used);
+    let reused = sim.client.try_pay_with_quote(&quote_id);
+    assert_eq!(reused, Err(Ok(Error::InvalidState)));
+
+    let (late_id, _) =
+        sim.client
+            .lock_bill_quote(&owner, &meter_id, &10, &electricity, &lagos, &sim.token);
+    sim.advance(5 * 60 + 1);
+    let expired = sim.client.try_pay_with_quote(&late_id);
+    assert_eq!(expired, Err(Ok(Error::QuoteExpired)));
+    let unknown = sim.client.try_pay_with_quote(&(late_id + 1));
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+}
+
+#[test]
+fn vending_agents_earn_and_claim_commission_on_sales() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    let agent = sim.customer(1_000_000);
+    let unregistered = sim
+        .client
+        .try_pay_via_agent(&agent, &sim.token, &meter_id, &10_000);
+    assert_eq!(unregistered, Err(Ok(Error::InvalidInput)));
+    let overpaid = sim.client.try_set_vending_agent(&agent, &10_001, &true);
+    assert_eq!(overpaid, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_vending_agent(&agent, &500, &true);
+
+    // The agent pays the meter in full and earns 5% of the sale.
+    sim.client
+        .pay_via_agent(&agent, &sim.token, &meter_id, &10_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+    assert_eq!(sim.token_balance(&agent), 990_000);
+    assert_eq!(sim.client.get_commission_balance(&agent, &sim.token), 500);
+    let reserve = sim.env.as_contract(&sim.contract, || {
+        crate::invariants::reserve(&sim.env, &sim.token)
+    });
+    assert_eq!(reserve, 9_500);
+
+    assert_eq!(sim.client.claim_commission(&agent, &sim.token), 500);
+    assert_eq!(sim.token_balance(&agent), 990_500);
+    assert_eq!(sim.client.get_commission_balance(&agent, &sim.token), 0);
+    let reclaimed = sim.client.try_claim_commission(&agent, &sim.token);
+    assert_eq!(reclaimed, Err(Ok(Error::InvalidState)));
+
+    sim.client.set_vending_agent(&agent, &500, &false);
+    let inactive = sim
+        .client
+        .try_pay_via_agent(&agent, &sim.token, &meter_id, &10_000);
+    assert_eq!(inactive, Err(Ok(Error::InvalidInput)));
+}
+
+#[test]
+fn loyalty_points_are_earned_on_payments_and_redeemed_as_credit() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let unconfigured = sim.client.try_redeem_points(&owner, &meter_id, &1);
+    assert_eq!(unconfigured, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_loyalty_config(&LoyaltyConfig {
+        points_per_ngn: 2,
+        redeem_value: 1_000_000,
+        expiry_seconds: 3_600,
+    });
+
+    // 15,000,000 NGN units is 1.5 NGN, worth 3 points.
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000);
+    assert_eq!(sim.client.get_loyalty_account(&owner).points, 3);
+    let greedy = sim.client.try_redeem_points(&owner, &meter_id, &4);
+    assert_eq!(greedy, Err(Ok(Error::InvalidInput)));
+    assert_eq!(sim.client.redeem_points(&owner, &meter_id, &2), 2_000_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), -2_000_000);
+    assert_eq!(sim.client.get_loyalty_account(&owner).points, 1);
+
+    // Points lapse an hour after they were last earned.
+    sim.advance(3_601);
+    assert_eq!(sim.client.get_loyalty_account(&owner).points, 0);
+    let lapsed = sim.client.try_redeem_points(&owner, &meter_id, &1);
+    assert_eq!(lapsed, Err(Ok(Error::InvalidInput)));
+}
+
+#[test]
+fn sessions_close_once_and_only_for_their_parties() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000_000_000);
+    let owner = sim.customer(1_000_000_000_000);
+    let unrated = sim.string("METER-0");
+    let opened = sim
+        .client
+        .try_open_session(&owner, &sim.token, &unrated, &100_000_000);
+    assert_eq!(opened, Err(Ok(Error::RateNotFound)));
+
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let session_id = sim
+        .client
+        .open_session(&owner, &sim.token, &meter_id, &100_000_000);
+    let stranger = Address::generate(&sim.env);
+    let usage = sim.client.try_report_usage(&stranger, &session_id, &1);
+    assert_eq!(usage, Err(Ok(Error::InvalidInput)));
+    let unknown = sim
+        .client
+        .try_report_usage(&sim.admin, &(session_id + 1), &1);
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+    let closed = sim.client.try_close_session(&session_id, &stranger);
+    assert_eq!(closed, Err(Ok(Error::InvalidInput)));
+
+    assert_eq!(sim.client.close_session(&session_id, &owner), 100_000_000);
+    let again = sim.client.try_close_session(&session_id, &owner);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+    let late = sim.client.try_report_usage(&sim.admin, &session_id, &1);
+    assert_eq!(late, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn sessions_draw_usage_from_the_lock_and_refund_the_rest() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let session_id = sim
+        .client
+        .open_session(&owner, &sim.token, &meter_id, &5_000_000);
+    assert_eq!(sim.token_balance(&owner), 995_000_000);
+    // The lock is held apart from the contract's own funds.
+    let reserve = sim.env.as_contract(&sim.contract, || {
+        crate::invariants::reserve(&sim.env, &sim.token)
+    });
+    assert_eq!(reserve, 0);
+
+    // 1,500,000,000 NGN units a kWh is 1,000,000 stroops.
+    assert_eq!(
+        sim.client.report_usage(&sim.admin, &session_id, &2),
+        2_000_000
+    );
+    let drained = sim.client.report_usage(&sim.admin, &session_id, &10);
+    assert_eq!(drained, 3_000_000);
+    let dry = sim.client.try_report_usage(&sim.admin, &session_id, &1);
+    assert_eq!(dry, Err(Ok(Error::InvalidState)));
+    let session = sim.client.get_session(&session_id).unwrap();
+    assert_eq!((session.drawn, session.kwh), (5_000_000, 12));
+    assert_eq!(session.drawn_ngn, 7_500_000_000);
+
+    let early = sim
+        .client
+        .open_session(&owner, &sim.token, &meter_id, &5_000_000);
+    sim.client.report_usage(&sim.admin, &early, &1);
+    assert_eq!(sim.client.close_session(&early, &owner), 4_000_000);
+    assert_eq!(sim.client.close_session(&session_id, &sim.admin), 0);
+    assert_eq!(sim.token_balance(&owner), 994_000_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+    assert_eq!(sim.client.get_payment_count(&meter_id), 2);
+}
+
+#[test]
+fn session_draws_carry_the_payment_fee_to_the_collector() {
+    let sim = Simulation::new();
+    let collector = Address::generate(&sim.env);
+    sim.client.set_fee_config(&FeeConfig {
+        collector: collector.clone(),
+        fx_spread_bps: 0,
+        service_fee_bps: 100,
+    });
+    // 1,485,000,000 NGN units a kWh is 990,000 stroops after the 1% fee.
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_485_000_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let session_id = sim
+        .client
+        .open_session(&owner, &sim.token, &meter_id, &5_000_000);
+
+    assert_eq!(
+        sim.client.report_usage(&sim.admin, &session_id, &2),
+        2_000_000
+    );
+    // The draw that empties the lock still pays its share of the fee.
+    assert_eq!(
+        sim.client.report_usage(&sim.admin, &session_id, &10),
+        3_000_000
+    );
+    let session = sim.client.get_session(&session_id).unwrap();
+    assert_eq!(session.fee_amount, 50_000);
+    assert_eq!(session.drawn_ngn, 7_425_000_000);
+    assert_eq!(session.fees.len(), 1);
+    assert_eq!(session.fees.get_unchecked(0).amount, 75_000_000);
+
+    assert_eq!(sim.client.close_session(&session_id, &owner), 0);
+    assert_eq!(sim.client.sweep_fees(&sim.token), 50_000);
+    assert_eq!(sim.token_balance(&collector), 50_000);
+}
+
+#[test]
+fn spending_limits_cap_each_payment_and_the_day() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &100);
+    let inverted = sim
+        .client
+        .try_set_spending_limit(&owner, &10_000_000, &20_000_000);
+    assert_eq!(inverted, Err(Ok(Error::InvalidConfig)));
+    sim.client
+        .set_spending_limit(&owner, &30_000_000, &20_000_000);
+
+    // 10,000 stroops is worth 15,000,000 NGN units.
+    let pay = |amount: i128| {
+        sim.client
+            .try_pay_bill_with_oracle(&owner, &sim.token, &meter_id, &amount)
+    };
+    assert!(pay(10_000).is_ok());
+    assert_eq!(pay(20_000), Err(Ok(Error::SpendingLimitExceeded)));
+    assert!(pay(10_000).is_ok());
+    assert_eq!(sim.client.get_spent_today(&owner), 30_000_000);
+    assert_eq!(pay(1), Err(Ok(Error::SpendingLimitExceeded)));
+    sim.advance_and_refresh(86_400);
+    assert_eq!(sim.client.get_spent_today(&owner), 0);
+    assert!(pay(10_000).is_ok());
+
+    // Tightening needs only the address; loosening needs the admin as well.
+    let admin_signed = || {
+        sim.env
+            .auths()
+            .iter()
+            .any(|(signer, _)| *signer == sim.admin)
+    };
+    sim.client
+        .set_spending_limit(&owner, &20_000_000, &10_000_000);
+    assert!(!admin_signed());
+    sim.client
+        .set_spending_limit(&owner, &40_000_000, &20_000_000);
+    assert!(admin_signed());
+    sim.client.reset_spending_limit(&owner);
+    assert!(admin_signed());
+    assert_eq!(sim.client.get_spending_limit(&owner), None);
+    assert_eq!(sim.client.get_spent_today(&owner), 0);
+}
+
+#[test]
+fn meter_transfers_need_both_owners_and_an_answer_for_the_debt() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let seller = sim.customer(1_000_000_000);
+    let buyer = Address::generate(&sim.env);
+    let meter_id = sim.register_meter("METER-1", &seller, &rate_id, "a");
+    let impostor = sim
+        .client
+        .try_initiate_meter_transfer(&buyer, &meter_id, &buyer);
+    assert_eq!(impostor, Err(Ok(Error::InvalidInput)));
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    sim.client
+        .initiate_meter_transfer(&seller, &meter_id, &buyer);
+    let pending = sim.client.get_pending_meter_transfer(&meter_id).unwrap();
+    assert_eq!(
+        (pending.to.clone(), pending.debt_at_initiation),
+        (buyer.clone(), 15_000_000)
+    );
+
+    let stranger = Address::generate(&sim.env);
+    let wrong = sim
+        .client
+        .try_accept_meter_transfer(&stranger, &meter_id, &true);
+    assert_eq!(wrong, Err(Ok(Error::InvalidInput)));
+    let indebted = sim
+        .client
+        .try_accept_meter_transfer(&buyer, &meter_id, &false);
+    assert_eq!(indebted, Err(Ok(Error::InvalidState)));
+    sim.client.cancel_meter_transfer(&buyer, &meter_id);
+    assert_eq!(sim.client.get_pending_meter_transfer(&meter_id), None);
+
+    // Once the seller settles up, the buyer need not take on any debt.
+    sim.client
+        .pay_bill_with_oracle(&seller, &sim.token, &meter_id, &10_000);
+    sim.client
+        .initiate_meter_transfer(&seller, &meter_id, &buyer);
+    sim.client.accept_meter_transfer(&buyer, &meter_id, &false);
+    assert_eq!(sim.client.get_meter_owner(&meter_id), Some(buyer.clone()));
+    assert_eq!(sim.client.get_pending_meter_transfer(&meter_id), None);
+    let resold = sim
+        .client
+        .try_initiate_meter_transfer(&seller, &meter_id, &stranger);
+    assert_eq!(resold, Err(Ok(Error::InvalidInput)));
+
+    sim.client.issue_bill(&meter_id, &202_312, &rate_id, &10);
+    sim.client
+        .initiate_meter_transfer(&buyer, &meter_id, &stranger);
+    sim.client
+        .accept_meter_transfer(&stranger, &meter_id, &true);
+    assert_eq!(sim.client.get_meter_owner(&meter_id), Some(stranger));
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 15_000_000);
+}
+
+#[test]
+fn group_payments_split_by_outstanding_balance_or_share() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let estate = sim.customer(1_000_000_000);
+    let flat_1 = sim.register_meter("METER-1", &estate, &rate_id, "a");
+    let flat_2 = sim.register_meter("METER-2", &estate, &rate_id, "a");
+    sim.client.issue_bill(&flat_1, &202_311, &rate_id, &10);
+    sim.client.issue_bill(&flat_2, &202_311, &rate_id, &30);
+    let (owed, shared) = (sim.string("ESTATE-OWED"), sim.string("ESTATE-SHARED"));
+    sim.client
+        .create_billing_group(&owed, &estate, &GroupSplit::Outstanding);
+    let taken = sim
+        .client
+        .try_create_billing_group(&owed, &estate, &GroupSplit::Shares);
+    assert_eq!(taken, Err(Ok(Error::AlreadyExists)));
+    sim.client.set_group_member(&owed, &flat_1, &0);
+    sim.client.set_group_member(&owed, &flat_2, &0);
+
+    // 40,000 stroops covers the 60,000,000 NGN units owed, split 1:3.
+    let paid = sim
+        .client
+        .pay_group_bill(&estate, &sim.token, &owed, &40_000);
+    assert_eq!(
+        paid,
+        vec![&sim.env, (flat_1.clone(), 10_000), (flat_2.clone(), 30_000)]
+    );
+    let statement = sim.client.get_group_statement(&owed, &202_311);
+    assert_eq!((statement.kwh, statement.billed), (40, 60_000_000));
+    assert_eq!(statement.outstanding, 0);
+    let settled = sim
+        .client
+        .try_pay_group_bill(&estate, &sim.token, &owed, &1_000);
+    assert_eq!(settled, Err(Ok(Error::InvalidState)));
+
+    sim.client
+        .create_billing_group(&shared, &estate, &GroupSplit::Shares);
+    sim.client.set_group_member(&shared, &flat_1, &3_000);
+    sim.client.set_group_member(&shared, &flat_2, &6_000);
+    let short = sim
+        .client
+        .try_pay_group_bill(&estate, &sim.token, &shared, &10_000);
+    assert_eq!(short, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_group_member(&shared, &flat_2, &7_000);
+    let paid = sim
+        .client
+        .pay_group_bill(&estate, &sim.token, &shared, &10_001);
+    assert_eq!(
+        paid,
+        vec![&sim.env, (flat_1.clone(), 3_000), (flat_2.clone(), 7_001)]
+    );
+    assert_eq!(sim.client.get_meter_balance(&flat_1), -4_500_000);
+}
+
+#[test]
+fn crossing_the_monthly_threshold_raises_one_alert() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let stranger = Address::generate(&sim.env);
+    let foreign = sim
+        .client
+        .try_set_budget_threshold(&stranger, &meter_id, &20_000_000);
+    assert_eq!(foreign, Err(Ok(Error::InvalidInput)));
+    sim.client
+        .set_budget_threshold(&owner, &meter_id, &20_000_000);
+    // Alerts raised so far in the test.
+    let alerted = || {
+        let topic = Symbol::new(&sim.env, "budget_exceeded");
+        sim.env
+            .events()
+            .all()
+            .iter()
+            .filter(|(_, topics, _)| {
+                topics.first().is_some_and(|first| {
+                    Symbol::try_from_val(&sim.env, &first) == Ok(topic.clone())
+                })
+            })
+            .count()
+    };
+
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000);
+    assert!(!sim.client.is_budget_exceeded(&meter_id));
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &5_000);
+    assert_eq!(alerted(), 1);
+    assert!(sim.client.is_budget_exceeded(&meter_id));
+    let spend = sim.client.get_monthly_spend(&meter_id, &202_311);
+    assert_eq!((spend.paid, spend.consumed), (22_500_000, 15_000_000));
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &5_000);
+    // Further spending in the month raises no second alert.
+    assert_eq!(alerted(), 1);
+
+    // Consumption alone crosses it too.
+    sim.client.issue_bill(&meter_id, &202_312, &rate_id, &20);
+    assert_eq!(alerted(), 2);
+    assert!(sim.client.get_monthly_spend(&meter_id, &202_312).exceeded);
+}
+
+#[test]
+fn monthly_rollups_total_each_meter_and_region() {
+    let sim = Simulation::new();
+    let lagos_rate = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let abuja_rate = sim.register_rate("electricity", "abuja", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let home = sim.register_meter("METER-1", &owner, &lagos_rate, "a");
+    let shop = sim.register_meter("METER-2", &owner, &lagos_rate, "a");
+    let office = sim.register_meter("METER-3", &owner, &abuja_rate, "a");
+    sim.client.issue_bill(&home, &202_311, &lagos_rate, &10);
+    sim.client.issue_bill(&shop, &202_311, &lagos_rate, &20);
+    sim.client.issue_bill(&office, &202_311, &abuja_rate, &40);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &home, &10_000);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &shop, &10_000);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &shop, &10_000);
+
+    let home_stats = sim.client.get_monthly_stats(&home, &2023, &11);
+    assert_eq!(
+        (home_stats.kwh, home_stats.paid, home_stats.payment_count),
+        (10, 15_000_000, 1)
+    );
+    let lagos = sim
+        .client
+        .get_region_stats(&sim.string("lagos"), &2023, &11);
+    assert_eq!(
+        (lagos.kwh, lagos.paid, lagos.payment_count),
+        (30, 45_000_000, 3)
+    );
+    let abuja = sim
+        .client
+        .get_region_stats(&sim.string("abuja"), &2023, &11);
+    assert_eq!((abuja.kwh, abuja.paid, abuja.payment_count), (40, 0, 0));
+    let quiet = sim.client.get_monthly_stats(&home, &2023, &12);
+    assert_eq!((quiet.kwh, quiet.payment_count), (0, 0));
+    let no_month = sim.client.try_get_monthly_stats(&home, &2023, &13);
+    assert_eq!(no_month, Err(Ok(Error::InvalidInput)));
+}
+
+#[test]
+fn audit_entries_are_numbered_without_gaps() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let start = sim.client.get_audit_head();
+    let agent = Address::generate(&sim.env);
+    sim.client.set_vending_agent(&agent, &500, &true);
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 2_000_000),
+    ];
+    sim.client.set_utility_rate(&rate_id, &formula);
+    let session_id = sim
+        .client
+        .open_session(&owner, &sim.token, &meter_id, &5_000_000);
+    sim.client.close_session(&session_id, &owner);
+    sim.client.set_vending_agent(&agent, &500, &false);
+
+    let entries = sim.client.get_audit_entries(&(start + 1), &10);
+    let seqs: std::vec::Vec<u64> = entries.iter().map(|entry| entry.seq).collect();
+    assert_eq!(seqs, [start + 1, start + 2, start + 3, start + 4]);
+    let role = Symbol::new(&sim.env, "vending_agent");
+    let actions: std::vec::Vec<AuditAction> = entries.iter().map(|entry| entry.action).collect();
+    assert_eq!(
+        actions,
+        [
+            AuditAction::RoleGranted(role.clone(), agent.clone()),
+            AuditAction::RateChanged(rate_id),
+            AuditAction::Refund(owner, sim.token.clone(), 5_000_000),
+            AuditAction::RoleRevoked(role, agent),
+        ]
+    );
+    assert_eq!(sim.client.get_audit_head(), start + 4);
+    assert_eq!(sim.client.get_audit_entries(&(start + 3), &1).len(), 1);
+    assert_eq!(sim.client.get_audit_entries(&(start + 5), &10).len(), 0);
+}
+
+// A bare token reporting six decimals, which the Stellar asset contract cannot.
+#[contract]
+struct SixDecimalToken;
+
+#[contractimpl]
+impl SixDecimalToken {
+    pub fn mint(env: Env, to: Address, amount: i128) {
+        let balance = Self::balance(env.clone(), to.clone());
+        env.storage().instance().set(&to, &(balance + amount));
+    }
+
+    pub fn balance(env: Env, id: Address) -> i128 {
+        env.storage().instance().get(&id).unwrap_or(0)
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        let balance = Self::balance(env.clone(), from.clone());
+        env.storage().instance().set(&from, &(balance - amount));
+        Self::mint(env, to, amount);
+    }
+
+    pub fn decimals(_env: Env) -> u32 {
+        6
+    }
+
+    pub fn symbol(env: Env) -> String {
+        String::from_str(&env, "SIX")
+    }
+}
+
+#[test]
+fn token_amounts_are_summed_in_seven_decimal_units() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let six = sim.env.register_contract(None, SixDecimalToken);
+    SixDecimalTokenClient::new(&sim.env, &six).mint(&owner, &1_000_000);
+    sim.set_price("SIX/NGN", TOKEN_PRICE);
+    let config = TokenConfig {
+        decimals: 7,
+        oracle_pair: sim.string("SIX/NGN"),
+        min_payment: 1,
+    };
+    let misreported = sim.client.try_add_accepted_token(&six, &config);
+    assert_eq!(misreported, Err(Ok(Error::InvalidConfig)));
+    sim.client.add_accepted_token(
+        &six,
+        &TokenConfig {
+            decimals: 6,
+            ..config
+        },
+    );
+
+    // 1,000 six-decimal units and 10,000 seven-decimal units are each worth
+    // 15,000,000 NGN units.
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &20);
+    sim.client
+        .pay_bill_with_oracle(&owner, &six, &meter_id, &1_000);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000);
+    let summary = sim.client.get_meter_summary(&meter_id);
+    assert_eq!(
+        (summary.total_paid, summary.total_paid_ngn),
+        (20_000, 30_000_000)
+    );
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+
+    sim.client.remove_accepted_token(&six);
+    let metadata = sim.client.get_token_metadata(&six).unwrap();
+    assert_eq!((metadata.decimals, metadata.symbol), (6, sim.string("SIX")));
+    assert_eq!(sim.client.get_meter_summary(&meter_id).total_paid, 20_000);
+}
+
+#[test]
+fn conversions_round_against_the_payer_and_refuse_to_overflow() {
+    use crate::accounting::{apply_bps, denormalize, denormalize_floor, normalize};
+
+    let feed = PriceFeed {
+        price: TOKEN_PRICE,
+        decimals: PRICE_DECIMALS,
+        last_updated: START_TIMESTAMP,
+    };
+    // One stroop is worth 1,500 NGN units; 1,501 units needs a second stroop.
+    assert_eq!(normalize(1, 7, &feed), Ok(1_500));
+    assert_eq!(denormalize(1_501, 7, &feed), Ok(2));
+    assert_eq!(denormalize_floor(1_501, 7, &feed), Ok(1));
+    // Low-precision tokens and feeds scale up exactly.
+    let coarse = PriceFeed {
+        price: 1_500,
+        decimals: 0,
+        ..feed.clone()
+    };
+    assert_eq!(normalize(3, 2, &coarse), Ok(450_000_000));
+    assert_eq!(denormalize(450_000_001, 2, &coarse), Ok(4));
+    assert_eq!(apply_bps(999, 750), Ok(74));
+
+    assert_eq!(
+        normalize(i128::MAX, 7, &feed),
+        Err(Error::ArithmeticOverflow)
+    );
+    assert_eq!(
+        normalize(i128::MAX, 2, &coarse),
+        Err(Error::ArithmeticOverflow)
+    );
+    assert_eq!(apply_bps(i128::MAX, 10_000), Err(Error::ArithmeticOverflow));
+}
+
+#[test]
+fn overflowing_credits_and_charges_fail_with_a_typed_error() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.set_feed_in_tariff(&rate_id, &(i128::MAX / 2));
+    assert_eq!(sim.client.record_export(&meter_id, &1), i128::MAX / 2);
+    let product = sim.client.try_record_export(&meter_id, &3);
+    assert_eq!(product, Err(Ok(Error::ArithmeticOverflow)));
+    let sum = sim.client.try_record_export(&meter_id, &2);
+    assert_eq!(sum, Err(Ok(Error::ArithmeticOverflow)));
+    assert_eq!(sim.client.get_export_credit(&meter_id), i128::MAX / 2);
+
+    let agreement = sim.string("ESTATE-1");
+    sim.client.create_capacity_agreement(
+        &agreement,
+        &owner,
+        &100,
+        &(i128::MAX / 50),
+        &rate_id,
+        &UnusedBlockPolicy::Forfeit,
+        &202_401,
+        &202_412,
+    );
+    let block = sim.client.try_close_capacity_period(&agreement, &202_401);
+    assert_eq!(block, Err(Ok(Error::ArithmeticOverflow)));
+}
+
+// A token that tries to pay a bill again from inside its own transfer, and
+// records whether it got through.
+mod reentering_token {
+    use soroban_sdk::{contract, contractimpl, Address, Env, String, Symbol};
+
+    use crate::NepaBillingContractClient;
+
+    #[contract]
+    pub struct ReenteringToken;
+
+    #[contractimpl]
+    impl ReenteringToken {
+        pub fn arm(env: Env, billing: Address, meter_id: String) {
+            env.storage()
+                .instance()
+                .set(&Symbol::new(&env, "target"), &(billing, meter_id));
+        }
+
+        pub fn reentered(env: Env) -> bool {
+            env.storage()
+                .instance()
+                .get(&Symbol::new(&env, "reentered"))
+                .unwrap_or(false)
+        }
+
+        pub fn transfer(env: Env, from: Address, _to: Address, amount: i128) {
+            let (billing, meter_id): (Address, String) = env
+                .storage()
+                .instance()
+                .get(&Symbol::new(&env, "target"))
+                .unwrap();
+            let client = NepaBillingContractClient::new(&env, &billing);
+            let token = env.current_contract_address();
+            let reentered = client
+                .try_pay_bill_with_oracle(&from, &token, &meter_id, &amount)
+                .is_ok();
+            env.storage()
+                .instance()
+                .set(&Symbol::new(&env, "reentered"), &reentered);
+        }
+
+        pub fn balance(_env: Env, _id: Address) -> i128 {
+            0
+        }
+
+        pub fn decimals(_env: Env) -> u32 {
+            7
+        }
+
+        pub fn symbol(env: Env) -> String {
+            String::from_str(&env, "EVIL")
+        }
+    }
+}
+
+#[test]
+fn token_callbacks_cannot_pay_again_mid_transfer() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let evil = sim
+        .env
+        .register_contract(None, reentering_token::ReenteringToken);
+    let evil_client = reentering_token::ReenteringTokenClient::new(&sim.env, &evil);
+    evil_client.arm(&sim.contract, &meter_id);
+    sim.client.add_accepted_token(
+        &evil,
+        &TokenConfig {
+            decimals: 7,
+            oracle_pair: sim.string(TOKEN_PAIR),
+            min_payment: 1,
+        },
+    );
+
+    sim.client
+        .pay_bill_with_oracle(&owner, &evil, &meter_id, &10_000);
+    assert!(!evil_client.reentered());
+    assert_eq!(sim.client.get_payment_count(&meter_id), 1);
+
+    // The contract's own guard refuses a nested call and clears on the way out.
+    sim.env.as_contract(&sim.contract, || {
+        let nested = crate::guard::non_reentrant(&sim.env, || {
+            crate::guard::non_reentrant(&sim.env, || Ok(()))
+        });
+        assert_eq!(nested, Err(Error::ReentrantCall));
+        assert_eq!(crate::guard::non_reentrant(&sim.env, || Ok(1)), Ok(1));
+    });
+}
+
+#[test]
+fn dust_payments_below_the_token_minimum_are_refused() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &20);
+
+    let empty = sim
+        .client
+        .try_pay_bill_with_oracle(&owner, &sim.token, &meter_id, &0);
+    assert_eq!(empty, Err(Ok(Error::AmountTooSmall)));
+
+    let negative = sim.client.try_set_min_payment(&sim.token, &-1);
+    assert_eq!(negative, Err(Ok(Error::InvalidConfig)));
+    let unknown = sim.env.register_contract(None, SixDecimalToken);
+    let unlisted = sim.client.try_set_min_payment(&unknown, &1_000);
+    assert_eq!(unlisted, Err(Ok(Error::UnsupportedToken)));
+
+    sim.client.set_min_payment(&sim.token, &1_000);
+    let dust = sim
+        .client
+        .try_pay_bill_with_oracle(&owner, &sim.token, &meter_id, &999);
+    assert_eq!(dust, Err(Ok(Error::AmountTooSmall)));
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &1_000);
+    assert_eq!(sim.client.get_meter_summary(&meter_id).total_paid, 1_000);
+
+    // At this price one six-decimal unit is worth less than one NGN unit, so
+    // it would book a payment of nothing.
+    SixDecimalTokenClient::new(&sim.env, &unknown).mint(&owner, &1_000_000);
+    sim.set_price("SIX/NGN", 1);
+    sim.client.add_accepted_token(
+        &unknown,
+        &TokenConfig {
+            decimals: 6,
+            oracle_pair: sim.string("SIX/NGN"),
+            min_payment: 1,
+        },
+    );
+    let worthless = sim
+        .client
+        .try_pay_bill_with_oracle(&owner, &unknown, &meter_id, &1);
+    assert_eq!(worthless, Err(Ok(Error::AmountTooSmall)));
+}
+
+#[test]
+fn numeric_inputs_are_checked_for_sign_ceiling_and_decimals() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let pair = sim.string(TOKEN_PAIR);
+
+    let negative_kwh = sim
+        .client
+        .try_issue_bill(&meter_id, &202_311, &rate_id, &-1);
+    assert_eq!(negative_kwh, Err(Ok(Error::InvalidInput)));
+    let negative_amount = sim
+        .client
+        .try_pay_bill_with_oracle(&owner, &sim.token, &meter_id, &-1);
+    assert_eq!(negative_amount, Err(Ok(Error::InvalidInput)));
+    let deep = sim.client.try_update_price_feed(&pair, &TOKEN_PRICE, &19);
+    assert_eq!(deep, Err(Ok(Error::InvalidInput)));
+
+    let bounds = InputBounds {
+        max_amount: 5_000,
+        max_kwh: 100,
+        max_price: 20_000_000_000,
+        max_rate: 2_000_000,
+    };
+    let unsigned = sim.client.try_set_input_bounds(&InputBounds {
+        max_kwh: -1,
+        ..bounds.clone()
+    });
+    assert_eq!(unsigned, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_input_bounds(&bounds);
+    assert_eq!(sim.client.get_input_bounds(), bounds);
+
+    let heavy = sim
+        .client
+        .try_issue_bill(&meter_id, &202_311, &rate_id, &101);
+    assert_eq!(heavy, Err(Ok(Error::OutOfBounds)));
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &100);
+    let large = sim
+        .client
+        .try_pay_bill_with_oracle(&owner, &sim.token, &meter_id, &5_001);
+    assert_eq!(large, Err(Ok(Error::OutOfBounds)));
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &5_000);
+    let dear = sim
+        .client
+        .try_update_price_feed(&pair, &20_000_000_001, &PRICE_DECIMALS);
+    assert_eq!(dear, Err(Ok(Error::OutOfBounds)));
+    let steep = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 2_000_001),
+    ];
+    let over = sim.client.try_set_utility_rate(&rate_id, &steep);
+    assert_eq!(over, Err(Ok(Error::OutOfBounds)));
+}
+
+#[test]
+fn closing_a_billing_period_freezes_it_and_carries_arrears() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let lagos = sim.string("lagos");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &5_000);
+
+    let early = sim.client.try_close_billing_period(&lagos, &202_311);
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+    let unknown = sim
+        .client
+        .try_close_billing_period(&sim.string("kano"), &202_311);
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+
+    // Into December, without moving the ledger sequence far enough to archive
+    // anything.
+    sim.env
+        .ledger()
+        .with_mut(|ledger| ledger.timestamp += 17 * 24 * 60 * 60);
+    sim.set_price(TOKEN_PAIR, TOKEN_PRICE);
+    let cycle = sim.client.close_billing_period(&lagos, &202_311);
+    assert_eq!(
+        (cycle.invoices, cycle.billed, cycle.arrears),
+        (1, 15_000_000, 7_500_000)
+    );
+    assert!(cycle.rates.contains_key(rate_id.clone()));
+    assert_eq!(sim.client.get_billing_cycle(&lagos, &202_311), Some(cycle));
+    assert!(sim.client.get_bill(&meter_id, &202_311).unwrap().finalized);
+    assert_eq!(sim.client.get_meter_arrears(&meter_id), 7_500_000);
+
+    let again = sim.client.try_close_billing_period(&lagos, &202_311);
+    assert_eq!(again, Err(Ok(Error::AlreadyExists)));
+    let late = sim.client.try_issue_bill(&meter_id, &202_311, &rate_id, &1);
+    assert_eq!(late, Err(Ok(Error::InvalidState)));
+
+    // Payments wear the arrears down to what is still owed.
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &3_000);
+    assert_eq!(sim.client.get_meter_arrears(&meter_id), 3_000_000);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &2_000);
+    assert_eq!(sim.client.get_meter_arrears(&meter_id), 0);
+}
+
+#[test]
+fn effective_rates_report_where_they_came_from() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let feed_id = sim.string("LAGOS-TEMP");
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 1_000),
+        TariffOp::FeedAdjust(feed_id.clone(), 30, 100),
+    ];
+    sim.client.update_data_feed(&feed_id, &35, &0);
+    sim.client.set_utility_rate(&rate_id, &formula);
+    // A price feed under the same id that keeps being held back says nothing
+    // about the weather readings the rate is priced against.
+    sim.client.update_price_feed(&feed_id, &1_000_000_000, &7);
+    let mut config = sim.client.get_oracle_config();
+    config.max_deviation_bps = 1_000;
+    sim.client.set_oracle_config(&config);
+    sim.client.update_price_feed(&feed_id, &3_000_000_000, &7);
+    assert!(sim.client.get_feed_reliability(&feed_id).score_bps < 10_000);
+
+    let electricity = sim.string("electricity");
+    let lagos = sim.string("lagos");
+    let now = sim.env.ledger().timestamp();
+    let effective = sim
+        .client
+        .get_effective_rate(&electricity, &lagos, &sim.string("a"), &now);
+    assert_eq!(effective.rate_id, rate_id);
+    assert_eq!(effective.per_kwh, 1_050);
+    assert_eq!(effective.rate_updated, now);
+    assert_eq!(
+        (effective.feed_id, effective.feed_updated),
+        (feed_id.clone(), now)
+    );
+    assert_eq!(
+        effective.feed_reliability_bps,
+        sim.client.get_data_feed_reliability(&feed_id).score_bps
+    );
+    assert_eq!(effective.feed_reliability_bps, 10_000);
+    assert!(!effective.fallback_used);
+
+    // A key with only an estimated rate falls back to it.
+    let key = RateKey {
+        utility_type: electricity.clone(),
+        region: lagos.clone(),
+        band: sim.string("b"),
+    };
+    let estimated_id = sim.client.register_rate_key(&key);
+    let unpriced = sim
+        .client
+        .try_get_effective_rate(&electricity, &lagos, &key.band, &now);
+    assert_eq!(unpriced, Err(Ok(Error::RateNotFound)));
+    sim.client.set_estimated_rate(&estimated_id, &900);
+    let estimated = sim
+        .client
+        .get_effective_rate(&electricity, &lagos, &key.band, &now);
+    assert_eq!((estimated.per_kwh, estimated.rate_updated), (900, 0));
+    assert!(estimated.fallback_used);
+    assert_eq!(estimated.feed_id, sim.string(""));
+
+    let unknown = sim
+        .client
+        .try_get_effective_rate(&electricity, &lagos, &sim.string("c"), &now);
+    assert_eq!(unknown, Err(Ok(Error::RateNotFound)));
+}
+
+#[test]
+fn fallback_chains_try_each_source_in_order_and_record_the_one_used() {
+    let sim = Simulation::new();
+    let pair = sim.string(TOKEN_PAIR);
+    let default = sim.client.get_fallback_chain(&pair);
+    assert_eq!(default.sources, vec![&sim.env, PriceSource::PushFeed]);
+
+    let chain = |sources: Vec<PriceSource>| FallbackChain {
+        sources,
+        cache_max_age_seconds: 10_000,
+    };
+    let empty = sim
+        .client
+        .try_set_fallback_chain(&pair, &chain(vec![&sim.env]));
+    assert_eq!(empty, Err(Ok(Error::InvalidConfig)));
+    let repeated = sim.client.try_set_fallback_chain(
+        &pair,
+        &chain(vec![&sim.env, PriceSource::PushFeed, PriceSource::PushFeed]),
+    );
+    assert_eq!(repeated, Err(Ok(Error::InvalidConfig)));
+    let unsourced = sim
+        .client
+        .try_set_fallback_chain(&pair, &chain(vec![&sim.env, PriceSource::ExternalOracle]));
+    assert_eq!(unsourced, Err(Ok(Error::InvalidConfig)));
+
+    let sources = vec![
+        &sim.env,
+        PriceSource::PushFeed,
+        PriceSource::CachedPrice,
+        PriceSource::StaticRate,
+    ];
+    sim.client
+        .set_fallback_chain(&pair, &chain(sources.clone()));
+    assert_eq!(sim.client.get_fallback_chain(&pair).sources, sources);
+    sim.client.set_fallback_price(&pair, &10_000_000_000, &7);
+    assert_eq!(
+        pay_once(&sim, "METER-1").price_source,
+        PriceSource::PushFeed
+    );
+
+    // Past the feed's hour, the cached price still serves for the chain's own
+    // age limit.
+    sim.advance(7_200);
+    let cached = pay_once(&sim, "METER-2");
+    assert_eq!(
+        (cached.price_source, cached.rate),
+        (PriceSource::CachedPrice, TOKEN_PRICE)
+    );
+    sim.advance(3_600);
+    let fallback = pay_once(&sim, "METER-3");
+    assert_eq!(
+        (fallback.price_source, fallback.rate),
+        (PriceSource::StaticRate, 10_000_000_000)
+    );
+
+    // The first failure is reported when nothing in the chain resolves.
+    sim.client.set_fallback_chain(
+        &pair,
+        &chain(vec![
+            &sim.env,
+            PriceSource::PushFeed,
+            PriceSource::CachedPrice,
+        ]),
+    );
+    let resolved = sim.client.try_resolve_payment_price(&pair);
+    assert_eq!(resolved, Err(Ok(Error::StalePriceFeed)));
+}
+
+#[test]
+fn frozen_rates_refuse_changes_until_the_freeze_ends() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let regulator = Address::generate(&sim.env);
+    sim.client.set_regulator(&regulator);
+    let now = sim.env.ledger().timestamp();
+    let per_kwh = |rate: i128| {
+        vec![
+            &sim.env,
+            TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), rate),
+        ]
+    };
+
+    let stranger = Address::generate(&sim.env);
+    let refused = sim
+        .client
+        .try_freeze_rate(&stranger, &rate_id, &(now + 600));
+    assert_eq!(refused, Err(Ok(Error::InvalidInput)));
+    let unset =
+        sim.client
+            .try_freeze_rate(&regulator, &sim.string("electricity:kano:a"), &(now + 600));
+    assert_eq!(unset, Err(Ok(Error::RateNotFound)));
+    let past = sim.client.try_freeze_rate(&regulator, &rate_id, &now);
+    assert_eq!(past, Err(Ok(Error::InvalidInput)));
+
+    assert!(!sim.client.is_rate_frozen(&rate_id));
+    sim.client.freeze_rate(&regulator, &rate_id, &(now + 600));
+    assert!(sim.client.is_rate_frozen(&rate_id));
+    assert_eq!(sim.client.get_rate_frozen_until(&rate_id), now + 600);
+    let updated = sim.client.try_set_utility_rate(&rate_id, &per_kwh(2_000));
+    assert_eq!(updated, Err(Ok(Error::RateFrozen)));
+    let scheduled =
+        sim.client
+            .try_add_utility_rate_scheduled(&rate_id, &per_kwh(2_000), &(now + 300));
+    assert_eq!(scheduled, Err(Ok(Error::RateFrozen)));
+
+    // The admin may extend the regulator's freeze but not cut it short.
+    let shortened = sim
+        .client
+        .try_freeze_rate(&sim.admin, &rate_id, &(now + 300));
+    assert_eq!(shortened, Err(Ok(Error::InvalidInput)));
+    sim.client.freeze_rate(&sim.admin, &rate_id, &(now + 900));
+    assert_eq!(sim.client.get_rate_frozen_until(&rate_id), now + 900);
+
+    sim.advance(901);
+    assert!(!sim.client.is_rate_frozen(&rate_id));
+    sim.client.set_utility_rate(&rate_id, &per_kwh(2_000));
+    let bill = sim
+        .client
+        .issue_bill(&sim.string("METER-1"), &202_311, &rate_id, &1);
+    assert_eq!(bill.amount, 2_000);
+}
+
+#[test]
+fn reclassified_meters_move_band_from_the_effective_time_only() {
+    let sim = Simulation::new();
+    let band_a = sim.register_rate("electricity", "lagos", "a", 1_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &band_a, "a");
+    let metadata = sim.client.get_meter_metadata(&meter_id).unwrap();
+    assert_eq!(
+        (metadata.phase, metadata.max_load_watts),
+        (SupplyPhase::Single, 5_000)
+    );
+    let bill = sim.client.issue_bill(&meter_id, &202_311, &band_a, &10);
+    let now = sim.env.ledger().timestamp();
+    let band_b = sim.string("b");
+
+    let unpriced = sim
+        .client
+        .try_reclassify_band(&meter_id, &band_b, &(now + 600));
+    assert_eq!(unpriced, Err(Ok(Error::RateNotFound)));
+    let band_b_rate = sim.register_rate("electricity", "lagos", "b", 2_000);
+    let backdated = sim
+        .client
+        .try_reclassify_band(&meter_id, &band_b, &(now - 1));
+    assert_eq!(backdated, Err(Ok(Error::InvalidInput)));
+    let unchanged = sim
+        .client
+        .try_reclassify_band(&meter_id, &sim.string("a"), &(now + 600));
+    assert_eq!(unchanged, Err(Ok(Error::InvalidInput)));
+    let unknown = sim
+        .client
+        .try_reclassify_band(&sim.string("METER-9"), &band_b, &(now + 600));
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+
+    sim.client.reclassify_band(&meter_id, &band_b, &(now + 600));
+    assert_eq!(sim.client.get_meter_band(&meter_id), Some(sim.string("a")));
+    assert_eq!(sim.client.get_meter_rate(&meter_id), Some(band_a.clone()));
+    let earlier = sim
+        .client
+        .try_reclassify_band(&meter_id, &sim.string("c"), &(now + 300));
+    assert_eq!(earlier, Err(Ok(Error::InvalidState)));
+
+    sim.advance(600);
+    assert_eq!(sim.client.get_meter_band(&meter_id), Some(band_b));
+    assert_eq!(sim.client.get_meter_rate(&meter_id), Some(band_b_rate));
+    let history = sim.client.get_meter_metadata(&meter_id).unwrap();
+    assert_eq!(history.band, sim.string("a"));
+    assert_eq!(history.band_changes.len(), 1);
+    assert_eq!(sim.client.get_bill(&meter_id, &202_311), Some(bill));
+}
+
+#[test]
+fn pools_only_share_out_unclaimed_revenue() {
+    let sim = Simulation::new();
+    let unset = sim
+        .client
+        .try_claim_pool(&WholesalePool::Generation, &sim.token);
+    assert_eq!(unset, Err(Ok(Error::InvalidInput)));
+
+    let recipient = Address::generate(&sim.env);
+    let mut shares = Map::new(&sim.env);
+    shares.set(
+        WholesalePool::Generation,
+        PoolShare {
+            recipient: recipient.clone(),
+            share_bps: 10_000,
+        },
+    );
+    sim.client.set_split_config(&SplitConfig { shares });
+    let unshared = sim
+        .client
+        .try_claim_pool(&WholesalePool::Regulator, &sim.token);
+    assert_eq!(unshared, Err(Ok(Error::InvalidInput)));
+    let empty = sim
+        .client
+        .try_claim_pool(&WholesalePool::Generation, &sim.token);
+    assert_eq!(empty, Err(Ok(Error::InvalidState)));
+
+    // Escrowed funds are still the payer's and cannot be distributed.
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    sim.client
+        .pay_bill_escrowed(&payer, &sim.token, &meter_id, &10_000_000);
+    let escrowed = sim.client.try_distribute_revenue(&sim.token, &10_000_000);
+    assert_eq!(escrowed, Err(Ok(Error::InvalidState)));
+
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    sim.client.distribute_revenue(&sim.token, &10_000_000);
+    let claimed = sim
+        .client
+        .claim_pool(&WholesalePool::Generation, &sim.token);
+    assert_eq!(claimed, 10_000_000);
+    assert_eq!(sim.token_balance(&recipient), claimed);
+    let again = sim
+        .client
+        .try_claim_pool(&WholesalePool::Generation, &sim.token);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn distributed_revenue_splits_into_pools_by_share() {
+    let sim = Simulation::new();
+    let recipients: std::vec::Vec<Address> = (0..4).map(|_| Address::generate(&sim.env)).collect();
+    let pools = [
+        WholesalePool::Generation,
+        WholesalePool::Transmission,
+        WholesalePool::Distribution,
+        WholesalePool::Regulator,
+    ];
+    let split = |bps: [u32; 4]| {
+        let mut shares = Map::new(&sim.env);
+        for i in 0..4 {
+            shares.set(
+                pools[i],
+                PoolShare {
+                    recipient: recipients[i].clone(),
+                    share_bps: bps[i],
+                },
+            );
+        }
+        SplitConfig { shares }
+    };
+    let short = sim
+        .client
+        .try_set_split_config(&split([6_000, 1_500, 2_000, 400]));
+    assert_eq!(short, Err(Ok(Error::InvalidConfig)));
+    let unsplit = sim.client.try_distribute_revenue(&sim.token, &10_001);
+    assert_eq!(unsplit, Err(Ok(Error::InvalidConfig)));
+    sim.client
+        .set_split_config(&split([6_000, 1_500, 2_000, 500]));
+
+    let payer = sim.customer(1_000_000_000);
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &sim.string("METER-1"), &10_001);
+    let over = sim.client.try_distribute_revenue(&sim.token, &10_002);
+    assert_eq!(over, Err(Ok(Error::InvalidState)));
+
+    // The rounding dust goes to the first pool in the split's key order,
+    // which is distribution.
+    let parts = sim.client.distribute_revenue(&sim.token, &10_001);
+    let expected = [6_000, 1_500, 2_001, 500];
+    for i in 0..4 {
+        assert_eq!(parts.get(pools[i]), Some(expected[i]));
+        assert_eq!(
+            sim.client.get_pool_balance(&pools[i], &sim.token),
+            expected[i]
+        );
+    }
+    let drained = sim.client.try_distribute_revenue(&sim.token, &1);
+    assert_eq!(drained, Err(Ok(Error::InvalidState)));
+
+    for i in 0..4 {
+        assert_eq!(sim.client.claim_pool(&pools[i], &sim.token), expected[i]);
+        assert_eq!(sim.token_balance(&recipients[i]), expected[i]);
+        assert_eq!(sim.client.get_pool_balance(&pools[i], &sim.token), 0);
+    }
+}
+
+#[test]
+fn stablecoins_off_their_usd_peg_are_not_converted_at_the_pair_price() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let unlisted = sim.env.register_contract(None, SixDecimalToken);
+    let unknown = sim
+        .client
+        .try_set_peg_guard(&unlisted, &9_800_000, &10_200_000);
+    assert_eq!(unknown, Err(Ok(Error::UnsupportedToken)));
+    let unpriced = sim.client.try_set_peg_guard(&sim.token, &0, &10_200_000);
+    assert_eq!(unpriced, Err(Ok(Error::InvalidConfig)));
+    let inverted = sim
+        .client
+        .try_set_peg_guard(&sim.token, &10_200_000, &9_800_000);
+    assert_eq!(inverted, Err(Ok(Error::InvalidConfig)));
+
+    sim.client
+        .set_peg_guard(&sim.token, &9_800_000, &10_200_000);
+    let guard = sim.client.get_peg_guard(&sim.token).unwrap();
+    let symbol = sim.client.get_token_metadata(&sim.token).unwrap().symbol;
+    let mut peg_feed = [0u8; 64];
+    let len = symbol.len() as usize;
+    symbol.copy_into_slice(&mut peg_feed[..len]);
+    peg_feed[len..len + 4].copy_from_slice(b"/USD");
+    assert_eq!(
+        guard.peg_feed,
+        String::from_bytes(&sim.env, &peg_feed[..len + 4])
+    );
+    let peg_pair = std::string::String::from_utf8(peg_feed[..len + 4].to_vec()).unwrap();
+
+    let unfed = sim
+        .client
+        .try_pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    assert_eq!(unfed, Err(Ok(Error::PriceFeedNotFound)));
+    sim.set_price(&peg_pair, 10_000_000);
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    let on_peg = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(on_peg.price_source, PriceSource::PushFeed);
+
+    sim.set_price(&peg_pair, 9_700_000);
+    let off_peg = sim
+        .client
+        .try_pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    assert_eq!(off_peg, Err(Ok(Error::PegDeviation)));
+    let batched = sim.client.try_pay_bills_batch(
+        &payer,
+        &sim.token,
+        &vec![&sim.env, (meter_id.clone(), 10_000)],
+    );
+    assert_eq!(batched, Err(Ok(Error::PegDeviation)));
+
+    // With fallbacks enabled, the payment is valued at the pair's static rate.
+    let pair = sim.string(TOKEN_PAIR);
+    sim.client.set_fallback_price(&pair, &10_000_000_000, &7);
+    let mut config = sim.client.get_oracle_config();
+    config.use_fallback = true;
+    sim.client.set_oracle_config(&config);
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    let fallback = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(
+        (fallback.price_source, fallback.rate),
+        (PriceSource::StaticRate, 10_000_000_000)
+    );
+
+    sim.client.remove_peg_guard(&sim.token);
+    assert_eq!(sim.client.get_peg_guard(&sim.token), None);
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    let unguarded = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(unguarded.price_source, PriceSource::PushFeed);
+}
+
+// Swaps at two output units per input unit, delivering `shortfall` fewer
+// than it quoted. The router is its own pair and pays out of its balance.
+mod doubling_router {
+    use soroban_sdk::{contract, contractimpl, symbol_short, token, vec, Address, Env, Vec};
+
+    #[contract]
+    pub struct DoublingRouter;
+
+    #[contractimpl]
+    impl DoublingRouter {
+        pub fn set_shortfall(env: Env, shortfall: i128) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("short"), &shortfall);
+        }
+
+        pub fn router_pair_for(env: Env, _token_a: Address, _token_b: Address) -> Address {
+            env.current_contract_address()
+        }
+
+        pub fn router_get_amounts_out(env: Env, amount_in: i128, _path: Vec<Address>) -> Vec<i128> {
+            vec![&env, amount_in, amount_in * 2]
+        }
+
+        pub fn swap_exact_tokens_for_tokens(
+            env: Env,
+            amount_in: i128,
+            _amount_out_min: i128,
+            path: Vec<Address>,
+            to: Address,
+            _deadline: u64,
+        ) -> Vec<i128> {
+            let router = env.current_contract_address();
+            let shortfall: i128 = env
+                .storage()
+                .instance()
+                .get(&symbol_short!("short"))
+                .unwrap_or(0);
+            let amount_out = amount_in * 2 - shortfall;
+            token::Client::new(&env, &path.get_unchecked(0)).transfer(&to, &router, &amount_in);
+            token::Client::new(&env, &path.get_unchecked(1)).transfer(&router, &to, &amount_out);
+            vec![&env, amount_in, amount_out]
+        }
+    }
+}
+
+#[test]
+fn swapped_payments_settle_in_the_utility_token_within_the_slippage() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let router = sim
+        .env
+        .register_contract(None, doubling_router::DoublingRouter);
+    let router_client = doubling_router::DoublingRouterClient::new(&sim.env, &router);
+    let settlement = sim.env.register_stellar_asset_contract(sim.admin.clone());
+    token::StellarAssetClient::new(&sim.env, &settlement).mint(&router, &1_000_000);
+    let config = SwapConfig {
+        router: router.clone(),
+        settlement_token: settlement.clone(),
+    };
+
+    let unconfigured = sim
+        .client
+        .try_pay_with_swap(&payer, &sim.token, &meter_id, &10_000, &100);
+    assert_eq!(unconfigured, Err(Ok(Error::InvalidConfig)));
+    let unaccepted = sim.client.try_set_swap_config(&config);
+    assert_eq!(unaccepted, Err(Ok(Error::UnsupportedToken)));
+    sim.set_price("NGNC/NGN", TOKEN_PRICE);
+    sim.client.add_accepted_token(
+        &settlement,
+        &TokenConfig {
+            decimals: 7,
+            oracle_pair: sim.string("NGNC/NGN"),
+            min_payment: 1,
+        },
+    );
+    sim.client.set_swap_config(&config);
+    assert_eq!(sim.client.get_swap_config(), Some(config));
+
+    let unswapped = sim
+        .client
+        .try_pay_with_swap(&payer, &settlement, &meter_id, &10_000, &100);
+    assert_eq!(unswapped, Err(Ok(Error::InvalidInput)));
+    let unbounded = sim
+        .client
+        .try_pay_with_swap(&payer, &sim.token, &meter_id, &10_000, &10_001);
+    assert_eq!(unbounded, Err(Ok(Error::InvalidInput)));
+
+    let index = sim
+        .client
+        .pay_with_swap(&payer, &sim.token, &meter_id, &10_000, &100);
+    let record = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(
+        (record.token, record.amount, record.normalized_amount),
+        (settlement.clone(), 20_000, 30_000_000)
+    );
+    let settlement_client = token::Client::new(&sim.env, &settlement);
+    assert_eq!(settlement_client.balance(&sim.contract), 20_000);
+    assert_eq!(sim.token_balance(&sim.contract), 0);
+    assert_eq!(sim.token_balance(&router), 10_000);
+
+    // 1% of the 20,000 quoted may be lost; 201 may not.
+    router_client.set_shortfall(&201);
+    let slipped = sim
+        .client
+        .try_pay_with_swap(&payer, &sim.token, &meter_id, &10_000, &100);
+    assert_eq!(slipped, Err(Ok(Error::InvalidState)));
+    router_client.set_shortfall(&200);
+    let index = sim
+        .client
+        .pay_with_swap(&payer, &sim.token, &meter_id, &10_000, &100);
+    assert_eq!(
+        sim.client.get_payment(&meter_id, &index).unwrap().amount,
+        19_800
+    );
+}
+
+#[test]
+fn collections_pull_the_balance_within_the_approval_and_cycle_cap() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let unmandated = sim.client.try_collect_bill(&meter_id);
+    assert_eq!(unmandated, Err(Ok(Error::InvalidState)));
+    let uncapped = sim
+        .client
+        .try_authorize_collection(&owner, &meter_id, &sim.token, &0);
+    assert_eq!(uncapped, Err(Ok(Error::InvalidInput)));
+    let unlisted = sim.env.register_contract(None, SixDecimalToken);
+    let unknown = sim
+        .client
+        .try_authorize_collection(&owner, &meter_id, &unlisted, &20_000_000);
+    assert_eq!(unknown, Err(Ok(Error::UnsupportedToken)));
+
+    sim.client
+        .authorize_collection(&owner, &meter_id, &sim.token, &20_000_000);
+    let settled = sim.client.try_collect_bill(&meter_id);
+    assert_eq!(settled, Err(Ok(Error::InvalidState)));
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    let unapproved = sim.client.try_collect_bill(&meter_id);
+    assert_eq!(unapproved, Err(Ok(Error::SpendingLimitExceeded)));
+
+    let expiry = sim.env.ledger().sequence() + 1_000;
+    token::Client::new(&sim.env, &sim.token).approve(&owner, &sim.contract, &100_000, &expiry);
+    let index = sim.client.collect_bill(&meter_id);
+    assert_eq!(
+        sim.client.get_payment(&meter_id, &index).unwrap().amount,
+        10_000
+    );
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+    assert_eq!(sim.token_balance(&owner), 1_000_000_000 - 10_000);
+    let mandate = sim.client.get_collection_mandate(&meter_id).unwrap();
+    assert_eq!((mandate.period, mandate.collected), (202_311, 15_000_000));
+
+    // A second 15,000,000 this month would pass the 20,000,000 cap.
+    sim.client.issue_bill(&meter_id, &202_310, &rate_id, &10);
+    let capped = sim.client.try_collect_bill(&meter_id);
+    assert_eq!(capped, Err(Ok(Error::SpendingLimitExceeded)));
+    sim.env
+        .ledger()
+        .with_mut(|ledger| ledger.timestamp += 17 * 24 * 60 * 60);
+    sim.set_price(TOKEN_PAIR, TOKEN_PRICE);
+    sim.client.collect_bill(&meter_id);
+    let mandate = sim.client.get_collection_mandate(&meter_id).unwrap();
+    assert_eq!((mandate.period, mandate.collected), (202_312, 15_000_000));
+
+    let stranger = Address::generate(&sim.env);
+    let foreign = sim.client.try_revoke_collection(&stranger, &meter_id);
+    assert_eq!(foreign, Err(Ok(Error::InvalidInput)));
+    sim.client.revoke_collection(&owner, &meter_id);
+    assert_eq!(sim.client.get_collection_mandate(&meter_id), None);
+    sim.client.issue_bill(&meter_id, &202_312, &rate_id, &1);
+    let revoked = sim.client.try_collect_bill(&meter_id);
+    assert_eq!(revoked, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn overpayments_stay_as_credit_and_refunds_go_to_the_current_owner() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &20_000);
+    assert_eq!(sim.client.get_credit_balance(&meter_id), 15_000_000);
+    // The next invoice is netted against the credit.
+    sim.client.issue_bill(&meter_id, &202_312, &rate_id, &5);
+    assert_eq!(sim.client.get_credit_balance(&meter_id), 7_500_000);
+
+    let nothing = sim
+        .client
+        .try_request_credit_refund(&meter_id, &sim.token, &0);
+    assert_eq!(nothing, Err(Ok(Error::InvalidInput)));
+    let beyond = sim
+        .client
+        .try_request_credit_refund(&meter_id, &sim.token, &7_500_001);
+    assert_eq!(beyond, Err(Ok(Error::InvalidInput)));
+    let unlisted = sim.env.register_contract(None, SixDecimalToken);
+    let unknown = sim
+        .client
+        .try_request_credit_refund(&meter_id, &unlisted, &3_000_000);
+    assert_eq!(unknown, Err(Ok(Error::UnsupportedToken)));
+    let unrequested = sim.client.try_refund_credit(&meter_id);
+    assert_eq!(unrequested, Err(Ok(Error::InvalidState)));
+
+    sim.client
+        .request_credit_refund(&meter_id, &sim.token, &3_000_000);
+    let twice = sim
+        .client
+        .try_request_credit_refund(&meter_id, &sim.token, &1_000_000);
+    assert_eq!(twice, Err(Ok(Error::AlreadyExists)));
+    sim.client.reject_credit_refund(&meter_id);
+    assert_eq!(sim.client.get_credit_refund_request(&meter_id), None);
+
+    // The meter changes hands while a refund waits; the credit went with it.
+    sim.client
+        .request_credit_refund(&meter_id, &sim.token, &3_000_000);
+    let buyer = Address::generate(&sim.env);
+    sim.client
+        .initiate_meter_transfer(&owner, &meter_id, &buyer);
+    sim.client.accept_meter_transfer(&buyer, &meter_id, &false);
+    let seller_balance = sim.token_balance(&owner);
+    assert_eq!(sim.client.refund_credit(&meter_id), 2_000);
+    assert_eq!(sim.token_balance(&buyer), 2_000);
+    assert_eq!(sim.token_balance(&owner), seller_balance);
+    assert_eq!(sim.client.get_credit_balance(&meter_id), 4_500_000);
+    assert_eq!(sim.client.get_credit_refund_request(&meter_id), None);
+}
+
+#[test]
+fn water_is_billed_by_volume_in_the_rate_unit() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("water", "lagos", "a", 50_000);
+    assert_eq!(sim.client.get_rate_unit(&rate_id), MeteredUnit::Kwh);
+    sim.client.set_rate_unit(&rate_id, &MeteredUnit::CubicMetre);
+    assert_eq!(sim.client.get_rate_unit(&rate_id), MeteredUnit::CubicMetre);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("WATER-1", &owner, &rate_id, "a");
+    let litres = |quantity: i128| Consumption {
+        quantity,
+        unit: MeteredUnit::Litre,
+    };
+
+    let bill = sim
+        .client
+        .issue_metered_bill(&meter_id, &202_310, &rate_id, &litres(12_000));
+    assert_eq!(
+        (bill.kwh, bill.unit, bill.amount),
+        (12, MeteredUnit::CubicMetre, 600_000)
+    );
+    let partial = sim
+        .client
+        .try_issue_metered_bill(&meter_id, &202_311, &rate_id, &litres(12_500));
+    assert_eq!(partial, Err(Ok(Error::InvalidInput)));
+    let energy = sim.client.try_issue_metered_bill(
+        &meter_id,
+        &202_311,
+        &rate_id,
+        &Consumption {
+            quantity: 12,
+            unit: MeteredUnit::Kwh,
+        },
+    );
+    assert_eq!(energy, Err(Ok(Error::UnitMismatch)));
+
+    // Readings are taken in litres and billed in cubic metres.
+    let agent = Address::generate(&sim.env);
+    sim.client.set_reading_agent(&agent, &true);
+    let october = START_TIMESTAMP - 30 * 86_400;
+    sim.client
+        .submit_metered_reading(&agent, &meter_id, &1_000, &MeteredUnit::Litre, &october);
+    let switched = sim.client.try_submit_metered_reading(
+        &agent,
+        &meter_id,
+        &7,
+        &MeteredUnit::CubicMetre,
+        &START_TIMESTAMP,
+    );
+    assert_eq!(switched, Err(Ok(Error::UnitMismatch)));
+    sim.client.submit_metered_reading(
+        &agent,
+        &meter_id,
+        &6_000,
+        &MeteredUnit::Litre,
+        &START_TIMESTAMP,
+    );
+    let read = sim.client.compute_bill_from_readings(&meter_id, &202_311);
+    assert_eq!(
+        (read.kwh, read.unit, read.amount),
+        (5, MeteredUnit::CubicMetre, 250_000)
+    );
+}
+
+#[test]
+fn deposits_are_held_apart_until_the_account_closes() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+
+    let unset = sim.client.try_pay_deposit(&owner, &meter_id, &sim.token);
+    assert_eq!(unset, Err(Ok(Error::InvalidConfig)));
+    let free = sim
+        .client
+        .try_pay_connection_fee(&owner, &meter_id, &sim.token);
+    assert_eq!(free, Err(Ok(Error::InvalidConfig)));
+    let negative = sim.client.try_set_deposit_config(&DepositConfig {
+        connection_fee: -1,
+        security_deposit: 0,
+    });
+    assert_eq!(negative, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_deposit_config(&DepositConfig {
+        connection_fee: 15_000_000,
+        security_deposit: 30_000_000,
+    });
+
+    // The fee is revenue; the deposit stays out of the reserve and off the
+    // meter's balance.
+    assert_eq!(
+        sim.client.pay_connection_fee(&owner, &meter_id, &sim.token),
+        10_000
+    );
+    let refee = sim
+        .client
+        .try_pay_connection_fee(&owner, &meter_id, &sim.token);
+    assert_eq!(refee, Err(Ok(Error::AlreadyExists)));
+    assert_eq!(
+        sim.client.get_connection_fee_paid(&meter_id),
+        Some(START_TIMESTAMP)
+    );
+    assert_eq!(
+        sim.client.pay_deposit(&owner, &meter_id, &sim.token),
+        20_000
+    );
+    let again = sim.client.try_pay_deposit(&owner, &meter_id, &sim.token);
+    assert_eq!(again, Err(Ok(Error::AlreadyExists)));
+    let held = sim.client.get_deposit(&meter_id).unwrap();
+    assert_eq!((held.amount, held.value), (20_000, 30_000_000));
+    let reserve = sim.env.as_contract(&sim.contract, || {
+        crate::invariants::reserve(&sim.env, &sim.token)
+    });
+    assert_eq!(reserve, 10_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+
+    // Debt beyond what the deposit settles blocks closure.
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &30);
+    let indebted = sim.client.try_close_account(&meter_id);
+    assert_eq!(indebted, Err(Ok(Error::InvalidState)));
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &20_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 15_000_000);
+
+    let before = sim.token_balance(&owner);
+    let account = sim.client.close_account(&meter_id);
+    assert_eq!(account.deposit_refunded, 10_000);
+    assert_eq!(sim.token_balance(&owner), before + 10_000);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+    assert_eq!(sim.client.get_deposit(&meter_id), None);
+    let reserve = sim.env.as_contract(&sim.contract, || {
+        crate::invariants::reserve(&sim.env, &sim.token)
+    });
+    assert_eq!(reserve, sim.token_balance(&sim.contract));
+    let closed = sim.client.try_pay_deposit(&owner, &meter_id, &sim.token);
+    assert_eq!(closed, Err(Ok(Error::MeterInactive)));
+}
+
+#[test]
+fn closed_accounts_refund_their_credit_and_take_no_more_payments() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &10);
+    let indebted = sim.client.try_close_account(&meter_id);
+    assert_eq!(indebted, Err(Ok(Error::InvalidState)));
+
+    // A friend overpays; the credit still goes back to the owner.
+    let friend = sim.customer(1_000_000_000);
+    sim.client
+        .pay_bill_with_oracle(&friend, &sim.token, &meter_id, &12_000);
+    assert_eq!(sim.client.get_credit_balance(&meter_id), 3_000_000);
+    sim.client
+        .request_credit_refund(&meter_id, &sim.token, &1_000_000);
+
+    let before = sim.token_balance(&owner);
+    let account = sim.client.close_account(&meter_id);
+    assert_eq!(
+        (
+            account.credit_refunded,
+            account.credit_payout,
+            account.deposit_refunded
+        ),
+        (3_000_000, 2_000, 0)
+    );
+    assert_eq!(
+        (account.summary.total_paid, account.closed_at),
+        (12_000, START_TIMESTAMP)
+    );
+    assert_eq!(sim.token_balance(&owner), before + 2_000);
+    assert_eq!(sim.client.get_credit_balance(&meter_id), 0);
+    assert_eq!(sim.client.get_credit_refund_request(&meter_id), None);
+    assert_eq!(
+        sim.client.get_closed_account_summary(&meter_id),
+        Some(account)
+    );
+
+    let paid = sim
+        .client
+        .try_pay_bill_with_oracle(&friend, &sim.token, &meter_id, &1_000);
+    assert_eq!(paid, Err(Ok(Error::MeterInactive)));
+    let again = sim.client.try_close_account(&meter_id);
+    assert_eq!(again, Err(Ok(Error::MeterInactive)));
+    // Its history is still there to read.
+    assert_eq!(
+        sim.client.get_payment(&meter_id, &0).unwrap().amount,
+        12_000
+    );
+}
+
+#[test]
+fn payment_proofs_carry_the_record_and_a_hash_bound_to_it() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let missing = sim.client.try_verify_payment(&meter_id, &0);
+    assert_eq!(missing, Err(Ok(Error::InvalidInput)));
+    let first = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    let second = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+
+    let record = sim.client.get_payment(&meter_id, &first).unwrap();
+    let proof = sim.client.verify_payment(&meter_id, &first);
+    assert_eq!(
+        (proof.meter_id.clone(), proof.payment_index),
+        (meter_id.clone(), first)
+    );
+    assert_eq!(
+        (proof.payer.clone(), proof.token.clone()),
+        (payer, sim.token.clone())
+    );
+    assert_eq!(
+        (
+            proof.amount,
+            proof.normalized_amount,
+            proof.rate,
+            proof.rate_decimals
+        ),
+        (10_000, 15_000_000, TOKEN_PRICE, PRICE_DECIMALS)
+    );
+    assert_eq!(
+        (proof.price_source, proof.timestamp),
+        (PriceSource::PushFeed, START_TIMESTAMP)
+    );
+    let preimage = (sim.contract.clone(), meter_id.clone(), first, record).to_xdr(&sim.env);
+    assert_eq!(proof.content_hash, sim.env.crypto().sha256(&preimage));
+
+    // The same payment made twice still proves as two.
+    let other = sim.client.verify_payment(&meter_id, &second);
+    assert_ne!(other.content_hash, proof.content_hash);
+    assert_eq!(sim.client.verify_payment(&meter_id, &first), proof);
+}
+
+// Counts the payments it hears about, and fails after counting when told to.
+mod vending_bridge {
+    use soroban_sdk::{contract, contracterror, contractimpl, symbol_short, Env, String};
+
+    use crate::PaymentRecord;
+
+    #[contracterror]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum BridgeError {
+        Offline = 1,
+    }
+
+    #[contract]
+    pub struct VendingBridge;
+
+    #[contractimpl]
+    impl VendingBridge {
+        pub fn set_failing(env: Env, failing: bool) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("failing"), &failing);
+        }
+
+        pub fn calls(env: Env) -> u32 {
+            env.storage()
+                .instance()
+                .get(&symbol_short!("calls"))
+                .unwrap_or(0)
+        }
+
+        pub fn last(env: Env) -> Option<(String, u32, PaymentRecord)> {
+            env.storage().instance().get(&symbol_short!("last"))
+        }
+
+        pub fn on_paid(
+            env: Env,
+            meter_id: String,
+            index: u32,
+            record: PaymentRecord,
+        ) -> Result<(), BridgeError> {
+            let calls = Self::calls(env.clone()) + 1;
+            env.storage()
+                .instance()
+                .set(&symbol_short!("calls"), &calls);
+            env.storage()
+                .instance()
+                .set(&symbol_short!("last"), &(meter_id, index, record));
+            let failing: bool = env
+                .storage()
+                .instance()
+                .get(&symbol_short!("failing"))
+                .unwrap_or(false);
+            if failing {
+                return Err(BridgeError::Offline);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn payment_hooks_hear_of_each_payment_and_never_fail_it() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let bridge = sim
+        .env
+        .register_contract(None, vending_bridge::VendingBridge);
+    let bridge_client = vending_bridge::VendingBridgeClient::new(&sim.env, &bridge);
+    sim.client
+        .set_payment_hook(&bridge, &Symbol::new(&sim.env, "on_paid"));
+
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    let record = sim.client.get_payment(&meter_id, &index).unwrap();
+    assert_eq!(bridge_client.calls(), 1);
+    assert_eq!(
+        bridge_client.last(),
+        Some((meter_id.clone(), index, record))
+    );
+
+    // A failing hook has its own writes undone and the payment goes through.
+    bridge_client.set_failing(&true);
+    let index = sim
+        .client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    assert_eq!(index, 1);
+    assert_eq!(bridge_client.calls(), 1);
+    let failed = sim.env.events().all().iter().any(|(contract, topics, _)| {
+        contract == sim.contract
+            && topics.first().is_some_and(|first| {
+                Symbol::try_from_val(&sim.env, &first)
+                    == Ok(Symbol::new(&sim.env, "payment_hook_failed"))
+            })
+    });
+    assert!(failed);
+    assert_eq!(sim.client.get_meter_summary(&meter_id).total_paid, 20_000);
+
+    bridge_client.set_failing(&false);
+    sim.client.remove_payment_hook();
+    assert_eq!(sim.client.get_payment_hook(), None);
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000);
+    assert_eq!(bridge_client.calls(), 1);
+}
+
+#[test]
+fn payments_on_behalf_record_both_parties_and_respect_the_opt_out() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let grandma = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &grandma, &rate_id, "a");
+    let grandson = sim.customer(1_000_000_000);
+    sim.client.set_loyalty_config(&LoyaltyConfig {
+        points_per_ngn: 2,
+        redeem_value: 1_000_000,
+        expiry_seconds: 3_600,
+    });
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &20);
+
+    let own = sim
+        .client
+        .try_pay_on_behalf(&grandma, &sim.token, &meter_id, &10_000);
+    assert_eq!(own, Err(Ok(Error::InvalidInput)));
+    let ownerless =
+        sim.client
+            .try_pay_on_behalf(&grandson, &sim.token, &sim.string("METER-9"), &10_000);
+    assert_eq!(ownerless, Err(Ok(Error::InvalidState)));
+
+    // Points go to the beneficiary unless the admin says otherwise.
+    assert_eq!(
+        sim.client.get_delegated_points(),
+        DelegatedPoints::Beneficiary
+    );
+    let index = sim
+        .client
+        .pay_on_behalf(&grandson, &sim.token, &meter_id, &10_000);
+    assert_eq!(
+        sim.client.get_delegated_payment(&meter_id, &index),
+        Some(DelegatedPayment {
+            payer: grandson.clone(),
+            beneficiary: grandma.clone(),
+            payment_index: index,
+        })
+    );
+    assert_eq!(
+        sim.client.get_payment(&meter_id, &index).unwrap().payer,
+        grandson
+    );
+    assert_eq!(sim.client.get_loyalty_account(&grandma).points, 3);
+    assert_eq!(sim.client.get_loyalty_account(&grandson).points, 0);
+
+    sim.client.set_delegated_points(&DelegatedPoints::Payer);
+    sim.client
+        .pay_on_behalf(&grandson, &sim.token, &meter_id, &10_000);
+    assert_eq!(sim.client.get_loyalty_account(&grandson).points, 3);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 0);
+
+    sim.client.set_third_party_opt_out(&meter_id, &true);
+    let refused = sim
+        .client
+        .try_pay_on_behalf(&grandson, &sim.token, &meter_id, &10_000);
+    assert_eq!(refused, Err(Ok(Error::InvalidState)));
+    sim.client.set_third_party_opt_out(&meter_id, &false);
+    sim.client
+        .pay_on_behalf(&grandson, &sim.token, &meter_id, &10_000);
+}
+
+#[test]
+fn emergency_credit_is_drawn_once_and_recovered_from_top_ups() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let vending = Address::generate(&sim.env);
+    sim.client.set_vending_oracle(&vending);
+
+    let unconfigured = sim
+        .client
+        .try_set_emergency_credit_config(&EmergencyCreditConfig {
+            kwh: 0,
+            grace_seconds: 0,
+        });
+    assert_eq!(unconfigured, Err(Ok(Error::InvalidConfig)));
+    sim.client
+        .set_emergency_credit_config(&EmergencyCreditConfig {
+            kwh: 5,
+            grace_seconds: 72 * 60 * 60,
+        });
+    let postpaid = sim
+        .client
+        .try_activate_emergency_credit(&meter_id, &vending);
+    assert_eq!(postpaid, Err(Ok(Error::InvalidState)));
+    sim.client.set_prepaid_meter(&meter_id, &true);
+    let stranger = Address::generate(&sim.env);
+    let unauthorised = sim
+        .client
+        .try_activate_emergency_credit(&meter_id, &stranger);
+    assert_eq!(unauthorised, Err(Ok(Error::InvalidInput)));
+
+    let drawn = sim.client.activate_emergency_credit(&meter_id, &vending);
+    assert_eq!((drawn.kwh, drawn.outstanding), (5, 5));
+    assert_eq!(drawn.expires_at, START_TIMESTAMP + 72 * 60 * 60);
+    assert_eq!(sim.client.get_voucher(&drawn.voucher_id).unwrap().kwh, 5);
+    let twice = sim
+        .client
+        .try_activate_emergency_credit(&meter_id, &vending);
+    assert_eq!(twice, Err(Ok(Error::AlreadyExists)));
+
+    // A 3 kWh top-up goes entirely to the overdraft, then a 10 kWh one
+    // repays the rest and vends 8.
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &3_000);
+    assert_eq!(sim.client.get_voucher(&2).unwrap().kwh, 0);
+    assert_eq!(
+        sim.client
+            .get_emergency_credit(&meter_id)
+            .unwrap()
+            .outstanding,
+        2
+    );
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000);
+    assert_eq!(sim.client.get_voucher(&3).unwrap().kwh, 8);
+    assert_eq!(sim.client.get_emergency_credit(&meter_id), None);
+
+    let again = sim.client.activate_emergency_credit(&meter_id, &sim.admin);
+    assert_eq!(again.voucher_id, 4);
+}
+
+#[test]
+fn payments_far_from_the_rolling_average_are_flagged_for_audit() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let config = AnomalyConfig {
+        multiple_bps: 30_000,
+        window: 4,
+        min_samples: 3,
+    };
+    let loose = sim.client.try_set_anomaly_config(&AnomalyConfig {
+        multiple_bps: 10_000,
+        ..config.clone()
+    });
+    assert_eq!(loose, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_anomaly_config(&config);
+    let pay = |amount: i128| {
+        sim.client
+            .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &amount)
+    };
+
+    for _ in 0..3 {
+        pay(10_000);
+    }
+    let rolling = sim
+        .client
+        .get_consumption_average(&meter_id, &AnomalyKind::Payment);
+    assert_eq!((rolling.average, rolling.samples), (15_000_000, 3));
+    assert!(sim.client.list_flagged_meters().is_empty());
+
+    // Five times the average is flagged but still accepted, and pulls the
+    // average up to 30,000,000.
+    let high = pay(50_000);
+    assert_eq!(
+        sim.client.get_payment(&meter_id, &high).unwrap().amount,
+        50_000
+    );
+    pay(10_000);
+    // Under a third of the average, now 26,250,000, is flagged too.
+    let low = pay(1_000);
+    let flagged = sim.client.get_anomalies(&meter_id);
+    assert_eq!(flagged.len(), 2);
+    let first = flagged.get_unchecked(0);
+    assert_eq!(
+        (first.kind, first.index, first.value, first.average),
+        (AnomalyKind::Payment, high, 75_000_000, 15_000_000)
+    );
+    let second = flagged.get_unchecked(1);
+    assert_eq!(
+        (second.index, second.value, second.average),
+        (low, 1_500_000, 26_250_000)
+    );
+    assert_eq!(
+        sim.client.list_flagged_meters(),
+        vec![&sim.env, meter_id.clone()]
+    );
+    assert_eq!(
+        sim.client
+            .get_consumption_average(&meter_id, &AnomalyKind::Reading)
+            .samples,
+        0
+    );
+
+    sim.client.clear_anomalies(&meter_id);
+    assert!(sim.client.get_anomalies(&meter_id).is_empty());
+    assert!(sim.client.list_flagged_meters().is_empty());
+    let cleared = sim.client.try_clear_anomalies(&meter_id);
+    assert_eq!(cleared, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn fee_sweeps_need_a_collector_and_accrued_fees() {
+    let sim = Simulation::new();
+    let unset = sim.client.try_sweep_fees(&sim.token);
+    assert_eq!(unset, Err(Ok(Error::InvalidConfig)));
+
+    let collector = Address::generate(&sim.env);
+    sim.client.set_fee_config(&FeeConfig {
+        collector: collector.clone(),
+        fx_spread_bps: 0,
+        service_fee_bps: 100,
+    });
+    let empty = sim.client.try_sweep_fees(&sim.token);
+    assert_eq!(empty, Err(Ok(Error::InvalidState)));
+
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    sim.client
+        .pay_bill_with_oracle(&payer, &sim.token, &meter_id, &10_000_000);
+    let swept = sim.client.sweep_fees(&sim.token);
+    assert!(swept > 0);
+    assert_eq!(sim.token_balance(&collector), swept);
+    let again = sim.client.try_sweep_fees(&sim.token);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn sponsorship_pools_cover_eligible_meters_up_to_the_monthly_cap() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let neighbour = sim.register_meter("METER-2", &owner, &rate_id, "a");
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &100);
+    sim.client.issue_bill(&neighbour, &202_311, &rate_id, &100);
+    let donor = sim.customer(1_000_000_000);
+    let lagos = sim.string("lagos");
+
+    let unknown = sim
+        .client
+        .try_fund_sponsorship(&donor, &sim.string("kano"), &sim.token, &20_000);
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+    assert_eq!(
+        sim.client
+            .fund_sponsorship(&donor, &lagos, &sim.token, &20_000),
+        20_000
+    );
+    // Donations are held apart from the contract's own funds.
+    let reserve = sim.env.as_contract(&sim.contract, || {
+        crate::invariants::reserve(&sim.env, &sim.token)
+    });
+    assert_eq!(reserve, 0);
+    sim.client.set_sponsorship_config(&SponsorshipConfig {
+        monthly_cap: 45_000_000,
+    });
+    sim.client.set_sponsorship_eligible(&meter_id, &true);
+
+    // 10,000 stroops is worth 15,000,000 NGN units. The pool's 30,000,000
+    // covers less than the cap, and the meter that is not eligible gets none.
+    let pay = |meter_id: &String| {
+        sim.client
+            .pay_bill_with_oracle(&owner, &sim.token, meter_id, &10_000)
+    };
+    pay(&meter_id);
+    pay(&neighbour);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 105_000_000);
+    assert_eq!(sim.client.get_meter_balance(&neighbour), 135_000_000);
+    let pool = sim.client.get_sponsorship_pool(&lagos, &sim.token);
+    assert_eq!((pool.balance, pool.funded, pool.used), (0, 20_000, 20_000));
+
+    // A refilled pool covers only what is left of this month's cap.
+    sim.client
+        .fund_sponsorship(&donor, &lagos, &sim.token, &100_000);
+    pay(&meter_id);
+    pay(&meter_id);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 60_000_000);
+    let sponsored = sim.client.get_sponsored_meter(&meter_id).unwrap();
+    assert_eq!(sponsored.region, lagos);
+    assert_eq!(
+        (sponsored.period, sponsored.period_covered),
+        (202_311, 45_000_000)
+    );
+    assert_eq!(sponsored.tokens_used.get(sim.token.clone()), Some(30_000));
+    assert_eq!(
+        sim.client.get_sponsorship_pool(&lagos, &sim.token).balance,
+        90_000
+    );
+
+    // Withdrawing the flag stops coverage but keeps the history.
+    sim.client.set_sponsorship_eligible(&meter_id, &false);
+    pay(&meter_id);
+    assert_eq!(sim.client.get_meter_balance(&meter_id), 45_000_000);
+    assert_eq!(
+        sim.client
+            .get_sponsored_meter(&meter_id)
+            .unwrap()
+            .total_covered,
+        45_000_000
+    );
+}
+
+#[test]
+fn billed_energy_accrues_green_credits_from_the_regional_mix() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(0);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let lagos = sim.string("lagos");
+    let unknown = sim
+        .client
+        .try_set_generation_mix(&sim.string("kano"), &4_000);
+    assert_eq!(unknown, Err(Ok(Error::InvalidInput)));
+    let over = sim.client.try_set_generation_mix(&lagos, &10_001);
+    assert_eq!(over, Err(Ok(Error::InvalidConfig)));
+    sim.client.set_generation_mix(&lagos, &4_000);
+
+    // 40% of 100 kWh is 40,000 Wh of renewable supply.
+    sim.client.issue_bill(&meter_id, &202_311, &rate_id, &100);
+    assert_eq!(sim.client.get_green_credits(&owner).balance, 40_000);
+
+    // A true-up down to 80 kWh takes back the renewable share of the 20.
+    let estimated = sim.client.register_rate_key(&RateKey {
+        utility_type: sim.string("electricity"),
+        region: lagos.clone(),
+        band: sim.string("b"),
+    });
+    let estimated_meter = sim.register_meter("METER-2", &owner, &estimated, "b");
+    sim.client.set_estimated_rate(&estimated, &1_000);
+    sim.client
+        .issue_bill(&estimated_meter, &202_311, &estimated, &100);
+    let formula = vec![
+        &sim.env,
+        TariffOp::PerUnit(Symbol::new(&sim.env, "kwh"), 900),
+    ];
+    sim.client.set_utility_rate(&estimated, &formula);
+    sim.client.true_up(&estimated_meter, &202_311, &80);
+    assert_eq!(sim.client.get_green_credits(&owner).balance, 72_000);
+
+    // Water bills accrue nothing.
+    let water = sim.register_rate("water", "lagos", "a", 50_000);
+    sim.client.set_rate_unit(&water, &MeteredUnit::CubicMetre);
+    let water_meter = sim.register_meter("WATER-1", &owner, &water, "a");
+    sim.client.issue_metered_bill(
+        &water_meter,
+        &202_311,
+        &water,
+        &Consumption {
+            quantity: 12,
+            unit: MeteredUnit::CubicMetre,
+        },
+    );
+    assert_eq!(sim.client.get_green_credits(&owner).earned, 72_000);
+
+    let buyer = Address::generate(&sim.env);
+    let locked = sim
+        .client
+        .try_transfer_green_credits(&owner, &buyer, &10_000);
+    assert_eq!(locked, Err(Ok(Error::InvalidState)));
+    sim.client.set_green_credits_transferable(&true);
+    sim.client.transfer_green_credits(&owner, &buyer, &10_000);
+    assert_eq!(sim.client.get_green_credits(&buyer).balance, 10_000);
+
+    // Retiring is always allowed, up to the balance held.
+    sim.client.set_green_credits_transferable(&false);
+    let excess = sim.client.try_retire_green_credits(&buyer, &10_001);
+    assert_eq!(excess, Err(Ok(Error::InvalidInput)));
+    let retired = sim.client.retire_green_credits(&buyer, &10_000);
+    assert_eq!(
+        (retired.balance, retired.earned, retired.retired),
+        (0, 0, 10_000)
+    );
+    let owner_credits = sim.client.get_green_credits(&owner);
+    assert_eq!(
+        (owner_credits.balance, owner_credits.earned),
+        (62_000, 72_000)
+    );
+}
+
+#[test]
+fn pruning_removes_old_records_but_keeps_the_latest_and_the_rollups() {
+    let sim = Simulation::new();
+    let rate_id = sim.register_rate("electricity", "lagos", "a", 1_500_000);
+    let owner = sim.customer(1_000_000_000);
+    let meter_id = sim.register_meter("METER-1", &owner, &rate_id, "a");
+    let agent = Address::generate(&sim.env);
+    sim.client.set_reading_agent(&agent, &true);
+    for (reading, taken_at) in [
+        (1_000, START_TIMESTAMP - 3_000),
+        (1_100, START_TIMESTAMP - 2_000),
+        (1_200, START_TIMESTAMP),
+    ] {
+        sim.client
+            .submit_meter_reading(&agent, &meter_id, &reading, &taken_at);
+    }
+    for _ in 0..3 {
+        sim.client
+            .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000);
+    }
+    sim.client.set_retention_config(&RetentionConfig {
+        retention_seconds: 1_000,
+    });
+    let records: Vec<PaymentRecord> = Vec::from_array(
+        &sim.env,
+        [0, 1, 2].map(|index| sim.client.get_payment(&meter_id, &index).unwrap()),
+    );
+    let spend = sim.client.get_monthly_spend(&meter_id, &202_311);
+
+    let stranger = Address::generate(&sim.env);
+    let unauthorised =
+        sim.client
+            .try_prune_billing_records(&stranger, &meter_id, &START_TIMESTAMP, &10);
+    assert_eq!(unauthorised, Err(Ok(Error::InvalidInput)));
+    let oversized = sim
+        .client
+        .try_prune_billing_records(&owner, &meter_id, &START_TIMESTAMP, &51);
+    assert_eq!(oversized, Err(Ok(Error::InvalidInput)));
+
+    // Payments are inside the retention period, and only the first reading
+    // is older than the timestamp asked for.
+    let state =
+        sim.client
+            .prune_billing_records(&owner, &meter_id, &(START_TIMESTAMP - 2_500), &10);
+    assert_eq!((state.payments_pruned, state.readings_pruned), (0, 1));
+    assert_eq!(sim.client.get_meter_reading(&meter_id, &0), None);
+
+    sim.advance_and_refresh(2_000);
+    sim.client
+        .pay_bill_with_oracle(&owner, &sim.token, &meter_id, &10_000);
+    let now = START_TIMESTAMP + 2_000;
+    let state = sim
+        .client
+        .prune_billing_records(&sim.admin, &meter_id, &now, &2);
+    assert_eq!((state.payments_pruned, state.readings_pruned), (2, 1));
+    assert_eq!(sim.client.get_payment(&meter_id, &0), None);
+
+    // The latest payment and reading stay, however old.
+    let state = sim
+        .client
+        .prune_billing_records(&owner, &meter_id, &now, &50);
+    assert_eq!((state.payments_pruned, state.readings_pruned), (3, 2));
+    assert_eq!(state.pruned_at, now);
+    assert!(sim.client.get_payment(&meter_id, &3).is_some());
+    assert!(sim.client.get_meter_reading(&meter_id, &2).is_some());
+    assert_eq!(sim.client.get_monthly_spend(&meter_id, &202_311), spend);
+
+    // The chain starts from zero and folds in each removed record's XDR.
+    let mut digest = BytesN::from_array(&sim.env, &[0; 32]);
+    for record in records.iter() {
+        let mut preimage = Bytes::from_array(&sim.env, &digest.to_array());
+        preimage.append(&record.to_xdr(&sim.env));
+        digest = sim.env.crypto().sha256(&preimage);
+    }
+    assert_eq!(state.payments_digest, digest);
+}
+
+#[test]
+fn recovery_takes_over_an_inactive_admin_after_the_challenge_window() {
+    let sim = Simulation::new();
+    let recovery = Address::generate(&sim.env);
+    let config = |recovery: &Address, inactivity_seconds: u64| RecoveryConfig {
+        recovery: recovery.clone(),
+        inactivity_seconds,
+        challenge_seconds: 7 * 86_400,
+    };
+    let itself = sim
+        .client
+        .try_set_recovery_config(&config(&sim.admin, 30 * 86_400));
+    assert_eq!(itself, Err(Ok(Error::InvalidConfig)));
+    let brief = sim
+        .client
+        .try_set_recovery_config(&config(&recovery, 29 * 86_400));
+    assert_eq!(brief, Err(Ok(Error::InvalidConfig)));
+    sim.client
+        .set_recovery_config(&config(&recovery, 30 * 86_400));
+    assert_eq!(sim.client.get_admin_last_active(), START_TIMESTAMP);
+
+    let early = sim.client.try_claim_admin_recovery();
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+    sim.advance(30 * 86_400);
+    let available_at = sim.client.claim_admin_recovery();
+    assert_eq!(available_at, START_TIMESTAMP + 37 * 86_400);
+    let twice = sim.client.try_claim_admin_recovery();
+    assert_eq!(twice, Err(Ok(Error::AlreadyExists)));
+
+    // Any admin call voids the claim and restarts the inactivity period.
+    sim.client.cancel_admin_recovery();
+    assert_eq!(sim.client.get_recovery_claim(), None);
+    let voided = sim.client.try_complete_admin_recovery();
+    assert_eq!(voided, Err(Ok(Error::InvalidState)));
+    let restarted = sim.client.try_claim_admin_recovery();
+    assert_eq!(restarted, Err(Ok(Error::InvalidState)));
+
+    sim.advance(30 * 86_400);
+    sim.client.claim_admin_recovery();
+    sim.advance(7 * 86_400 - 1);
+    let challenged = sim.client.try_complete_admin_recovery();
+    assert_eq!(challenged, Err(Ok(Error::InvalidState)));
+    sim.advance(1);
+    sim.client.complete_admin_recovery();
+    let admin = sim
+        .env
+        .as_contract(&sim.contract, || crate::admin::read_admin(&sim.env));
+    assert_eq!(admin, recovery);
+    // The role is used up with the handover.
+    assert_eq!(sim.client.get_recovery_config(), None);
+    assert_eq!(sim.client.get_recovery_claim(), None);
+}
+
+#[test]
+fn reservations_are_captured_once_by_a_terminal() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let now = sim.env.ledger().timestamp();
+    for expires_at in [now, now + 7 * 86_400 + 1] {
+        let reserved =
+            sim.client
+                .try_reserve_payment(&payer, &sim.token, &meter_id, &10_000_000, &expires_at);
+        assert_eq!(reserved, Err(Ok(Error::InvalidInput)));
+    }
+
+    let reservation_id =
+        sim.client
+            .reserve_payment(&payer, &sim.token, &meter_id, &10_000_000, &(now + 86_400));
+    let stranger = Address::generate(&sim.env);
+    let captured = sim
+        .client
+        .try_capture_reservation(&reservation_id, &stranger);
+    assert_eq!(captured, Err(Ok(Error::InvalidInput)));
+    sim.client.capture_reservation(&reservation_id, &sim.admin);
+    let again = sim
+        .client
+        .try_capture_reservation(&reservation_id, &sim.admin);
+    assert_eq!(again, Err(Ok(Error::InvalidState)));
+    let reclaimed = sim.client.try_reclaim_escrow(&reservation_id);
+    assert_eq!(reclaimed, Err(Ok(Error::InvalidState)));
+}
+
+#[test]
+fn terminals_capture_reservations_until_they_expire_for_the_payer() {
+    let sim = Simulation::new();
+    let payer = sim.customer(1_000_000_000);
+    let meter_id = sim.string("METER-1");
+    let terminal = Address::generate(&sim.env);
+    sim.client.set_vending_agent(&terminal, &0, &false);
+    let now = sim.env.ledger().timestamp();
+    let reserve = || {
+        sim.client
+            .reserve_payment(&payer, &sim.token, &meter_id, &10_000_000, &(now + 86_400))
+    };
+
+    // Funds leave the payer when reserved and are booked only on capture.
+    let captured = reserve();
+    let expiring = reserve();
+    assert_eq!(sim.token_balance(&payer), 980_000_000);
+    assert_eq!(sim.client.get_payment_count(&meter_id), 0);
+    let inactive = sim.client.try_capture_reservation(&captured, &terminal);
+    assert_eq!(inactive, Err(Ok(Error::InvalidInput)));
+    sim.client.set_vending_agent(&terminal, &0, &true);
+    assert_eq!(sim.client.capture_reservation(&captured, &terminal), 0);
+    assert_eq!(sim.client.get_payment_count(&meter_id), 1);
+    assert_eq!(
+        sim.client.get_escrow(&captured).unwrap().status,
+        EscrowStatus::Confirmed
+    );
+
+    let early = sim.client.try_reclaim_escrow(&expiring);
+    assert_eq!(early, Err(Ok(Error::InvalidState)));
+    sim.advance(86_400);
+    let late = sim.client.try_capture_reservation(&expiring, &terminal);
+    assert_eq!(late, Err(Ok(Error::InvalidState)));
+    sim.client.reclaim_escrow(&expiring);
+    assert_eq!(sim.token_balance(&payer), 990_000_000);
+    assert_eq!(sim.client.get_payment_count(&meter_id), 1);
+}
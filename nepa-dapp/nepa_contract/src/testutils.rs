@@ -0,0 +1,180 @@
+// Fixtures for integration tests of dApps built on the contract, behind the
+// `testutils` feature: a deployed, initialized contract with a mock token,
+// sample meters, rates and feeds, and ledger time control.
+use soroban_sdk::testutils::{Address as _, BytesN as _, Ledger};
+use soroban_sdk::{token, vec, Address, BytesN, Env, String, Symbol};
+
+use crate::meters::{MeterMetadata, SupplyPhase};
+use crate::mock_oracle::{MockPriceOracle, MockPriceOracleClient};
+use crate::tariff::{RateKey, TariffOp};
+use crate::tokens::TokenConfig;
+use crate::{NepaBillingContract, NepaBillingContractClient};
+
+pub const START_TIMESTAMP: u64 = 1_700_000_000;
+pub const TOKEN_DECIMALS: u32 = 7;
+pub const PRICE_DECIMALS: u32 = 7;
+// Oracle pair the mock token is priced by: 1,500 NGN per token.
+pub const TOKEN_PAIR: &str = "USDC/NGN";
+pub const TOKEN_PRICE: i128 = 15_000_000_000;
+const SECONDS_PER_LEDGER: u64 = 5;
+
+pub struct Simulation {
+    pub env: Env,
+    pub contract: Address,
+    pub client: NepaBillingContractClient<'static>,
+    pub admin: Address,
+    // Stellar asset contract accepted for payments, administered by `admin`.
+    pub token: Address,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulation {
+    // Contract initialized at START_TIMESTAMP with every authorization mocked,
+    // accepting `token`, pushed at TOKEN_PRICE when the oracle is embedded.
+    pub fn new() -> Self {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger()
+            .with_mut(|ledger| ledger.timestamp = START_TIMESTAMP);
+        let contract = env.register_contract(None, NepaBillingContract);
+        let client = NepaBillingContractClient::new(&env, &contract);
+        let admin = Address::generate(&env);
+        client.initialize(&admin);
+
+        let token = env.register_stellar_asset_contract(admin.clone());
+        let pair = String::from_str(&env, TOKEN_PAIR);
+        // Without the embedded oracle the pair is priced once a test gives it
+        // an external source.
+        if cfg!(feature = "embedded-oracle") {
+            client.update_price_feed(&pair, &TOKEN_PRICE, &PRICE_DECIMALS);
+        }
+        client.add_accepted_token(
+            &token,
+            &TokenConfig {
+                decimals: TOKEN_DECIMALS,
+                oracle_pair: pair,
+                min_payment: 1,
+            },
+        );
+        Simulation {
+            env,
+            contract,
+            client,
+            admin,
+            token,
+        }
+    }
+
+    pub fn string(&self, value: &str) -> String {
+        String::from_str(&self.env, value)
+    }
+
+    pub fn mint(&self, to: &Address, amount: i128) {
+        token::StellarAssetClient::new(&self.env, &self.token).mint(to, &amount);
+    }
+
+    pub fn token_balance(&self, of: &Address) -> i128 {
+        token::Client::new(&self.env, &self.token).balance(of)
+    }
+
+    // A fresh address holding `amount` of the mock token.
+    pub fn customer(&self, amount: i128) -> Address {
+        let customer = Address::generate(&self.env);
+        self.mint(&customer, amount);
+        customer
+    }
+
+    // Sets a price feed at PRICE_DECIMALS, creating it if needed.
+    pub fn set_price(&self, pair: &str, price: i128) {
+        self.client
+            .update_price_feed(&self.string(pair), &price, &PRICE_DECIMALS);
+    }
+
+    // Registers the rate key, with its region and utility type, under a flat
+    // `per_kwh` formula. Returns the rate id.
+    pub fn register_rate(
+        &self,
+        utility_type: &str,
+        region: &str,
+        band: &str,
+        per_kwh: i128,
+    ) -> String {
+        let key = RateKey {
+            utility_type: self.string(utility_type),
+            region: self.string(region),
+            band: self.string(band),
+        };
+        if !self.client.list_regions().contains(&key.region) {
+            self.client.register_region(&key.region);
+        }
+        if !self.client.list_utility_types().contains(&key.utility_type) {
+            self.client.register_utility_type(&key.utility_type);
+        }
+        let rate_id = self.client.register_rate_key(&key);
+        let formula = vec![
+            &self.env,
+            TariffOp::PerUnit(Symbol::new(&self.env, "kwh"), per_kwh),
+        ];
+        self.client.set_utility_rate(&rate_id, &formula);
+        rate_id
+    }
+
+    // A single-phase meter owned by `owner`, connected now and billed under
+    // `rate_id` in `band`.
+    pub fn register_meter(
+        &self,
+        meter_id: &str,
+        owner: &Address,
+        rate_id: &String,
+        band: &str,
+    ) -> String {
+        let meter_id = self.string(meter_id);
+        self.client.set_meter_owner(&meter_id, owner);
+        self.client.assign_meter_rate(&meter_id, rate_id);
+        self.client.set_meter_metadata(
+            &meter_id,
+            &MeterMetadata {
+                band: self.string(band),
+                phase: SupplyPhase::Single,
+                max_load_watts: 5_000,
+                location_hash: BytesN::random(&self.env),
+                connected_at: self.env.ledger().timestamp(),
+                band_changes: vec![&self.env],
+            },
+        );
+        meter_id
+    }
+
+    // A MockPriceOracle deployed alongside the contract, to serve as a feed's
+    // external price source.
+    pub fn register_mock_oracle(&self) -> MockPriceOracleClient<'static> {
+        let oracle = self.env.register_contract(None, MockPriceOracle);
+        MockPriceOracleClient::new(&self.env, &oracle)
+    }
+
+    // Moves the ledger `seconds` forward, advancing the sequence at one ledger
+    // per SECONDS_PER_LEDGER.
+    pub fn advance(&self, seconds: u64) {
+        self.env.ledger().with_mut(|ledger| {
+            ledger.timestamp += seconds;
+            ledger.sequence_number += (seconds / SECONDS_PER_LEDGER) as u32;
+        });
+    }
+
+    // As `advance`, then re-submits every price feed at its last price so
+    // payments do not fail on a stale feed.
+    pub fn advance_and_refresh(&self, seconds: u64) {
+        self.advance(seconds);
+        for feed_id in self.client.get_price_feed_ids().iter() {
+            if let Some(feed) = self.client.get_price_feed(&feed_id) {
+                self.client
+                    .update_price_feed(&feed_id, &feed.price, &feed.decimals);
+            }
+        }
+    }
+}
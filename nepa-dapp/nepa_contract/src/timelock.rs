@@ -0,0 +1,214 @@
+use soroban_sdk::{contracttype, Env, String, Symbol, Vec};
+
+use crate::admin;
+use crate::billing;
+use crate::errors::Error;
+use crate::multisig;
+use crate::oracle::{OracleConfig, OracleManager};
+use crate::storage;
+use crate::tariff::{self, TariffOp, TouSchedule};
+use crate::units::MeteredUnit;
+
+// Customer-facing configuration that changes only after the delay has passed.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TimelockChange {
+    UtilityRate(String, Vec<TariffOp>),
+    // Entries apply independently, as in `update_utility_rates_batch`.
+    UtilityRates(Vec<(String, Vec<TariffOp>)>),
+    // rate_id, formula, effective_from
+    ScheduledRate(String, Vec<TariffOp>, u64),
+    // rate_id, sunset_at
+    RateDeprecation(String, u64),
+    RateRemoval(String),
+    TouSchedule(String, TouSchedule),
+    RateUnit(String, MeteredUnit),
+    // rate_id, NGN units per kWh
+    EstimatedRate(String, i128),
+    OracleConfig(OracleConfig),
+    // A new delay, itself subject to the current one.
+    Delay(u64),
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChangeStatus {
+    Queued,
+    Executed,
+    Cancelled,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueuedChange {
+    pub change: TimelockChange,
+    // Earliest time the change may be executed.
+    pub eta: u64,
+    pub status: ChangeStatus,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TimelockKey {
+    TimelockDelay,
+    NextChangeId,
+    QueuedChange(u64),
+}
+
+// 0 until configured, in which case changes apply immediately.
+pub fn delay(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&TimelockKey::TimelockDelay)
+        .unwrap_or(0)
+}
+
+pub fn read_change(env: &Env, change_id: u64) -> Option<QueuedChange> {
+    env.storage()
+        .persistent()
+        .get(&TimelockKey::QueuedChange(change_id))
+}
+
+fn write_change(env: &Env, change_id: u64, queued: &QueuedChange) {
+    storage::write_persistent(env, &TimelockKey::QueuedChange(change_id), queued);
+}
+
+// Direct setters are refused while a delay is in force.
+pub fn ensure_not_required(env: &Env) -> Result<(), Error> {
+    if delay(env) > 0 {
+        return Err(Error::TimelockRequired);
+    }
+    Ok(())
+}
+
+fn validate(env: &Env, change: &TimelockChange) -> Result<(), Error> {
+    match change {
+        TimelockChange::UtilityRate(rate_id, formula) => {
+            tariff::ensure_rate_registered(env, rate_id)?;
+            tariff::validate(formula)
+        }
+        TimelockChange::UtilityRates(rates) => tariff::validate_batch(rates),
+        // The formula must still be in the future once the delay has passed.
+        TimelockChange::ScheduledRate(rate_id, formula, effective_from) => {
+            if *effective_from <= env.ledger().timestamp() + delay(env) {
+                return Err(Error::InvalidInput);
+            }
+            tariff::ensure_rate_registered(env, rate_id)?;
+            tariff::validate(formula)
+        }
+        // Checked against the rate's state when executed.
+        TimelockChange::RateDeprecation(..) | TimelockChange::RateRemoval(_) => Ok(()),
+        TimelockChange::TouSchedule(_, schedule) => tariff::validate_tou_schedule(schedule),
+        TimelockChange::RateUnit(..) => Ok(()),
+        TimelockChange::EstimatedRate(_, per_kwh) => {
+            billing::validate_estimated_rate(env, *per_kwh)
+        }
+        TimelockChange::OracleConfig(config) => OracleManager::validate_config(config),
+        TimelockChange::Delay(_) => Ok(()),
+    }
+}
+
+fn apply(env: &Env, change: &TimelockChange) -> Result<(), Error> {
+    match change {
+        TimelockChange::UtilityRate(rate_id, formula) => tariff::apply_rate(env, rate_id, formula),
+        TimelockChange::UtilityRates(rates) => tariff::apply_rates_batch(env, rates).map(|_| ()),
+        TimelockChange::ScheduledRate(rate_id, formula, effective_from) => {
+            tariff::apply_scheduled_rate(env, rate_id, formula, *effective_from)
+        }
+        TimelockChange::RateDeprecation(rate_id, sunset_at) => {
+            tariff::apply_deprecation(env, rate_id, *sunset_at)
+        }
+        TimelockChange::RateRemoval(rate_id) => tariff::apply_removal(env, rate_id),
+        TimelockChange::TouSchedule(rate_id, schedule) => {
+            tariff::apply_tou_schedule(env, rate_id, schedule)
+        }
+        TimelockChange::RateUnit(rate_id, unit) => tariff::apply_rate_unit(env, rate_id, *unit),
+        TimelockChange::EstimatedRate(rate_id, per_kwh) => {
+            billing::apply_estimated_rate(env, rate_id, *per_kwh)
+        }
+        TimelockChange::OracleConfig(config) => {
+            OracleManager::store_config(env, config);
+            Ok(())
+        }
+        TimelockChange::Delay(seconds) => {
+            env.storage()
+                .instance()
+                .set(&TimelockKey::TimelockDelay, seconds);
+            Ok(())
+        }
+    }
+}
+
+// Queues a change to take effect after the delay; returns its id.
+pub fn enqueue(env: &Env, change: &TimelockChange) -> Result<u64, Error> {
+    validate(env, change)?;
+    let change_id: u64 = env
+        .storage()
+        .instance()
+        .get(&TimelockKey::NextChangeId)
+        .unwrap_or(1);
+    env.storage()
+        .instance()
+        .set(&TimelockKey::NextChangeId, &(change_id + 1));
+
+    let queued = QueuedChange {
+        change: change.clone(),
+        eta: env.ledger().timestamp() + delay(env),
+        status: ChangeStatus::Queued,
+    };
+    write_change(env, change_id, &queued);
+    env.events().publish(
+        (Symbol::new(env, "change_queued"), change_id),
+        (queued.change.clone(), queued.eta),
+    );
+    Ok(change_id)
+}
+
+// Rate changes made by multisig honour the timelock too: applied now when no
+// delay is configured, queued otherwise.
+pub fn apply_or_enqueue(env: &Env, change: &TimelockChange) -> Result<(), Error> {
+    if delay(env) == 0 {
+        return apply(env, change);
+    }
+    enqueue(env, change).map(|_| ())
+}
+
+// Tariff changes go through multisig once a signer set exists; its
+// proposals are queued from `multisig::execute` instead.
+pub fn queue(env: &Env, change: &TimelockChange) -> Result<u64, Error> {
+    if !matches!(
+        change,
+        TimelockChange::OracleConfig(_) | TimelockChange::Delay(_)
+    ) {
+        multisig::ensure_not_required(env)?;
+    }
+    admin::require_admin(env);
+    enqueue(env, change)
+}
+
+// Anyone may execute a change once its eta has passed.
+pub fn execute(env: &Env, change_id: u64) -> Result<(), Error> {
+    let mut queued = read_change(env, change_id).ok_or(Error::InvalidInput)?;
+    if queued.status != ChangeStatus::Queued || env.ledger().timestamp() < queued.eta {
+        return Err(Error::InvalidState);
+    }
+    queued.status = ChangeStatus::Executed;
+    write_change(env, change_id, &queued);
+    apply(env, &queued.change)?;
+    env.events()
+        .publish((Symbol::new(env, "change_executed"), change_id), ());
+    Ok(())
+}
+
+pub fn cancel(env: &Env, change_id: u64) -> Result<(), Error> {
+    admin::require_admin(env);
+    let mut queued = read_change(env, change_id).ok_or(Error::InvalidInput)?;
+    if queued.status != ChangeStatus::Queued {
+        return Err(Error::InvalidState);
+    }
+    queued.status = ChangeStatus::Cancelled;
+    write_change(env, change_id, &queued);
+    env.events()
+        .publish((Symbol::new(env, "change_cancelled"), change_id), ());
+    Ok(())
+}
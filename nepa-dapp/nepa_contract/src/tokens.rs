@@ -0,0 +1,158 @@
+use soroban_fixed_point_math::FixedPoint;
+use soroban_sdk::{contracttype, token, Address, Env, String, Symbol, Vec};
+
+use crate::admin;
+use crate::bounds;
+use crate::errors::Error;
+use crate::math;
+use crate::storage;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenConfig {
+    pub decimals: u32,
+    // Price feed used to value this token, e.g. "USDC/NGN".
+    pub oracle_pair: String,
+    pub min_payment: i128,
+}
+
+// Token amounts from different tokens are only summed or compared after
+// conversion to this many decimals.
+pub const ACCOUNTING_DECIMALS: u32 = 7;
+
+// What the token contract reports about itself, recorded when it is first
+// accepted and kept after it is removed so its history stays readable.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenMetadata {
+    pub decimals: u32,
+    pub symbol: String,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TokenKey {
+    AcceptedTokens,
+    AcceptedToken(Address),
+    TokenMetadata(Address),
+}
+
+pub fn metadata(env: &Env, token: &Address) -> Option<TokenMetadata> {
+    env.storage()
+        .persistent()
+        .get(&TokenKey::TokenMetadata(token.clone()))
+}
+
+// The token's decimals: from its config while it is accepted, which the
+// payment path has already loaded, else from the metadata kept after removal.
+pub fn decimals(env: &Env, token: &Address) -> u32 {
+    match read_config(env, token) {
+        Some(config) => config.decimals,
+        None => metadata(env, token).map_or(ACCOUNTING_DECIMALS, |metadata| metadata.decimals),
+    }
+}
+
+// Converts a raw token amount into 7-decimal accounting units, rounding down.
+pub fn to_units(amount: i128, token_decimals: u32) -> Result<i128, Error> {
+    let units = if token_decimals >= ACCOUNTING_DECIMALS {
+        amount.fixed_div_floor(math::pow10(token_decimals - ACCOUNTING_DECIMALS)?, 1)
+    } else {
+        amount.checked_mul(math::pow10(ACCOUNTING_DECIMALS - token_decimals)?)
+    };
+    units.ok_or(Error::ArithmeticOverflow)
+}
+
+pub fn read_config(env: &Env, token: &Address) -> Option<TokenConfig> {
+    env.storage()
+        .persistent()
+        .get(&TokenKey::AcceptedToken(token.clone()))
+}
+
+pub fn list(env: &Env) -> Vec<Address> {
+    env.storage()
+        .instance()
+        .get(&TokenKey::AcceptedTokens)
+        .unwrap_or(Vec::new(env))
+}
+
+// Rejects payments in tokens the treasury has not listed, and zero amounts or
+// dust below the token's minimum.
+pub fn require_accepted(env: &Env, token: &Address, amount: i128) -> Result<TokenConfig, Error> {
+    let config = read_config(env, token).ok_or(Error::UnsupportedToken)?;
+    bounds::amount(env, amount)?;
+    if amount == 0 || amount < config.min_payment {
+        return Err(Error::AmountTooSmall);
+    }
+    Ok(config)
+}
+
+// Writes a token's config and keeps the allowlist index in step.
+pub fn store(env: &Env, token: &Address, config: &TokenConfig) {
+    let mut tokens = list(env);
+    if !tokens.contains(token) {
+        tokens.push_back(token.clone());
+        env.storage()
+            .instance()
+            .set(&TokenKey::AcceptedTokens, &tokens);
+    }
+    storage::write_persistent(env, &TokenKey::AcceptedToken(token.clone()), config);
+}
+
+pub fn add(env: &Env, token: &Address, config: &TokenConfig) -> Result<(), Error> {
+    admin::require_treasury(env);
+    if config.min_payment < 0 || config.decimals > bounds::MAX_DECIMALS {
+        return Err(Error::InvalidConfig);
+    }
+    // The configured decimals must match what the token itself reports.
+    let client = token::Client::new(env, token);
+    let metadata = TokenMetadata {
+        decimals: client.decimals(),
+        symbol: client.symbol(),
+    };
+    if config.decimals != metadata.decimals {
+        return Err(Error::InvalidConfig);
+    }
+    storage::write_persistent(env, &TokenKey::TokenMetadata(token.clone()), &metadata);
+
+    store(env, token, config);
+    env.events().publish(
+        (Symbol::new(env, "token_accepted"), token.clone()),
+        config.clone(),
+    );
+    Ok(())
+}
+
+// Changes the smallest payment accepted in the token, in its own units.
+pub fn set_min_payment(env: &Env, token: &Address, min_payment: i128) -> Result<(), Error> {
+    admin::require_treasury(env);
+    if min_payment < 0 {
+        return Err(Error::InvalidConfig);
+    }
+    let mut config = read_config(env, token).ok_or(Error::UnsupportedToken)?;
+    config.min_payment = min_payment;
+    store(env, token, &config);
+    env.events().publish(
+        (Symbol::new(env, "min_payment_set"), token.clone()),
+        min_payment,
+    );
+    Ok(())
+}
+
+pub fn remove(env: &Env, token: &Address) -> Result<(), Error> {
+    admin::require_treasury(env);
+    let mut tokens = list(env);
+    let Some(index) = tokens.first_index_of(token) else {
+        return Err(Error::UnsupportedToken);
+    };
+    tokens.remove(index);
+    env.storage()
+        .instance()
+        .set(&TokenKey::AcceptedTokens, &tokens);
+    env.storage()
+        .persistent()
+        .remove(&TokenKey::AcceptedToken(token.clone()));
+
+    env.events()
+        .publish((Symbol::new(env, "token_removed"), token.clone()), ());
+    Ok(())
+}
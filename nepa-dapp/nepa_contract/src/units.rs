@@ -0,0 +1,43 @@
+use soroban_sdk::contracttype;
+
+use crate::errors::Error;
+use crate::math;
+
+const LITRES_PER_CUBIC_METRE: i128 = 1_000;
+
+// What a rate prices and a meter counts: energy for electricity, volume for
+// water and gas.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MeteredUnit {
+    Kwh,
+    CubicMetre,
+    Litre,
+}
+
+// A quantity read or reported in a given unit.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Consumption {
+    pub quantity: i128,
+    pub unit: MeteredUnit,
+}
+
+// Converts `quantity` between units of the same dimension. Whole cubic metres
+// only: litres that do not make up a whole number of them are refused rather
+// than rounded.
+pub fn convert(quantity: i128, from: MeteredUnit, to: MeteredUnit) -> Result<i128, Error> {
+    match (from, to) {
+        _ if from == to => Ok(quantity),
+        (MeteredUnit::CubicMetre, MeteredUnit::Litre) => {
+            math::mul(quantity, LITRES_PER_CUBIC_METRE)
+        }
+        (MeteredUnit::Litre, MeteredUnit::CubicMetre) => {
+            if quantity % LITRES_PER_CUBIC_METRE != 0 {
+                return Err(Error::InvalidInput);
+            }
+            Ok(quantity / LITRES_PER_CUBIC_METRE)
+        }
+        _ => Err(Error::UnitMismatch),
+    }
+}
@@ -0,0 +1,124 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol, Vec};
+
+use crate::admin;
+use crate::errors::Error;
+use crate::storage;
+
+const WINDOW_SECONDS: u64 = 3600;
+// Roughly one hour of ledgers, so a window's counters outlive the window itself.
+const ACTIVITY_TTL_LEDGERS: u32 = 720;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VelocityConfig {
+    pub max_payments_per_hour: u32,
+    pub max_distinct_meters: u32,
+    // When enabled, flagged payers need the attestor to co-sign every payment.
+    pub require_attestation: bool,
+    pub attestor: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PayerActivity {
+    pub window_start: u64,
+    pub payment_count: u32,
+    pub meters: Vec<String>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VelocityKey {
+    VelocityConfig,
+    PayerActivity(Address),
+    FlaggedPayer(Address),
+}
+
+pub fn read_config(env: &Env) -> Option<VelocityConfig> {
+    env.storage().instance().get(&VelocityKey::VelocityConfig)
+}
+
+pub fn is_flagged(env: &Env, payer: &Address) -> bool {
+    env.storage()
+        .persistent()
+        .has(&VelocityKey::FlaggedPayer(payer.clone()))
+}
+
+// Called before funds move: a flagged payer's payment also needs the attestor's signature.
+pub fn require_attestation(env: &Env, payer: &Address) {
+    let Some(config) = read_config(env) else {
+        return;
+    };
+    if config.require_attestation && is_flagged(env, payer) {
+        config.attestor.require_auth();
+    }
+}
+
+// Called after a payment succeeds; flags the payer once a threshold is crossed.
+pub fn record_payment(env: &Env, payer: &Address, meter_id: &String) {
+    let Some(config) = read_config(env) else {
+        return;
+    };
+
+    let now = env.ledger().timestamp();
+    let window_start = now - now % WINDOW_SECONDS;
+    let key = VelocityKey::PayerActivity(payer.clone());
+
+    let mut activity: PayerActivity = env
+        .storage()
+        .temporary()
+        .get(&key)
+        .filter(|a: &PayerActivity| a.window_start == window_start)
+        .unwrap_or(PayerActivity {
+            window_start,
+            payment_count: 0,
+            meters: Vec::new(env),
+        });
+
+    activity.payment_count += 1;
+    // One entry past the threshold is enough to know it was exceeded.
+    if !activity.meters.contains(meter_id) && activity.meters.len() <= config.max_distinct_meters {
+        activity.meters.push_back(meter_id.clone());
+    }
+
+    env.storage().temporary().set(&key, &activity);
+    env.storage()
+        .temporary()
+        .extend_ttl(&key, ACTIVITY_TTL_LEDGERS, ACTIVITY_TTL_LEDGERS);
+
+    let exceeded = activity.payment_count > config.max_payments_per_hour
+        || activity.meters.len() > config.max_distinct_meters;
+    if exceeded && !is_flagged(env, payer) {
+        storage::write_persistent(env, &VelocityKey::FlaggedPayer(payer.clone()), &now);
+        env.events().publish(
+            (Symbol::new(env, "suspicious_activity"), payer.clone()),
+            (activity.payment_count, activity.meters.len()),
+        );
+    }
+}
+
+pub fn set_config(env: &Env, config: &VelocityConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    if config.max_payments_per_hour == 0 || config.max_distinct_meters == 0 {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&VelocityKey::VelocityConfig, config);
+    Ok(())
+}
+
+pub fn read_activity(env: &Env, payer: &Address) -> Option<PayerActivity> {
+    env.storage()
+        .temporary()
+        .get(&VelocityKey::PayerActivity(payer.clone()))
+}
+
+pub fn clear_flag(env: &Env, payer: &Address) {
+    admin::require_admin(env);
+    env.storage()
+        .persistent()
+        .remove(&VelocityKey::FlaggedPayer(payer.clone()));
+    env.events()
+        .publish((Symbol::new(env, "payer_flag_cleared"), payer.clone()), ());
+}
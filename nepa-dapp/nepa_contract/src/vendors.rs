@@ -0,0 +1,140 @@
+use soroban_sdk::{contracttype, token, Address, Env, String, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::errors::Error;
+use crate::math;
+use crate::payments;
+use crate::storage;
+
+const BPS_DENOMINATOR: i128 = 10_000;
+
+// A registered vending agent and the share of each sale it earns.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VendingAgent {
+    pub commission_bps: u32,
+    pub active: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VendorKey {
+    VendingAgent(Address),
+    // (agent, token) -> commission earned and not yet claimed.
+    Commission(Address, Address),
+    // token -> unclaimed commission across all agents, held back from the pool.
+    CommissionOwed(Address),
+}
+
+pub fn read_agent(env: &Env, agent: &Address) -> Option<VendingAgent> {
+    env.storage()
+        .persistent()
+        .get(&VendorKey::VendingAgent(agent.clone()))
+}
+
+pub fn set_agent(
+    env: &Env,
+    agent: &Address,
+    commission_bps: u32,
+    active: bool,
+) -> Result<(), Error> {
+    admin::require_admin(env);
+    if commission_bps as i128 > BPS_DENOMINATOR {
+        return Err(Error::InvalidConfig);
+    }
+    let record = VendingAgent {
+        commission_bps,
+        active,
+    };
+    storage::write_persistent(env, &VendorKey::VendingAgent(agent.clone()), &record);
+    audit::role_change(env, "vending_agent", agent, active);
+    env.events().publish(
+        (Symbol::new(env, "vending_agent_updated"), agent.clone()),
+        (commission_bps, active),
+    );
+    Ok(())
+}
+
+pub fn commission(env: &Env, agent: &Address, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&VendorKey::Commission(agent.clone(), token_address.clone()))
+        .unwrap_or(0)
+}
+
+pub fn owed(env: &Env, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&VendorKey::CommissionOwed(token_address.clone()))
+        .unwrap_or(0)
+}
+
+fn add_commission(
+    env: &Env,
+    agent: &Address,
+    token_address: &Address,
+    delta: i128,
+) -> Result<(), Error> {
+    storage::write_persistent(
+        env,
+        &VendorKey::Commission(agent.clone(), token_address.clone()),
+        &math::add(commission(env, agent, token_address), delta)?,
+    );
+    storage::write_persistent(
+        env,
+        &VendorKey::CommissionOwed(token_address.clone()),
+        &math::add(owed(env, token_address), delta)?,
+    );
+    Ok(())
+}
+
+// The agent pays the customer's meter in full from its own funds; its
+// commission is set aside out of the utility's share of the sale.
+pub fn pay_via_agent(
+    env: &Env,
+    agent: &Address,
+    token_address: &Address,
+    meter_id: &String,
+    amount: i128,
+) -> Result<u32, Error> {
+    let record = read_agent(env, agent)
+        .filter(|record| record.active)
+        .ok_or(Error::InvalidInput)?;
+    let index = payments::pay(env, agent, token_address, meter_id, amount)?;
+
+    let earned = accounting::apply_bps(amount, record.commission_bps as i128)?;
+    if earned > 0 {
+        add_commission(env, agent, token_address, earned)?;
+    }
+    env.events().publish(
+        (Symbol::new(env, "agent_sale"), agent.clone()),
+        (meter_id.clone(), token_address.clone(), amount, earned),
+    );
+    Ok(index)
+}
+
+// Pays out the agent's commission in `token_address`. Returns the amount.
+pub fn claim(env: &Env, agent: &Address, token_address: &Address) -> Result<i128, Error> {
+    agent.require_auth();
+    let amount = commission(env, agent, token_address);
+    if amount <= 0 {
+        return Err(Error::InvalidState);
+    }
+    add_commission(env, agent, token_address, -amount)?;
+    token::Client::new(env, token_address).transfer(
+        &env.current_contract_address(),
+        agent,
+        &amount,
+    );
+    audit::record(
+        env,
+        AuditAction::Withdrawal(agent.clone(), token_address.clone(), amount),
+    );
+    env.events().publish(
+        (Symbol::new(env, "commission_claimed"), agent.clone()),
+        (token_address.clone(), amount),
+    );
+    Ok(amount)
+}
@@ -0,0 +1,33 @@
+use soroban_sdk::{contracttype, Env, String, Vec};
+
+// Bump whenever the layout of stored data changes in a way that needs migration.
+// 3: payment records carry their price source and fees.
+// 4: payment records carry their global sequence number.
+// 5: bills carry the rate snapshot they were computed from.
+pub const STORAGE_SCHEMA_VERSION: u32 = 5;
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VersionInfo {
+    pub version: String,
+    pub schema_version: u32,
+    pub features: Vec<String>,
+    pub git_commit: String,
+}
+
+pub fn version_info(env: &Env) -> VersionInfo {
+    let mut features = Vec::new(env);
+    if cfg!(feature = "testutils") {
+        features.push_back(String::from_str(env, "testutils"));
+    }
+    if cfg!(feature = "embedded-oracle") {
+        features.push_back(String::from_str(env, "embedded-oracle"));
+    }
+
+    VersionInfo {
+        version: String::from_str(env, env!("CARGO_PKG_VERSION")),
+        schema_version: STORAGE_SCHEMA_VERSION,
+        features,
+        git_commit: String::from_str(env, env!("NEPA_GIT_COMMIT")),
+    }
+}
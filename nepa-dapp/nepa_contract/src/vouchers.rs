@@ -0,0 +1,146 @@
+use soroban_sdk::{contracttype, Address, Env, String, Symbol};
+
+use crate::accounting::PaymentRecord;
+use crate::admin;
+use crate::billing;
+use crate::emergency;
+use crate::errors::Error;
+use crate::escrow;
+use crate::storage;
+
+// Bounds the unit search; 2^40 kWh is far beyond any single top-up.
+const MAX_UNIT_SEARCH_BITS: u32 = 40;
+
+// A vending record for a prepaid top-up. Off-chain vending systems turn it
+// into an STS token and mark it redeemed once the meter has been credited.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Voucher {
+    pub voucher_id: u64,
+    pub meter_id: String,
+    pub kwh: i128,
+    pub nonce: u64,
+    pub issued_at: u64,
+    pub redeemed: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VoucherKey {
+    NextVoucherId,
+    Voucher(u64),
+    PrepaidMeter(String),
+}
+
+pub fn read(env: &Env, voucher_id: u64) -> Option<Voucher> {
+    env.storage()
+        .persistent()
+        .get(&VoucherKey::Voucher(voucher_id))
+}
+
+pub fn is_prepaid(env: &Env, meter_id: &String) -> bool {
+    env.storage()
+        .persistent()
+        .get(&VoucherKey::PrepaidMeter(meter_id.clone()))
+        .unwrap_or(false)
+}
+
+pub fn set_prepaid(env: &Env, meter_id: &String, prepaid: bool) {
+    admin::require_admin(env);
+    storage::write_persistent(env, &VoucherKey::PrepaidMeter(meter_id.clone()), &prepaid);
+}
+
+// Largest whole kWh whose full bill (tariff, subsidy, taxes) `value` covers.
+fn units_for(env: &Env, meter_id: &String, rate_id: &String, value: i128) -> Result<i128, Error> {
+    let covers = |kwh: i128| -> Result<bool, Error> {
+        Ok(billing::assess(env, meter_id, rate_id, kwh)?.net()? <= value)
+    };
+    let mut low = 0;
+    let mut high = 1;
+    while covers(high)? {
+        low = high;
+        high *= 2;
+        if high > 1 << MAX_UNIT_SEARCH_BITS {
+            return Err(Error::InvalidTariff);
+        }
+    }
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        if covers(mid)? {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+// Issues a voucher for a settled payment to a prepaid meter with a rate.
+pub fn issue_for_payment(env: &Env, meter_id: &String, record: &PaymentRecord) {
+    if !is_prepaid(env, meter_id) {
+        return;
+    }
+    let Some(rate_id) = billing::meter_rate(env, meter_id) else {
+        return;
+    };
+    // A tariff that cannot be evaluated leaves the payment as account credit.
+    let Ok(kwh) = units_for(env, meter_id, &rate_id, record.normalized_amount) else {
+        return;
+    };
+    let kwh = emergency::recover(env, meter_id, kwh);
+    issue(env, meter_id, kwh, record.timestamp);
+}
+
+// Ids start at 1 and are never reused.
+pub fn issue(env: &Env, meter_id: &String, kwh: i128, issued_at: u64) -> Voucher {
+    let voucher_id: u64 = env
+        .storage()
+        .instance()
+        .get(&VoucherKey::NextVoucherId)
+        .unwrap_or(1);
+    env.storage()
+        .instance()
+        .set(&VoucherKey::NextVoucherId, &(voucher_id + 1));
+
+    let voucher = Voucher {
+        voucher_id,
+        meter_id: meter_id.clone(),
+        kwh,
+        nonce: env.prng().gen(),
+        issued_at,
+        redeemed: false,
+    };
+    storage::write_persistent(env, &VoucherKey::Voucher(voucher_id), &voucher);
+    env.events().publish(
+        (Symbol::new(env, "voucher_issued"), meter_id.clone()),
+        (voucher_id, kwh, voucher.nonce),
+    );
+    voucher
+}
+
+// Called by the vending system (admin or vending oracle) once the code is used.
+pub fn redeem(
+    env: &Env,
+    meter_id: &String,
+    voucher_id: u64,
+    redeemer: &Address,
+) -> Result<Voucher, Error> {
+    let authorised = *redeemer == admin::read_admin(env)
+        || escrow::vending_oracle(env).as_ref() == Some(redeemer);
+    if !authorised {
+        return Err(Error::InvalidInput);
+    }
+    redeemer.require_auth();
+
+    let mut voucher = read(env, voucher_id).ok_or(Error::InvalidInput)?;
+    if voucher.meter_id != *meter_id || voucher.redeemed {
+        return Err(Error::InvalidState);
+    }
+    voucher.redeemed = true;
+    storage::write_persistent(env, &VoucherKey::Voucher(voucher_id), &voucher);
+    env.events().publish(
+        (Symbol::new(env, "voucher_redeemed"), meter_id.clone()),
+        voucher_id,
+    );
+    Ok(voucher)
+}
@@ -0,0 +1,183 @@
+use soroban_sdk::{contracttype, token, Address, Env, Map, Symbol};
+
+use crate::accounting;
+use crate::admin;
+use crate::audit::{self, AuditAction};
+use crate::disputes;
+use crate::errors::Error;
+use crate::invariants;
+use crate::math;
+use crate::storage;
+use crate::tokens;
+
+const BPS_DENOMINATOR: u32 = 10_000;
+
+// Market participants collected retail revenue is passed on to.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum WholesalePool {
+    Generation,
+    Transmission,
+    Distribution,
+    Regulator,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolShare {
+    // Address allowed to claim the pool.
+    pub recipient: Address,
+    pub share_bps: u32,
+}
+
+// Shares must add up to the whole of each distribution.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SplitConfig {
+    pub shares: Map<WholesalePool, PoolShare>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WholesaleKey {
+    WholesaleSplit,
+    // (pool, token) -> distributed and not yet claimed.
+    PoolBalance(WholesalePool, Address),
+    // token -> unclaimed across all pools, held back from the reserve.
+    PooledTotal(Address),
+}
+
+pub fn read_split(env: &Env) -> Option<SplitConfig> {
+    env.storage().instance().get(&WholesaleKey::WholesaleSplit)
+}
+
+pub fn set_split(env: &Env, config: &SplitConfig) -> Result<(), Error> {
+    admin::require_admin(env);
+    let mut total: u32 = 0;
+    for (_, share) in config.shares.iter() {
+        if share.share_bps == 0 {
+            return Err(Error::InvalidConfig);
+        }
+        total = total.saturating_add(share.share_bps);
+    }
+    if total != BPS_DENOMINATOR {
+        return Err(Error::InvalidConfig);
+    }
+    env.storage()
+        .instance()
+        .set(&WholesaleKey::WholesaleSplit, config);
+    env.events().publish(
+        (Symbol::new(env, "wholesale_split_set"),),
+        config.shares.len(),
+    );
+    Ok(())
+}
+
+pub fn pool_balance(env: &Env, pool: WholesalePool, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&WholesaleKey::PoolBalance(pool, token_address.clone()))
+        .unwrap_or(0)
+}
+
+pub fn pooled_in(env: &Env, token_address: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&WholesaleKey::PooledTotal(token_address.clone()))
+        .unwrap_or(0)
+}
+
+fn add_to_pool(
+    env: &Env,
+    pool: WholesalePool,
+    token_address: &Address,
+    delta: i128,
+) -> Result<(), Error> {
+    storage::write_persistent(
+        env,
+        &WholesaleKey::PoolBalance(pool, token_address.clone()),
+        &math::add(pool_balance(env, pool, token_address), delta)?,
+    );
+    storage::write_persistent(
+        env,
+        &WholesaleKey::PooledTotal(token_address.clone()),
+        &math::add(pooled_in(env, token_address), delta)?,
+    );
+    Ok(())
+}
+
+// Treasury splits `amount` of collected revenue across the pools by the
+// configured shares. Rounding dust goes to the first pool of the split.
+// Returns what each pool received.
+pub fn distribute(
+    env: &Env,
+    token_address: &Address,
+    amount: i128,
+) -> Result<Map<WholesalePool, i128>, Error> {
+    admin::require_treasury(env);
+    disputes::ensure_settlements_unfrozen(env)?;
+    let config = read_split(env).ok_or(Error::InvalidConfig)?;
+    if tokens::read_config(env, token_address).is_none() {
+        return Err(Error::UnsupportedToken);
+    }
+    if amount <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    if amount > invariants::reserve(env, token_address) {
+        return Err(Error::InvalidState);
+    }
+
+    let mut parts = Map::new(env);
+    let mut allotted: i128 = 0;
+    for (pool, share) in config.shares.iter() {
+        let part = accounting::apply_bps(amount, share.share_bps as i128)?;
+        parts.set(pool, part);
+        allotted = math::add(allotted, part)?;
+    }
+    if let Some(first) = parts.keys().first() {
+        let dust = math::sub(amount, allotted)?;
+        parts.set(first, math::add(parts.get_unchecked(first), dust)?);
+    }
+    for (pool, part) in parts.iter() {
+        if part > 0 {
+            add_to_pool(env, pool, token_address, part)?;
+        }
+    }
+    env.events().publish(
+        (
+            Symbol::new(env, "revenue_distributed"),
+            token_address.clone(),
+        ),
+        (amount, parts.clone()),
+    );
+    Ok(parts)
+}
+
+// Pays the pool's balance in `token_address` to its registered recipient.
+// Returns the amount.
+pub fn claim(env: &Env, pool: WholesalePool, token_address: &Address) -> Result<i128, Error> {
+    let share = read_split(env)
+        .and_then(|config| config.shares.get(pool))
+        .ok_or(Error::InvalidInput)?;
+    share.recipient.require_auth();
+    disputes::ensure_settlements_unfrozen(env)?;
+    let amount = pool_balance(env, pool, token_address);
+    if amount <= 0 {
+        return Err(Error::InvalidState);
+    }
+    add_to_pool(env, pool, token_address, -amount)?;
+    token::Client::new(env, token_address).transfer(
+        &env.current_contract_address(),
+        &share.recipient,
+        &amount,
+    );
+    audit::record(
+        env,
+        AuditAction::Withdrawal(share.recipient.clone(), token_address.clone(), amount),
+    );
+    env.events().publish(
+        (Symbol::new(env, "pool_claimed"), pool),
+        (share.recipient, token_address.clone(), amount),
+    );
+    Ok(amount)
+}